@@ -1,3 +1,6 @@
+// Profiles the deprecated per-format methods.
+#![allow(deprecated)]
+
 use dhat::{Alloc, Profiler};
 use grim_rs::{Box as GrimBox, CaptureParameters, Grim};
 use std::env;
@@ -107,6 +107,38 @@ fn benchmark_capture_output(c: &mut Criterion) {
     group.finish();
 }
 
+/// Compares a long-lived `Grim`, whose shm pool is reused and only grown, against a fresh
+/// instance per capture, which has to allocate, map and register a new pool every time.
+fn benchmark_shm_pool_reuse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("shm_pool_reuse");
+
+    let mut grim = Grim::new().expect("Failed to create Grim");
+    let outputs = grim.get_outputs().expect("Failed to get outputs");
+    let output = match outputs.first() {
+        Some(output) => output,
+        None => return,
+    };
+    let name = output.name().to_string();
+
+    group.bench_function("reused_pool", |b| {
+        b.iter(|| {
+            let result = grim.capture_output(&name).expect("Failed to capture");
+            black_box(result);
+        });
+    });
+
+    // Same connection, so only the pool and buffer allocation differ.
+    group.bench_function("fresh_pool", |b| {
+        b.iter(|| {
+            grim.release_shm_pool();
+            let result = grim.capture_output(&name).expect("Failed to capture");
+            black_box(result);
+        });
+    });
+
+    group.finish();
+}
+
 fn benchmark_capture_output_with_scale(c: &mut Criterion) {
     let mut group = c.benchmark_group("capture_output_scale");
 
@@ -218,6 +250,7 @@ criterion_group!(
     benchmark_capture_region,
     benchmark_capture_region_with_scale,
     benchmark_capture_output,
+    benchmark_shm_pool_reuse,
     benchmark_capture_output_with_scale,
     benchmark_capture_outputs,
     benchmark_capture_outputs_with_scale,
@@ -1,5 +1,9 @@
+// The png/jpeg groups measure the deprecated per-format methods.
+#![allow(deprecated)]
+
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
-use grim_rs::Grim;
+use grim_rs::encode::{PngCompression, PngFilter};
+use grim_rs::{CaptureResult, EncodeOptions, Grim, ImageFormat, PixelFormat, Transform};
 #[cfg(unix)]
 use std::ffi::CString;
 use std::path::PathBuf;
@@ -72,6 +76,30 @@ fn generate_test_data(width: u32, height: u32) -> Vec<u8> {
     vec![0xAA; size]
 }
 
+/// Screenshot-like frame: a gradient header, flat panels, noisy "text" blocks and a
+/// photo-like quadrant. Uniform data compresses equally well at every PNG setting.
+fn generate_screenshot_data(width: u32, height: u32) -> Vec<u8> {
+    let mut seed = 0x1234_5678u32;
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let pixel = if y < height / 4 {
+                [(x * 255 / width) as u8, (y * 4) as u8, 0x80, 0xff]
+            } else if (x / 8 + y / 12) % 5 == 0 {
+                let value = if seed >> 29 == 0 { 0x20 } else { 0xf0 };
+                [value, value, value, 0xff]
+            } else if x > width / 2 && y > height / 2 {
+                [(seed >> 24) as u8 & 0x3f | 0x40, 0x90, (x ^ y) as u8, 0xff]
+            } else {
+                [0xf5, 0xf5, 0xf5, 0xff]
+            };
+            data.extend_from_slice(&pixel);
+        }
+    }
+    data
+}
+
 fn benchmark_png_encoding(c: &mut Criterion) {
     let mut group = c.benchmark_group("png_encoding");
 
@@ -360,6 +388,283 @@ fn benchmark_jpeg_stdout_quality(c: &mut Criterion) {
     group.finish();
 }
 
+#[cfg(feature = "qoi")]
+fn benchmark_qoi_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("qoi_encoding");
+
+    let sizes = [
+        ("640x480", 640, 480),
+        ("1920x1080", 1920, 1080),
+        ("3840x2160", 3840, 2160),
+    ];
+
+    let options = EncodeOptions::new(ImageFormat::Qoi);
+    for (name, width, height) in sizes.iter() {
+        let image = CaptureResult::new(generate_test_data(*width, *height), *width, *height);
+        let bytes = image.data().len() as u64;
+
+        group.throughput(Throughput::Bytes(bytes));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &image, |b, image| {
+            let grim = Grim::new().expect("Failed to create Grim");
+            b.iter(|| {
+                let mut result = Vec::new();
+                grim.encode(image, &options, &mut result)
+                    .expect("Failed to encode QOI");
+                black_box(result);
+            });
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "qoi")]
+fn benchmark_qoi_save(c: &mut Criterion) {
+    let mut group = c.benchmark_group("qoi_save");
+
+    let sizes = [
+        ("640x480", 640, 480),
+        ("1920x1080", 1920, 1080),
+        ("3840x2160", 3840, 2160),
+    ];
+
+    let options = EncodeOptions::new(ImageFormat::Qoi);
+    for (name, width, height) in sizes.iter() {
+        let image = CaptureResult::new(generate_test_data(*width, *height), *width, *height);
+        let path = temp_path("save_qoi", "qoi", *width, *height);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &image, |b, image| {
+            let grim = Grim::new().expect("Failed to create Grim");
+            b.iter(|| {
+                grim.save(image, &path, &options)
+                    .expect("Failed to save QOI");
+            });
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "qoi")]
+fn benchmark_qoi_stdout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("qoi_stdout");
+
+    let width = 1920;
+    let height = 1080;
+    let image = CaptureResult::new(generate_test_data(width, height), width, height);
+    let options = EncodeOptions::new(ImageFormat::Qoi);
+
+    group.bench_function("encode_qoi_to_stdout", |b| {
+        let grim = Grim::new().expect("Failed to create Grim");
+        b.iter(|| {
+            let _silencer = StdoutSilencer::new().expect("Failed to silence stdout");
+            grim.encode(&image, &options, std::io::stdout().lock())
+                .expect("Failed to write QOI");
+        });
+    });
+
+    group.finish();
+}
+
+/// Every encoder on the same frames, so their timings can be read side by side.
+fn benchmark_format_comparison(c: &mut Criterion) {
+    let mut group = c.benchmark_group("format_comparison");
+
+    let sizes = [("1920x1080", 1920, 1080), ("3840x2160", 3840, 2160)];
+
+    for (name, width, height) in sizes.iter() {
+        let image = CaptureResult::new(generate_test_data(*width, *height), *width, *height);
+        group.throughput(Throughput::Bytes(image.data().len() as u64));
+        let grim = Grim::new().expect("Failed to create Grim");
+
+        let other_formats = [
+            #[cfg(feature = "qoi")]
+            ("qoi".to_string(), EncodeOptions::new(ImageFormat::Qoi)),
+            #[cfg(feature = "jpeg")]
+            ("jpeg_80".to_string(), EncodeOptions::new(ImageFormat::Jpeg)),
+        ];
+        let cases: Vec<(String, EncodeOptions)> = (0..=9u8)
+            .map(|level| {
+                (
+                    format!("png_level_{}", level),
+                    EncodeOptions::new(ImageFormat::Png).compression(level),
+                )
+            })
+            .chain(other_formats)
+            .collect();
+
+        for (case, options) in cases.iter() {
+            group.bench_with_input(BenchmarkId::new(case, name), options, |b, options| {
+                b.iter(|| {
+                    let mut result = Vec::new();
+                    grim.encode(&image, options, &mut result)
+                        .expect("Failed to encode");
+                    black_box(result);
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// PNG levels 0-9, the fast preset and each row filter on a screenshot-like frame.
+/// Criterion reports the time; the encoded sizes are printed once before measuring.
+fn benchmark_png_settings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("png_settings");
+
+    let width = 1920;
+    let height = 1080;
+    let result = CaptureResult::new(generate_screenshot_data(width, height), width, height);
+    group.throughput(Throughput::Bytes(result.data().len() as u64));
+    let grim = Grim::new().expect("Failed to create Grim");
+
+    let png = EncodeOptions::new(ImageFormat::Png);
+    let mut cases: Vec<(String, EncodeOptions)> = (0..=9u8)
+        .map(|level| (format!("level_{}", level), png.clone().compression(level)))
+        .collect();
+    cases.push((
+        "fast".to_string(),
+        png.clone().png_compression(PngCompression::Fast),
+    ));
+    for filter in [
+        PngFilter::NoFilter,
+        PngFilter::Sub,
+        PngFilter::Up,
+        PngFilter::Avg,
+        PngFilter::Paeth,
+        PngFilter::Adaptive,
+    ] {
+        cases.push((
+            format!("level_6_{:?}", filter).to_lowercase(),
+            png.clone().png_filter(filter),
+        ));
+    }
+
+    for (name, options) in cases.iter() {
+        let mut encoded = Vec::new();
+        grim.encode(&result, options, &mut encoded)
+            .expect("Failed to encode PNG");
+        eprintln!(
+            "png_settings/{}: {} bytes ({:.1}% of raw)",
+            name,
+            encoded.len(),
+            encoded.len() as f64 * 100.0 / result.data().len() as f64
+        );
+
+        group.bench_with_input(BenchmarkId::from_parameter(name), options, |b, options| {
+            b.iter(|| {
+                let mut encoded = Vec::with_capacity(result.data().len() / 4);
+                grim.encode(&result, options, &mut encoded)
+                    .expect("Failed to encode PNG");
+                black_box(encoded);
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// The conversion chain used before the fused pipeline: convert in place, transform,
+/// then flip for Y-invert, each as a separate pass over the image.
+fn multi_pass_pipeline(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    transform: Transform,
+    y_invert: bool,
+) -> (Vec<u8>, u32, u32) {
+    fn remap(
+        data: &[u8],
+        width: u32,
+        height: u32,
+        swap: bool,
+        map: impl Fn(u32, u32) -> (u32, u32),
+    ) -> (Vec<u8>, u32, u32) {
+        let (new_width, new_height) = if swap {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let mut out = vec![0u8; data.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let src = ((y * width + x) * 4) as usize;
+                let (new_x, new_y) = map(x, y);
+                let dst = ((new_y * new_width + new_x) * 4) as usize;
+                out[dst..dst + 4].copy_from_slice(&data[src..src + 4]);
+            }
+        }
+        (out, new_width, new_height)
+    }
+
+    let mut converted = data.to_vec();
+    for chunk in converted.chunks_exact_mut(4) {
+        chunk.swap(0, 2);
+        chunk[3] = 255;
+    }
+    let (w, h) = (width, height);
+    let (data, w, h) = match transform {
+        Transform::Rotated90 => remap(&converted, w, h, true, |x, y| (h - 1 - y, x)),
+        Transform::Flipped270 => {
+            let (flipped, w, h) = remap(&converted, w, h, false, |x, y| (w - 1 - x, y));
+            remap(&flipped, w, h, true, |x, y| (y, w - 1 - x))
+        }
+        _ => (converted, w, h),
+    };
+    if y_invert {
+        remap(&data, w, h, false, |x, y| (x, h - 1 - y))
+    } else {
+        (data, w, h)
+    }
+}
+
+fn benchmark_pixel_pipeline(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pixel_pipeline");
+
+    let width = 1920;
+    let height = 1080;
+    let data = generate_test_data(width, height);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    let cases = [
+        ("normal_y_invert", Transform::Normal),
+        ("rotated90_y_invert", Transform::Rotated90),
+        ("flipped270_y_invert", Transform::Flipped270),
+    ];
+
+    for (name, transform) in cases.iter() {
+        group.bench_with_input(
+            BenchmarkId::new("multi_pass", name),
+            transform,
+            |b, &transform| {
+                b.iter(|| black_box(multi_pass_pipeline(&data, width, height, transform, true)));
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("fused", name),
+            transform,
+            |b, &transform| {
+                b.iter(|| {
+                    black_box(
+                        CaptureResult::from_raw(
+                            &data,
+                            width,
+                            height,
+                            width * 4,
+                            PixelFormat::Xrgb8888,
+                            transform,
+                            true,
+                        )
+                        .expect("Failed to convert frame"),
+                    )
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
 #[cfg(feature = "jpeg")]
 criterion_group!(
     benches,
@@ -374,7 +679,10 @@ criterion_group!(
     benchmark_png_stdout,
     benchmark_png_stdout_compression,
     benchmark_jpeg_stdout,
-    benchmark_jpeg_stdout_quality
+    benchmark_jpeg_stdout_quality,
+    benchmark_png_settings,
+    benchmark_format_comparison,
+    benchmark_pixel_pipeline
 );
 
 #[cfg(not(feature = "jpeg"))]
@@ -385,7 +693,22 @@ criterion_group!(
     benchmark_png_save,
     benchmark_png_save_compression_levels,
     benchmark_png_stdout,
-    benchmark_png_stdout_compression
+    benchmark_png_stdout_compression,
+    benchmark_png_settings,
+    benchmark_format_comparison,
+    benchmark_pixel_pipeline
 );
 
+#[cfg(feature = "qoi")]
+criterion_group!(
+    qoi_benches,
+    benchmark_qoi_encoding,
+    benchmark_qoi_save,
+    benchmark_qoi_stdout
+);
+
+#[cfg(feature = "qoi")]
+criterion_main!(benches, qoi_benches);
+
+#[cfg(not(feature = "qoi"))]
 criterion_main!(benches);
@@ -8,7 +8,7 @@ use chrono::Local;
 ///     cargo run --example comprehensive_demo
 ///
 /// All screenshots will be saved to the project root directory.
-use grim_rs::{Box, CaptureParameters, Grim, Result};
+use grim_rs::{Box, CaptureParameters, EncodeOptions, Grim, ImageFormat, Result};
 use std::fs::File;
 use std::io::Write;
 
@@ -61,7 +61,7 @@ fn main() -> Result<()> {
     );
 
     let filename = generate_demo_filename("capture_all", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     // Capture at 50% scale
@@ -73,12 +73,7 @@ fn main() -> Result<()> {
     );
 
     let filename = generate_demo_filename("capture_all_half", "png");
-    grim.save_png(
-        result_scaled.data(),
-        result_scaled.width(),
-        result_scaled.height(),
-        &filename,
-    )?;
+    grim.save(&result_scaled, &filename, &EncodeOptions::default())?;
     println!("Saved: {}", filename);
 
     // Capture at 25% scale
@@ -90,12 +85,7 @@ fn main() -> Result<()> {
     );
 
     let filename = generate_demo_filename("capture_all_quarter", "png");
-    grim.save_png(
-        result_scaled_25.data(),
-        result_scaled_25.width(),
-        result_scaled_25.height(),
-        &filename,
-    )?;
+    grim.save(&result_scaled_25, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     let first_output_name = outputs[0].name();
@@ -109,12 +99,7 @@ fn main() -> Result<()> {
     );
 
     let filename = generate_demo_filename("output_full", "png");
-    grim.save_png(
-        output_result.data(),
-        output_result.width(),
-        output_result.height(),
-        &filename,
-    )?;
+    grim.save(&output_result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     let output_scaled = grim.capture_output_with_scale(first_output_name, 0.5)?;
@@ -125,12 +110,7 @@ fn main() -> Result<()> {
     );
 
     let filename = generate_demo_filename("output_half", "png");
-    grim.save_png(
-        output_scaled.data(),
-        output_scaled.width(),
-        output_scaled.height(),
-        &filename,
-    )?;
+    grim.save(&output_scaled, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     // Capture 800x600 region starting at (100, 100)
@@ -145,12 +125,7 @@ fn main() -> Result<()> {
     );
 
     let filename = generate_demo_filename("region_full", "png");
-    grim.save_png(
-        region_result.data(),
-        region_result.width(),
-        region_result.height(),
-        &filename,
-    )?;
+    grim.save(&region_result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     let region_scaled = grim.capture_region_with_scale(region, 0.75)?;
@@ -161,12 +136,7 @@ fn main() -> Result<()> {
     );
 
     let filename = generate_demo_filename("region_scaled", "png");
-    grim.save_png(
-        region_scaled.data(),
-        region_scaled.width(),
-        region_scaled.height(),
-        &filename,
-    )?;
+    grim.save(&region_scaled, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     if outputs.len() >= 2 {
@@ -183,7 +153,7 @@ fn main() -> Result<()> {
         for (output_name, capture) in multi_result.outputs().iter() {
             let filename =
                 generate_demo_filename(&format!("multi_{}", output_name.to_lowercase()), "png");
-            grim.save_png(capture.data(), capture.width(), capture.height(), &filename)?;
+            grim.save(capture, &filename, &EncodeOptions::default())?;
             println!(
                 "Saved: {} ({}x{})",
                 filename,
@@ -202,22 +172,15 @@ fn main() -> Result<()> {
 
     // PNG with default compression
     let filename_png = generate_demo_filename("format_png_default", "png");
-    grim.save_png(
-        format_result.data(),
-        format_result.width(),
-        format_result.height(),
-        &filename_png,
-    )?;
+    grim.save(&format_result, &filename_png, &EncodeOptions::default())?;
     println!("Saved PNG (default compression): {}", filename_png);
 
     // PNG with high compression (compression level 0-9)
     let filename_png_compressed = generate_demo_filename("format_png_best", "png");
-    grim.save_png_with_compression(
-        format_result.data(),
-        format_result.width(),
-        format_result.height(),
+    grim.save(
+        &format_result,
         &filename_png_compressed,
-        9,
+        &EncodeOptions::default().compression(9),
     )?;
     println!("Saved PNG (best compression): {}", filename_png_compressed);
 
@@ -225,21 +188,14 @@ fn main() -> Result<()> {
     #[cfg(feature = "jpeg")]
     {
         let filename_jpeg = generate_demo_filename("format_jpeg_default", "jpg");
-        grim.save_jpeg(
-            format_result.data(),
-            format_result.width(),
-            format_result.height(),
-            &filename_jpeg,
-        )?;
+        grim.save(&format_result, &filename_jpeg, &EncodeOptions::default())?;
         println!("Saved JPEG (default quality): {}", filename_jpeg);
 
         let filename_jpeg_hq = generate_demo_filename("format_jpeg_q95", "jpg");
-        grim.save_jpeg_with_quality(
-            format_result.data(),
-            format_result.width(),
-            format_result.height(),
+        grim.save(
+            &format_result,
             &filename_jpeg_hq,
-            95,
+            &EncodeOptions::default().quality(95),
         )?;
         println!("Saved JPEG (quality 95): {}", filename_jpeg_hq);
     }
@@ -253,28 +209,30 @@ fn main() -> Result<()> {
     let small_result = grim.capture_region(small_region)?;
 
     // Convert to PNG bytes
-    let png_bytes = grim.to_png(
-        small_result.data(),
-        small_result.width(),
-        small_result.height(),
+    let mut png_bytes = Vec::new();
+    grim.encode(
+        &small_result,
+        &EncodeOptions::new(ImageFormat::Png),
+        &mut png_bytes,
     )?;
     println!("PNG bytes: {} bytes", png_bytes.len());
 
     #[cfg(feature = "jpeg")]
     {
         // Convert to JPEG bytes
-        let jpeg_bytes = grim.to_jpeg(
-            small_result.data(),
-            small_result.width(),
-            small_result.height(),
+        let mut jpeg_bytes = Vec::new();
+        grim.encode(
+            &small_result,
+            &EncodeOptions::new(ImageFormat::Jpeg),
+            &mut jpeg_bytes,
         )?;
         println!("JPEG bytes: {} bytes", jpeg_bytes.len());
 
-        let jpeg_hq_bytes = grim.to_jpeg_with_quality(
-            small_result.data(),
-            small_result.width(),
-            small_result.height(),
-            90,
+        let mut jpeg_hq_bytes = Vec::new();
+        grim.encode(
+            &small_result,
+            &EncodeOptions::new(ImageFormat::Jpeg).quality(90),
+            &mut jpeg_hq_bytes,
         )?;
         println!("JPEG bytes (quality 90): {} bytes", jpeg_hq_bytes.len());
     }
@@ -301,12 +259,7 @@ fn main() -> Result<()> {
         );
 
         let filename = generate_demo_filename("span_region", "png");
-        grim.save_png(
-            span_result.data(),
-            span_result.width(),
-            span_result.height(),
-            &filename,
-        )?;
+        grim.save(&span_result, &filename, &EncodeOptions::default())?;
         println!("Saved: {}\n", filename);
     } else {
         println!("Skipping spanning region (only 1 output available)\n");
@@ -317,12 +270,7 @@ fn main() -> Result<()> {
 
     let filename_png = generate_demo_filename("compare_png", "png");
 
-    grim.save_png(
-        test_result.data(),
-        test_result.width(),
-        test_result.height(),
-        &filename_png,
-    )?;
+    grim.save(&test_result, &filename_png, &EncodeOptions::default())?;
 
     let png_size = std::fs::metadata(&filename_png)?.len();
 
@@ -336,12 +284,7 @@ fn main() -> Result<()> {
     #[cfg(feature = "jpeg")]
     {
         let filename_jpg = generate_demo_filename("compare_jpeg", "jpg");
-        grim.save_jpeg(
-            test_result.data(),
-            test_result.width(),
-            test_result.height(),
-            &filename_jpg,
-        )?;
+        grim.save(&test_result, &filename_jpg, &EncodeOptions::default())?;
         let jpeg_size = std::fs::metadata(&filename_jpg)?.len();
         println!("  JPEG ({}): {} bytes", filename_jpg, jpeg_size);
     }
@@ -1,4 +1,4 @@
-use grim_rs::Grim;
+use grim_rs::{EncodeOptions, Grim, ImageFormat};
 use std::time::Instant;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -23,8 +23,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("2. PNG encoding (different compression levels):");
     for level in [1, 6, 9] {
         let start = Instant::now();
-        let png_data =
-            grim.to_png_with_compression(result.data(), result.width(), result.height(), level)?;
+        let mut png_data = Vec::new();
+        let options = EncodeOptions::new(ImageFormat::Png).compression(level);
+        grim.encode(&result, &options, &mut png_data)?;
         let encode_time = start.elapsed();
         println!(
             "Level {}: {:?} -> {} bytes ({:.2} MB)",
@@ -40,8 +41,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("3. JPEG encoding (different quality levels):");
         for quality in [60, 80, 95] {
             let start = Instant::now();
-            let jpeg_data =
-                grim.to_jpeg_with_quality(result.data(), result.width(), result.height(), quality)?;
+            let mut jpeg_data = Vec::new();
+            let options = EncodeOptions::new(ImageFormat::Jpeg).quality(quality);
+            grim.encode(&result, &options, &mut jpeg_data)?;
             let encode_time = start.elapsed();
             println!(
                 "Quality {}: {:?} -> {} bytes ({:.2} MB)",
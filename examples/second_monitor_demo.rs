@@ -1,5 +1,5 @@
 use chrono::Local;
-use grim_rs::{Box, Grim, Result};
+use grim_rs::{Box, EncodeOptions, Grim, ImageFormat, Result};
 
 fn generate_filename(description: &str, extension: &str) -> String {
     let now = Local::now();
@@ -62,7 +62,7 @@ fn main() -> Result<()> {
     let result = grim.capture_output(second_output.name())?;
     println!("Captured: {}x{} pixels", result.width(), result.height());
     let filename = generate_filename("full", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     println!("Capturing second monitor with different scales...");
@@ -75,12 +75,7 @@ fn main() -> Result<()> {
         result_half.height()
     );
     let filename = generate_filename("half_scale", "png");
-    grim.save_png(
-        result_half.data(),
-        result_half.width(),
-        result_half.height(),
-        &filename,
-    )?;
+    grim.save(&result_half, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     println!("- At 0.25x scale...");
@@ -91,12 +86,7 @@ fn main() -> Result<()> {
         result_quarter.height()
     );
     let filename = generate_filename("quarter_scale", "png");
-    grim.save_png(
-        result_quarter.data(),
-        result_quarter.width(),
-        result_quarter.height(),
-        &filename,
-    )?;
+    grim.save(&result_quarter, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     println!("Capturing regions of second monitor...");
@@ -113,7 +103,7 @@ fn main() -> Result<()> {
     let result = grim.capture_region(region)?;
     println!("Captured: {}x{} pixels", result.width(), result.height());
     let filename = generate_filename("top_left", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     println!("- Center region (800x600)...");
@@ -128,7 +118,7 @@ fn main() -> Result<()> {
     let result = grim.capture_region(region)?;
     println!("Captured: {}x{} pixels", result.width(), result.height());
     let filename = generate_filename("center", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     println!("- Bottom-right corner (400x300)...");
@@ -143,7 +133,7 @@ fn main() -> Result<()> {
     let result = grim.capture_region(region)?;
     println!("Captured: {}x{} pixels", result.width(), result.height());
     let filename = generate_filename("bottom_right", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
 
     println!("Saving second monitor in different formats...");
@@ -151,30 +141,24 @@ fn main() -> Result<()> {
 
     println!("- PNG (default compression)...");
     let filename = generate_filename("format", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}", filename);
 
     println!("- PNG (best compression)...");
     let filename = generate_filename("format_best_comp", "png");
-    grim.save_png_with_compression(result.data(), result.width(), result.height(), &filename, 9)?;
+    grim.save(&result, &filename, &EncodeOptions::default().compression(9))?;
     println!("Saved: {}", filename);
 
     #[cfg(feature = "jpeg")]
     {
         println!("- JPEG (default quality)...");
         let filename = generate_filename("format", "jpg");
-        grim.save_jpeg(result.data(), result.width(), result.height(), &filename)?;
+        grim.save(&result, &filename, &EncodeOptions::default())?;
         println!("Saved: {}", filename);
 
         println!("- JPEG (quality 95)...");
         let filename = generate_filename("format_q95", "jpg");
-        grim.save_jpeg_with_quality(
-            result.data(),
-            result.width(),
-            result.height(),
-            &filename,
-            95,
-        )?;
+        grim.save(&result, &filename, &EncodeOptions::default().quality(95))?;
         println!("Saved: {}", filename);
     }
 
@@ -192,7 +176,7 @@ fn main() -> Result<()> {
     let result = grim.capture_region_with_scale(region, 0.75)?;
     println!("Captured: {}x{} pixels", result.width(), result.height());
     let filename = generate_filename("center_scaled", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
     println!("Capturing horizontal strip from second monitor...");
     let strip_height = (200).min(geom.height());
@@ -205,7 +189,7 @@ fn main() -> Result<()> {
     let result = grim.capture_region(region)?;
     println!("Captured: {}x{} pixels", result.width(), result.height());
     let filename = generate_filename("horizontal_strip", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
     println!("Capturing vertical strip from second monitor...");
     let strip_width = (200).min(geom.width());
@@ -218,17 +202,27 @@ fn main() -> Result<()> {
     let result = grim.capture_region(region)?;
     println!("Captured: {}x{} pixels", result.width(), result.height());
     let filename = generate_filename("vertical_strip", "png");
-    grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+    grim.save(&result, &filename, &EncodeOptions::default())?;
     println!("Saved: {}\n", filename);
     println!("Converting to different formats in memory...");
     let result = grim.capture_output(second_output.name())?;
 
-    let png_bytes = grim.to_png(result.data(), result.width(), result.height())?;
+    let mut png_bytes = Vec::new();
+    grim.encode(
+        &result,
+        &EncodeOptions::new(ImageFormat::Png),
+        &mut png_bytes,
+    )?;
     println!("PNG bytes: {} bytes", png_bytes.len());
 
     #[cfg(feature = "jpeg")]
     {
-        let jpeg_bytes = grim.to_jpeg(result.data(), result.width(), result.height())?;
+        let mut jpeg_bytes = Vec::new();
+        grim.encode(
+            &result,
+            &EncodeOptions::new(ImageFormat::Jpeg),
+            &mut jpeg_bytes,
+        )?;
         println!("JPEG bytes: {} bytes", jpeg_bytes.len());
     }
 
@@ -247,7 +241,7 @@ fn main() -> Result<()> {
             );
             let result = grim.capture_region(region)?;
             let filename = generate_filename(&format!("grid_{}_{}", row, col), "png");
-            grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+            grim.save(&result, &filename, &EncodeOptions::default())?;
         }
     }
     println!(
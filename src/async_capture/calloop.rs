@@ -0,0 +1,123 @@
+use std::io;
+use std::os::fd::{BorrowedFd, OwnedFd};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+use ::calloop::generic::Generic;
+use ::calloop::ping::{make_ping, Ping, PingSource};
+use ::calloop::{
+    EventSource, Interest, LoopHandle, Mode, Poll as LoopPoll, PostAction,
+    Readiness as LoopReadiness, Token, TokenFactory,
+};
+
+use super::Readiness;
+
+/// Readiness seen by the event loop, waiting to be picked up by a capture future.
+#[derive(Default)]
+struct Shared {
+    readable: bool,
+    waker: Option<Waker>,
+}
+
+/// [`Readiness`] backed by a calloop event loop.
+///
+/// The loop owns a [`ConnectionSource`]; dropping this value drops the `Ping` that keeps
+/// the source alive, which removes it from the loop.
+pub(super) struct CalloopReadiness {
+    shared: Arc<Mutex<Shared>>,
+    _alive: Ping,
+}
+
+impl CalloopReadiness {
+    /// Insert a source watching a duplicate of `fd` into the loop behind `handle`.
+    pub(super) fn new<D>(fd: BorrowedFd<'_>, handle: &LoopHandle<'_, D>) -> io::Result<Self> {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let (alive, closed) = make_ping()?;
+        let source = ConnectionSource {
+            // Edge-triggered so an idle connection with unread events does not spin the
+            // loop; `Shared::readable` remembers the edge until a future consumes it.
+            fd: Generic::new(fd.try_clone_to_owned()?, Interest::READ, Mode::Edge),
+            closed,
+            shared: shared.clone(),
+        };
+        handle
+            .insert_source(source, |(), _, _| {})
+            .map_err(|e| io::Error::from(e.error))?;
+        Ok(Self {
+            shared,
+            _alive: alive,
+        })
+    }
+}
+
+impl Readiness for CalloopReadiness {
+    fn poll_readable(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut shared = self.shared.lock().unwrap_or_else(PoisonError::into_inner);
+        if std::mem::take(&mut shared.readable) {
+            return Poll::Ready(Ok(()));
+        }
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Event source for the connection fd that wakes the pending capture future.
+struct ConnectionSource {
+    fd: Generic<OwnedFd>,
+    closed: PingSource,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl EventSource for ConnectionSource {
+    type Event = ();
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: LoopReadiness,
+        token: Token,
+        _callback: F,
+    ) -> io::Result<PostAction>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let shared = &self.shared;
+        self.fd.process_events(readiness, token, |_, _| {
+            let mut shared = shared.lock().unwrap_or_else(PoisonError::into_inner);
+            shared.readable = true;
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+            Ok(PostAction::Continue)
+        })?;
+        // Only reports `Remove`, once the `CalloopReadiness` holding the `Ping` is gone.
+        self.closed
+            .process_events(readiness, token, |(), _| {})
+            .map_err(io::Error::other)
+    }
+
+    fn register(
+        &mut self,
+        poll: &mut LoopPoll,
+        token_factory: &mut TokenFactory,
+    ) -> ::calloop::Result<()> {
+        self.fd.register(poll, token_factory)?;
+        self.closed.register(poll, token_factory)
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &mut LoopPoll,
+        token_factory: &mut TokenFactory,
+    ) -> ::calloop::Result<()> {
+        self.fd.reregister(poll, token_factory)?;
+        self.closed.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut LoopPoll) -> ::calloop::Result<()> {
+        self.fd.unregister(poll)?;
+        self.closed.unregister(poll)
+    }
+}
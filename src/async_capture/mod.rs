@@ -0,0 +1,297 @@
+//! Async capture API.
+//!
+//! Enabled with the `async` feature. [`AsyncGrim`] wraps a [`Grim`] and returns futures
+//! instead of blocking: every wait for the compositor becomes a wait for the Wayland
+//! socket to become readable, reported by a [`Readiness`] source from the caller's event
+//! loop. No thread is spawned, so a GUI or server can take screenshots from the loop it
+//! already runs.
+//!
+//! Ready-made sources are provided for tokio (`tokio` feature, [`AsyncGrim::tokio`]) and
+//! calloop (`calloop` feature, [`AsyncGrim::calloop`]); other runtimes can implement
+//! [`Readiness`] themselves and use [`AsyncGrim::with_readiness`].
+//!
+//! Captures still take `&mut self`, so one `AsyncGrim` runs one capture at a time. Use
+//! several instances, which may share a connection (see [`Grim::from_connection`]), to
+//! capture concurrently.
+//!
+//! [`Grim::set_timeout`] and [`CaptureParameters::timeout`] apply as for blocking
+//! captures: a `timerfd`, watched by a second source from the same [`Readiness`] factory,
+//! wakes the future at the deadline and it fails with [`Error::Timeout`]. Dropping a
+//! capture future destroys its pending frames, and a [`CancellationToken`] wakes and
+//! aborts the capture waiting on it.
+//!
+//! [`Error::Timeout`]: crate::Error::Timeout
+
+#[cfg(feature = "calloop")]
+mod calloop;
+mod timer;
+#[cfg(feature = "tokio")]
+mod tokio;
+
+use std::io;
+use std::os::fd::BorrowedFd;
+use std::task::{Context, Poll};
+
+use crate::{
+    Box, CancellationToken, CaptureBackend, CaptureParameters, CaptureResult, Grim,
+    MultiOutputCaptureResult, Output, Result, Toplevel,
+};
+
+pub(crate) use timer::DeadlineTimer;
+
+/// Reports when the Wayland connection's file descriptor becomes readable.
+///
+/// grim-rs reads the socket without blocking and only polls for readiness after a read
+/// came back empty, so spurious wake-ups are harmless. An implementation must however
+/// not lose readiness that arrives after it last returned `Ready`, and must register
+/// `cx`'s waker whenever it returns `Pending`.
+pub trait Readiness: Send + Sync {
+    /// Resolve once the connection may have become readable.
+    fn poll_readable(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+/// Readiness sources of an [`AsyncGrim`]: one for the connection, one for its deadlines.
+pub(crate) struct AsyncWait {
+    pub(crate) connection: std::boxed::Box<dyn Readiness>,
+    pub(crate) timer: DeadlineTimer,
+}
+
+/// Screenshot interface whose captures are futures.
+///
+/// Created from a [`Grim`] and a [`Readiness`] source; the methods mirror their blocking
+/// counterparts on [`Grim`].
+pub struct AsyncGrim {
+    grim: Grim,
+}
+
+impl AsyncGrim {
+    /// Wrap `grim`, waiting on the readiness sources `readiness` builds.
+    ///
+    /// `readiness` is called twice: for the connection, and for the `timerfd` that ends
+    /// captures at their timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Io` if the timer cannot be created or `readiness` fails.
+    pub fn with_readiness<R, F>(mut grim: Grim, mut readiness: F) -> Result<Self>
+    where
+        R: Readiness + 'static,
+        F: FnMut(BorrowedFd<'_>) -> io::Result<R>,
+    {
+        let connection = {
+            let backend = grim.platform_capture.connection().backend();
+            readiness(backend.poll_fd())?
+        };
+        let timer = DeadlineTimer::new(readiness)?;
+        grim.platform_capture.set_readiness(Some(AsyncWait {
+            connection: std::boxed::Box::new(connection),
+            timer,
+        }));
+        Ok(Self { grim })
+    }
+
+    /// Wrap `grim`, waiting on the reactor of the current tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Io` if the connection cannot be registered with the reactor.
+    ///
+    /// # Panics
+    ///
+    /// Panics when called outside a tokio runtime, or if its IO driver is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::async_capture::AsyncGrim;
+    /// use grim_rs::Grim;
+    ///
+    /// # async fn run() -> grim_rs::Result<()> {
+    /// let mut grim = AsyncGrim::tokio(Grim::new()?)?;
+    /// let result = grim.capture_all().await?;
+    /// println!("{}x{}", result.width(), result.height());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn tokio(grim: Grim) -> Result<Self> {
+        Self::with_readiness(grim, tokio::TokioReadiness::new)
+    }
+
+    /// Wrap `grim`, waiting on the calloop event loop behind `handle`.
+    ///
+    /// The returned futures are woken from the loop, so they have to be polled by an
+    /// executor running in that loop, such as `calloop::futures::executor`. The event
+    /// source is removed when the `AsyncGrim` is dropped or unwrapped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Io` if the connection cannot be added to the loop.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::async_capture::AsyncGrim;
+    /// use grim_rs::Grim;
+    ///
+    /// # fn main() -> Result<(), std::boxed::Box<dyn std::error::Error>> {
+    /// let mut event_loop = calloop::EventLoop::<()>::try_new()?;
+    /// let (executor, scheduler) = calloop::futures::executor()?;
+    /// let mut grim = AsyncGrim::calloop(Grim::new()?, &event_loop.handle())?;
+    /// scheduler.schedule(async move {
+    ///     let result = grim.capture_all().await;
+    ///     println!("{:?}", result.map(|result| result.width()));
+    /// })?;
+    /// event_loop
+    ///     .handle()
+    ///     .insert_source(executor, |(), _, _| {})
+    ///     .map_err(|e| e.error)?;
+    /// event_loop.run(None, &mut (), |_| {})?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "calloop")]
+    pub fn calloop<D>(grim: Grim, handle: &::calloop::LoopHandle<'_, D>) -> Result<Self> {
+        Self::with_readiness(grim, |fd| calloop::CalloopReadiness::new(fd, handle))
+    }
+
+    /// The wrapped instance, e.g. for the encoding helpers.
+    pub fn grim(&self) -> &Grim {
+        &self.grim
+    }
+
+    /// Unwrap the blocking [`Grim`] again.
+    pub fn into_inner(mut self) -> Grim {
+        self.grim.platform_capture.set_readiness(None);
+        self.grim
+    }
+
+    /// Capture protocol selected for this instance.
+    pub fn backend(&self) -> CaptureBackend {
+        self.grim.backend()
+    }
+
+    /// Same as [`Grim::set_timeout`].
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.grim.set_timeout(timeout);
+    }
+
+    /// Same as [`Grim::set_cancellation_token`].
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.grim.set_cancellation_token(token);
+    }
+
+    /// Async version of [`Grim::get_outputs`].
+    pub async fn get_outputs(&mut self) -> Result<Vec<Output>> {
+        self.grim.platform_capture.get_outputs().await
+    }
+
+    /// Async version of [`Grim::list_toplevels`].
+    pub async fn list_toplevels(&mut self) -> Result<Vec<Toplevel>> {
+        self.grim.platform_capture.list_toplevels().await
+    }
+
+    /// Async version of [`Grim::capture_all`].
+    pub async fn capture_all(&mut self) -> Result<CaptureResult> {
+        self.grim.platform_capture.capture_all().await
+    }
+
+    /// Async version of [`Grim::capture_all_with_scale`].
+    pub async fn capture_all_with_scale(&mut self, scale: f64) -> Result<CaptureResult> {
+        self.grim
+            .platform_capture
+            .capture_all_with_scale(scale)
+            .await
+    }
+
+    /// Async version of [`Grim::capture_output`].
+    pub async fn capture_output(&mut self, output_name: &str) -> Result<CaptureResult> {
+        self.grim.platform_capture.capture_output(output_name).await
+    }
+
+    /// Async version of [`Grim::capture_output_with_scale`].
+    pub async fn capture_output_with_scale(
+        &mut self,
+        output_name: &str,
+        scale: f64,
+    ) -> Result<CaptureResult> {
+        self.grim
+            .platform_capture
+            .capture_output_with_scale(output_name, scale)
+            .await
+    }
+
+    /// Async version of [`Grim::capture_region`].
+    pub async fn capture_region(&mut self, region: Box) -> Result<CaptureResult> {
+        self.grim.platform_capture.capture_region(region).await
+    }
+
+    /// Async version of [`Grim::capture_region_with_scale`].
+    pub async fn capture_region_with_scale(
+        &mut self,
+        region: Box,
+        scale: f64,
+    ) -> Result<CaptureResult> {
+        self.grim
+            .platform_capture
+            .capture_region_with_scale(region, scale)
+            .await
+    }
+
+    /// Async version of [`Grim::capture_outputs`].
+    pub async fn capture_outputs(
+        &mut self,
+        parameters: Vec<CaptureParameters>,
+    ) -> Result<MultiOutputCaptureResult> {
+        self.grim.platform_capture.capture_outputs(parameters).await
+    }
+
+    /// Async version of [`Grim::capture_outputs_with_scale`].
+    pub async fn capture_outputs_with_scale(
+        &mut self,
+        parameters: Vec<CaptureParameters>,
+        default_scale: f64,
+    ) -> Result<MultiOutputCaptureResult> {
+        self.grim
+            .platform_capture
+            .capture_outputs_with_scale(parameters, default_scale)
+            .await
+    }
+
+    /// Async version of [`Grim::capture_toplevel`].
+    pub async fn capture_toplevel(&mut self, toplevel: &Toplevel) -> Result<CaptureResult> {
+        self.grim
+            .platform_capture
+            .capture_toplevel(toplevel, false)
+            .await
+    }
+
+    /// Async version of [`Grim::capture_toplevel_with_scale`].
+    pub async fn capture_toplevel_with_scale(
+        &mut self,
+        toplevel: &Toplevel,
+        scale: f64,
+    ) -> Result<CaptureResult> {
+        self.grim
+            .platform_capture
+            .capture_toplevel_with_scale(toplevel, scale, false)
+            .await
+    }
+
+    /// Async version of [`Grim::capture_toplevel_with_cursor`].
+    pub async fn capture_toplevel_with_cursor(
+        &mut self,
+        toplevel: &Toplevel,
+        scale: Option<f64>,
+    ) -> Result<CaptureResult> {
+        let capture = &mut self.grim.platform_capture;
+        match scale {
+            Some(scale) => {
+                capture
+                    .capture_toplevel_with_scale(toplevel, scale, true)
+                    .await
+            }
+            None => capture.capture_toplevel(toplevel, true).await,
+        }
+    }
+}
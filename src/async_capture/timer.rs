@@ -0,0 +1,89 @@
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use super::Readiness;
+
+/// A `timerfd` watched by the same kind of [`Readiness`] source as the connection, so
+/// async captures give up at their deadline on any runtime without a timer of its own.
+pub(crate) struct DeadlineTimer {
+    fd: OwnedFd,
+    readiness: std::boxed::Box<dyn Readiness>,
+    /// Deadline the timer is currently armed for.
+    armed: Option<Instant>,
+}
+
+impl DeadlineTimer {
+    /// Create a disarmed timer and let `readiness` build the source that watches it.
+    pub(crate) fn new<R, F>(readiness: F) -> io::Result<Self>
+    where
+        R: Readiness + 'static,
+        F: FnOnce(BorrowedFd<'_>) -> io::Result<R>,
+    {
+        // SAFETY: timerfd_create takes no pointers; the result is checked below.
+        let raw = unsafe {
+            libc::timerfd_create(
+                libc::CLOCK_MONOTONIC,
+                libc::TFD_CLOEXEC | libc::TFD_NONBLOCK,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `raw` is a freshly created descriptor nothing else owns.
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+        let readiness = std::boxed::Box::new(readiness(fd.as_fd())?);
+        Ok(Self {
+            fd,
+            readiness,
+            armed: None,
+        })
+    }
+
+    /// Resolve once `deadline` has passed, arming the timer for it if needed.
+    pub(crate) fn poll_expired(
+        &mut self,
+        deadline: Instant,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Poll::Ready(Ok(()));
+            }
+            if self.armed != Some(deadline) {
+                self.arm(left)?;
+                self.armed = Some(deadline);
+            }
+            match self.readiness.poll_readable(cx) {
+                // Expired, or left over from an earlier deadline: re-check the clock and
+                // re-arm, which also clears the expiration.
+                Poll::Ready(Ok(())) => self.armed = None,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Fire once after `after`, which must not be zero; that would disarm the timer.
+    fn arm(&self, after: Duration) -> io::Result<()> {
+        let spec = libc::itimerspec {
+            it_interval: libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            it_value: libc::timespec {
+                tv_sec: libc::time_t::try_from(after.as_secs()).unwrap_or(libc::time_t::MAX),
+                tv_nsec: after.subsec_nanos().into(),
+            },
+        };
+        // SAFETY: `spec` outlives the call and the old value is not requested.
+        let result =
+            unsafe { libc::timerfd_settime(self.fd.as_raw_fd(), 0, &spec, std::ptr::null_mut()) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use std::io;
+use std::os::fd::{BorrowedFd, OwnedFd};
+use std::task::{Context, Poll};
+
+use ::tokio::io::unix::AsyncFd;
+
+use super::Readiness;
+
+/// [`Readiness`] backed by the reactor of a tokio runtime.
+pub(super) struct TokioReadiness {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl TokioReadiness {
+    /// Register a duplicate of `fd` with the current runtime's reactor.
+    pub(super) fn new(fd: BorrowedFd<'_>) -> io::Result<Self> {
+        Ok(Self {
+            fd: AsyncFd::new(fd.try_clone_to_owned()?)?,
+        })
+    }
+}
+
+impl Readiness for TokioReadiness {
+    fn poll_readable(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.fd.poll_read_ready(cx) {
+            Poll::Ready(Ok(mut guard)) => {
+                // The read that follows consumes the data; tokio only re-arms after a clear.
+                guard.clear_ready();
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use grim_rs::{Box as GrimBox, CaptureParameters, Grim};
+use grim_rs::encode::PngCompression;
+use grim_rs::focus::PointerResolver;
+use grim_rs::{
+    Box as GrimBox, CaptureParameters, EncodeOptions, Grim, ImageFormat, Output, Transform,
+};
 use std::env;
 use std::fs;
 use std::io::{self, BufRead};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 fn main() -> grim_rs::Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -35,10 +39,15 @@ fn main() -> grim_rs::Result<()> {
                     eprintln!("Error: -g requires an argument");
                     std::process::exit(1);
                 }
-                if args[arg_idx] == "-" {
-                    opts.geometry = Some(Grim::read_region_from_stdin()?);
-                } else {
-                    opts.geometry = Some(args[arg_idx].parse()?);
+                match args[arg_idx].as_str() {
+                    "-" => opts.geometry = Some(Grim::read_region_from_stdin()?),
+                    #[cfg(feature = "ipc")]
+                    kind @ ("focused-window" | "active-workspace") => {
+                        opts.geometry = Some(ipc_geometry(kind)?)
+                    }
+                    #[cfg(feature = "select")]
+                    "select" => opts.select = true,
+                    geometry => opts.geometry = Some(geometry.parse()?),
                 }
             }
             "-t" => {
@@ -47,18 +56,13 @@ fn main() -> grim_rs::Result<()> {
                     eprintln!("Error: -t requires an argument");
                     std::process::exit(1);
                 }
-                match args[arg_idx].as_str() {
-                    "png" => {
-                        opts.filetype = FileType::Png;
-                    }
-                    "jpeg" => {
-                        opts.filetype = FileType::Jpeg;
-                    }
-                    _ => {
+                opts.filetype = match args[arg_idx].parse() {
+                    Ok(filetype) => filetype,
+                    Err(_) => {
                         eprintln!("Error: invalid filetype: {}", args[arg_idx]);
                         std::process::exit(1);
                     }
-                }
+                };
             }
             "-q" => {
                 arg_idx += 1;
@@ -73,10 +77,10 @@ fn main() -> grim_rs::Result<()> {
                     ))
                 })?;
                 if !(0..=100).contains(&quality) {
-                    eprintln!("Error: JPEG quality must be between 0 and 100");
+                    eprintln!("Error: quality must be between 0 and 100");
                     std::process::exit(1);
                 }
-                opts.jpeg_quality = quality as u8;
+                opts.quality = Some(quality as u8);
             }
             "-l" => {
                 arg_idx += 1;
@@ -84,17 +88,21 @@ fn main() -> grim_rs::Result<()> {
                     eprintln!("Error: -l requires an argument");
                     std::process::exit(1);
                 }
-                let level: i32 = args[arg_idx].parse().map_err(|_| {
-                    grim_rs::Error::Io(std::io::Error::new(
-                        std::io::ErrorKind::InvalidInput,
-                        "Invalid compression level",
-                    ))
-                })?;
-                if !(0..=9).contains(&level) {
-                    eprintln!("Error: PNG compression level must be between 0 and 9");
-                    std::process::exit(1);
+                if args[arg_idx] == "fast" {
+                    opts.png_compression = PngCompression::Fast;
+                } else {
+                    let level: i32 = args[arg_idx].parse().map_err(|_| {
+                        grim_rs::Error::Io(std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            "Invalid compression level",
+                        ))
+                    })?;
+                    if !(0..=9).contains(&level) {
+                        eprintln!("Error: PNG compression level must be between 0 and 9");
+                        std::process::exit(1);
+                    }
+                    opts.png_compression = PngCompression::Level(level as u8);
                 }
-                opts.png_level = level as u8;
             }
             "-o" => {
                 arg_idx += 1;
@@ -104,9 +112,23 @@ fn main() -> grim_rs::Result<()> {
                 }
                 opts.output_name = Some(args[arg_idx].clone());
             }
+            "-T" => {
+                arg_idx += 1;
+                if arg_idx >= args.len() {
+                    eprintln!("Error: -T requires an argument");
+                    std::process::exit(1);
+                }
+                opts.toplevel = Some(args[arg_idx].clone());
+            }
             "-c" => {
                 opts.with_cursor = true;
             }
+            "--list-outputs" => {
+                opts.list_outputs = true;
+            }
+            "--json" => {
+                opts.json = true;
+            }
             _ => {
                 if output_file.is_none() {
                     output_file = Some(args[arg_idx].clone());
@@ -119,16 +141,65 @@ fn main() -> grim_rs::Result<()> {
         arg_idx += 1;
     }
 
+    if opts.toplevel.is_some()
+        && (opts.geometry.is_some() || opts.select || opts.output_name.is_some())
+    {
+        eprintln!("Error: -T cannot be combined with -g or -o");
+        std::process::exit(1);
+    }
+    if opts.quality.is_some() && opts.filetype == ImageFormat::Jxl {
+        eprintln!("Error: -q is not supported for JPEG XL, which is always lossless");
+        std::process::exit(1);
+    }
+    if opts.json && !opts.list_outputs {
+        eprintln!("Error: --json requires --list-outputs");
+        std::process::exit(1);
+    }
+    if opts.list_outputs {
+        if output_file.is_some() {
+            eprintln!("Error: --list-outputs does not take an output file");
+            std::process::exit(1);
+        }
+        let outputs = Grim::new()?.get_outputs()?;
+        if opts.json {
+            println!("{}", outputs_json(&outputs));
+        } else {
+            print!("{}", outputs_table(&outputs));
+        }
+        return Ok(());
+    }
+
     let output_file = if let Some(file) = output_file {
         file
     } else {
-        generate_default_filename(opts.filetype)?
+        generate_default_filename(&opts.filetype)?
     };
 
     let mut grim = Grim::new()?;
+
+    if let Some(ref identifier) = opts.toplevel {
+        let result = capture_toplevel(&mut grim, identifier, opts.scale, opts.with_cursor)?;
+        return save_or_write_result(&grim, &result, &output_file, &opts);
+    }
+
+    #[cfg(feature = "select")]
+    if opts.select {
+        opts.geometry = Some(grim.select_region(&selectable_windows())?);
+    }
+
+    let output_name = match opts.output_name.as_deref() {
+        Some("focused") => Some(grim.focused_output()?.name().to_string()),
+        Some("cursor") => Some(
+            grim.focused_output_with(&mut PointerResolver)?
+                .name()
+                .to_string(),
+        ),
+        name => name.map(str::to_string),
+    };
+
     let effective_scale = resolve_effective_scale(&mut grim, opts.scale)?;
 
-    let result = if let Some(ref output_name) = opts.output_name {
+    let result = if let Some(ref output_name) = output_name {
         if opts.with_cursor {
             let mut params =
                 CaptureParameters::new(output_name.clone()).overlay_cursor(opts.with_cursor);
@@ -163,11 +234,17 @@ fn main() -> grim_rs::Result<()> {
 struct Options {
     scale: Option<f64>,
     geometry: Option<GrimBox>,
-    filetype: FileType,
-    jpeg_quality: u8,
-    png_level: u8,
+    /// `-g select`: ask the user for the region once connected.
+    select: bool,
+    filetype: ImageFormat,
+    /// `-q` as given. WebP is lossless without it; JPEG XL rejects it.
+    quality: Option<u8>,
+    png_compression: PngCompression,
     output_name: Option<String>,
+    toplevel: Option<String>,
     with_cursor: bool,
+    list_outputs: bool,
+    json: bool,
 }
 
 impl Default for Options {
@@ -175,154 +252,91 @@ impl Default for Options {
         Self {
             scale: None,
             geometry: None,
-            filetype: FileType::Png,
-            jpeg_quality: 80,
-            png_level: 6,
+            select: false,
+            filetype: ImageFormat::Png,
+            quality: None,
+            png_compression: PngCompression::Level(6),
             output_name: None,
+            toplevel: None,
             with_cursor: false,
+            list_outputs: false,
+            json: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum FileType {
-    Png,
-    Jpeg,
-}
+fn capture_toplevel(
+    grim: &mut Grim,
+    identifier: &str,
+    scale: Option<f64>,
+    with_cursor: bool,
+) -> grim_rs::Result<grim_rs::CaptureResult> {
+    let toplevel = grim
+        .list_toplevels()?
+        .into_iter()
+        .find(|toplevel| toplevel.identifier() == identifier)
+        .ok_or_else(|| grim_rs::Error::ToplevelNotFound(identifier.to_string()))?;
 
-fn save_or_write_result(
-    grim: &grim_rs::Grim,
-    result: &grim_rs::CaptureResult,
-    output_file: &str,
-    opts: &Options,
-) -> grim_rs::Result<()> {
-    if output_file == "-" {
-        write_to_stdout(grim, result, opts)
-    } else {
-        save_to_file(grim, result, output_file, opts)
+    if with_cursor {
+        return grim.capture_toplevel_with_cursor(&toplevel, scale);
     }
-}
-
-fn write_to_stdout(
-    grim: &grim_rs::Grim,
-    result: &grim_rs::CaptureResult,
-    opts: &Options,
-) -> grim_rs::Result<()> {
-    match opts.filetype {
-        FileType::Png => write_png_to_stdout(grim, result, opts.png_level),
-        FileType::Jpeg => write_jpeg_to_stdout(grim, result, opts.jpeg_quality),
+    match scale {
+        Some(scale) => grim.capture_toplevel_with_scale(&toplevel, scale),
+        None => grim.capture_toplevel(&toplevel),
     }
 }
 
-fn save_to_file(
+fn save_or_write_result(
     grim: &grim_rs::Grim,
     result: &grim_rs::CaptureResult,
     output_file: &str,
     opts: &Options,
 ) -> grim_rs::Result<()> {
-    let path = Path::new(output_file);
-    match opts.filetype {
-        FileType::Png => save_png_to_file(grim, result, path, opts.png_level),
-        FileType::Jpeg => save_jpeg_to_file(grim, result, path, opts.jpeg_quality),
-    }
-}
-
-fn write_png_to_stdout(
-    grim: &grim_rs::Grim,
-    result: &grim_rs::CaptureResult,
-    compression_level: u8,
-) -> grim_rs::Result<()> {
-    if compression_level == 6 {
-        grim.write_png_to_stdout(result.data(), result.width(), result.height())
+    let options = encode_options(opts);
+    if output_file == "-" {
+        grim.encode(result, &options, io::BufWriter::new(io::stdout().lock()))
     } else {
-        grim.write_png_to_stdout_with_compression(
-            result.data(),
-            result.width(),
-            result.height(),
-            compression_level,
-        )
+        grim.save(result, output_file, &options)
     }
 }
 
-fn save_png_to_file(
-    grim: &grim_rs::Grim,
-    result: &grim_rs::CaptureResult,
-    path: &Path,
-    compression_level: u8,
-) -> grim_rs::Result<()> {
-    if compression_level == 6 {
-        grim.save_png(result.data(), result.width(), result.height(), path)
-    } else {
-        grim.save_png_with_compression(
-            result.data(),
-            result.width(),
-            result.height(),
-            path,
-            compression_level,
-        )
+fn encode_options(opts: &Options) -> EncodeOptions {
+    let options = EncodeOptions::new(opts.filetype.clone()).png_compression(opts.png_compression);
+    match opts.quality {
+        Some(quality) => options.quality(quality),
+        None => options,
     }
 }
 
-fn write_jpeg_to_stdout(
-    grim: &grim_rs::Grim,
-    result: &grim_rs::CaptureResult,
-    quality: u8,
-) -> grim_rs::Result<()> {
-    #[cfg(feature = "jpeg")]
-    {
-        if quality == 80 {
-            grim.write_jpeg_to_stdout(result.data(), result.width(), result.height())
-        } else {
-            grim.write_jpeg_to_stdout_with_quality(
-                result.data(),
-                result.width(),
-                result.height(),
-                quality,
-            )
-        }
-    }
-    #[cfg(not(feature = "jpeg"))]
-    {
-        let _ = (grim, result, quality);
-        Err(create_jpeg_not_supported_error())
-    }
+/// Geometry of the focused window or the active workspace from sway or Hyprland IPC.
+#[cfg(feature = "ipc")]
+fn ipc_geometry(kind: &str) -> grim_rs::Result<GrimBox> {
+    let Some(ipc) = grim_rs::ipc::CompositorIpc::from_env() else {
+        eprintln!(
+            "Error: -g {} requires sway or Hyprland (SWAYSOCK or HYPRLAND_INSTANCE_SIGNATURE)",
+            kind
+        );
+        std::process::exit(1);
+    };
+    let (geometry, what) = if kind == "focused-window" {
+        (ipc.focused_window()?, "focused window")
+    } else {
+        (ipc.active_workspace()?, "active workspace")
+    };
+    geometry.ok_or_else(|| {
+        eprintln!("Error: there is no {}", what);
+        std::process::exit(1);
+    })
 }
 
-fn save_jpeg_to_file(
-    grim: &grim_rs::Grim,
-    result: &grim_rs::CaptureResult,
-    path: &Path,
-    quality: u8,
-) -> grim_rs::Result<()> {
-    #[cfg(feature = "jpeg")]
-    {
-        if quality == 80 {
-            grim.save_jpeg(result.data(), result.width(), result.height(), path)
-        } else {
-            grim.save_jpeg_with_quality(
-                result.data(),
-                result.width(),
-                result.height(),
-                path,
-                quality,
-            )
-        }
-    }
-    #[cfg(not(feature = "jpeg"))]
-    {
-        let _ = (grim, result, path, quality);
-        Err(create_jpeg_not_supported_error())
+/// Windows a click selects with `-g select`, if the compositor's IPC can list them.
+#[cfg(feature = "select")]
+fn selectable_windows() -> Vec<GrimBox> {
+    #[cfg(feature = "ipc")]
+    if let Some(ipc) = grim_rs::ipc::CompositorIpc::from_env() {
+        return ipc.windows().unwrap_or_default();
     }
-}
-
-#[cfg(not(feature = "jpeg"))]
-fn create_jpeg_not_supported_error() -> grim_rs::Error {
-    grim_rs::Error::ImageProcessing(image::ImageError::Unsupported(
-        image::error::UnsupportedError::from_format_and_kind(
-            image::error::ImageFormatHint::Name("JPEG".to_string()),
-            image::error::UnsupportedErrorKind::Format(image::ImageFormat::Jpeg.into()),
-        ),
-    ))
+    Vec::new()
 }
 
 fn print_help() {
@@ -332,18 +346,132 @@ fn print_help() {
          Options:\n\
          -h              Show help message and quit.\n\
          -s <factor>     Set the output image's scale factor (default: greatest output scale).\n\
-         -g <geometry>   Set the region to capture.\n\
-         -t png|jpeg    Set the output filetype.\n\
-         -q <quality>    Set the JPEG filetype compression rate (0-100).\n\
-         -l <level>      Set the PNG filetype compression level (0-9).\n\
-         -o <output>     Set the output name to capture.\n\
+         -g <geometry>   Set the region to capture, \"focused-window\", \"active-workspace\" or \"select\".\n\
+         -t png|jpeg|webp|qoi|avif|jxl Set the output filetype.\n\
+         -q <quality>    Set the JPEG, AVIF or lossy WebP quality (0-100).\n\
+         -l <level>      Set the PNG filetype compression level (0-9), or \"fast\".\n\
+         -o <output>     Set the output name to capture, or \"focused\" / \"cursor\".\n\
+         -T <identifier> Set the identifier of a foreign toplevel handle to capture.\n\
          -c              Include cursors in the screenshot.\n\
+         --list-outputs  List outputs with their geometry, scale and transform, then quit.\n\
+         --json          Print the output list as JSON (with --list-outputs).\n\
          \n\
          If output-file is '-', output to standard output.\n\
          If no output-file is specified, use a default timestamped filename."
     );
 }
 
+/// Name of a transform as used by compositor tools such as `swaymsg`.
+fn transform_name(transform: Transform) -> &'static str {
+    match transform {
+        Transform::Normal => "normal",
+        Transform::Rotated90 => "90",
+        Transform::Rotated180 => "180",
+        Transform::Rotated270 => "270",
+        Transform::Flipped => "flipped",
+        Transform::Flipped90 => "flipped-90",
+        Transform::Flipped180 => "flipped-180",
+        Transform::Flipped270 => "flipped-270",
+    }
+}
+
+/// Logical geometry in the `-g` format, so it can be passed back to grim.
+fn geometry_string(output: &Output) -> String {
+    let geometry = output.geometry();
+    format!(
+        "{},{} {}x{}",
+        geometry.x(),
+        geometry.y(),
+        geometry.width(),
+        geometry.height()
+    )
+}
+
+fn outputs_table(outputs: &[Output]) -> String {
+    let header = ["NAME", "GEOMETRY", "SCALE", "TRANSFORM", "DESCRIPTION"];
+    let rows: Vec<[String; 5]> = outputs
+        .iter()
+        .map(|output| {
+            [
+                output.name().to_string(),
+                geometry_string(output),
+                format!("{}", output.logical_scale()),
+                transform_name(output.transform()).to_string(),
+                output.description().unwrap_or("-").to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    let header = header.map(str::to_string);
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+fn outputs_json(outputs: &[Output]) -> String {
+    let entries: Vec<String> = outputs
+        .iter()
+        .map(|output| {
+            let geometry = output.geometry();
+            format!(
+                "{{\"name\":{},\"description\":{},\"x\":{},\"y\":{},\"width\":{},\"height\":{},\"scale\":{},\"logical_scale\":{},\"transform\":\"{}\"}}",
+                json_string(output.name()),
+                output.description().map_or("null".to_string(), json_string),
+                geometry.x(),
+                geometry.y(),
+                geometry.width(),
+                geometry.height(),
+                output.scale(),
+                json_number(output.logical_scale()),
+                transform_name(output.transform()),
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if u32::from(c) < 0x20 => escaped.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// JSON has no NaN or infinity.
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    } else {
+        "null".to_string()
+    }
+}
+
 fn resolve_effective_scale(grim: &mut Grim, requested_scale: Option<f64>) -> grim_rs::Result<f64> {
     if let Some(scale) = requested_scale {
         return Ok(scale);
@@ -367,20 +495,15 @@ where
     f64::from(max_scale)
 }
 
-fn generate_default_filename(filetype: FileType) -> grim_rs::Result<String> {
+fn generate_default_filename(filetype: &ImageFormat) -> grim_rs::Result<String> {
     use chrono::Local;
 
     // Format: YYYYMMDD_HHhMMmSSs_grim.ext (e.g., 20241004_10h30m45s_grim.png)
     let now = Local::now();
     let timestamp = now.format("%Y%m%d_%Hh%Mm%Ss");
 
-    let ext = match filetype {
-        FileType::Png => "png",
-        FileType::Jpeg => "jpeg",
-    };
-
     let output_dir = get_output_dir();
-    let filename = format!("{}_grim.{}", timestamp, ext);
+    let filename = format!("{}_grim.{}", timestamp, filetype.extension());
 
     Ok(output_dir.join(filename).to_string_lossy().to_string())
 }
@@ -461,7 +584,18 @@ fn get_output_dir() -> PathBuf {
 
 #[cfg(test)]
 mod tests {
-    use super::default_scale_from_output_scales;
+    use super::{default_scale_from_output_scales, json_number, json_string};
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("DP-1"), "\"DP-1\"");
+        assert_eq!(
+            json_string("Dell \"U2720Q\"\\\n\u{1}"),
+            "\"Dell \\\"U2720Q\\\"\\\\\\n\\u0001\""
+        );
+        assert_eq!(json_number(1.5), "1.5");
+        assert_eq!(json_number(f64::NAN), "null");
+    }
 
     #[test]
     fn default_scale_uses_max_positive_scale() {
@@ -0,0 +1,664 @@
+//! Encoding captures into image files.
+//!
+//! [`Grim::encode`](crate::Grim::encode) writes a [`CaptureResult`] to any [`Write`] in the
+//! [`ImageFormat`] and settings chosen by [`EncodeOptions`], and
+//! [`Grim::save`](crate::Grim::save) writes it to a file, taking the format from the file
+//! extension unless the options name one. [`encode()`] does the same as `Grim::encode`
+//! with the built-in encoders only, without a Wayland connection.
+//!
+//! PNG needs the `png_support` feature, which is on by default; JPEG, WebP, QOI, AVIF and
+//! JPEG XL need their own cargo features.
+//! Implement [`Encoder`] and pass it to
+//! [`Grim::register_encoder`](crate::Grim::register_encoder) to add other formats, or to
+//! replace a built-in one.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use grim_rs::encode::{EncodeOptions, ImageFormat};
+//! use grim_rs::Grim;
+//!
+//! let mut grim = Grim::new()?;
+//! let result = grim.capture_all()?;
+//! // PNG, from the extension.
+//! grim.save(&result, "screenshot.png", &EncodeOptions::default())?;
+//! // JPEG at quality 90, to stdout.
+//! let options = EncodeOptions::new(ImageFormat::Jpeg).quality(90);
+//! grim.encode(&result, &options, std::io::stdout().lock())?;
+//! # Ok::<(), grim_rs::Error>(())
+//! ```
+
+use std::fmt;
+use std::io::Write;
+
+use crate::{CaptureResult, Error, Result};
+
+/// PNG compression used when [`EncodeOptions::compression`] is not set.
+const DEFAULT_PNG_COMPRESSION: PngCompression = PngCompression::Level(6);
+
+/// JPEG and AVIF quality used when [`EncodeOptions::quality`] is not set.
+#[cfg(any(feature = "jpeg", feature = "avif"))]
+const DEFAULT_QUALITY: u8 = 80;
+
+/// An image file format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Qoi,
+    Avif,
+    /// JPEG XL, always lossless.
+    Jxl,
+    /// A format handled by an [`Encoder`] registered with
+    /// [`Grim::register_encoder`](crate::Grim::register_encoder), named by its lowercase
+    /// file extension.
+    Custom(String),
+}
+
+impl ImageFormat {
+    /// A format for a registered encoder, e.g. `ImageFormat::custom("bmp")`.
+    pub fn custom(extension: impl Into<String>) -> Self {
+        Self::Custom(extension.into().to_ascii_lowercase())
+    }
+
+    /// The built-in format for a file extension, ignoring case. `jpg` is JPEG.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            "webp" => Some(Self::Webp),
+            "qoi" => Some(Self::Qoi),
+            "avif" => Some(Self::Avif),
+            "jxl" => Some(Self::Jxl),
+            _ => None,
+        }
+    }
+
+    /// The file extension for this format, without the dot.
+    pub fn extension(&self) -> &str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::Webp => "webp",
+            Self::Qoi => "qoi",
+            Self::Avif => "avif",
+            Self::Jxl => "jxl",
+            Self::Custom(extension) => extension,
+        }
+    }
+
+    /// The format as reported in errors.
+    fn hint(&self) -> image::error::ImageFormatHint {
+        use image::error::ImageFormatHint;
+
+        match self {
+            Self::Png => image::ImageFormat::Png.into(),
+            Self::Jpeg => image::ImageFormat::Jpeg.into(),
+            Self::Webp => image::ImageFormat::WebP.into(),
+            Self::Qoi => image::ImageFormat::Qoi.into(),
+            Self::Avif => image::ImageFormat::Avif.into(),
+            Self::Jxl => ImageFormatHint::Name("JPEG XL".to_string()),
+            Self::Custom(extension) => ImageFormatHint::Name(extension.clone()),
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// Parses the built-in format names accepted by `grim -t`, plus `jpg`.
+impl std::str::FromStr for ImageFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_extension(s)
+            .ok_or_else(|| unsupported(image::error::ImageFormatHint::Name(s.to_string())))
+    }
+}
+
+/// How hard the PNG encoder compresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PngCompression {
+    /// zlib level, as in grim's `-l`: 0 stores the pixels uncompressed, 1 is the fastest
+    /// deflate and 9 the smallest. Higher values are treated as 9.
+    Level(u8),
+    /// The fast preset: a deflate specialised for PNG rows, with the
+    /// [`Up`](PngFilter::Up) filter by default. Several times faster than level 1 and
+    /// usually smaller.
+    Fast,
+}
+
+/// The filter applied to each PNG row before compression.
+///
+/// Filtering turns rows into differences from neighbouring pixels, which deflate
+/// compresses much better for photos and gradients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PngFilter {
+    NoFilter,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Pick the filter per row with the minimum-sum-of-absolute-differences heuristic,
+    /// as libpng does.
+    Adaptive,
+}
+
+impl PngCompression {
+    /// The filter used when [`EncodeOptions::png_filter`] is not set: none for level 0,
+    /// where it would only cost time, `Up` for the fast preset and adaptive otherwise.
+    pub fn default_filter(self) -> PngFilter {
+        match self {
+            Self::Level(0) => PngFilter::NoFilter,
+            Self::Level(_) => PngFilter::Adaptive,
+            Self::Fast => PngFilter::Up,
+        }
+    }
+}
+
+/// Format and settings for [`Grim::encode`](crate::Grim::encode) and
+/// [`Grim::save`](crate::Grim::save).
+///
+/// Settings a format does not use are ignored, so one set of options can serve every
+/// format:
+///
+/// - PNG: [`compression`](Self::compression) 0-9, default 6, or the
+///   [fast preset](PngCompression::Fast), and the row [`png_filter`](Self::png_filter).
+///   Captures with [`CaptureResult::data16`] are written as 16-bit PNGs.
+/// - JPEG: [`quality`](Self::quality) 0-100, default 80.
+/// - WebP: lossless, or lossy at [`quality`](Self::quality) 0-100 when it is set.
+/// - QOI: no settings.
+/// - AVIF: [`quality`](Self::quality) 1-100, default 80, and [`speed`](Self::speed) 1-10,
+///   default [`AVIF_DEFAULT_SPEED`](crate::AVIF_DEFAULT_SPEED).
+/// - JPEG XL: [`effort`](Self::effort) 1-9, default
+///   [`JXL_DEFAULT_EFFORT`](crate::JXL_DEFAULT_EFFORT).
+///
+/// # Example
+///
+/// ```rust
+/// use grim_rs::encode::{EncodeOptions, ImageFormat};
+///
+/// let options = EncodeOptions::new(ImageFormat::Avif).quality(60).speed(8);
+/// assert_eq!(options.format_ref(), Some(&ImageFormat::Avif));
+/// assert_eq!(options.compression_level(), 6);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// `None` to infer the format from the path in [`Grim::save`](crate::Grim::save);
+    /// [`Grim::encode`](crate::Grim::encode) then writes PNG.
+    format: Option<ImageFormat>,
+    compression: Option<PngCompression>,
+    png_filter: Option<PngFilter>,
+    quality: Option<u8>,
+    speed: Option<u8>,
+    effort: Option<u8>,
+}
+
+impl EncodeOptions {
+    /// Options for `format` with every setting at its default.
+    pub fn new(format: ImageFormat) -> Self {
+        Self {
+            format: Some(format),
+            ..Self::default()
+        }
+    }
+
+    /// PNG compression level, from 0 (uncompressed) to 9 (smallest).
+    pub fn compression(self, level: u8) -> Self {
+        self.png_compression(PngCompression::Level(level))
+    }
+
+    /// PNG compression level or preset.
+    pub fn png_compression(mut self, compression: PngCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// PNG row filter, instead of the compression's
+    /// [default](PngCompression::default_filter). Level 0 never filters.
+    pub fn png_filter(mut self, filter: PngFilter) -> Self {
+        self.png_filter = Some(filter);
+        self
+    }
+
+    /// Quality for lossy formats, from 0 to 100. Setting it makes WebP lossy.
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// AVIF encoder speed, from 1 (slowest, smallest files) to 10.
+    pub fn speed(mut self, speed: u8) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// JPEG XL effort, from 1 (fastest) to 9 (smallest files).
+    pub fn effort(mut self, effort: u8) -> Self {
+        self.effort = Some(effort);
+        self
+    }
+
+    /// Get the format, if one was chosen.
+    pub fn format_ref(&self) -> Option<&ImageFormat> {
+        self.format.as_ref()
+    }
+
+    /// Get the PNG compression level, with the fast preset counted as level 1.
+    pub fn compression_level(&self) -> u8 {
+        match self.png_compression_value() {
+            PngCompression::Level(level) => level.min(9),
+            PngCompression::Fast => 1,
+        }
+    }
+
+    /// Get the PNG compression level or preset.
+    pub fn png_compression_value(&self) -> PngCompression {
+        self.compression.unwrap_or(DEFAULT_PNG_COMPRESSION)
+    }
+
+    /// Get the PNG row filter.
+    pub fn png_filter_value(&self) -> PngFilter {
+        self.png_filter
+            .unwrap_or_else(|| self.png_compression_value().default_filter())
+    }
+
+    /// Get the quality, if one was set.
+    pub fn quality_value(&self) -> Option<u8> {
+        self.quality
+    }
+
+    /// Get the AVIF speed, if one was set.
+    pub fn speed_value(&self) -> Option<u8> {
+        self.speed
+    }
+
+    /// Get the JPEG XL effort, if one was set.
+    pub fn effort_value(&self) -> Option<u8> {
+        self.effort
+    }
+}
+
+/// RGBA pixels handed to an [`Encoder`], with their length checked against the size.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageRef<'a> {
+    data: &'a [u8],
+    data16: Option<&'a [u16]>,
+    width: u32,
+    height: u32,
+}
+
+impl<'a> ImageRef<'a> {
+    /// Wrap RGBA data with 8 bits per channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data` is not `width * height * 4` bytes long or the image is
+    /// empty.
+    pub fn new(data: &'a [u8], width: u32, height: u32) -> Result<Self> {
+        check_len(data.len(), width, height)?;
+        Ok(Self {
+            data,
+            data16: None,
+            width,
+            height,
+        })
+    }
+
+    /// Wrap a capture, including its 16-bit data if it has any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the capture's data does not match its size.
+    pub fn from_capture(result: &'a CaptureResult) -> Result<Self> {
+        let image = Self::new(result.data(), result.width(), result.height())?;
+        match result.data16() {
+            Some(data16) => image.with_data16(data16),
+            None => Ok(image),
+        }
+    }
+
+    /// Attach the same image with 16 bits per channel, for encoders that can keep it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data16` is not `width * height * 4` samples long.
+    pub fn with_data16(mut self, data16: &'a [u16]) -> Result<Self> {
+        check_len(data16.len(), self.width, self.height)?;
+        self.data16 = Some(data16);
+        Ok(self)
+    }
+
+    /// Get the RGBA data with 8 bits per channel.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Get the RGBA data with 16 bits per channel, if the image has it.
+    pub fn data16(&self) -> Option<&'a [u16]> {
+        self.data16
+    }
+
+    /// Get the image width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the image height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Writes images in one format.
+///
+/// Register implementations with [`Grim::register_encoder`](crate::Grim::register_encoder).
+/// Closures with the same signature as [`encode`](Self::encode) implement it too.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::Write;
+///
+/// use grim_rs::encode::{EncodeOptions, Encoder, ImageFormat, ImageRef};
+/// use grim_rs::Grim;
+///
+/// /// Binary PPM, dropping alpha.
+/// struct Ppm;
+///
+/// impl Encoder for Ppm {
+///     fn encode(
+///         &self,
+///         image: ImageRef<'_>,
+///         _options: &EncodeOptions,
+///         writer: &mut dyn Write,
+///     ) -> grim_rs::Result<()> {
+///         write!(writer, "P6 {} {} 255\n", image.width(), image.height())?;
+///         for rgba in image.data().chunks_exact(4) {
+///             writer.write_all(&rgba[..3])?;
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let mut grim = Grim::new()?;
+/// grim.register_encoder(ImageFormat::custom("ppm"), Ppm);
+/// let result = grim.capture_all()?;
+/// grim.save(&result, "screenshot.ppm", &EncodeOptions::default())?;
+/// # Ok::<(), grim_rs::Error>(())
+/// ```
+pub trait Encoder: Send + Sync {
+    /// Write `image` to `writer` using the settings in `options`.
+    fn encode(
+        &self,
+        image: ImageRef<'_>,
+        options: &EncodeOptions,
+        writer: &mut dyn Write,
+    ) -> Result<()>;
+}
+
+impl<F> Encoder for F
+where
+    F: Fn(ImageRef<'_>, &EncodeOptions, &mut dyn Write) -> Result<()> + Send + Sync,
+{
+    fn encode(
+        &self,
+        image: ImageRef<'_>,
+        options: &EncodeOptions,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        self(image, options, writer)
+    }
+}
+
+/// Encode a capture with the built-in encoders and write it to `writer`.
+///
+/// Like [`Grim::encode`](crate::Grim::encode) without registered encoders, so it needs no
+/// Wayland connection: uses the format in `options`, or PNG if it has none.
+///
+/// # Errors
+///
+/// Returns an error if the format's cargo feature is disabled, the capture's data does not
+/// match its size, or encoding or writing failed.
+///
+/// # Example
+///
+/// ```rust
+/// use grim_rs::encode::{self, EncodeOptions};
+/// use grim_rs::CaptureResult;
+///
+/// let result = CaptureResult::new(vec![0xff; 4 * 4 * 4], 4, 4);
+/// let mut png = Vec::new();
+/// encode::encode(&result, &EncodeOptions::default(), &mut png)?;
+/// assert_eq!(&png[1..4], b"PNG");
+/// # Ok::<(), grim_rs::Error>(())
+/// ```
+pub fn encode<W: Write>(
+    result: &CaptureResult,
+    options: &EncodeOptions,
+    mut writer: W,
+) -> Result<()> {
+    let format = options.format_ref().unwrap_or(&ImageFormat::Png);
+    encode_builtin(
+        format,
+        ImageRef::from_capture(result)?,
+        options,
+        &mut writer,
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Encode `image` with the encoder built in for `format`.
+pub(crate) fn encode_builtin(
+    format: &ImageFormat,
+    image: ImageRef<'_>,
+    options: &EncodeOptions,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    match format {
+        ImageFormat::Png => match image.data16() {
+            Some(data16) => png16(data16, image.width(), image.height(), options, writer),
+            None => encode_png(image, options, writer),
+        },
+        #[cfg(feature = "jpeg")]
+        ImageFormat::Jpeg => encode_jpeg(image, options.quality.unwrap_or(DEFAULT_QUALITY), writer),
+        #[cfg(feature = "webp")]
+        ImageFormat::Webp => encode_webp(image, options.quality, writer),
+        #[cfg(feature = "qoi")]
+        ImageFormat::Qoi => encode_qoi(image, writer),
+        #[cfg(feature = "avif")]
+        ImageFormat::Avif => encode_avif(
+            image,
+            options.quality.unwrap_or(DEFAULT_QUALITY),
+            options.speed.unwrap_or(crate::AVIF_DEFAULT_SPEED),
+            writer,
+        ),
+        #[cfg(feature = "jxl")]
+        ImageFormat::Jxl => encode_jxl(
+            image,
+            options.effort.unwrap_or(crate::JXL_DEFAULT_EFFORT),
+            writer,
+        ),
+        _ => Err(unsupported(format.hint())),
+    }
+}
+
+/// The error for a format that has no encoder.
+pub(crate) fn unsupported(hint: image::error::ImageFormatHint) -> Error {
+    Error::ImageProcessing(image::ImageError::Unsupported(
+        image::error::UnsupportedError::from_format_and_kind(
+            hint.clone(),
+            image::error::UnsupportedErrorKind::Format(hint),
+        ),
+    ))
+}
+
+fn dimension_mismatch() -> Error {
+    Error::ImageProcessing(image::ImageError::Parameter(
+        image::error::ParameterError::from_kind(
+            image::error::ParameterErrorKind::DimensionMismatch,
+        ),
+    ))
+}
+
+/// Check that `len` samples are exactly one RGBA image of `width` x `height`.
+fn check_len(len: usize, width: u32, height: u32) -> Result<()> {
+    let expected = (u64::from(width) * u64::from(height)).checked_mul(4);
+    if expected == Some(len as u64) && len > 0 {
+        Ok(())
+    } else {
+        Err(dimension_mismatch())
+    }
+}
+
+fn encoding_error(format: &str, error: impl fmt::Display) -> Error {
+    Error::Io(std::io::Error::other(format!(
+        "{} encoding error: {}",
+        format, error
+    )))
+}
+
+fn encode_png(image: ImageRef<'_>, options: &EncodeOptions, writer: &mut dyn Write) -> Result<()> {
+    write_png(
+        image.data(),
+        image.width(),
+        image.height(),
+        png::BitDepth::Eight,
+        options,
+        writer,
+    )
+}
+
+/// Encode RGBA data with 16 bits per channel as a 16-bit PNG.
+pub(crate) fn png16(
+    data: &[u16],
+    width: u32,
+    height: u32,
+    options: &EncodeOptions,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    check_len(data.len(), width, height)?;
+    // PNG stores 16-bit samples big-endian.
+    let samples: Vec<u8> = data.iter().flat_map(|value| value.to_be_bytes()).collect();
+    write_png(
+        &samples,
+        width,
+        height,
+        png::BitDepth::Sixteen,
+        options,
+        writer,
+    )
+}
+
+fn write_png(
+    samples: &[u8],
+    width: u32,
+    height: u32,
+    depth: png::BitDepth,
+    options: &EncodeOptions,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(depth);
+    encoder.set_deflate_compression(match options.png_compression_value() {
+        PngCompression::Level(0) => png::DeflateCompression::NoCompression,
+        PngCompression::Level(level) => png::DeflateCompression::Level(level.min(9)),
+        PngCompression::Fast => png::DeflateCompression::FdeflateUltraFast,
+    });
+    encoder.set_filter(match options.png_filter_value() {
+        PngFilter::NoFilter => png::Filter::NoFilter,
+        PngFilter::Sub => png::Filter::Sub,
+        PngFilter::Up => png::Filter::Up,
+        PngFilter::Avg => png::Filter::Avg,
+        PngFilter::Paeth => png::Filter::Paeth,
+        PngFilter::Adaptive => png::Filter::Adaptive,
+    });
+
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| encoding_error("PNG", e))?;
+    writer
+        .write_image_data(samples)
+        .map_err(|e| encoding_error("PNG", e))?;
+    writer.finish().map_err(|e| encoding_error("PNG", e))
+}
+
+#[cfg(feature = "jpeg")]
+fn encode_jpeg(image: ImageRef<'_>, quality: u8, writer: &mut dyn Write) -> Result<()> {
+    let (Ok(width), Ok(height)) = (u16::try_from(image.width()), u16::try_from(image.height()))
+    else {
+        return Err(dimension_mismatch());
+    };
+    jpeg_encoder::Encoder::new(writer, quality.min(100))
+        .encode(image.data(), width, height, jpeg_encoder::ColorType::Rgba)
+        .map_err(|e| encoding_error("JPEG", e))
+}
+
+#[cfg(feature = "webp")]
+fn encode_webp(image: ImageRef<'_>, quality: Option<u8>, writer: &mut dyn Write) -> Result<()> {
+    let encoder = webp::Encoder::from_rgba(image.data(), image.width(), image.height());
+    let encoded = match quality {
+        None => encoder.encode_simple(true, 75.0),
+        Some(quality) => encoder.encode_simple(false, f32::from(quality.min(100))),
+    }
+    .map_err(|e| encoding_error("WebP", format!("{:?}", e)))?;
+    writer.write_all(&encoded)?;
+    Ok(())
+}
+
+#[cfg(feature = "qoi")]
+fn encode_qoi(image: ImageRef<'_>, writer: &mut dyn Write) -> Result<()> {
+    let encoded = qoi::encode_to_vec(image.data(), image.width(), image.height())
+        .map_err(|e| encoding_error("QOI", e))?;
+    writer.write_all(&encoded)?;
+    Ok(())
+}
+
+#[cfg(feature = "avif")]
+fn encode_avif(image: ImageRef<'_>, quality: u8, speed: u8, writer: &mut dyn Write) -> Result<()> {
+    let pixels: Vec<ravif::RGBA8> = image
+        .data()
+        .chunks_exact(4)
+        .map(|rgba| ravif::RGBA8::new(rgba[0], rgba[1], rgba[2], rgba[3]))
+        .collect();
+    let encoded = ravif::Encoder::new()
+        .with_quality(f32::from(quality.clamp(1, 100)))
+        .with_speed(speed.clamp(1, 10))
+        .encode_rgba(ravif::Img::new(
+            pixels.as_slice(),
+            image.width() as usize,
+            image.height() as usize,
+        ))
+        .map_err(|e| encoding_error("AVIF", e))?;
+    writer.write_all(&encoded.avif_file)?;
+    Ok(())
+}
+
+#[cfg(feature = "jxl")]
+fn encode_jxl(image: ImageRef<'_>, effort: u8, writer: &mut dyn Write) -> Result<()> {
+    use zune_core::bit_depth::BitDepth;
+    use zune_core::colorspace::ColorSpace;
+    use zune_core::options::EncoderOptions;
+
+    const SAMPLED_ROWS: [u8; 9] = [1, 2, 4, 8, 16, 32, 64, 96, 127];
+
+    let options = EncoderOptions::new(
+        image.width() as usize,
+        image.height() as usize,
+        ColorSpace::RGBA,
+        BitDepth::Eight,
+    )
+    .set_effort(SAMPLED_ROWS[usize::from(effort.clamp(1, 9)) - 1]);
+    let mut encoded = Vec::new();
+    zune_jpegxl::JxlSimpleEncoder::new(image.data(), options)
+        .encode(&mut encoded)
+        .map_err(|e| encoding_error("JPEG XL", format!("{:?}", e)))?;
+    writer.write_all(&encoded)?;
+    Ok(())
+}
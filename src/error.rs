@@ -11,6 +11,9 @@ pub enum Error {
     #[error("Output not found: {0}")]
     OutputNotFound(String),
 
+    #[error("Toplevel not found: {0}")]
+    ToplevelNotFound(String),
+
     #[error("Invalid capture region: {0}")]
     InvalidRegion(String),
 
@@ -41,6 +44,24 @@ pub enum Error {
     #[error("Frame capture failed: {0}")]
     FrameCapture(String),
 
+    #[error("Timed out after {0:?} waiting for the compositor")]
+    Timeout(std::time::Duration),
+
+    #[error("Capture was cancelled")]
+    Cancelled,
+
+    #[error("Could not determine the focused output")]
+    NoFocusedOutput,
+
+    #[error("Compositor IPC error: {0}")]
+    Ipc(String),
+
+    #[error("Selection was cancelled")]
+    SelectionCancelled,
+
+    #[error("Unsupported pixel format: {0}")]
+    UnsupportedFormat(String),
+
     #[error("Output transform not supported: {0}")]
     TransformNotSupported(String),
 
@@ -0,0 +1,52 @@
+//! Hyprland's request socket (`.socket.sock`).
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{Error, Result};
+
+/// Upper bound for a reply, far above what a real client list needs.
+const MAX_REPLY: u64 = 64 * 1024 * 1024;
+
+/// Request socket of the running Hyprland instance, from `HYPRLAND_INSTANCE_SIGNATURE`.
+///
+/// Hyprland 0.40 moved its sockets from `/tmp/hypr` to `$XDG_RUNTIME_DIR/hypr`; the old
+/// location is used when the new one does not exist.
+pub(crate) fn socket_from_env() -> Option<PathBuf> {
+    let signature = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+    let runtime = std::env::var_os("XDG_RUNTIME_DIR").map(|dir| {
+        Path::new(&dir)
+            .join("hypr")
+            .join(&signature)
+            .join(".socket.sock")
+    });
+    match runtime {
+        Some(path) if path.exists() => Some(path),
+        _ => Some(Path::new("/tmp/hypr").join(&signature).join(".socket.sock")),
+    }
+}
+
+/// Send `command` (e.g. `j/monitors`) to the Hyprland socket at `socket` and return the
+/// reply, which Hyprland ends by closing the connection.
+pub(crate) fn request(socket: &Path, command: &str, timeout: Option<Duration>) -> Result<String> {
+    let io_error = |source| Error::IoWithContext {
+        operation: format!("Hyprland IPC request to {}", socket.display()),
+        source,
+    };
+    let mut stream = UnixStream::connect(socket).map_err(io_error)?;
+    stream.set_read_timeout(timeout).map_err(io_error)?;
+    stream.set_write_timeout(timeout).map_err(io_error)?;
+    stream.write_all(command.as_bytes()).map_err(io_error)?;
+
+    let mut reply = Vec::new();
+    stream
+        .take(MAX_REPLY + 1)
+        .read_to_end(&mut reply)
+        .map_err(io_error)?;
+    if reply.len() as u64 > MAX_REPLY {
+        return Err(Error::Ipc("Hyprland IPC reply is too large".to_string()));
+    }
+    String::from_utf8(reply).map_err(|_| Error::Ipc("Hyprland IPC reply is not UTF-8".to_string()))
+}
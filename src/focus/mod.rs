@@ -0,0 +1,174 @@
+//! Finding the output the user is working on.
+//!
+//! Wayland has no protocol for asking which output has focus, so [`Grim::focused_output`]
+//! asks a [`FocusResolver`]. Three are provided:
+//!
+//! - [`SwayResolver`] asks sway over its i3-compatible IPC socket (`SWAYSOCK`).
+//! - [`HyprlandResolver`] asks Hyprland over its request socket
+//!   (`HYPRLAND_INSTANCE_SIGNATURE`).
+//! - [`PointerResolver`] works on any compositor with `zwlr_layer_shell_v1`: it briefly
+//!   maps an invisible overlay on every output and reports the one the pointer is on.
+//!
+//! [`ResolverChain::from_env`], which [`Grim::focused_output`] uses, tries the IPC
+//! resolvers of the compositor that is running and falls back to the pointer. Implement
+//! [`FocusResolver`] to plug in other sources, e.g. a different compositor's IPC.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use grim_rs::focus::PointerResolver;
+//! use grim_rs::Grim;
+//!
+//! let mut grim = Grim::new()?;
+//! let focused = grim.capture_focused_output()?;
+//! let under_cursor = grim.focused_output_with(&mut PointerResolver)?;
+//! println!("{}x{}, pointer on {}", focused.width(), focused.height(), under_cursor.name());
+//! # Ok::<(), grim_rs::Error>(())
+//! ```
+
+pub(crate) mod hyprland;
+pub(crate) mod json;
+pub(crate) mod sway;
+
+use std::path::PathBuf;
+
+use crate::wayland_capture::block_on;
+use crate::{Error, Grim, Result};
+
+use json::Value;
+
+/// A source for the name of the focused output.
+pub trait FocusResolver {
+    /// Name of the focused output, or `None` if this resolver cannot tell.
+    ///
+    /// `grim` is the instance asking; resolvers may use its connection and should
+    /// respect its [`timeout`](Grim::timeout).
+    fn focused_output(&mut self, grim: &mut Grim) -> Result<Option<String>>;
+}
+
+/// Asks sway which output is focused, via `GET_OUTPUTS` on its IPC socket.
+#[derive(Debug, Clone)]
+pub struct SwayResolver {
+    socket: PathBuf,
+}
+
+impl SwayResolver {
+    /// Talk to the sway IPC socket at `socket`.
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    /// The socket of the running sway session, or `None` if `SWAYSOCK` is not set.
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("SWAYSOCK").map(Self::new)
+    }
+}
+
+impl FocusResolver for SwayResolver {
+    fn focused_output(&mut self, grim: &mut Grim) -> Result<Option<String>> {
+        let reply = sway::request(&self.socket, sway::GET_OUTPUTS, "", grim.timeout())?;
+        focused_name(&reply, "sway")
+    }
+}
+
+/// Asks Hyprland which monitor is focused, via `j/monitors` on its request socket.
+#[derive(Debug, Clone)]
+pub struct HyprlandResolver {
+    socket: PathBuf,
+}
+
+impl HyprlandResolver {
+    /// Talk to the Hyprland request socket (`.socket.sock`) at `socket`.
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+        }
+    }
+
+    /// The socket of the running Hyprland instance, or `None` if
+    /// `HYPRLAND_INSTANCE_SIGNATURE` is not set.
+    pub fn from_env() -> Option<Self> {
+        hyprland::socket_from_env().map(Self::new)
+    }
+}
+
+impl FocusResolver for HyprlandResolver {
+    fn focused_output(&mut self, grim: &mut Grim) -> Result<Option<String>> {
+        let reply = hyprland::request(&self.socket, "j/monitors", grim.timeout())?;
+        focused_name(&reply, "Hyprland")
+    }
+}
+
+/// Reports the output under the pointer, using an invisible `zwlr_layer_shell_v1`
+/// overlay on every output.
+///
+/// Only the first seat is considered. Compositors that do not send `wl_pointer.enter`
+/// until the pointer moves make this wait for a movement, up to the instance's timeout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PointerResolver;
+
+impl FocusResolver for PointerResolver {
+    fn focused_output(&mut self, grim: &mut Grim) -> Result<Option<String>> {
+        block_on(grim.platform_capture.pointer_output()).map(Some)
+    }
+}
+
+/// Tries several resolvers in order and returns the first answer.
+pub struct ResolverChain {
+    resolvers: Vec<std::boxed::Box<dyn FocusResolver>>,
+}
+
+impl ResolverChain {
+    /// Ask `resolvers` in the given order.
+    pub fn new(resolvers: Vec<std::boxed::Box<dyn FocusResolver>>) -> Self {
+        Self { resolvers }
+    }
+
+    /// The IPC resolvers of the compositors named by the environment, then the pointer.
+    pub fn from_env() -> Self {
+        let mut resolvers: Vec<std::boxed::Box<dyn FocusResolver>> = Vec::new();
+        if let Some(sway) = SwayResolver::from_env() {
+            resolvers.push(std::boxed::Box::new(sway));
+        }
+        if let Some(hyprland) = HyprlandResolver::from_env() {
+            resolvers.push(std::boxed::Box::new(hyprland));
+        }
+        resolvers.push(std::boxed::Box::new(PointerResolver));
+        Self::new(resolvers)
+    }
+}
+
+impl Default for ResolverChain {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+impl FocusResolver for ResolverChain {
+    fn focused_output(&mut self, grim: &mut Grim) -> Result<Option<String>> {
+        for resolver in &mut self.resolvers {
+            if let Some(name) = resolver.focused_output(grim)? {
+                return Ok(Some(name));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Name of the entry with `"focused": true` in a JSON array of outputs, as returned by
+/// sway's `GET_OUTPUTS` and Hyprland's `j/monitors`.
+pub(crate) fn focused_name(reply: &str, compositor: &str) -> Result<Option<String>> {
+    let value = json::parse(reply);
+    let outputs = value
+        .as_ref()
+        .and_then(Value::as_array)
+        .ok_or_else(|| Error::Ipc(format!("{} sent a malformed output list", compositor)))?;
+    Ok(outputs
+        .iter()
+        .find(|output| output.get("focused").and_then(Value::as_bool) == Some(true))
+        .and_then(|output| output.get("name"))
+        .and_then(Value::as_str)
+        .map(str::to_string))
+}
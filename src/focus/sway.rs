@@ -0,0 +1,72 @@
+//! sway's i3-compatible IPC protocol.
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{Error, Result};
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+
+/// `GET_WORKSPACES` message type.
+#[cfg(feature = "ipc")]
+pub(crate) const GET_WORKSPACES: u32 = 1;
+/// `GET_OUTPUTS` message type.
+pub(crate) const GET_OUTPUTS: u32 = 3;
+/// `GET_TREE` message type.
+#[cfg(feature = "ipc")]
+pub(crate) const GET_TREE: u32 = 4;
+
+/// Upper bound for a reply, far above what a real tree needs.
+const MAX_REPLY: usize = 64 * 1024 * 1024;
+
+/// Send one message to the sway socket at `socket` and return the reply payload.
+pub(crate) fn request(
+    socket: &Path,
+    message_type: u32,
+    payload: &str,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    let io_error = |source| Error::IoWithContext {
+        operation: format!("sway IPC request to {}", socket.display()),
+        source,
+    };
+    let mut stream = UnixStream::connect(socket).map_err(io_error)?;
+    stream.set_read_timeout(timeout).map_err(io_error)?;
+    stream.set_write_timeout(timeout).map_err(io_error)?;
+
+    let length = u32::try_from(payload.len())
+        .map_err(|_| Error::Ipc("sway IPC payload is too large".to_string()))?;
+    let mut message = Vec::with_capacity(14 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&length.to_ne_bytes());
+    message.extend_from_slice(&message_type.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message).map_err(io_error)?;
+
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header).map_err(io_error)?;
+    if &header[..6] != MAGIC {
+        return Err(Error::Ipc(
+            "sway IPC reply has a bad magic string".to_string(),
+        ));
+    }
+    let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    let reply_type = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+    if reply_type != message_type {
+        return Err(Error::Ipc(format!(
+            "sway IPC replied with type {} to type {}",
+            reply_type, message_type
+        )));
+    }
+    if length > MAX_REPLY {
+        return Err(Error::Ipc(format!(
+            "sway IPC reply of {} bytes is too large",
+            length
+        )));
+    }
+    let mut reply = vec![0u8; length];
+    stream.read_exact(&mut reply).map_err(io_error)?;
+    String::from_utf8(reply).map_err(|_| Error::Ipc("sway IPC reply is not UTF-8".to_string()))
+}
@@ -0,0 +1,385 @@
+//! Window and workspace geometry from the compositor's IPC.
+//!
+//! Enabled with the `ipc` feature (on by default). Wayland does not tell clients where
+//! other windows are, so screenshots of "the focused window" usually pipe
+//! `swaymsg -t get_tree` through `jq` into `grim -g -`. [`SwayIpc`] and [`HyprlandIpc`]
+//! ask sway's i3-compatible IPC socket and Hyprland's request socket directly and return
+//! [`Box`] values in the global logical coordinate space, ready for
+//! [`Grim::capture_region`](crate::Grim::capture_region).
+//!
+//! [`CompositorIpc::from_env`] picks whichever of the two is running.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use grim_rs::ipc::CompositorIpc;
+//! use grim_rs::{EncodeOptions, Grim};
+//!
+//! let ipc = CompositorIpc::from_env().expect("not running under sway or Hyprland");
+//! if let Some(window) = ipc.focused_window()? {
+//!     let mut grim = Grim::new()?;
+//!     let result = grim.capture_region(window)?;
+//!     grim.save(&result, "window.png", &EncodeOptions::default())?;
+//! }
+//! # Ok::<(), grim_rs::Error>(())
+//! ```
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::focus::json::{self, Value};
+use crate::focus::{focused_name, hyprland, sway};
+use crate::wayland_capture::DEFAULT_TIMEOUT;
+use crate::{Box, Error, Result};
+
+/// Client for sway's IPC socket.
+#[derive(Debug, Clone)]
+pub struct SwayIpc {
+    socket: PathBuf,
+    timeout: Option<Duration>,
+}
+
+impl SwayIpc {
+    /// Talk to the sway IPC socket at `socket`.
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+            timeout: Some(DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// The socket of the running sway session, or `None` if `SWAYSOCK` is not set.
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("SWAYSOCK").map(Self::new)
+    }
+
+    /// Limit for each request; the default is 10 seconds.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Change the limit for each request; `None` waits forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    fn request(&self, message_type: u32) -> Result<Value> {
+        let reply = sway::request(&self.socket, message_type, "", self.timeout)?;
+        json::parse(&reply).ok_or_else(|| Error::Ipc("sway sent malformed JSON".to_string()))
+    }
+
+    /// Name of the focused output.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::IoWithContext` if the socket cannot be reached and `Error::Ipc`
+    /// for a reply that cannot be understood.
+    pub fn focused_output(&self) -> Result<Option<String>> {
+        let reply = sway::request(&self.socket, sway::GET_OUTPUTS, "", self.timeout)?;
+        focused_name(&reply, "sway")
+    }
+
+    /// Geometry of the focused window, including its border and title bar; `None` when
+    /// an empty workspace has focus.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SwayIpc::focused_output`].
+    pub fn focused_window(&self) -> Result<Option<Box>> {
+        let tree = self.request(sway::GET_TREE)?;
+        let Some(node) = find_focused_node(&tree) else {
+            return Ok(None);
+        };
+        match node.get("type").and_then(Value::as_str) {
+            Some("con" | "floating_con") => sway_rect(node).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Geometry of every window on a visible workspace, including borders and title bars.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SwayIpc::focused_output`].
+    pub fn windows(&self) -> Result<Vec<Box>> {
+        let tree = self.request(sway::GET_TREE)?;
+        let mut windows = Vec::new();
+        collect_windows(&tree, &mut windows)?;
+        Ok(windows)
+    }
+
+    /// Geometry of the focused workspace, i.e. of its output minus panels.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SwayIpc::focused_output`].
+    pub fn active_workspace(&self) -> Result<Option<Box>> {
+        let workspaces = self.request(sway::GET_WORKSPACES)?;
+        let workspaces = workspaces
+            .as_array()
+            .ok_or_else(|| Error::Ipc("sway sent a malformed workspace list".to_string()))?;
+        workspaces
+            .iter()
+            .find(|workspace| workspace.get("focused").and_then(Value::as_bool) == Some(true))
+            .map(sway_rect)
+            .transpose()
+    }
+}
+
+/// The node with `"focused": true` in a sway tree, searching tiled and floating children.
+fn find_focused_node(node: &Value) -> Option<&Value> {
+    if node.get("focused").and_then(Value::as_bool) == Some(true) {
+        return Some(node);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node.get(key).and_then(Value::as_array))
+        .flatten()
+        .find_map(find_focused_node)
+}
+
+/// Append the rects of the visible leaf windows under `node` to `windows`.
+fn collect_windows(node: &Value, windows: &mut Vec<Box>) -> Result<()> {
+    let children = ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node.get(key).and_then(Value::as_array))
+        .flatten();
+    let mut leaf = true;
+    for child in children {
+        leaf = false;
+        collect_windows(child, windows)?;
+    }
+    let is_window = matches!(
+        node.get("type").and_then(Value::as_str),
+        Some("con" | "floating_con")
+    );
+    if leaf && is_window && node.get("visible").and_then(Value::as_bool) == Some(true) {
+        windows.push(sway_rect(node)?);
+    }
+    Ok(())
+}
+
+/// The `rect` of a sway node.
+fn sway_rect(node: &Value) -> Result<Box> {
+    let rect = node.get("rect");
+    let field = |name| rect.and_then(|rect| rect.get(name)).and_then(int);
+    match (field("x"), field("y"), field("width"), field("height")) {
+        (Some(x), Some(y), Some(width), Some(height)) => Ok(Box::new(x, y, width, height)),
+        _ => Err(Error::Ipc("sway sent a node without a rect".to_string())),
+    }
+}
+
+/// A JSON number as `i32`, rounded and saturated.
+fn int(value: &Value) -> Option<i32> {
+    value.as_f64().map(|n| n.round() as i32)
+}
+
+/// Client for Hyprland's request socket (`.socket.sock`).
+#[derive(Debug, Clone)]
+pub struct HyprlandIpc {
+    socket: PathBuf,
+    timeout: Option<Duration>,
+}
+
+impl HyprlandIpc {
+    /// Talk to the Hyprland request socket at `socket`.
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+            timeout: Some(DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// The socket of the running Hyprland instance, or `None` if
+    /// `HYPRLAND_INSTANCE_SIGNATURE` is not set.
+    pub fn from_env() -> Option<Self> {
+        hyprland::socket_from_env().map(Self::new)
+    }
+
+    /// Limit for each request; the default is 10 seconds.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Change the limit for each request; `None` waits forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    fn request(&self, command: &str) -> Result<Value> {
+        let reply = hyprland::request(&self.socket, command, self.timeout)?;
+        json::parse(&reply)
+            .ok_or_else(|| Error::Ipc(format!("Hyprland sent malformed JSON for {}", command)))
+    }
+
+    /// Name of the focused monitor.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::IoWithContext` if the socket cannot be reached and `Error::Ipc`
+    /// for a reply that cannot be understood.
+    pub fn focused_output(&self) -> Result<Option<String>> {
+        let reply = hyprland::request(&self.socket, "j/monitors", self.timeout)?;
+        focused_name(&reply, "Hyprland")
+    }
+
+    /// Geometry of the active window, without its border; `None` if no window is active.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`HyprlandIpc::focused_output`].
+    pub fn focused_window(&self) -> Result<Option<Box>> {
+        let window = self.request("j/activewindow")?;
+        // Hyprland answers `{}` when nothing has focus.
+        let (Some(at), Some(size)) = (pair(&window, "at"), pair(&window, "size")) else {
+            return Ok(None);
+        };
+        Ok(Some(Box::new(at.0, at.1, size.0, size.1)))
+    }
+
+    /// Geometry of every mapped window on a workspace shown on some monitor, without
+    /// borders.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`HyprlandIpc::focused_output`].
+    pub fn windows(&self) -> Result<Vec<Box>> {
+        let monitors = self.request("j/monitors")?;
+        let shown: Vec<f64> = monitors
+            .as_array()
+            .ok_or_else(|| Error::Ipc("Hyprland sent a malformed monitor list".to_string()))?
+            .iter()
+            .filter_map(|monitor| monitor.get("activeWorkspace")?.get("id")?.as_f64())
+            .collect();
+        let clients = self.request("j/clients")?;
+        let clients = clients
+            .as_array()
+            .ok_or_else(|| Error::Ipc("Hyprland sent a malformed client list".to_string()))?;
+        Ok(clients
+            .iter()
+            .filter(|client| client.get("mapped").and_then(Value::as_bool) != Some(false))
+            .filter(|client| client.get("hidden").and_then(Value::as_bool) != Some(true))
+            .filter(|client| {
+                let workspace = client
+                    .get("workspace")
+                    .and_then(|workspace| workspace.get("id"))
+                    .and_then(Value::as_f64);
+                workspace.is_some_and(|id| shown.contains(&id))
+            })
+            .filter_map(|client| {
+                let (at, size) = (pair(client, "at")?, pair(client, "size")?);
+                Some(Box::new(at.0, at.1, size.0, size.1))
+            })
+            .collect())
+    }
+
+    /// Geometry of the monitor showing the active workspace.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`HyprlandIpc::focused_output`].
+    pub fn active_workspace(&self) -> Result<Option<Box>> {
+        let workspace = self.request("j/activeworkspace")?;
+        let Some(monitor_name) = workspace.get("monitor").and_then(Value::as_str) else {
+            return Ok(None);
+        };
+        let monitors = self.request("j/monitors")?;
+        let monitor = monitors
+            .as_array()
+            .ok_or_else(|| Error::Ipc("Hyprland sent a malformed monitor list".to_string()))?
+            .iter()
+            .find(|monitor| monitor.get("name").and_then(Value::as_str) == Some(monitor_name))
+            .ok_or_else(|| Error::OutputNotFound(monitor_name.to_string()))?;
+        hyprland_monitor_rect(monitor).map(Some)
+    }
+}
+
+/// A two-element number array such as Hyprland's `at` and `size`.
+fn pair(value: &Value, key: &str) -> Option<(i32, i32)> {
+    match value.get(key)?.as_array()? {
+        [first, second] => Some((int(first)?, int(second)?)),
+        _ => None,
+    }
+}
+
+/// Logical geometry of a Hyprland monitor, whose `width` and `height` are the mode size.
+fn hyprland_monitor_rect(monitor: &Value) -> Result<Box> {
+    let field = |name| monitor.get(name).and_then(Value::as_f64);
+    let (Some(x), Some(y), Some(width), Some(height)) =
+        (field("x"), field("y"), field("width"), field("height"))
+    else {
+        return Err(Error::Ipc(
+            "Hyprland sent a monitor without a geometry".to_string(),
+        ));
+    };
+    let scale = field("scale").filter(|scale| *scale > 0.0).unwrap_or(1.0);
+    // wl_output transforms; the odd ones rotate by 90 or 270 degrees.
+    let rotated = field("transform").is_some_and(|transform| transform as i32 % 2 == 1);
+    let (width, height) = if rotated {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    Ok(Box::new(
+        x.round() as i32,
+        y.round() as i32,
+        (width / scale).round() as i32,
+        (height / scale).round() as i32,
+    ))
+}
+
+/// The IPC of whichever supported compositor is running.
+#[derive(Debug, Clone)]
+pub enum CompositorIpc {
+    Sway(SwayIpc),
+    Hyprland(HyprlandIpc),
+}
+
+impl CompositorIpc {
+    /// sway if `SWAYSOCK` is set, else Hyprland if `HYPRLAND_INSTANCE_SIGNATURE` is set.
+    pub fn from_env() -> Option<Self> {
+        SwayIpc::from_env()
+            .map(Self::Sway)
+            .or_else(|| HyprlandIpc::from_env().map(Self::Hyprland))
+    }
+
+    /// Change the limit for each request; `None` waits forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        match self {
+            Self::Sway(ipc) => ipc.set_timeout(timeout),
+            Self::Hyprland(ipc) => ipc.set_timeout(timeout),
+        }
+    }
+
+    /// See [`SwayIpc::focused_output`] and [`HyprlandIpc::focused_output`].
+    pub fn focused_output(&self) -> Result<Option<String>> {
+        match self {
+            Self::Sway(ipc) => ipc.focused_output(),
+            Self::Hyprland(ipc) => ipc.focused_output(),
+        }
+    }
+
+    /// See [`SwayIpc::focused_window`] and [`HyprlandIpc::focused_window`].
+    pub fn focused_window(&self) -> Result<Option<Box>> {
+        match self {
+            Self::Sway(ipc) => ipc.focused_window(),
+            Self::Hyprland(ipc) => ipc.focused_window(),
+        }
+    }
+
+    /// See [`SwayIpc::windows`] and [`HyprlandIpc::windows`].
+    pub fn windows(&self) -> Result<Vec<Box>> {
+        match self {
+            Self::Sway(ipc) => ipc.windows(),
+            Self::Hyprland(ipc) => ipc.windows(),
+        }
+    }
+
+    /// See [`SwayIpc::active_workspace`] and [`HyprlandIpc::active_workspace`].
+    pub fn active_workspace(&self) -> Result<Option<Box>> {
+        match self {
+            Self::Sway(ipc) => ipc.active_workspace(),
+            Self::Hyprland(ipc) => ipc.active_workspace(),
+        }
+    }
+}
@@ -3,7 +3,7 @@
 //! A pure Rust implementation of the grim screenshot utility for Wayland.
 //!
 //! This library provides a simple interface for taking screenshots on Wayland
-//! compositors that support the `wlr-screencopy` protocol.
+//! compositors that support the `ext-image-copy-capture` or `wlr-screencopy` protocol.
 //!
 //! ## Features
 //!
@@ -11,13 +11,13 @@
 //! - Capture specific output by name
 //! - Capture specific region
 //! - Capture multiple outputs with different parameters
-//! - Save screenshots as PNG or JPEG
-//! - Get screenshot data as PNG or JPEG bytes
+//! - Save screenshots as PNG, JPEG, WebP, QOI, AVIF or JPEG XL with [`Grim::save`]
+//! - Encode screenshots to any writer with [`Grim::encode`]
 //!
 //! ## Example
 //!
 //! ```rust,no_run
-//! use grim_rs::Grim;
+//! use grim_rs::{EncodeOptions, Grim};
 //! use chrono::Local;
 //!
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -26,20 +26,42 @@
 //!
 //! // Generate timestamped filename (like grim-rs does by default)
 //! let filename = format!("{}_grim.png", Local::now().format("%Y%m%d_%Hh%Mm%Ss"));
-//! grim.save_png(result.data(), result.width(), result.height(), &filename)?;
+//! grim.save(&result, &filename, &EncodeOptions::default())?;
 //! # Ok(())
 //! # }
 //! ```
 
+#[cfg(feature = "async")]
+pub mod async_capture;
+pub mod encode;
 pub mod error;
+pub mod focus;
 pub mod geometry;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+#[cfg(feature = "select")]
+pub mod select;
 
+#[cfg(feature = "testing")]
+pub mod testing;
 mod wayland_capture;
 
+pub use encode::{EncodeOptions, ImageFormat};
 pub use error::{Error, Result};
 pub use geometry::Box;
 
-use wayland_capture::WaylandCapture as PlatformCapture;
+pub use wayland_capture::{CancellationToken, FrameView, OutputStream, OutputWatch};
+
+use encode::{Encoder, ImageRef};
+use wayland_capture::{block_on, WaylandCapture as PlatformCapture};
+
+/// AV1 encoder speed used for AVIF when [`EncodeOptions::speed`] is not set, on a scale
+/// from 1 (slowest, smallest files) to 10.
+pub const AVIF_DEFAULT_SPEED: u8 = 6;
+
+/// JPEG XL effort used when [`EncodeOptions::effort`] is not set, on a scale from 1
+/// (fastest) to 9 (smallest files).
+pub const JXL_DEFAULT_EFFORT: u8 = 3;
 
 /// Result of a screenshot capture operation.
 ///
@@ -50,19 +72,125 @@ pub struct CaptureResult {
     ///
     /// Each pixel is represented by 4 bytes in RGBA format (Red, Green, Blue, Alpha).
     data: Vec<u8>,
+    /// RGBA image data with 16 bits per channel, kept for buffers with more than 8 bits
+    /// per channel (10-bit and 16-bit formats).
+    data16: Option<Vec<u16>>,
     /// Width of the captured image in pixels.
     width: u32,
     /// Height of the captured image in pixels.
     height: u32,
+    /// Presentation time reported by the compositor (`CLOCK_MONOTONIC`), if known.
+    timestamp: Option<std::time::Duration>,
+    /// Name of the output the image was captured from.
+    ///
+    /// `None` for window captures and for images composited from several outputs.
+    output_name: Option<String>,
+    /// Pixel format of the buffer the compositor copied into, before RGBA conversion.
+    format: Option<PixelFormat>,
+    /// Output transform that was undone to produce upright pixels.
+    transform: Transform,
+    /// Whether the compositor delivered the buffer upside down (and it was flipped back).
+    y_invert: bool,
+    /// Damaged regions reported by the compositor, in image coordinates.
+    damage: Vec<Box>,
 }
 
 impl CaptureResult {
     pub fn new(data: Vec<u8>, width: u32, height: u32) -> Self {
         Self {
             data,
+            data16: None,
             width,
             height,
+            timestamp: None,
+            output_name: None,
+            format: None,
+            transform: Transform::Normal,
+            y_invert: false,
+            damage: Vec::new(),
+        }
+    }
+
+    /// Convert a raw buffer, as a compositor delivers it, into an upright RGBA image.
+    ///
+    /// Converts the channels to RGBA, undoes `transform` and flips the rows back when
+    /// `y_invert` is set, all in a single pass over the pixels. This is what the capture
+    /// methods do with every frame and what [`FrameView::to_rgba`] does with a borrowed one.
+    /// The result records `format`, `transform` and `y_invert` as its metadata, and keeps
+    /// 16-bit data (see [`CaptureResult::data16`]) for high bit depth formats.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Pixel bytes in `format`, `stride` bytes per row
+    /// * `width` - Buffer width in pixels
+    /// * `height` - Buffer height in pixels
+    /// * `stride` - Number of bytes between the starts of two consecutive rows
+    /// * `format` - Pixel format of `data`
+    /// * `transform` - Output transform to undo
+    /// * `y_invert` - Whether the rows are stored bottom-up
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnsupportedFormat` for [`PixelFormat::Other`], and
+    /// `Error::BufferCreation` if `stride` is shorter than a row or `data` is too short for
+    /// `height` rows.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use grim_rs::{CaptureResult, PixelFormat, Transform};
+    ///
+    /// // One 2x1 row of Xrgb8888 pixels (B, G, R, X in memory), rotated by 90 degrees.
+    /// let raw = [1, 2, 3, 0, 4, 5, 6, 0];
+    /// let image =
+    ///     CaptureResult::from_raw(&raw, 2, 1, 8, PixelFormat::Xrgb8888, Transform::Rotated90, false)?;
+    ///
+    /// assert_eq!((image.width(), image.height()), (1, 2));
+    /// assert_eq!(image.data(), &[3, 2, 1, 255, 6, 5, 4, 255]);
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn from_raw(
+        data: &[u8],
+        width: u32,
+        height: u32,
+        stride: u32,
+        format: PixelFormat,
+        transform: Transform,
+        y_invert: bool,
+    ) -> Result<Self> {
+        let bytes_per_pixel = format
+            .bytes_per_pixel()
+            .ok_or_else(|| Error::UnsupportedFormat(format!("{:?}", format)))?;
+        let row_len = width as usize * bytes_per_pixel as usize;
+        if (stride as usize) < row_len {
+            return Err(Error::BufferCreation(format!(
+                "stride {} is shorter than a row of {} pixels",
+                stride, width
+            )));
         }
+        let needed = match height as usize {
+            0 => 0,
+            rows => (rows - 1) * stride as usize + row_len,
+        };
+        if data.len() < needed {
+            return Err(Error::BufferCreation(format!(
+                "buffer of {} bytes is too short for a {}x{} frame with stride {}",
+                data.len(),
+                width,
+                height,
+                stride
+            )));
+        }
+
+        let frame = wayland_capture::process_frame(
+            data, width, height, stride, format, transform, y_invert,
+        )?;
+        let mut result = Self::new(frame.data, frame.width, frame.height);
+        result.data16 = frame.data16;
+        result.format = Some(format);
+        result.transform = transform;
+        result.y_invert = y_invert;
+        Ok(result)
     }
 
     pub fn data(&self) -> &[u8] {
@@ -80,19 +208,188 @@ impl CaptureResult {
     pub fn into_data(self) -> Vec<u8> {
         self.data
     }
+
+    /// RGBA image data with 16 bits per channel, if the capture has more than 8 bits of
+    /// precision.
+    ///
+    /// Present when the compositor delivered a 10-bit or 16-bit format (see
+    /// [`PixelFormat::is_high_bit_depth`]); [`CaptureResult::data`] then holds the same
+    /// image reduced to 8 bits. Scaled and composited images only carry 8-bit data.
+    /// [`Grim::encode`] and [`Grim::save`] write it as a 16-bit PNG to keep the full
+    /// precision.
+    pub fn data16(&self) -> Option<&[u16]> {
+        self.data16.as_deref()
+    }
+
+    /// Presentation time of the captured frame, if the compositor reported one.
+    ///
+    /// The clock is `CLOCK_MONOTONIC`, so it can be compared with input event times.
+    pub fn timestamp(&self) -> Option<std::time::Duration> {
+        self.timestamp
+    }
+
+    /// Name of the output the image was captured from, if it came from a single output.
+    pub fn output_name(&self) -> Option<&str> {
+        self.output_name.as_deref()
+    }
+
+    /// Pixel format the compositor wrote into the capture buffer, if known.
+    pub fn format(&self) -> Option<PixelFormat> {
+        self.format
+    }
+
+    /// Output transform that was applied to produce upright pixels.
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Whether the buffer was Y-inverted by the compositor and flipped back.
+    pub fn y_invert(&self) -> bool {
+        self.y_invert
+    }
+
+    /// Damaged regions reported for this frame, in image coordinates.
+    ///
+    /// Empty if the compositor did not report damage.
+    pub fn damage(&self) -> &[Box] {
+        &self.damage
+    }
+}
+
+/// Orientation of an output relative to its buffer, as in `wl_output.transform`.
+///
+/// Rotations are counter-clockwise, and flipped variants mirror around the vertical axis
+/// before rotating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transform {
+    #[default]
+    Normal,
+    Rotated90,
+    Rotated180,
+    Rotated270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+
+/// `wl_shm` pixel format of a captured buffer.
+///
+/// Names follow the DRM/`wl_shm` convention: channels are listed from the most significant
+/// byte of a little-endian word, so `Xrgb8888` is stored as `B, G, R, X` in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PixelFormat {
+    Argb8888,
+    Xrgb8888,
+    Abgr8888,
+    Xbgr8888,
+    /// 24 bits per pixel, stored as `B, G, R` in memory.
+    Rgb888,
+    /// 24 bits per pixel, stored as `R, G, B` in memory.
+    Bgr888,
+    /// 16 bits per pixel, stored as a little-endian `R:G:B 5:6:5` word.
+    Rgb565,
+    /// 16 bits per pixel, stored as a little-endian `B:G:R 5:6:5` word.
+    Bgr565,
+    /// 10 bits per color channel, stored as a little-endian `x:R:G:B 2:10:10:10` word.
+    Xrgb2101010,
+    Argb2101010,
+    Xbgr2101010,
+    Abgr2101010,
+    /// 16 bits per channel, stored as little-endian `R, G, B, x` words.
+    Xbgr16161616,
+    Abgr16161616,
+    /// Half-float channels, stored as little-endian `R, G, B, x` binary16 values.
+    Xbgr16161616f,
+    Abgr16161616f,
+    /// Any other `wl_shm` format code.
+    Other(u32),
+}
+
+impl PixelFormat {
+    /// Size of one pixel in bytes, or `None` for formats grim-rs does not understand.
+    pub fn bytes_per_pixel(&self) -> Option<u32> {
+        match self {
+            Self::Argb8888
+            | Self::Xrgb8888
+            | Self::Abgr8888
+            | Self::Xbgr8888
+            | Self::Xrgb2101010
+            | Self::Argb2101010
+            | Self::Xbgr2101010
+            | Self::Abgr2101010 => Some(4),
+            Self::Xbgr16161616 | Self::Abgr16161616 | Self::Xbgr16161616f | Self::Abgr16161616f => {
+                Some(8)
+            }
+            Self::Rgb888 | Self::Bgr888 => Some(3),
+            Self::Rgb565 | Self::Bgr565 => Some(2),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// Whether the format has more than 8 bits per color channel.
+    ///
+    /// Captures in these formats also carry [`CaptureResult::data16`].
+    pub fn is_high_bit_depth(&self) -> bool {
+        matches!(
+            self,
+            Self::Xrgb2101010
+                | Self::Argb2101010
+                | Self::Xbgr2101010
+                | Self::Abgr2101010
+                | Self::Xbgr16161616
+                | Self::Abgr16161616
+                | Self::Xbgr16161616f
+                | Self::Abgr16161616f
+        )
+    }
+}
+
+/// Wayland protocol used to copy output contents.
+///
+/// Most compositors advertise at least one of these. By default ([`CaptureBackend::Auto`])
+/// the standardized `ext-image-copy-capture-v1` protocol is preferred and
+/// `wlr-screencopy-unstable-v1` is used as a fallback, matching upstream grim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureBackend {
+    /// Prefer `ext-image-copy-capture-v1`, fall back to `wlr-screencopy-unstable-v1`.
+    #[default]
+    Auto,
+    /// Force `zwlr_screencopy_manager_v1`.
+    WlrScreencopy,
+    /// Force `ext_image_copy_capture_manager_v1` together with
+    /// `ext_output_image_capture_source_manager_v1`.
+    ExtImageCopyCapture,
 }
 
 /// Information about a display output.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Output {
     /// Name of the output (e.g., "eDP-1", "HDMI-A-1").
     name: String,
     /// Geometry of the output (position and size).
     geometry: Box,
+    /// Position from `wl_output.geometry` and size of the current mode after the transform.
+    physical_geometry: Box,
+    /// Size of the current mode, before the transform.
+    mode_size: (i32, i32),
     /// Scale factor of the output (e.g., 1 for normal DPI, 2 for HiDPI).
     scale: i32,
+    /// Ratio of physical to logical width, fractional with fractional scaling.
+    logical_scale: f64,
+    /// Whether the logical geometry was derived from the mode and `scale` instead of
+    /// being reported through xdg-output.
+    logical_scale_inferred: bool,
+    transform: Transform,
     /// Description of the output (e.g., monitor model, manufacturer info).
     description: Option<String>,
+    make: String,
+    model: String,
+    /// Physical size in millimeters, `None` when the compositor reports zero.
+    physical_size_mm: Option<(i32, i32)>,
+    /// Refresh rate of the current mode in mHz.
+    refresh_mhz: Option<u32>,
 }
 
 impl Output {
@@ -100,17 +397,123 @@ impl Output {
         &self.name
     }
 
+    /// Position and size in the global compositor space, in logical pixels.
     pub fn geometry(&self) -> &Box {
         &self.geometry
     }
 
+    /// Position and size in physical pixels: the current mode, rotated by the transform.
+    pub fn physical_geometry(&self) -> &Box {
+        &self.physical_geometry
+    }
+
+    /// Width and height of the current mode in buffer pixels, before the transform.
+    pub fn mode_size(&self) -> (i32, i32) {
+        self.mode_size
+    }
+
+    /// Integer scale factor from `wl_output.scale`.
     pub fn scale(&self) -> i32 {
         self.scale
     }
 
+    /// Effective scale, physical width over logical width; fractional when the
+    /// compositor uses fractional scaling.
+    pub fn logical_scale(&self) -> f64 {
+        self.logical_scale
+    }
+
+    /// Whether [`Output::geometry`] was inferred from the mode, transform and integer
+    /// scale because the compositor does not support xdg-output.
+    pub fn logical_scale_inferred(&self) -> bool {
+        self.logical_scale_inferred
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
     pub fn description(&self) -> Option<&str> {
         self.description.as_deref()
     }
+
+    /// Manufacturer from `wl_output.geometry` (may be empty).
+    pub fn make(&self) -> &str {
+        &self.make
+    }
+
+    /// Model from `wl_output.geometry` (may be empty).
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Physical width and height in millimeters, `None` if unknown (e.g. projectors).
+    pub fn physical_size_mm(&self) -> Option<(i32, i32)> {
+        self.physical_size_mm
+    }
+
+    /// Refresh rate of the current mode in mHz, `None` if unknown.
+    pub fn refresh_mhz(&self) -> Option<u32> {
+        self.refresh_mhz
+    }
+
+    /// Horizontal pixel density in dots per inch, from the mode width and the physical
+    /// width. `None` if the physical size is unknown.
+    pub fn dpi(&self) -> Option<f64> {
+        let (width_mm, _) = self.physical_size_mm?;
+        Some(f64::from(self.mode_size.0) * 25.4 / f64::from(width_mm))
+    }
+}
+
+/// Change to the set of outputs, yielded by [`OutputWatch`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputEvent {
+    /// A monitor was connected or enabled.
+    Added(Output),
+    /// A monitor was disconnected or disabled; carries its last known state.
+    Removed(Output),
+    /// Name, geometry, scale or description of a monitor changed, e.g. after a mode
+    /// change, rotation or rescale.
+    Changed { before: Output, after: Output },
+}
+
+/// A toplevel window advertised through `ext-foreign-toplevel-list-v1`.
+///
+/// Obtained from [`Grim::list_toplevels`] and passed to [`Grim::capture_toplevel`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toplevel {
+    /// Compositor-assigned identifier, unique for the lifetime of the window.
+    identifier: String,
+    /// Window title (may be empty).
+    title: String,
+    /// Application ID (may be empty).
+    app_id: String,
+}
+
+impl Toplevel {
+    pub fn new(
+        identifier: impl Into<String>,
+        title: impl Into<String>,
+        app_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            identifier: identifier.into(),
+            title: title.into(),
+            app_id: app_id.into(),
+        }
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn app_id(&self) -> &str {
+        &self.app_id
+    }
 }
 
 /// Parameters for capturing a specific output.
@@ -144,6 +547,15 @@ pub struct CaptureParameters {
     ///
     /// If `Some(scale)`, the output image will be scaled accordingly.
     scale: Option<f64>,
+    /// Whether to try copying the frame through a linux-dmabuf buffer.
+    ///
+    /// Only used by the wlr-screencopy backend. If the compositor does not offer a
+    /// dmabuf buffer or the import fails, the capture falls back to `wl_shm`.
+    dmabuf: bool,
+    /// Wall-clock limit for capturing this output.
+    ///
+    /// If `None`, the timeout of the [`Grim`] instance applies.
+    timeout: Option<std::time::Duration>,
 }
 
 impl CaptureParameters {
@@ -156,6 +568,8 @@ impl CaptureParameters {
             region: None,
             overlay_cursor: false,
             scale: None,
+            dmabuf: false,
+            timeout: None,
         }
     }
 
@@ -177,6 +591,28 @@ impl CaptureParameters {
         self
     }
 
+    /// Sets whether to copy the frame through a linux-dmabuf buffer when possible.
+    ///
+    /// The dmabuf is allocated in system memory via `/dev/udmabuf` and read back on
+    /// the CPU, so the result is identical to a `wl_shm` capture. Falls back to `wl_shm`
+    /// if the compositor or the kernel cannot provide a dmabuf.
+    ///
+    /// Only the wlr-screencopy backend uses dmabufs; the ext-image-copy-capture backend
+    /// ignores this setting and always copies through `wl_shm`.
+    pub fn dmabuf(mut self, dmabuf: bool) -> Self {
+        self.dmabuf = dmabuf;
+        self
+    }
+
+    /// Sets how long to wait for the compositor before failing with [`Error::Timeout`].
+    ///
+    /// Overrides [`Grim::set_timeout`] for this output. When several outputs are captured
+    /// in one batch, their frames are in flight together and the shortest timeout applies.
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Returns the output name.
     pub fn output_name(&self) -> &str {
         &self.output_name
@@ -196,6 +632,16 @@ impl CaptureParameters {
     pub fn scale_factor(&self) -> Option<f64> {
         self.scale
     }
+
+    /// Returns whether the dmabuf copy path is requested.
+    pub fn dmabuf_enabled(&self) -> bool {
+        self.dmabuf
+    }
+
+    /// Returns the timeout, if set.
+    pub fn timeout_duration(&self) -> Option<std::time::Duration> {
+        self.timeout
+    }
 }
 
 /// Result of capturing multiple outputs.
@@ -232,12 +678,117 @@ impl MultiOutputCaptureResult {
     }
 }
 
+/// Parameters for [`Grim::stream_output`].
+///
+/// By default the whole output is streamed without the cursor.
+#[derive(Debug, Clone, Default)]
+pub struct StreamParameters {
+    /// Optional region within the output to stream, in output-local logical coordinates.
+    ///
+    /// If `None`, the entire output is streamed.
+    region: Option<Box>,
+    /// Whether to include the cursor in each frame.
+    overlay_cursor: bool,
+    /// Limit on the whole of each frame, including the wait for damage.
+    frame_timeout: Option<std::time::Duration>,
+}
+
+impl StreamParameters {
+    /// Creates parameters that stream the entire output without the cursor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the region to stream within the output.
+    pub fn region(mut self, region: Box) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Sets whether to include the cursor in each frame.
+    pub fn overlay_cursor(mut self, overlay_cursor: bool) -> Self {
+        self.overlay_cursor = overlay_cursor;
+        self
+    }
+
+    /// Sets how long each frame may take, including the wait for the compositor to
+    /// redraw something, before the stream fails with [`Error::Timeout`].
+    ///
+    /// Without it only the buffer negotiation is bounded, by [`Grim::set_timeout`], and
+    /// a stream of an output that never changes waits forever.
+    pub fn frame_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.frame_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the region, if set.
+    pub fn region_ref(&self) -> Option<&Box> {
+        self.region.as_ref()
+    }
+
+    /// Returns whether cursor overlay is enabled.
+    pub fn overlay_cursor_enabled(&self) -> bool {
+        self.overlay_cursor
+    }
+
+    /// Returns the per-frame timeout, if set.
+    pub fn frame_timeout_duration(&self) -> Option<std::time::Duration> {
+        self.frame_timeout
+    }
+}
+
+/// A single frame yielded by [`OutputStream`].
+#[derive(Debug, Clone)]
+pub struct StreamFrame {
+    /// The captured image.
+    capture: CaptureResult,
+    /// Regions that changed since the previous frame, in image coordinates.
+    ///
+    /// The first frame of a stream is usually reported as fully damaged.
+    damage: Vec<Box>,
+    /// Presentation time of the frame (`CLOCK_MONOTONIC`), as reported by the compositor.
+    timestamp: std::time::Duration,
+}
+
+impl StreamFrame {
+    /// Creates a new StreamFrame.
+    pub fn new(capture: CaptureResult, damage: Vec<Box>, timestamp: std::time::Duration) -> Self {
+        Self {
+            capture,
+            damage,
+            timestamp,
+        }
+    }
+
+    /// Returns the captured image.
+    pub fn capture(&self) -> &CaptureResult {
+        &self.capture
+    }
+
+    /// Returns the regions that changed since the previous frame.
+    pub fn damage(&self) -> &[Box] {
+        &self.damage
+    }
+
+    /// Returns the presentation timestamp of the frame.
+    pub fn timestamp(&self) -> std::time::Duration {
+        self.timestamp
+    }
+
+    /// Consumes self and returns the captured image.
+    pub fn into_capture(self) -> CaptureResult {
+        self.capture
+    }
+}
+
 /// Main interface for taking screenshots.
 ///
 /// Provides methods for capturing screenshots of the entire screen,
 /// specific outputs, regions, or multiple outputs with different parameters.
 pub struct Grim {
     platform_capture: PlatformCapture,
+    /// Encoders registered with [`Grim::register_encoder`].
+    encoders: std::collections::HashMap<ImageFormat, std::boxed::Box<dyn Encoder>>,
 }
 
 impl Grim {
@@ -264,142 +815,218 @@ impl Grim {
     /// # }
     /// ```
     pub fn new() -> Result<Self> {
-        let platform_capture = PlatformCapture::new()?;
-        Ok(Self { platform_capture })
+        Self::with_backend(CaptureBackend::Auto)
     }
 
-    /// Get information about available display outputs.
+    /// Create a new Grim instance using a specific capture protocol.
     ///
-    /// Returns a list of all connected display outputs with their names,
-    /// geometries, and scale factors.
+    /// Use [`CaptureBackend::Auto`] to let grim-rs pick the best protocol the
+    /// compositor offers, or force one of the concrete backends.
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - No outputs are available
-    /// - Failed to retrieve output information
+    /// - Cannot connect to the Wayland compositor
+    /// - The requested capture protocol is not advertised by the compositor
+    /// - `wl_shm` is not available
     ///
     /// # Example
     ///
     /// ```rust,no_run
-    /// use grim_rs::Grim;
-    ///
-    /// let mut grim = Grim::new()?;
-    /// let outputs = grim.get_outputs()?;
+    /// use grim_rs::{CaptureBackend, Grim};
     ///
-    /// for output in outputs {
-    ///     println!("Output: {} ({}x{})", output.name(), output.geometry().width(), output.geometry().height());
-    /// }
+    /// let grim = Grim::with_backend(CaptureBackend::WlrScreencopy)?;
+    /// assert_eq!(grim.backend(), CaptureBackend::WlrScreencopy);
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
-    pub fn get_outputs(&mut self) -> Result<Vec<Output>> {
-        self.platform_capture.get_outputs()
+    pub fn with_backend(backend: CaptureBackend) -> Result<Self> {
+        let platform_capture = PlatformCapture::new(backend)?;
+        Ok(Self {
+            platform_capture,
+            encoders: std::collections::HashMap::new(),
+        })
     }
 
-    /// Capture the entire screen (all outputs).
+    /// Create a new Grim instance on an existing Wayland connection.
     ///
-    /// Captures a screenshot that includes all connected display outputs,
-    /// arranged according to their physical positions.
+    /// The capture protocol is chosen as with [`Grim::new`]. Grim uses its own event
+    /// queues on the connection, so the caller's queues and objects are left untouched.
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - No outputs are available
-    /// - Failed to capture the screen
-    /// - Buffer creation failed
+    /// - No capture protocol is advertised by the compositor
+    /// - `wl_shm` is not available
     ///
     /// # Example
     ///
     /// ```rust,no_run
     /// use grim_rs::Grim;
+    /// use wayland_client::Connection;
     ///
-    /// let mut grim = Grim::new()?;
+    /// let connection = Connection::connect_to_env().expect("no Wayland compositor");
+    /// let mut grim = Grim::from_connection(connection.clone())?;
     /// let result = grim.capture_all()?;
-    /// println!("Captured screen: {}x{}", result.width(), result.height());
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
-    pub fn capture_all(&mut self) -> Result<CaptureResult> {
-        self.platform_capture.capture_all()
+    pub fn from_connection(connection: wayland_client::Connection) -> Result<Self> {
+        Self::from_connection_with_backend(connection, CaptureBackend::Auto)
     }
 
-    /// Capture the entire screen (all outputs) with specified scale factor.
+    /// Create a new Grim instance on an existing Wayland connection, using a specific
+    /// capture protocol.
     ///
-    /// Captures a screenshot that includes all connected display outputs,
-    /// arranged according to their physical positions, with a specified scale factor.
+    /// # Errors
     ///
-    /// # Arguments
+    /// Returns an error if:
+    /// - The requested capture protocol is not advertised by the compositor
+    /// - `wl_shm` is not available
+    pub fn from_connection_with_backend(
+        connection: wayland_client::Connection,
+        backend: CaptureBackend,
+    ) -> Result<Self> {
+        let platform_capture = PlatformCapture::from_connection(connection, backend)?;
+        Ok(Self {
+            platform_capture,
+            encoders: std::collections::HashMap::new(),
+        })
+    }
+
+    /// Create a new Grim instance from the globals an application already collected.
     ///
-    /// * `scale` - Scale factor for the output image
+    /// For applications that set up their connection with
+    /// [`wayland_client::globals::registry_queue_init`]: the globals grim-rs needs are
+    /// bound through the application's registry, so no second connection or registry is
+    /// created up front. The new objects are dispatched on grim-rs' own event queues, so
+    /// the application's queue and state are never touched, and captures can be started
+    /// from inside the application's event loop. The capture protocol is chosen as with
+    /// [`Grim::new`].
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - No outputs are available
-    /// - Failed to capture the screen
-    /// - Buffer creation failed
+    /// - No capture protocol is in `globals`
+    /// - `wl_shm` is not in `globals`
     ///
     /// # Example
     ///
     /// ```rust,no_run
     /// use grim_rs::Grim;
+    /// use wayland_client::globals::{registry_queue_init, GlobalListContents};
+    /// use wayland_client::protocol::wl_registry::{self, WlRegistry};
+    /// use wayland_client::{Connection, Dispatch, QueueHandle};
+    ///
+    /// struct App;
+    ///
+    /// impl Dispatch<WlRegistry, GlobalListContents> for App {
+    ///     fn event(
+    ///         _: &mut Self,
+    ///         _: &WlRegistry,
+    ///         _: wl_registry::Event,
+    ///         _: &GlobalListContents,
+    ///         _: &Connection,
+    ///         _: &QueueHandle<Self>,
+    ///     ) {
+    ///     }
+    /// }
     ///
-    /// let mut grim = Grim::new()?;
-    /// let result = grim.capture_all_with_scale(1.0)?;
-    /// println!("Captured screen: {}x{}", result.width(), result.height());
+    /// let connection = Connection::connect_to_env().expect("no Wayland compositor");
+    /// let (globals, _queue) = registry_queue_init::<App>(&connection).expect("registry");
+    /// let mut grim = Grim::from_globals(&connection, &globals)?;
+    /// let result = grim.capture_all()?;
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
-    pub fn capture_all_with_scale(&mut self, scale: f64) -> Result<CaptureResult> {
-        self.platform_capture.capture_all_with_scale(scale)
+    pub fn from_globals(
+        connection: &wayland_client::Connection,
+        globals: &wayland_client::globals::GlobalList,
+    ) -> Result<Self> {
+        let platform_capture =
+            PlatformCapture::from_globals(connection, globals, CaptureBackend::Auto)?;
+        Ok(Self {
+            platform_capture,
+            encoders: std::collections::HashMap::new(),
+        })
     }
 
-    /// Capture a specific output by name.
+    /// Capture protocol selected for this instance.
     ///
-    /// Captures a screenshot of the specified display output.
+    /// Never returns [`CaptureBackend::Auto`]; the automatic choice is resolved
+    /// when the instance is created.
+    pub fn backend(&self) -> CaptureBackend {
+        self.platform_capture.backend()
+    }
+
+    /// Wall-clock limit for each capture, `Some(10s)` by default.
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        self.platform_capture.timeout()
+    }
+
+    /// Set how long a capture may wait for the compositor, or `None` to wait forever.
     ///
-    /// # Arguments
+    /// The timeout covers one call: refreshing the output list and copying the frames.
+    /// When it expires, pending frames and buffers are destroyed and the call fails with
+    /// [`Error::Timeout`]. Streams only apply it to the buffer negotiation of each frame,
+    /// and async captures ignore it in favour of the runtime's own timeouts.
     ///
-    /// * `output_name` - Name of the output to capture (e.g., "eDP-1", "HDMI-A-1")
+    /// # Example
     ///
-    /// # Errors
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    /// use std::time::Duration;
     ///
-    /// Returns an error if:
-    /// - The specified output is not found
-    /// - Failed to capture the output
-    /// - Buffer creation failed
+    /// let mut grim = Grim::new()?;
+    /// grim.set_timeout(Some(Duration::from_secs(2)));
+    /// let result = grim.capture_all()?;
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.platform_capture.set_timeout(timeout);
+    }
+
+    /// Free the shared-memory pool kept between captures.
+    ///
+    /// Captures reuse one pool that only grows, so a long-running program that captured a
+    /// large output once keeps that memory. The next capture allocates a new pool.
+    pub fn release_shm_pool(&mut self) {
+        self.platform_capture.release_shm_pool();
+    }
+
+    /// Abort captures when `token` is cancelled, or remove the token with `None`.
+    ///
+    /// A capture that is waiting on the compositor, blocking or async, returns
+    /// [`Error::Cancelled`] as soon as [`CancellationToken::cancel`] is called from
+    /// another thread, and destroys its pending frames.
     ///
     /// # Example
     ///
     /// ```rust,no_run
-    /// use grim_rs::Grim;
+    /// use grim_rs::{CancellationToken, Error, Grim};
     ///
+    /// let token = CancellationToken::new()?;
     /// let mut grim = Grim::new()?;
-    /// // Get available outputs first
-    /// let outputs = grim.get_outputs()?;
-    /// if let Some(output) = outputs.first() {
-    ///     let result = grim.capture_output(output.name())?;
-    ///     println!("Captured output: {}x{}", result.width(), result.height());
+    /// grim.set_cancellation_token(Some(token.clone()));
+    ///
+    /// std::thread::spawn(move || token.cancel());
+    /// match grim.capture_all() {
+    ///     Err(Error::Cancelled) => println!("cancelled"),
+    ///     other => println!("{:?}", other.map(|result| result.width())),
     /// }
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
-    pub fn capture_output(&mut self, output_name: &str) -> Result<CaptureResult> {
-        self.platform_capture.capture_output(output_name)
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.platform_capture.set_cancellation_token(token);
     }
 
-    /// Capture a specific output by name with specified scale factor.
-    ///
-    /// Captures a screenshot of the specified display output with a specified scale factor.
-    ///
-    /// # Arguments
+    /// Get information about available display outputs.
     ///
-    /// * `output_name` - Name of the output to capture (e.g., "eDP-1", "HDMI-A-1")
-    /// * `scale` - Scale factor for the output image
+    /// Returns a list of all connected display outputs with their names,
+    /// geometries, and scale factors.
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - The specified output is not found
-    /// - Failed to capture the output
-    /// - Buffer creation failed
+    /// - No outputs are available
+    /// - Failed to retrieve output information
     ///
     /// # Example
     ///
@@ -407,10 +1034,242 @@ impl Grim {
     /// use grim_rs::Grim;
     ///
     /// let mut grim = Grim::new()?;
-    /// // Get available outputs first
     /// let outputs = grim.get_outputs()?;
-    /// if let Some(output) = outputs.first() {
-    ///     let result = grim.capture_output_with_scale(output.name(), 0.5)?;
+    ///
+    /// for output in outputs {
+    ///     println!("Output: {} ({}x{})", output.name(), output.geometry().width(), output.geometry().height());
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn get_outputs(&mut self) -> Result<Vec<Output>> {
+        block_on(self.platform_capture.get_outputs())
+    }
+
+    /// Get the list of toplevel windows.
+    ///
+    /// Uses `ext-foreign-toplevel-list-v1` to enumerate windows with their
+    /// identifier, title and application ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The compositor does not support `ext_foreign_toplevel_list_v1`
+    /// - Failed to retrieve window information
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// for toplevel in grim.list_toplevels()? {
+    ///     println!("{} {} ({})", toplevel.identifier(), toplevel.title(), toplevel.app_id());
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn list_toplevels(&mut self) -> Result<Vec<Toplevel>> {
+        block_on(self.platform_capture.list_toplevels())
+    }
+
+    /// Capture a single toplevel window.
+    ///
+    /// The window contents are captured through
+    /// `ext_foreign_toplevel_image_capture_source_manager_v1`, so the result is
+    /// complete even if the window is occluded or partly off-screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `toplevel` - Window returned by [`Grim::list_toplevels`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The window no longer exists
+    /// - The compositor does not support toplevel capture
+    /// - Failed to capture the window
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// if let Some(toplevel) = grim.list_toplevels()?.first() {
+    ///     let result = grim.capture_toplevel(toplevel)?;
+    ///     println!("Captured window: {}x{}", result.width(), result.height());
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn capture_toplevel(&mut self, toplevel: &Toplevel) -> Result<CaptureResult> {
+        block_on(self.platform_capture.capture_toplevel(toplevel, false))
+    }
+
+    /// Capture a single toplevel window with specified scale factor.
+    ///
+    /// # Arguments
+    ///
+    /// * `toplevel` - Window returned by [`Grim::list_toplevels`]
+    /// * `scale` - Scale factor for the output image
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The window no longer exists
+    /// - The compositor does not support toplevel capture
+    /// - Failed to capture or scale the window
+    pub fn capture_toplevel_with_scale(
+        &mut self,
+        toplevel: &Toplevel,
+        scale: f64,
+    ) -> Result<CaptureResult> {
+        block_on(
+            self.platform_capture
+                .capture_toplevel_with_scale(toplevel, scale, false),
+        )
+    }
+
+    /// Capture a single toplevel window with the cursor painted in, optionally scaled.
+    ///
+    /// # Arguments
+    ///
+    /// * `toplevel` - Window returned by [`Grim::list_toplevels`]
+    /// * `scale` - Scale factor for the output image, `None` to keep the window's size
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The window no longer exists
+    /// - The compositor does not support toplevel capture
+    /// - Failed to capture or scale the window
+    pub fn capture_toplevel_with_cursor(
+        &mut self,
+        toplevel: &Toplevel,
+        scale: Option<f64>,
+    ) -> Result<CaptureResult> {
+        match scale {
+            Some(scale) => block_on(
+                self.platform_capture
+                    .capture_toplevel_with_scale(toplevel, scale, true),
+            ),
+            None => block_on(self.platform_capture.capture_toplevel(toplevel, true)),
+        }
+    }
+
+    /// Capture the entire screen (all outputs).
+    ///
+    /// Captures a screenshot that includes all connected display outputs,
+    /// arranged according to their physical positions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - No outputs are available
+    /// - Failed to capture the screen
+    /// - Buffer creation failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// let result = grim.capture_all()?;
+    /// println!("Captured screen: {}x{}", result.width(), result.height());
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn capture_all(&mut self) -> Result<CaptureResult> {
+        block_on(self.platform_capture.capture_all())
+    }
+
+    /// Capture the entire screen (all outputs) with specified scale factor.
+    ///
+    /// Captures a screenshot that includes all connected display outputs,
+    /// arranged according to their physical positions, with a specified scale factor.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - Scale factor for the output image
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - No outputs are available
+    /// - Failed to capture the screen
+    /// - Buffer creation failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// let result = grim.capture_all_with_scale(1.0)?;
+    /// println!("Captured screen: {}x{}", result.width(), result.height());
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn capture_all_with_scale(&mut self, scale: f64) -> Result<CaptureResult> {
+        block_on(self.platform_capture.capture_all_with_scale(scale))
+    }
+
+    /// Capture a specific output by name.
+    ///
+    /// Captures a screenshot of the specified display output.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_name` - Name of the output to capture (e.g., "eDP-1", "HDMI-A-1")
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The specified output is not found
+    /// - Failed to capture the output
+    /// - Buffer creation failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// // Get available outputs first
+    /// let outputs = grim.get_outputs()?;
+    /// if let Some(output) = outputs.first() {
+    ///     let result = grim.capture_output(output.name())?;
+    ///     println!("Captured output: {}x{}", result.width(), result.height());
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn capture_output(&mut self, output_name: &str) -> Result<CaptureResult> {
+        block_on(self.platform_capture.capture_output(output_name))
+    }
+
+    /// Capture a specific output by name with specified scale factor.
+    ///
+    /// Captures a screenshot of the specified display output with a specified scale factor.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_name` - Name of the output to capture (e.g., "eDP-1", "HDMI-A-1")
+    /// * `scale` - Scale factor for the output image
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The specified output is not found
+    /// - Failed to capture the output
+    /// - Buffer creation failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// // Get available outputs first
+    /// let outputs = grim.get_outputs()?;
+    /// if let Some(output) = outputs.first() {
+    ///     let result = grim.capture_output_with_scale(output.name(), 0.5)?;
     ///     println!("Captured output at 50% scale: {}x{}", result.width(), result.height());
     /// }
     /// # Ok::<(), grim_rs::Error>(())
@@ -420,8 +1279,52 @@ impl Grim {
         output_name: &str,
         scale: f64,
     ) -> Result<CaptureResult> {
-        self.platform_capture
-            .capture_output_with_scale(output_name, scale)
+        block_on(
+            self.platform_capture
+                .capture_output_with_scale(output_name, scale),
+        )
+    }
+
+    /// Capture a specific output without copying or converting the pixels.
+    ///
+    /// Returns a [`FrameView`] that borrows the mapped shm buffer the compositor wrote into.
+    /// The view exposes the raw bytes in their native [`PixelFormat`] together with the
+    /// stride, transform and Y-invert flag, so large frames can be handed to an encoder
+    /// without intermediate full-frame allocations. Call [`FrameView::to_rgba`] to get
+    /// the same result as [`Grim::capture_output`].
+    ///
+    /// # Arguments
+    ///
+    /// * `output_name` - Name of the output to capture (e.g., "eDP-1", "HDMI-A-1")
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The specified output is not found
+    /// - Failed to capture the output
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// let view = grim.capture_output_view("eDP-1")?;
+    /// println!(
+    ///     "{}x{} {:?}, {} bytes per row",
+    ///     view.width(),
+    ///     view.height(),
+    ///     view.format(),
+    ///     view.stride()
+    /// );
+    /// for row in view.rows() {
+    ///     // feed `row` to an encoder that understands the native format
+    ///     let _ = row;
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn capture_output_view(&mut self, output_name: &str) -> Result<FrameView<'_>> {
+        block_on(self.platform_capture.capture_output_view(output_name))
     }
 
     /// Capture a specific region.
@@ -452,7 +1355,7 @@ impl Grim {
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
     pub fn capture_region(&mut self, region: Box) -> Result<CaptureResult> {
-        self.platform_capture.capture_region(region)
+        block_on(self.platform_capture.capture_region(region))
     }
 
     /// Capture a specific region with specified scale factor.
@@ -484,8 +1387,10 @@ impl Grim {
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
     pub fn capture_region_with_scale(&mut self, region: Box, scale: f64) -> Result<CaptureResult> {
-        self.platform_capture
-            .capture_region_with_scale(region, scale)
+        block_on(
+            self.platform_capture
+                .capture_region_with_scale(region, scale),
+        )
     }
 
     /// Capture multiple outputs with different parameters.
@@ -540,28 +1445,405 @@ impl Grim {
         &mut self,
         parameters: Vec<CaptureParameters>,
     ) -> Result<MultiOutputCaptureResult> {
-        self.platform_capture.capture_outputs(parameters)
+        block_on(self.platform_capture.capture_outputs(parameters))
+    }
+
+    /// Capture outputs with scale factor.
+    ///
+    /// Captures screenshots of multiple outputs simultaneously with a specific scale factor.
+    ///
+    /// # Arguments
+    ///
+    /// * `parameters` - Vector of CaptureParameters with scale factors
+    /// * `default_scale` - Default scale factor to use when not specified in parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the outputs can't be captured
+    pub fn capture_outputs_with_scale(
+        &mut self,
+        parameters: Vec<CaptureParameters>,
+        default_scale: f64,
+    ) -> Result<MultiOutputCaptureResult> {
+        block_on(
+            self.platform_capture
+                .capture_outputs_with_scale(parameters, default_scale),
+        )
+    }
+
+    /// Stream frames of a specific output.
+    ///
+    /// Returns an iterator that yields a new frame every time the output content changes.
+    /// A single shm buffer is allocated up front and reused for every frame, and frames
+    /// are requested with `copy_with_damage`, so the compositor only answers once something
+    /// on the output has been redrawn. Each frame carries the damaged regions and the
+    /// presentation timestamp reported by the compositor.
+    ///
+    /// Requires `zwlr_screencopy_manager_v1` version 2 or newer, regardless of the
+    /// selected [`CaptureBackend`].
+    ///
+    /// # Arguments
+    ///
+    /// * `output_name` - Name of the output to stream (e.g., "eDP-1", "HDMI-A-1")
+    /// * `parameters` - Region and cursor options for the stream
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The specified output is not found
+    /// - The region extends outside the output
+    /// - The compositor does not support `copy_with_damage`
+    ///
+    /// Errors while capturing individual frames are yielded by the iterator, which
+    /// ends after the first error.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::{Grim, StreamParameters};
+    ///
+    /// let mut grim = Grim::new()?;
+    /// for frame in grim.stream_output("eDP-1", StreamParameters::new())?.take(10) {
+    ///     let frame = frame?;
+    ///     println!(
+    ///         "Frame at {:?}: {} damaged regions",
+    ///         frame.timestamp(),
+    ///         frame.damage().len()
+    ///     );
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn stream_output(
+        &mut self,
+        output_name: &str,
+        parameters: StreamParameters,
+    ) -> Result<OutputStream<'_>> {
+        block_on(self.platform_capture.stream_output(output_name, parameters))
+    }
+
+    /// Watch for monitors being added, removed or reconfigured.
+    ///
+    /// Returns an iterator whose `next()` blocks until the set of outputs changes and
+    /// yields [`OutputEvent::Added`], [`OutputEvent::Removed`] or [`OutputEvent::Changed`]
+    /// with the old and new [`Output`]. Changes are reported once the compositor has sent
+    /// the complete new state, so a rotation is one `Changed` event rather than one per
+    /// property.
+    ///
+    /// The watch has its own event queue on this instance's connection and does not
+    /// borrow `self`, so it can be moved to another thread while this instance keeps
+    /// capturing. It inherits the cancellation token, which is the way to stop it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the initial list of outputs cannot be retrieved.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::{Grim, OutputEvent};
+    ///
+    /// let grim = Grim::new()?;
+    /// let watch = grim.watch_outputs()?;
+    /// println!("{} outputs", watch.outputs().len());
+    /// for event in watch {
+    ///     match event? {
+    ///         OutputEvent::Added(output) => println!("added {}", output.name()),
+    ///         OutputEvent::Removed(output) => println!("removed {}", output.name()),
+    ///         OutputEvent::Changed { after, .. } => println!("changed {}", after.name()),
+    ///     }
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn watch_outputs(&self) -> Result<OutputWatch> {
+        self.platform_capture.watch_outputs()
+    }
+
+    /// The output the user is working on.
+    ///
+    /// Asks the compositor's IPC when running under sway or Hyprland and otherwise
+    /// reports the output under the pointer; see [`focus::ResolverChain::from_env`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NoFocusedOutput` if no resolver could tell, `Error::OutputNotFound`
+    /// if the reported output is not advertised over Wayland, or the resolver's error.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// println!("working on {}", grim.focused_output()?.name());
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn focused_output(&mut self) -> Result<Output> {
+        self.focused_output_with(&mut focus::ResolverChain::from_env())
     }
 
-    /// Capture outputs with scale factor.
-    ///
-    /// Captures screenshots of multiple outputs simultaneously with a specific scale factor.
-    ///
-    /// # Arguments
-    ///
-    /// * `parameters` - Vector of CaptureParameters with scale factors
-    /// * `default_scale` - Default scale factor to use when not specified in parameters
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if any of the outputs can't be captured
-    pub fn capture_outputs_with_scale(
-        &mut self,
-        parameters: Vec<CaptureParameters>,
-        default_scale: f64,
-    ) -> Result<MultiOutputCaptureResult> {
-        self.platform_capture
-            .capture_outputs_with_scale(parameters, default_scale)
+    /// The output `resolver` reports as focused.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Grim::focused_output`].
+    pub fn focused_output_with(
+        &mut self,
+        resolver: &mut dyn focus::FocusResolver,
+    ) -> Result<Output> {
+        let name = resolver
+            .focused_output(self)?
+            .ok_or(Error::NoFocusedOutput)?;
+        self.get_outputs()?
+            .into_iter()
+            .find(|output| output.name() == name)
+            .ok_or(Error::OutputNotFound(name))
+    }
+
+    /// Capture the output returned by [`Grim::focused_output`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Grim::focused_output`] and [`Grim::capture_output`].
+    pub fn capture_focused_output(&mut self) -> Result<CaptureResult> {
+        let output = self.focused_output()?;
+        self.capture_output(output.name())
+    }
+
+    /// Capture the output `resolver` reports as focused.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Grim::focused_output_with`] and [`Grim::capture_output`].
+    pub fn capture_focused_output_with(
+        &mut self,
+        resolver: &mut dyn focus::FocusResolver,
+    ) -> Result<CaptureResult> {
+        let output = self.focused_output_with(resolver)?;
+        self.capture_output(output.name())
+    }
+
+    /// Let the user select a region, like `slurp`.
+    ///
+    /// Shades every output with a `zwlr_layer_shell_v1` overlay. Dragging selects a
+    /// rectangle whose corners snap to nearby output edges; a click selects the smallest
+    /// of `windows` under the pointer, or else the output. Pass `&[]` to select only
+    /// rectangles and outputs, or window geometry from the compositor, e.g.
+    /// `ipc::CompositorIpc::windows()`. See [`select::Selection`] for the exact rules.
+    ///
+    /// Escape or the right button cancels. The wait for the user is not limited by the
+    /// timeout, but a cancellation token aborts it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SelectionCancelled` if the user cancels, `Error::UnsupportedProtocol`
+    /// without `wl_compositor`, `zwlr_layer_shell_v1` or a seat with a pointer, and
+    /// `Error::Cancelled` if the cancellation token fires.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::{EncodeOptions, Grim};
+    ///
+    /// let mut grim = Grim::new()?;
+    /// let region = grim.select_region(&[])?;
+    /// let result = grim.capture_region(region)?;
+    /// grim.save(&result, "selection.png", &EncodeOptions::default())?;
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    #[cfg(feature = "select")]
+    pub fn select_region(&mut self, windows: &[Box]) -> Result<Box> {
+        block_on(self.platform_capture.select_region(windows.to_vec()))
+    }
+
+    /// Use `encoder` for `format` in [`Grim::encode`], [`Grim::save`] and the deprecated
+    /// PNG and JPEG methods, replacing any built-in or previously registered encoder.
+    ///
+    /// Register a [`ImageFormat::Custom`] format to add a new one; [`Grim::save`] picks it
+    /// for paths with that extension.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::encode::{EncodeOptions, ImageFormat, ImageRef};
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// // Raw RGBA with no header.
+    /// grim.register_encoder(
+    ///     ImageFormat::custom("rgba"),
+    ///     |image: ImageRef<'_>, _: &EncodeOptions, writer: &mut dyn std::io::Write| {
+    ///         Ok(writer.write_all(image.data())?)
+    ///     },
+    /// );
+    /// let result = grim.capture_all()?;
+    /// grim.save(&result, "screenshot.rgba", &EncodeOptions::default())?;
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn register_encoder(&mut self, format: ImageFormat, encoder: impl Encoder + 'static) {
+        self.encoders.insert(format, std::boxed::Box::new(encoder));
+    }
+
+    /// Encode a capture and write it to `writer`.
+    ///
+    /// Uses the format in `options`, or PNG if it has none. Captures with 16-bit data are
+    /// written as 16-bit PNGs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The format's cargo feature is disabled and no encoder is registered for it
+    /// - The capture's data does not match its size
+    /// - Encoding or writing failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::{EncodeOptions, Grim, ImageFormat};
+    ///
+    /// let mut grim = Grim::new()?;
+    /// let result = grim.capture_all()?;
+    /// let options = EncodeOptions::new(ImageFormat::Jpeg).quality(90);
+    /// grim.encode(&result, &options, std::io::stdout().lock())?;
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn encode<W: std::io::Write>(
+        &self,
+        result: &CaptureResult,
+        options: &EncodeOptions,
+        mut writer: W,
+    ) -> Result<()> {
+        let format = options.format_ref().unwrap_or(&ImageFormat::Png);
+        self.encode_image(
+            format,
+            ImageRef::from_capture(result)?,
+            options,
+            &mut writer,
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Encode a capture and save it to `path`.
+    ///
+    /// Uses the format in `options`, or else the one for the file extension: a built-in
+    /// format (see [`ImageFormat::from_extension`]) or a registered custom one. Nothing is
+    /// written if encoding fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `options` has no format and the extension is missing or unknown
+    /// - The format's cargo feature is disabled and no encoder is registered for it
+    /// - The capture's data does not match its size
+    /// - Encoding failed, or the file could not be written
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::{EncodeOptions, Grim};
+    ///
+    /// let mut grim = Grim::new()?;
+    /// let result = grim.capture_all()?;
+    /// grim.save(&result, "screenshot.webp", &EncodeOptions::default().quality(90))?;
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    pub fn save<P: AsRef<std::path::Path>>(
+        &self,
+        result: &CaptureResult,
+        path: P,
+        options: &EncodeOptions,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let format = match options.format_ref() {
+            Some(format) => format.clone(),
+            None => self.format_for_path(path)?,
+        };
+        let mut output = Vec::new();
+        self.encode_image(
+            &format,
+            ImageRef::from_capture(result)?,
+            options,
+            &mut output,
+        )?;
+        write_file(path, &output)
+    }
+
+    /// The built-in or registered format for the extension of `path`.
+    fn format_for_path(&self, path: &std::path::Path) -> Result<ImageFormat> {
+        let Some(extension) = path.extension() else {
+            return Err(encode::unsupported(image::error::ImageFormatHint::Unknown));
+        };
+        let format = extension.to_str().and_then(|extension| {
+            ImageFormat::from_extension(extension).or_else(|| {
+                let custom = ImageFormat::custom(extension);
+                self.encoders.contains_key(&custom).then_some(custom)
+            })
+        });
+        format.ok_or_else(|| {
+            encode::unsupported(image::error::ImageFormatHint::PathExtension(
+                extension.into(),
+            ))
+        })
+    }
+
+    fn encode_image(
+        &self,
+        format: &ImageFormat,
+        image: ImageRef<'_>,
+        options: &EncodeOptions,
+        writer: &mut dyn std::io::Write,
+    ) -> Result<()> {
+        match self.encoders.get(format) {
+            Some(encoder) => encoder.encode(image, options, writer),
+            None => encode::encode_builtin(format, image, options, writer),
+        }
+    }
+
+    /// Encode raw RGBA `data` in the format of `options`, which always has one.
+    fn encode_data(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>> {
+        let format = options.format_ref().unwrap_or(&ImageFormat::Png);
+        let mut output = Vec::new();
+        self.encode_image(
+            format,
+            ImageRef::new(data, width, height)?,
+            options,
+            &mut output,
+        )?;
+        Ok(output)
+    }
+
+    fn save_data(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        path: &std::path::Path,
+        options: &EncodeOptions,
+    ) -> Result<()> {
+        write_file(path, &self.encode_data(data, width, height, options)?)
+    }
+
+    fn write_data_to_stdout(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        options: &EncodeOptions,
+    ) -> Result<()> {
+        use std::io::Write;
+
+        let encoded = self.encode_data(data, width, height, options)?;
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
+        handle.write_all(&encoded)?;
+        handle.flush()?;
+        Ok(())
     }
 
     /// Save captured data as PNG.
@@ -597,6 +1879,7 @@ impl Grim {
     /// # Ok(())
     /// # }
     /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn save_png<P: AsRef<std::path::Path>>(
         &self,
         data: &[u8],
@@ -604,7 +1887,13 @@ impl Grim {
         height: u32,
         path: P,
     ) -> Result<()> {
-        self.save_png_with_compression(data, width, height, path, 6) // Default compression level of 6
+        self.save_data(
+            data,
+            width,
+            height,
+            path.as_ref(),
+            &EncodeOptions::new(ImageFormat::Png),
+        )
     }
 
     /// Save captured data as PNG with compression level control.
@@ -641,6 +1930,7 @@ impl Grim {
     /// # Ok(())
     /// # }
     /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn save_png_with_compression<P: AsRef<std::path::Path>>(
         &self,
         data: &[u8],
@@ -649,69 +1939,111 @@ impl Grim {
         path: P,
         compression: u8,
     ) -> Result<()> {
-        use std::io::BufWriter;
-
-        let pixels = u64::from(width) * u64::from(height);
-        let expected = pixels.checked_mul(4).ok_or_else(|| {
-            Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            ))
-        })?;
-        if expected > usize::MAX as u64 {
-            return Err(Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            )));
-        }
-        if data.len() != expected as usize {
-            return Err(Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            )));
-        }
-
-        let file = std::fs::File::create(&path).map_err(|e| Error::IoWithContext {
-            operation: format!("creating output file '{}'", path.as_ref().display()),
-            source: e,
-        })?;
-        let writer = BufWriter::new(file);
-        let mut encoder = png::Encoder::new(writer, width, height);
-
-        let compression_level = match compression {
-            0 => png::Compression::Fast,
-            1..=3 => png::Compression::Best,
-            4..=6 => png::Compression::Default,
-            7..=9 => png::Compression::Best,
-            _ => png::Compression::Default,
-        };
-        encoder.set_compression(compression_level);
-
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_filter(png::FilterType::NoFilter);
-
-        let mut writer = encoder
-            .write_header()
-            .map_err(|e| Error::Io(std::io::Error::other(format!("PNG encoding error: {}", e))))?;
+        self.save_data(
+            data,
+            width,
+            height,
+            path.as_ref(),
+            &EncodeOptions::new(ImageFormat::Png).compression(compression),
+        )
+    }
 
-        writer
-            .write_image_data(data)
-            .map_err(|e| Error::Io(std::io::Error::other(format!("PNG encoding error: {}", e))))?;
-        writer
-            .finish()
-            .map_err(|e| Error::Io(std::io::Error::other(format!("PNG encoding error: {}", e))))?;
+    /// Save 16-bit image data as a 16-bit PNG.
+    ///
+    /// Use this with [`CaptureResult::data16`] to keep the full precision of captures from
+    /// 10-bit and 16-bit outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - RGBA image data with 16 bits per channel
+    /// * `width` - Width of the image in pixels
+    /// * `height` - Height of the image in pixels
+    /// * `path` - Path where to save the PNG file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The data length does not match the dimensions
+    /// - Failed to create or write to the file
+    /// - PNG encoding failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut grim = Grim::new()?;
+    /// let result = grim.capture_output("DP-1")?;
+    /// match result.data16() {
+    ///     Some(data16) => grim.save_png16(data16, result.width(), result.height(), "hdr.png")?,
+    ///     None => grim.save_png(result.data(), result.width(), result.height(), "hdr.png")?,
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
+    pub fn save_png16<P: AsRef<std::path::Path>>(
+        &self,
+        data: &[u16],
+        width: u32,
+        height: u32,
+        path: P,
+    ) -> Result<()> {
+        write_file(path.as_ref(), &encode_png16(data, width, height, 6)?)
+    }
 
-        Ok(())
+    /// Save 16-bit image data as a 16-bit PNG with compression level control.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - RGBA image data with 16 bits per channel
+    /// * `width` - Width of the image in pixels
+    /// * `height` - Height of the image in pixels
+    /// * `path` - Path where to save the PNG file
+    /// * `compression` - PNG compression level (0-9, where 9 is highest compression)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The data length does not match the dimensions
+    /// - Failed to create or write to the file
+    /// - PNG encoding failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut grim = Grim::new()?;
+    /// let result = grim.capture_output("DP-1")?;
+    /// if let Some(data16) = result.data16() {
+    ///     grim.save_png16_with_compression(data16, result.width(), result.height(), "hdr.png", 9)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
+    pub fn save_png16_with_compression<P: AsRef<std::path::Path>>(
+        &self,
+        data: &[u16],
+        width: u32,
+        height: u32,
+        path: P,
+        compression: u8,
+    ) -> Result<()> {
+        write_file(
+            path.as_ref(),
+            &encode_png16(data, width, height, compression)?,
+        )
     }
 
     /// Save captured data as JPEG.
     ///
     /// Saves the captured image data to a JPEG file.
     ///
-    /// This function is only available when the `jpeg` feature is enabled.
+    /// Requires the `jpeg` feature; without it this returns an error.
     ///
     /// # Arguments
     ///
@@ -743,7 +2075,7 @@ impl Grim {
     /// # Ok(())
     /// # }
     /// ```
-    #[cfg(feature = "jpeg")]
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn save_jpeg<P: AsRef<std::path::Path>>(
         &self,
         data: &[u8],
@@ -751,14 +2083,20 @@ impl Grim {
         height: u32,
         path: P,
     ) -> Result<()> {
-        self.save_jpeg_with_quality(data, width, height, path, 80)
+        self.save_data(
+            data,
+            width,
+            height,
+            path.as_ref(),
+            &EncodeOptions::new(ImageFormat::Jpeg),
+        )
     }
 
     /// Save captured data as JPEG with quality control.
     ///
     /// Saves the captured image data to a JPEG file with specified quality.
     ///
-    /// This function is only available when the `jpeg` feature is enabled.
+    /// Requires the `jpeg` feature; without it this returns an error.
     ///
     /// # Arguments
     ///
@@ -791,7 +2129,7 @@ impl Grim {
     /// # Ok(())
     /// # }
     /// ```
-    #[cfg(feature = "jpeg")]
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn save_jpeg_with_quality<P: AsRef<std::path::Path>>(
         &self,
         data: &[u8],
@@ -800,114 +2138,20 @@ impl Grim {
         path: P,
         quality: u8,
     ) -> Result<()> {
-        let pixels = u64::from(width) * u64::from(height);
-        let expected = pixels.checked_mul(4).ok_or_else(|| {
-            Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            ))
-        })?;
-        if expected > usize::MAX as u64 {
-            return Err(Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            )));
-        }
-        if data.len() != expected as usize {
-            return Err(Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            )));
-        }
-        let pixels = expected as usize / 4;
-        let rgb_len = pixels.checked_mul(3).ok_or_else(|| {
-            Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            ))
-        })?;
-        let mut rgb_data = vec![0u8; rgb_len];
-        for (i, rgba) in data.chunks_exact(4).enumerate() {
-            let base = i * 3;
-            rgb_data[base] = rgba[0];
-            rgb_data[base + 1] = rgba[1];
-            rgb_data[base + 2] = rgba[2];
-        }
-
-        let mut output_file = std::fs::File::create(&path).map_err(|e| Error::IoWithContext {
-            operation: format!("creating output file '{}'", path.as_ref().display()),
-            source: e,
-        })?;
-        let mut _encoder = jpeg_encoder::Encoder::new(&mut output_file, quality);
-
-        _encoder
-            .encode(
-                &rgb_data,
-                width as u16,
-                height as u16,
-                jpeg_encoder::ColorType::Rgb,
-            )
-            .map_err(|e| Error::Io(std::io::Error::other(format!("JPEG encoding error: {}", e))))?;
-
-        Ok(())
-    }
-
-    /// Save captured data as JPEG (stub when feature is disabled).
-    ///
-    /// This stub is used when the `jpeg` feature is disabled.
-    ///
-    /// # Errors
-    ///
-    /// Always returns an error indicating that JPEG support is not enabled.
-    #[cfg(not(feature = "jpeg"))]
-    pub fn save_jpeg<P: AsRef<std::path::Path>>(
-        &self,
-        _data: &[u8],
-        _width: u32,
-        _height: u32,
-        _path: P,
-    ) -> Result<()> {
-        Err(Error::ImageProcessing(image::ImageError::Unsupported(
-            image::error::UnsupportedError::from_format_and_kind(
-                image::error::ImageFormatHint::Name("JPEG".to_string()),
-                image::error::UnsupportedErrorKind::Format(image::ImageFormat::Jpeg.into()),
-            ),
-        )))
-    }
-
-    /// Save captured data as JPEG with quality control (stub when feature is disabled).
-    ///
-    /// This stub is used when the `jpeg` feature is disabled.
-    ///
-    /// # Errors
-    ///
-    /// Always returns an error indicating that JPEG support is not enabled.
-    #[cfg(not(feature = "jpeg"))]
-    pub fn save_jpeg_with_quality<P: AsRef<std::path::Path>>(
-        &self,
-        _data: &[u8],
-        _width: u32,
-        _height: u32,
-        _path: P,
-        _quality: u8,
-    ) -> Result<()> {
-        Err(Error::ImageProcessing(image::ImageError::Unsupported(
-            image::error::UnsupportedError::from_format_and_kind(
-                image::error::ImageFormatHint::Name("JPEG".to_string()),
-                image::error::UnsupportedErrorKind::Format(image::ImageFormat::Jpeg.into()),
-            ),
-        )))
+        self.save_data(
+            data,
+            width,
+            height,
+            path.as_ref(),
+            &EncodeOptions::new(ImageFormat::Jpeg).quality(quality),
+        )
     }
 
     /// Get image data as JPEG bytes.
     ///
     /// Converts the captured image data to JPEG format and returns the bytes.
     ///
-    /// This function is only available when the `jpeg` feature is enabled.
+    /// Requires the `jpeg` feature; without it this returns an error.
     ///
     /// # Arguments
     ///
@@ -936,16 +2180,16 @@ impl Grim {
     /// println!("JPEG data size: {} bytes", jpeg_bytes.len());
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
-    #[cfg(feature = "jpeg")]
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn to_jpeg(&self, data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
-        self.to_jpeg_with_quality(data, width, height, 80)
+        self.encode_data(data, width, height, &EncodeOptions::new(ImageFormat::Jpeg))
     }
 
     /// Get image data as JPEG bytes with quality control.
     ///
     /// Converts the captured image data to JPEG format with specified quality and returns the bytes.
     ///
-    /// This function is only available when the `jpeg` feature is enabled.
+    /// Requires the `jpeg` feature; without it this returns an error.
     ///
     /// # Arguments
     ///
@@ -975,7 +2219,7 @@ impl Grim {
     /// println!("JPEG data size: {} bytes", jpeg_bytes.len());
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
-    #[cfg(feature = "jpeg")]
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn to_jpeg_with_quality(
         &self,
         data: &[u8],
@@ -983,97 +2227,12 @@ impl Grim {
         height: u32,
         quality: u8,
     ) -> Result<Vec<u8>> {
-        let pixels = u64::from(width) * u64::from(height);
-        let expected = pixels.checked_mul(4).ok_or_else(|| {
-            Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            ))
-        })?;
-        if expected > usize::MAX as u64 {
-            return Err(Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            )));
-        }
-        if data.len() != expected as usize {
-            return Err(Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            )));
-        }
-        let pixels = expected as usize / 4;
-        let rgb_len = pixels.checked_mul(3).ok_or_else(|| {
-            Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            ))
-        })?;
-        let mut rgb_data = vec![0u8; rgb_len];
-        for (i, rgba) in data.chunks_exact(4).enumerate() {
-            let base = i * 3;
-            rgb_data[base] = rgba[0];
-            rgb_data[base + 1] = rgba[1];
-            rgb_data[base + 2] = rgba[2];
-        }
-
-        let mut jpeg_data = Vec::new();
-        let mut _encoder = jpeg_encoder::Encoder::new(&mut jpeg_data, quality);
-
-        _encoder
-            .encode(
-                &rgb_data,
-                width as u16,
-                height as u16,
-                jpeg_encoder::ColorType::Rgb,
-            )
-            .map_err(|e| Error::Io(std::io::Error::other(format!("JPEG encoding error: {}", e))))?;
-
-        Ok(jpeg_data)
-    }
-
-    /// Get image data as JPEG bytes (stub when feature is disabled).
-    ///
-    /// This stub is used when the `jpeg` feature is disabled.
-    ///
-    /// # Errors
-    ///
-    /// Always returns an error indicating that JPEG support is not enabled.
-    #[cfg(not(feature = "jpeg"))]
-    pub fn to_jpeg(&self, _data: &[u8], _width: u32, _height: u32) -> Result<Vec<u8>> {
-        Err(Error::ImageProcessing(image::ImageError::Unsupported(
-            image::error::UnsupportedError::from_format_and_kind(
-                image::error::ImageFormatHint::Name("JPEG".to_string()),
-                image::error::UnsupportedErrorKind::Format(image::ImageFormat::Jpeg.into()),
-            ),
-        )))
-    }
-
-    /// Get image data as JPEG bytes with quality control (stub when feature is disabled).
-    ///
-    /// This stub is used when the `jpeg` feature is disabled.
-    ///
-    /// # Errors
-    ///
-    /// Always returns an error indicating that JPEG support is not enabled.
-    #[cfg(not(feature = "jpeg"))]
-    pub fn to_jpeg_with_quality(
-        &self,
-        _data: &[u8],
-        _width: u32,
-        _height: u32,
-        _quality: u8,
-    ) -> Result<Vec<u8>> {
-        Err(Error::ImageProcessing(image::ImageError::Unsupported(
-            image::error::UnsupportedError::from_format_and_kind(
-                image::error::ImageFormatHint::Name("JPEG".to_string()),
-                image::error::UnsupportedErrorKind::Format(image::ImageFormat::Jpeg.into()),
-            ),
-        )))
+        self.encode_data(
+            data,
+            width,
+            height,
+            &EncodeOptions::new(ImageFormat::Jpeg).quality(quality),
+        )
     }
 
     /// Get image data as PNG bytes.
@@ -1106,8 +2265,9 @@ impl Grim {
     /// println!("PNG data size: {} bytes", png_bytes.len());
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn to_png(&self, data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
-        self.to_png_with_compression(data, width, height, 6)
+        self.encode_data(data, width, height, &EncodeOptions::new(ImageFormat::Png))
     }
 
     /// Get image data as PNG bytes with compression level control.
@@ -1141,6 +2301,7 @@ impl Grim {
     /// println!("PNG data size: {} bytes", png_bytes.len());
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn to_png_with_compression(
         &self,
         data: &[u8],
@@ -1148,61 +2309,94 @@ impl Grim {
         height: u32,
         compression: u8,
     ) -> Result<Vec<u8>> {
-        use std::io::Cursor;
-
-        let pixels = u64::from(width) * u64::from(height);
-        let expected = pixels.checked_mul(4).ok_or_else(|| {
-            Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            ))
-        })?;
-        if expected > usize::MAX as u64 {
-            return Err(Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            )));
-        }
-        if data.len() != expected as usize {
-            return Err(Error::ImageProcessing(image::ImageError::Parameter(
-                image::error::ParameterError::from_kind(
-                    image::error::ParameterErrorKind::DimensionMismatch,
-                ),
-            )));
-        }
+        self.encode_data(
+            data,
+            width,
+            height,
+            &EncodeOptions::new(ImageFormat::Png).compression(compression),
+        )
+    }
 
-        let mut output = Vec::new();
-        {
-            let writer = Cursor::new(&mut output);
-            let mut encoder = png::Encoder::new(writer, width, height);
-
-            let compression_level = match compression {
-                0 => png::Compression::Fast,
-                1..=3 => png::Compression::Best,
-                4..=6 => png::Compression::Default,
-                7..=9 => png::Compression::Best,
-                _ => png::Compression::Default,
-            };
-            encoder.set_compression(compression_level);
-
-            encoder.set_color(png::ColorType::Rgba);
-            encoder.set_filter(png::FilterType::NoFilter);
-
-            let mut writer = encoder.write_header().map_err(|e| {
-                Error::Io(std::io::Error::other(format!("PNG encoding error: {}", e)))
-            })?;
-
-            writer.write_image_data(data).map_err(|e| {
-                Error::Io(std::io::Error::other(format!("PNG encoding error: {}", e)))
-            })?;
-            writer.finish().map_err(|e| {
-                Error::Io(std::io::Error::other(format!("PNG encoding error: {}", e)))
-            })?;
-        }
+    /// Get image data as a 16-bit PNG.
+    ///
+    /// Encodes RGBA data with 16 bits per channel, such as [`CaptureResult::data16`], into a
+    /// PNG with 16-bit samples, so captures of 10-bit and 16-bit outputs keep their precision.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - RGBA image data with 16 bits per channel
+    /// * `width` - Width of the image in pixels
+    /// * `height` - Height of the image in pixels
+    ///
+    /// # Returns
+    ///
+    /// Returns the PNG data as a vector of bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The data length does not match the dimensions
+    /// - PNG encoding failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// let result = grim.capture_output("DP-1")?;
+    /// if let Some(data16) = result.data16() {
+    ///     let png_bytes = grim.to_png16(data16, result.width(), result.height())?;
+    ///     println!("16-bit PNG data size: {} bytes", png_bytes.len());
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
+    pub fn to_png16(&self, data: &[u16], width: u32, height: u32) -> Result<Vec<u8>> {
+        encode_png16(data, width, height, 6)
+    }
 
-        Ok(output)
+    /// Get image data as a 16-bit PNG with compression level control.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - RGBA image data with 16 bits per channel
+    /// * `width` - Width of the image in pixels
+    /// * `height` - Height of the image in pixels
+    /// * `compression` - PNG compression level (0-9, where 9 is highest compression)
+    ///
+    /// # Returns
+    ///
+    /// Returns the PNG data as a vector of bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The data length does not match the dimensions
+    /// - PNG encoding failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use grim_rs::Grim;
+    ///
+    /// let mut grim = Grim::new()?;
+    /// let result = grim.capture_output("DP-1")?;
+    /// if let Some(data16) = result.data16() {
+    ///     let png_bytes = grim.to_png16_with_compression(data16, result.width(), result.height(), 9)?;
+    ///     println!("16-bit PNG data size: {} bytes", png_bytes.len());
+    /// }
+    /// # Ok::<(), grim_rs::Error>(())
+    /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
+    pub fn to_png16_with_compression(
+        &self,
+        data: &[u16],
+        width: u32,
+        height: u32,
+        compression: u8,
+    ) -> Result<Vec<u8>> {
+        encode_png16(data, width, height, compression)
     }
 
     /// Read region from stdin.
@@ -1270,14 +2464,9 @@ impl Grim {
     /// grim.write_png_to_stdout(result.data(), result.width(), result.height())?;
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn write_png_to_stdout(&self, data: &[u8], width: u32, height: u32) -> Result<()> {
-        let png_data = self.to_png(data, width, height)?;
-        use std::io::Write;
-        let stdout = std::io::stdout();
-        let mut handle = stdout.lock();
-        handle.write_all(&png_data)?;
-        handle.flush()?;
-        Ok(())
+        self.write_data_to_stdout(data, width, height, &EncodeOptions::new(ImageFormat::Png))
     }
 
     /// Write image data to stdout as PNG with compression level.
@@ -1307,6 +2496,7 @@ impl Grim {
     /// grim.write_png_to_stdout_with_compression(result.data(), result.width(), result.height(), 6)?;
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn write_png_to_stdout_with_compression(
         &self,
         data: &[u8],
@@ -1314,13 +2504,12 @@ impl Grim {
         height: u32,
         compression: u8,
     ) -> Result<()> {
-        let png_data = self.to_png_with_compression(data, width, height, compression)?;
-        use std::io::Write;
-        let stdout = std::io::stdout();
-        let mut handle = stdout.lock();
-        handle.write_all(&png_data)?;
-        handle.flush()?;
-        Ok(())
+        self.write_data_to_stdout(
+            data,
+            width,
+            height,
+            &EncodeOptions::new(ImageFormat::Png).compression(compression),
+        )
     }
 
     /// Write image data to stdout as JPEG.
@@ -1350,9 +2539,9 @@ impl Grim {
     /// grim.write_jpeg_to_stdout(result.data(), result.width(), result.height())?;
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
-    #[cfg(feature = "jpeg")]
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn write_jpeg_to_stdout(&self, data: &[u8], width: u32, height: u32) -> Result<()> {
-        self.write_jpeg_to_stdout_with_quality(data, width, height, 80)
+        self.write_data_to_stdout(data, width, height, &EncodeOptions::new(ImageFormat::Jpeg))
     }
 
     /// Write image data to stdout as JPEG with quality control.
@@ -1383,7 +2572,7 @@ impl Grim {
     /// grim.write_jpeg_to_stdout_with_quality(result.data(), result.width(), result.height(), 90)?;
     /// # Ok::<(), grim_rs::Error>(())
     /// ```
-    #[cfg(feature = "jpeg")]
+    #[deprecated(note = "use Grim::encode/Grim::save with EncodeOptions")]
     pub fn write_jpeg_to_stdout_with_quality(
         &self,
         data: &[u8],
@@ -1391,12 +2580,32 @@ impl Grim {
         height: u32,
         quality: u8,
     ) -> Result<()> {
-        let jpeg_data = self.to_jpeg_with_quality(data, width, height, quality)?;
-        use std::io::Write;
-        let stdout = std::io::stdout();
-        let mut handle = stdout.lock();
-        handle.write_all(&jpeg_data)?;
-        handle.flush()?;
-        Ok(())
+        self.write_data_to_stdout(
+            data,
+            width,
+            height,
+            &EncodeOptions::new(ImageFormat::Jpeg).quality(quality),
+        )
     }
 }
+
+/// Encode 16-bit RGBA `data` as a 16-bit PNG.
+fn encode_png16(data: &[u16], width: u32, height: u32, compression: u8) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    encode::png16(
+        data,
+        width,
+        height,
+        &EncodeOptions::new(ImageFormat::Png).compression(compression),
+        &mut output,
+    )?;
+    Ok(output)
+}
+
+/// Write encoded image data to `path`.
+fn write_file(path: &std::path::Path, data: &[u8]) -> Result<()> {
+    std::fs::write(path, data).map_err(|e| Error::IoWithContext {
+        operation: format!("writing output file '{}'", path.display()),
+        source: e,
+    })
+}
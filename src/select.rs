@@ -0,0 +1,214 @@
+//! Interactive region selection, the built-in alternative to `slurp`.
+//!
+//! Enabled with the `select` feature. [`Grim::select_region`](crate::Grim::select_region)
+//! maps a fullscreen `zwlr_layer_shell_v1` overlay on every output and lets the user
+//! drag a rectangle or click an output or window. This module holds the part that does
+//! not need a compositor: [`Selection`] turns pointer input into a region, and
+//! [`Selection::render`] draws one output's overlay into an `Argb8888` shm buffer.
+//!
+//! All coordinates are in the global logical coordinate space, like [`Box`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use grim_rs::select::Selection;
+//! use grim_rs::Box;
+//!
+//! let mut selection = Selection::new(vec![Box::new(0, 0, 1920, 1080)]);
+//! selection.pointer_motion(100, 100);
+//! selection.press();
+//! // Within the snap distance of the bottom edge.
+//! selection.pointer_motion(500, 1075);
+//! assert_eq!(selection.release(), Some(Box::new(100, 100, 400, 980)));
+//! ```
+
+use crate::Box;
+
+/// How close, in logical pixels, a dragged corner has to get to an output edge to snap
+/// to it.
+pub const DEFAULT_SNAP_DISTANCE: i32 = 8;
+
+/// How far the pointer has to move after a press before it counts as a drag.
+const DRAG_THRESHOLD: i32 = 2;
+
+/// Width of the border around the highlighted region, in buffer pixels.
+const BORDER_WIDTH: i32 = 2;
+
+/// Colours as `Argb8888` bytes in memory order (little-endian B, G, R, A), premultiplied.
+const SHADE: [u8; 4] = [0, 0, 0, 0x80];
+const CLEAR: [u8; 4] = [0, 0, 0, 0];
+const BORDER: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+/// Pointer-driven selection of a region across outputs.
+///
+/// Feed it pointer positions with [`Selection::pointer_motion`] and the primary button
+/// with [`Selection::press`] and [`Selection::release`]. A release after a drag gives
+/// the dragged rectangle, with corners snapped to nearby output edges; a release without
+/// a drag gives the window or output under the pointer.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    outputs: Vec<Box>,
+    windows: Vec<Box>,
+    snap_distance: i32,
+    pointer: Option<(i32, i32)>,
+    /// Where the button was pressed, while it is held.
+    anchor: Option<(i32, i32)>,
+    dragging: bool,
+}
+
+impl Selection {
+    /// Selection over `outputs`, the logical geometry of each output.
+    pub fn new(outputs: Vec<Box>) -> Self {
+        Self {
+            outputs,
+            windows: Vec::new(),
+            snap_distance: DEFAULT_SNAP_DISTANCE,
+            pointer: None,
+            anchor: None,
+            dragging: false,
+        }
+    }
+
+    /// Windows a click can select, e.g. from `ipc::CompositorIpc::windows()`.
+    pub fn windows(mut self, windows: Vec<Box>) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    /// Change how close a corner has to get to an output edge to snap; 0 disables snapping.
+    pub fn snap_distance(mut self, distance: i32) -> Self {
+        self.snap_distance = distance.max(0);
+        self
+    }
+
+    /// The pointer moved to `(x, y)`.
+    pub fn pointer_motion(&mut self, x: i32, y: i32) {
+        self.pointer = Some((x, y));
+        if let Some((anchor_x, anchor_y)) = self.anchor {
+            let moved = (x - anchor_x).abs().max((y - anchor_y).abs());
+            self.dragging |= moved > DRAG_THRESHOLD;
+        }
+    }
+
+    /// The pointer left every overlay. A drag in progress keeps its last position.
+    pub fn pointer_left(&mut self) {
+        if self.anchor.is_none() {
+            self.pointer = None;
+        }
+    }
+
+    /// The primary button was pressed; ignored until the pointer position is known.
+    pub fn press(&mut self) {
+        if self.anchor.is_none() {
+            self.anchor = self.pointer;
+            self.dragging = false;
+        }
+    }
+
+    /// The primary button was released: the selected region, or `None` if there was no
+    /// press or the click hit neither a window nor an output.
+    pub fn release(&mut self) -> Option<Box> {
+        let anchor = self.anchor.take()?;
+        if self.dragging {
+            self.dragging = false;
+            self.drag_region(anchor)
+        } else {
+            self.target_at(anchor.0, anchor.1)
+        }
+    }
+
+    /// Whether the button is held after moving past the drag threshold.
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// What a click at `(x, y)` selects: the smallest window containing the point, or
+    /// else the output containing it.
+    pub fn target_at(&self, x: i32, y: i32) -> Option<Box> {
+        self.windows
+            .iter()
+            .filter(|window| contains(window, x, y))
+            .min_by_key(|window| i64::from(window.width()) * i64::from(window.height()))
+            .or_else(|| self.outputs.iter().find(|output| contains(output, x, y)))
+            .copied()
+    }
+
+    /// `(x, y)` moved onto the nearest output edge on each axis, if one is within the
+    /// snap distance.
+    pub fn snap(&self, x: i32, y: i32) -> (i32, i32) {
+        let nearest = |value: i32, edges: &mut dyn Iterator<Item = i32>| {
+            edges
+                .filter(|edge| (edge - value).abs() <= self.snap_distance)
+                .min_by_key(|edge| (edge - value).abs())
+                .unwrap_or(value)
+        };
+        let mut x_edges = self
+            .outputs
+            .iter()
+            .flat_map(|output| [output.x(), output.x() + output.width()]);
+        let mut y_edges = self
+            .outputs
+            .iter()
+            .flat_map(|output| [output.y(), output.y() + output.height()]);
+        (nearest(x, &mut x_edges), nearest(y, &mut y_edges))
+    }
+
+    /// The region the overlay highlights: the dragged rectangle during a drag, otherwise
+    /// what a click would select.
+    pub fn highlight(&self) -> Option<Box> {
+        match self.anchor {
+            Some(anchor) if self.dragging => self.drag_region(anchor),
+            _ => self
+                .anchor
+                .or(self.pointer)
+                .and_then(|(x, y)| self.target_at(x, y)),
+        }
+    }
+
+    fn drag_region(&self, anchor: (i32, i32)) -> Option<Box> {
+        let (x0, y0) = self.snap(anchor.0, anchor.1);
+        let (x1, y1) = self.snap(self.pointer?.0, self.pointer?.1);
+        let region = Box::new(x0.min(x1), y0.min(y1), (x1 - x0).abs(), (y1 - y0).abs());
+        (!region.is_empty()).then_some(region)
+    }
+
+    /// Draw the overlay for the part of the layout covered by `area` into `canvas`, an
+    /// `Argb8888` buffer of `area.width()`×`area.height()` pixels with rows `stride`
+    /// bytes apart.
+    ///
+    /// Everything is shaded except [`Selection::highlight`], which is left clear inside
+    /// a white border. Rows or columns the canvas is too small for are skipped.
+    pub fn render(&self, area: Box, canvas: &mut [u8], stride: usize) {
+        let width = area.width().max(0) as usize;
+        let rows = canvas
+            .chunks_mut(stride.max(1))
+            .take(area.height().max(0) as usize);
+        let highlight = self.highlight();
+        for (row, line) in rows.enumerate() {
+            let y = area.y() + row as i32;
+            for (column, pixel) in line.chunks_exact_mut(4).take(width).enumerate() {
+                let x = area.x() + column as i32;
+                let colour = match highlight {
+                    Some(ref region) if contains(region, x, y) => {
+                        let inset = (x - region.x())
+                            .min(y - region.y())
+                            .min(region.x() + region.width() - 1 - x)
+                            .min(region.y() + region.height() - 1 - y);
+                        if inset < BORDER_WIDTH {
+                            BORDER
+                        } else {
+                            CLEAR
+                        }
+                    }
+                    _ => SHADE,
+                };
+                pixel.copy_from_slice(&colour);
+            }
+        }
+    }
+}
+
+fn contains(region: &Box, x: i32, y: i32) -> bool {
+    (region.x()..region.x() + region.width()).contains(&x)
+        && (region.y()..region.y() + region.height()).contains(&y)
+}
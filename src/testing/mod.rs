@@ -0,0 +1,551 @@
+//! In-process fake compositor for testing code that captures screenshots.
+//!
+//! Enabled with the `testing` feature. [`MockCompositor`] runs a small `wayland-server`
+//! display on a background thread and talks to grim-rs over a socketpair, so the real
+//! capture code (registry binding, xdg-output geometry, wlr-screencopy frames, output
+//! transforms, Y-invert and compositing) runs without a real compositor.
+//!
+//! The mock advertises `wl_shm`, `wl_output` (version 4), `zxdg_output_manager_v1`,
+//! `zwlr_screencopy_manager_v1`, and for clients that map overlays `wl_compositor`,
+//! a `wl_seat` with a pointer and `zwlr_layer_shell_v1`. [`MockOptions`] swaps or adds
+//! `ext_image_copy_capture_manager_v1` with `ext_output_image_capture_source_manager_v1`,
+//! lists [`MockToplevel`]s through `ext_foreign_toplevel_list_v1`, and adds
+//! `zwp_linux_dmabuf_v1`.
+//! Every output serves deterministic contents: the pixel at
+//! `(x, y)` of the upright output image is [`MockOutput::pixel`], and the mock lays it out
+//! in buffer orientation according to the output's transform and Y-invert flag, so a
+//! correct capture reproduces the contents exactly.
+//!
+//! # Example
+//!
+//! ```rust
+//! use grim_rs::testing::{MockCompositor, MockOutput};
+//! use grim_rs::{Grim, Transform};
+//!
+//! let output = MockOutput::new("DP-1", 8, 4).transform(Transform::Rotated90);
+//! let compositor = MockCompositor::new(vec![output.clone()])?;
+//! let mut grim = Grim::from_connection(compositor.connect()?)?;
+//!
+//! let result = grim.capture_output("DP-1")?;
+//! assert_eq!((result.width(), result.height()), (4, 8));
+//! assert_eq!(&result.data()[..4], &output.pixel(0, 0));
+//! # Ok::<(), grim_rs::Error>(())
+//! ```
+
+mod server;
+
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+
+use wayland_client::Connection;
+
+use crate::{Error, PixelFormat, Result, Transform};
+
+/// Contents of a mock output, as RGBA for a pixel of the upright output image.
+type Content = Arc<dyn Fn(u32, u32) -> [u8; 4] + Send + Sync>;
+
+/// An output advertised by [`MockCompositor`].
+///
+/// Built with [`MockOutput::new`] and the chained setters; everything not set keeps the
+/// defaults of a plain 1x monitor at the origin.
+#[derive(Clone)]
+pub struct MockOutput {
+    name: String,
+    description: String,
+    width: i32,
+    height: i32,
+    x: i32,
+    y: i32,
+    scale: i32,
+    transform: Transform,
+    format: PixelFormat,
+    y_invert: bool,
+    stride_padding: u32,
+    fail_captures: bool,
+    fail_dmabuf_copies: bool,
+    stall_captures: bool,
+    physical_size_mm: (i32, i32),
+    content: Content,
+}
+
+impl std::fmt::Debug for MockOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockOutput")
+            .field("name", &self.name)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .field("scale", &self.scale)
+            .field("transform", &self.transform)
+            .field("format", &self.format)
+            .field("y_invert", &self.y_invert)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MockOutput {
+    /// Output with a `width`×`height` mode in buffer pixels, i.e. before the transform.
+    ///
+    /// Contents default to `[x, y, x ^ y, 255]` (each channel truncated to 8 bits), and
+    /// frames are served as `Xrgb8888`.
+    pub fn new(name: &str, width: i32, height: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            description: format!("Mock output {}", name),
+            width,
+            height,
+            x: 0,
+            y: 0,
+            scale: 1,
+            transform: Transform::Normal,
+            format: PixelFormat::Xrgb8888,
+            y_invert: false,
+            stride_padding: 0,
+            fail_captures: false,
+            fail_dmabuf_copies: false,
+            stall_captures: false,
+            physical_size_mm: (0, 0),
+            content: Arc::new(|x, y| [x as u8, y as u8, (x ^ y) as u8, 255]),
+        }
+    }
+
+    /// Position in the global compositor space, in logical pixels.
+    pub fn position(mut self, x: i32, y: i32) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    /// Integer scale factor; the logical size is the transformed mode divided by it.
+    pub fn scale(mut self, scale: i32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Output transform, reported through `wl_output.geometry`.
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// `wl_shm` format frames are served in.
+    ///
+    /// Half-float formats and [`PixelFormat::Other`] are advertised as requested, but the
+    /// buffer is filled with zeros.
+    pub fn format(mut self, format: PixelFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Serve wlr-screencopy frames bottom-up and report `y_invert` in the frame flags.
+    pub fn y_invert(mut self, y_invert: bool) -> Self {
+        self.y_invert = y_invert;
+        self
+    }
+
+    /// Extra bytes at the end of every row, on top of the packed row size.
+    pub fn stride_padding(mut self, bytes: u32) -> Self {
+        self.stride_padding = bytes;
+        self
+    }
+
+    /// Answer every copy request with a `failed` event.
+    pub fn fail_captures(mut self, fail: bool) -> Self {
+        self.fail_captures = fail;
+        self
+    }
+
+    /// Answer wlr-screencopy copies into linux-dmabuf buffers with a `failed` event, like
+    /// a compositor that cannot blit into the client's dmabuf; `wl_shm` copies still work.
+    pub fn fail_dmabuf_copies(mut self, fail: bool) -> Self {
+        self.fail_dmabuf_copies = fail;
+        self
+    }
+
+    /// Never answer copy requests, like a compositor that hangs.
+    pub fn stall_captures(mut self, stall: bool) -> Self {
+        self.stall_captures = stall;
+        self
+    }
+
+    /// Physical size in millimeters sent with `wl_output.geometry`; zero by default.
+    pub fn physical_size_mm(mut self, width: i32, height: i32) -> Self {
+        self.physical_size_mm = (width, height);
+        self
+    }
+
+    /// Human-readable description sent with `wl_output.description`.
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    /// Replace the contents; `content(x, y)` is the RGBA value of the pixel at `(x, y)` of
+    /// the upright output image, in physical pixels.
+    pub fn content(
+        mut self,
+        content: impl Fn(u32, u32) -> [u8; 4] + Send + Sync + 'static,
+    ) -> Self {
+        self.content = Arc::new(content);
+        self
+    }
+
+    /// Expected RGBA value of the pixel at `(x, y)` of the upright output image.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        (self.content)(x, y)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Size of the upright output image in physical pixels.
+    pub fn physical_size(&self) -> (i32, i32) {
+        if server::is_rotated(self.transform) {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
+    /// Size of the output in logical pixels.
+    pub fn logical_size(&self) -> (i32, i32) {
+        let (width, height) = self.physical_size();
+        (width / self.scale, height / self.scale)
+    }
+}
+
+/// A window listed through `ext_foreign_toplevel_list_v1`.
+///
+/// Captures serve a `width`×`height` `Xrgb8888` image with no transform, using the same
+/// default contents as [`MockOutput::new`].
+#[derive(Clone)]
+pub struct MockToplevel {
+    identifier: String,
+    title: String,
+    app_id: String,
+    /// Rendered like an output with the toplevel's size and contents.
+    surface: MockOutput,
+}
+
+impl std::fmt::Debug for MockToplevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockToplevel")
+            .field("identifier", &self.identifier)
+            .field("title", &self.title)
+            .field("app_id", &self.app_id)
+            .field("width", &self.surface.width)
+            .field("height", &self.surface.height)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MockToplevel {
+    /// Toplevel with an empty title and app ID.
+    pub fn new(identifier: &str, width: i32, height: i32) -> Self {
+        Self {
+            identifier: identifier.to_string(),
+            title: String::new(),
+            app_id: String::new(),
+            surface: MockOutput::new(identifier, width, height),
+        }
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn app_id(mut self, app_id: &str) -> Self {
+        self.app_id = app_id.to_string();
+        self
+    }
+
+    /// Replace the contents; `content(x, y)` is the RGBA value of the pixel at `(x, y)`.
+    pub fn content(
+        mut self,
+        content: impl Fn(u32, u32) -> [u8; 4] + Send + Sync + 'static,
+    ) -> Self {
+        self.surface = self.surface.content(content);
+        self
+    }
+
+    /// Expected RGBA value of the pixel at `(x, y)` of a capture.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        self.surface.pixel(x, y)
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// Size of a capture in pixels.
+    pub fn size(&self) -> (i32, i32) {
+        (self.surface.width, self.surface.height)
+    }
+}
+
+/// Capture protocols advertised by [`MockCompositor::with_options`].
+///
+/// The default matches [`MockCompositor::new`]: wlr-screencopy only.
+#[derive(Debug, Clone)]
+pub struct MockOptions {
+    wlr_screencopy: bool,
+    ext_image_copy_capture: bool,
+    toplevels: Option<Vec<MockToplevel>>,
+    linux_dmabuf: bool,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        Self {
+            wlr_screencopy: true,
+            ext_image_copy_capture: false,
+            toplevels: None,
+            linux_dmabuf: false,
+        }
+    }
+}
+
+impl MockOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advertise `zwlr_screencopy_manager_v1`.
+    pub fn wlr_screencopy(mut self, enabled: bool) -> Self {
+        self.wlr_screencopy = enabled;
+        self
+    }
+
+    /// Advertise `ext_image_copy_capture_manager_v1` and
+    /// `ext_output_image_capture_source_manager_v1`. Frames always cover the whole output.
+    pub fn ext_image_copy_capture(mut self, enabled: bool) -> Self {
+        self.ext_image_copy_capture = enabled;
+        self
+    }
+
+    /// Advertise `ext_foreign_toplevel_list_v1` listing `toplevels`; an empty list is
+    /// still advertised. With ext-image-copy-capture, also advertise
+    /// `ext_foreign_toplevel_image_capture_source_manager_v1` so they can be captured.
+    pub fn toplevels(mut self, toplevels: Vec<MockToplevel>) -> Self {
+        self.toplevels = Some(toplevels);
+        self
+    }
+
+    /// Advertise `zwp_linux_dmabuf_v1` and offer a linux-dmabuf buffer next to the
+    /// `wl_shm` one in every wlr-screencopy frame. Any plane that can be memory-mapped
+    /// imports; see [`MockCompositor::dmabuf_copies`].
+    pub fn linux_dmabuf(mut self, enabled: bool) -> Self {
+        self.linux_dmabuf = enabled;
+        self
+    }
+}
+
+/// A fake Wayland compositor running on a background thread.
+///
+/// Each call to [`MockCompositor::connect`] creates a new client connected over a
+/// socketpair. Outputs can be added, removed and reconfigured while clients are
+/// connected. The thread stops when the compositor is dropped.
+pub struct MockCompositor {
+    commands: Option<mpsc::Sender<server::Command>>,
+    counters: Arc<Counters>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Counts the compositor thread keeps up to date for [`MockCompositor`].
+#[derive(Default)]
+struct Counters {
+    /// Capture frames of either protocol that exist on the server.
+    live_frames: AtomicUsize,
+    /// Layer surfaces with a buffer.
+    mapped_overlays: AtomicUsize,
+    /// wlr-screencopy copies into dmabufs.
+    dmabuf_copies: AtomicUsize,
+    /// Buffers created from `wl_shm` pools.
+    shm_buffers: AtomicUsize,
+    /// `wl_shm` pools.
+    shm_pools: AtomicUsize,
+    /// ext-image-copy-capture sessions with `paint_cursors`.
+    cursor_sessions: AtomicUsize,
+}
+
+impl MockCompositor {
+    /// Start a compositor advertising `outputs` and wlr-screencopy.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the server display cannot be created.
+    pub fn new(outputs: Vec<MockOutput>) -> Result<Self> {
+        Self::with_options(outputs, MockOptions::default())
+    }
+
+    /// Start a compositor advertising `outputs` and the capture protocols in `options`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the server display cannot be created.
+    pub fn with_options(outputs: Vec<MockOutput>, options: MockOptions) -> Result<Self> {
+        let (commands, incoming) = mpsc::channel();
+        let (started, startup) = mpsc::channel();
+        let counters = Arc::new(Counters::default());
+        let server_counters = counters.clone();
+        let thread = std::thread::Builder::new()
+            .name("grim-rs-mock-compositor".to_string())
+            .spawn(move || server::run(outputs, options, server_counters, incoming, started))
+            .map_err(|e| {
+                Error::WaylandConnection(format!("Failed to start mock compositor: {}", e))
+            })?;
+        match startup.recv() {
+            Ok(Ok(())) => Ok(Self {
+                commands: Some(commands),
+                counters,
+                thread: Some(thread),
+            }),
+            Ok(Err(reason)) => Err(Error::WaylandConnection(reason)),
+            Err(_) => Err(Error::WaylandConnection(
+                "Mock compositor exited during startup".to_string(),
+            )),
+        }
+    }
+
+    /// Open a new client connection to the compositor.
+    ///
+    /// The mock imports any mappable fd as a dmabuf, so dmabuf captures on this connection
+    /// offer a plain memfd when `/dev/udmabuf` is unavailable, where a capture on a real
+    /// compositor falls back to `wl_shm`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the socketpair cannot be created or the
+    /// compositor thread has stopped.
+    pub fn connect(&self) -> Result<Connection> {
+        let connection = Connection::from_socket(self.connect_socket()?).map_err(|e| {
+            Error::WaylandConnection(format!("Failed to connect to mock compositor: {}", e))
+        })?;
+        crate::wayland_capture::accept_memfd_dmabufs(&connection);
+        Ok(connection)
+    }
+
+    /// Open a new client socket without wrapping it in a `Connection`, e.g. to hand it
+    /// to a child process through `WAYLAND_SOCKET`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the socketpair cannot be created or the
+    /// compositor thread has stopped.
+    pub fn connect_socket(&self) -> Result<UnixStream> {
+        let (client, server) = UnixStream::pair()
+            .map_err(|e| Error::WaylandConnection(format!("Failed to create socketpair: {}", e)))?;
+        self.send(server::Command::Connect(server))?;
+        Ok(client)
+    }
+
+    /// Advertise a new output, as if a monitor was plugged in.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the compositor thread has stopped.
+    pub fn add_output(&self, output: MockOutput) -> Result<()> {
+        self.send(server::Command::AddOutput(output))
+    }
+
+    /// Remove the output called `name`, as if the monitor was unplugged.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the compositor thread has stopped.
+    pub fn remove_output(&self, name: &str) -> Result<()> {
+        self.send(server::Command::RemoveOutput(name.to_string()))
+    }
+
+    /// Reconfigure the output with the same name as `output`, e.g. to change its mode,
+    /// position, scale or transform, and send the new state to every client.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the compositor thread has stopped.
+    pub fn update_output(&self, output: MockOutput) -> Result<()> {
+        self.send(server::Command::UpdateOutput(output))
+    }
+
+    /// Move the pointer to `(x, y)` in the global logical coordinate space.
+    ///
+    /// The pointer starts at the origin. It enters a client's layer surface once the
+    /// surface is mapped on the output under the pointer, and sends `motion` while it
+    /// stays over the same surface.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the compositor thread has stopped.
+    pub fn move_pointer(&self, x: i32, y: i32) -> Result<()> {
+        self.send(server::Command::MovePointer(x, y))
+    }
+
+    /// Press or release the pointer button with evdev code `button`, e.g. `0x110` for
+    /// the left button, over the surface the pointer is on.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WaylandConnection` if the compositor thread has stopped.
+    pub fn pointer_button(&self, button: u32, pressed: bool) -> Result<()> {
+        self.send(server::Command::PointerButton(button, pressed))
+    }
+
+    fn send(&self, command: server::Command) -> Result<()> {
+        self.commands
+            .as_ref()
+            .and_then(|commands| commands.send(command).ok())
+            .ok_or_else(|| Error::WaylandConnection("Mock compositor has stopped".to_string()))
+    }
+
+    /// Capture frames, wlr-screencopy or ext, that clients have created and not
+    /// destroyed yet.
+    ///
+    /// Requests are processed asynchronously, so a destroy sent just before the call may
+    /// not be counted yet.
+    pub fn live_frames(&self) -> usize {
+        self.counters.live_frames.load(Ordering::Relaxed)
+    }
+
+    /// Layer surfaces that clients have mapped with a buffer and not destroyed yet.
+    ///
+    /// Like [`MockCompositor::live_frames`], this trails the requests clients have sent.
+    pub fn mapped_overlays(&self) -> usize {
+        self.counters.mapped_overlays.load(Ordering::Relaxed)
+    }
+
+    /// wlr-screencopy copies into linux-dmabuf buffers so far, including failed ones.
+    pub fn dmabuf_copies(&self) -> usize {
+        self.counters.dmabuf_copies.load(Ordering::Relaxed)
+    }
+
+    /// `wl_buffer`s clients have created from `wl_shm` pools so far, destroyed or not.
+    pub fn shm_buffers(&self) -> usize {
+        self.counters.shm_buffers.load(Ordering::Relaxed)
+    }
+
+    /// `wl_shm_pool`s clients have created so far, destroyed or not.
+    pub fn shm_pools(&self) -> usize {
+        self.counters.shm_pools.load(Ordering::Relaxed)
+    }
+
+    /// ext-image-copy-capture sessions clients have created with the `paint_cursors`
+    /// option so far. The mock has no cursor to paint, so their frames look the same.
+    pub fn cursor_sessions(&self) -> usize {
+        self.counters.cursor_sessions.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for MockCompositor {
+    fn drop(&mut self) {
+        // Closing the channel tells the server loop to exit.
+        self.commands.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
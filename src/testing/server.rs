@@ -0,0 +1,827 @@
+mod dmabuf;
+mod ext;
+mod shell;
+mod toplevel;
+
+use std::fs::File;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Arc, Mutex};
+
+use wayland_server::backend::{ClientData, ClientId, DisconnectReason, GlobalId};
+use wayland_server::protocol::{
+    wl_buffer::{self, WlBuffer},
+    wl_output::{self, WlOutput},
+    wl_pointer::WlPointer,
+    wl_shm::{self, Format, WlShm},
+    wl_shm_pool::{self, WlShmPool},
+    wl_surface::WlSurface,
+};
+use wayland_server::{
+    Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+
+use wayland_protocols::xdg::xdg_output::zv1::server::{
+    zxdg_output_manager_v1::{self, ZxdgOutputManagerV1},
+    zxdg_output_v1::{self, ZxdgOutputV1},
+};
+use wayland_protocols_wlr::screencopy::v1::server::{
+    zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::{self, ZwlrScreencopyManagerV1},
+};
+
+use super::{Counters, MockOptions, MockOutput, MockToplevel};
+use crate::{PixelFormat, Transform};
+
+/// How long the server waits for client requests before checking for new clients.
+const POLL_INTERVAL_MS: i32 = 10;
+
+/// Request from [`super::MockCompositor`] to the compositor thread.
+pub(super) enum Command {
+    Connect(UnixStream),
+    AddOutput(MockOutput),
+    RemoveOutput(String),
+    UpdateOutput(MockOutput),
+    MovePointer(i32, i32),
+    PointerButton(u32, bool),
+}
+
+/// Run the compositor until the command channel is closed.
+pub(super) fn run(
+    outputs: Vec<MockOutput>,
+    options: MockOptions,
+    counters: Arc<Counters>,
+    commands: mpsc::Receiver<Command>,
+    started: mpsc::Sender<std::result::Result<(), String>>,
+) {
+    let mut display = match Display::<Server>::new() {
+        Ok(display) => display,
+        Err(e) => {
+            let _ = started.send(Err(format!("Failed to create mock display: {}", e)));
+            return;
+        }
+    };
+    let mut handle = display.handle();
+    handle.create_global::<Server, WlShm, ()>(1, ());
+    handle.create_global::<Server, ZxdgOutputManagerV1, ()>(3, ());
+    if options.wlr_screencopy {
+        handle.create_global::<Server, ZwlrScreencopyManagerV1, ()>(3, ());
+    }
+    if options.ext_image_copy_capture {
+        ext::create_globals(&mut handle, options.toplevels.is_some());
+    }
+    if options.toplevels.is_some() {
+        toplevel::create_global(&mut handle);
+    }
+    if options.linux_dmabuf {
+        dmabuf::create_global(&mut handle);
+    }
+    shell::create_globals(&mut handle);
+    let mut server = Server {
+        outputs: Vec::new(),
+        output_globals: Vec::new(),
+        wl_outputs: Vec::new(),
+        xdg_outputs: Vec::new(),
+        frames: 0,
+        counters,
+        offer_dmabuf: options.linux_dmabuf,
+        serial: 0,
+        pointer: (0, 0),
+        pointers: Vec::new(),
+        pointer_focus: None,
+        layer_surfaces: Vec::new(),
+        toplevels: options.toplevels.unwrap_or_default(),
+    };
+    for output in outputs {
+        server.add_output(&mut handle, output);
+    }
+    let _ = started.send(Ok(()));
+
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(Command::Connect(stream)) => {
+                    if let Err(e) = handle.insert_client(stream, Arc::new(MockClient)) {
+                        log::error!("Mock compositor failed to add client: {}", e);
+                    }
+                }
+                Ok(Command::AddOutput(output)) => server.add_output(&mut handle, output),
+                Ok(Command::RemoveOutput(name)) => server.remove_output(&mut handle, &name),
+                Ok(Command::UpdateOutput(output)) => server.update_output(output),
+                Ok(Command::MovePointer(x, y)) => server.move_pointer(x, y),
+                Ok(Command::PointerButton(button, pressed)) => {
+                    server.pointer_button(button, pressed)
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return,
+            }
+        }
+
+        let mut poll_fd = libc::pollfd {
+            fd: std::os::fd::AsRawFd::as_raw_fd(&display.backend().poll_fd()),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut poll_fd, 1, POLL_INTERVAL_MS) };
+
+        if let Err(e) = display.dispatch_clients(&mut server) {
+            log::error!("Mock compositor failed to dispatch clients: {}", e);
+        }
+        if let Err(e) = display.flush_clients() {
+            log::debug!("Mock compositor failed to flush clients: {}", e);
+        }
+    }
+}
+
+pub(super) struct Server {
+    /// Every output ever advertised; indices stay valid after an output is removed.
+    outputs: Vec<MockOutput>,
+    /// Global of each entry in `outputs`, `None` once removed.
+    output_globals: Vec<Option<GlobalId>>,
+    /// Bound `wl_output`s, to send configuration changes to.
+    wl_outputs: Vec<WlOutput>,
+    /// Bound `zxdg_output_v1`s and the `wl_output` each one describes.
+    xdg_outputs: Vec<(ZxdgOutputV1, WlOutput)>,
+    /// Number of frames copied so far, by either capture protocol; used as the presentation timestamp.
+    frames: u32,
+    /// Shared with `MockCompositor`.
+    counters: Arc<Counters>,
+    /// Whether wlr-screencopy frames offer a linux-dmabuf buffer.
+    offer_dmabuf: bool,
+    /// Last serial sent with an input event.
+    serial: u32,
+    /// Pointer position in the global logical space.
+    pointer: (i32, i32),
+    /// Bound `wl_pointer`s of every client.
+    pointers: Vec<WlPointer>,
+    /// Surface the pointer is over, if any.
+    pointer_focus: Option<WlSurface>,
+    layer_surfaces: Vec<shell::LayerSurface>,
+    /// Windows sent to every client that binds the foreign toplevel list.
+    toplevels: Vec<MockToplevel>,
+}
+
+struct MockClient;
+
+impl ClientData for MockClient {
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+/// A `wl_shm_pool` backed by the client's file.
+struct Pool {
+    file: File,
+    size: usize,
+}
+
+struct BufferData {
+    pool: Arc<Mutex<Pool>>,
+    offset: usize,
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: WEnum<Format>,
+    /// Imported through linux-dmabuf rather than created from a `wl_shm` pool.
+    dmabuf: bool,
+}
+
+/// Which part of a surface a frame shows, and the buffer layout it is copied into.
+#[derive(Clone)]
+struct FrameData {
+    /// Region of the upright image, in physical pixels.
+    region: (u32, u32, u32, u32),
+    width: u32,
+    height: u32,
+    stride: u32,
+    /// Whether rows are stored bottom-up.
+    y_invert: bool,
+}
+
+/// A pending screencopy frame and the output it shows; the layout is the one announced
+/// in the `buffer` event.
+struct ScreencopyFrame {
+    output: usize,
+    layout: FrameData,
+}
+
+/// Whether `transform` swaps width and height.
+pub(super) fn is_rotated(transform: Transform) -> bool {
+    matches!(
+        transform,
+        Transform::Rotated90 | Transform::Rotated270 | Transform::Flipped90 | Transform::Flipped270
+    )
+}
+
+/// Where pixel `(x, y)` of a `width`×`height` buffer ends up in the upright image.
+///
+/// The transform is applied first (flipping horizontally before rotating for the flipped
+/// variants), then the rows are reversed for Y-invert.
+fn image_position(
+    transform: Transform,
+    y_invert: bool,
+    (width, height): (u32, u32),
+    (x, y): (u32, u32),
+) -> (u32, u32) {
+    let (w, h) = (width - 1, height - 1);
+    let (image_x, image_y) = match transform {
+        Transform::Normal => (x, y),
+        Transform::Rotated90 => (h - y, x),
+        Transform::Rotated180 => (w - x, h - y),
+        Transform::Rotated270 => (y, w - x),
+        Transform::Flipped => (w - x, y),
+        Transform::Flipped90 => (h - y, w - x),
+        Transform::Flipped180 => (x, h - y),
+        Transform::Flipped270 => (y, x),
+    };
+    let image_height = if is_rotated(transform) { w } else { h };
+    if y_invert {
+        (image_x, image_height - image_y)
+    } else {
+        (image_x, image_y)
+    }
+}
+
+fn shm_format(format: PixelFormat) -> Format {
+    match format {
+        PixelFormat::Argb8888 => Format::Argb8888,
+        PixelFormat::Xrgb8888 => Format::Xrgb8888,
+        PixelFormat::Abgr8888 => Format::Abgr8888,
+        PixelFormat::Xbgr8888 => Format::Xbgr8888,
+        PixelFormat::Rgb888 => Format::Rgb888,
+        PixelFormat::Bgr888 => Format::Bgr888,
+        PixelFormat::Rgb565 => Format::Rgb565,
+        PixelFormat::Bgr565 => Format::Bgr565,
+        PixelFormat::Xrgb2101010 => Format::Xrgb2101010,
+        PixelFormat::Argb2101010 => Format::Argb2101010,
+        PixelFormat::Xbgr2101010 => Format::Xbgr2101010,
+        PixelFormat::Abgr2101010 => Format::Abgr2101010,
+        PixelFormat::Xbgr16161616 => Format::Xbgr16161616,
+        PixelFormat::Abgr16161616 => Format::Abgr16161616,
+        PixelFormat::Xbgr16161616f => Format::Xbgr16161616f,
+        PixelFormat::Abgr16161616f => Format::Abgr16161616f,
+        PixelFormat::Other(code) => Format::try_from(code).unwrap_or(Format::Yuyv),
+    }
+}
+
+/// Store an RGBA value in `format`. Padding channels are written as zero.
+fn encode(format: PixelFormat, [r, g, b, a]: [u8; 4], out: &mut [u8]) {
+    let ten = |c: u8| ((c as u32) << 2) | (c as u32 >> 6);
+    let sixteen = |c: u8| c as u16 * 257;
+    match format {
+        PixelFormat::Argb8888 => out.copy_from_slice(&[b, g, r, a]),
+        PixelFormat::Xrgb8888 => out.copy_from_slice(&[b, g, r, 0]),
+        PixelFormat::Abgr8888 => out.copy_from_slice(&[r, g, b, a]),
+        PixelFormat::Xbgr8888 => out.copy_from_slice(&[r, g, b, 0]),
+        PixelFormat::Rgb888 => out.copy_from_slice(&[b, g, r]),
+        PixelFormat::Bgr888 => out.copy_from_slice(&[r, g, b]),
+        PixelFormat::Rgb565 | PixelFormat::Bgr565 => {
+            let (high, low) = if format == PixelFormat::Rgb565 {
+                (r, b)
+            } else {
+                (b, r)
+            };
+            let word = ((high as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (low as u16 >> 3);
+            out.copy_from_slice(&word.to_le_bytes());
+        }
+        PixelFormat::Xrgb2101010
+        | PixelFormat::Argb2101010
+        | PixelFormat::Xbgr2101010
+        | PixelFormat::Abgr2101010 => {
+            let (high, low) = match format {
+                PixelFormat::Xrgb2101010 | PixelFormat::Argb2101010 => (r, b),
+                _ => (b, r),
+            };
+            let alpha = match format {
+                PixelFormat::Argb2101010 | PixelFormat::Abgr2101010 => a as u32 >> 6,
+                _ => 0,
+            };
+            let word = (alpha << 30) | (ten(high) << 20) | (ten(g) << 10) | ten(low);
+            out.copy_from_slice(&word.to_le_bytes());
+        }
+        PixelFormat::Xbgr16161616 | PixelFormat::Abgr16161616 => {
+            let alpha = if format == PixelFormat::Abgr16161616 {
+                sixteen(a)
+            } else {
+                0
+            };
+            for (chunk, word) in
+                out.chunks_exact_mut(2)
+                    .zip([sixteen(r), sixteen(g), sixteen(b), alpha])
+            {
+                chunk.copy_from_slice(&word.to_le_bytes());
+            }
+        }
+        _ => out.fill(0),
+    }
+}
+
+impl Server {
+    fn add_output(&mut self, handle: &mut DisplayHandle, output: MockOutput) {
+        let index = self.outputs.len();
+        self.outputs.push(output);
+        self.output_globals.push(Some(
+            handle.create_global::<Server, WlOutput, usize>(4, index),
+        ));
+    }
+
+    fn output_index(&self, name: &str) -> Option<usize> {
+        (0..self.outputs.len())
+            .find(|&index| self.output_globals[index].is_some() && self.outputs[index].name == name)
+    }
+
+    fn remove_output(&mut self, handle: &mut DisplayHandle, name: &str) {
+        let Some(index) = self.output_index(name) else {
+            log::warn!("Mock compositor has no output {}", name);
+            return;
+        };
+        if let Some(global) = self.output_globals[index].take() {
+            handle.remove_global::<Server>(global);
+        }
+    }
+
+    /// Replace the configuration of the output with the same name and send it to every
+    /// client that bound the output.
+    fn update_output(&mut self, output: MockOutput) {
+        let Some(index) = self.output_index(&output.name) else {
+            log::warn!("Mock compositor has no output {}", output.name);
+            return;
+        };
+        self.outputs[index] = output;
+        let config = &self.outputs[index];
+        self.wl_outputs.retain(|resource| resource.is_alive());
+        self.xdg_outputs.retain(|(resource, _)| resource.is_alive());
+        for resource in &self.wl_outputs {
+            if *resource.data::<usize>().expect("wl_output has an index") == index {
+                send_output_state(config, resource);
+            }
+        }
+        for (xdg_output, output) in &self.xdg_outputs {
+            if *output.data::<usize>().expect("wl_output has an index") == index {
+                send_xdg_output_state(config, xdg_output);
+            }
+        }
+        for resource in &self.wl_outputs {
+            if resource.version() >= 2
+                && *resource.data::<usize>().expect("wl_output has an index") == index
+            {
+                resource.done();
+            }
+        }
+    }
+
+    /// Fill `buffer` with the contents of `frame`, taken from `output`, or explain why it
+    /// cannot be used.
+    fn render(
+        output: &MockOutput,
+        frame: &FrameData,
+        buffer: &BufferData,
+    ) -> std::result::Result<(), String> {
+        if buffer.width != frame.width
+            || buffer.height != frame.height
+            || buffer.stride != frame.stride
+            || buffer.format != WEnum::Value(shm_format(output.format))
+        {
+            return Err("buffer does not match the advertised layout".to_string());
+        }
+        let pool = buffer.pool.lock().map_err(|e| e.to_string())?;
+        let len = frame.stride as usize * frame.height as usize;
+        if buffer.offset + len > pool.size {
+            return Err("buffer is outside the pool".to_string());
+        }
+        let mut map = unsafe {
+            memmap2::MmapOptions::new()
+                .len(pool.size)
+                .map_mut(&pool.file)
+                .map_err(|e| e.to_string())?
+        };
+
+        let bpp = output.format.bytes_per_pixel().unwrap_or(4) as usize;
+        let (region_x, region_y, _, _) = frame.region;
+        let pixels = &mut map[buffer.offset..buffer.offset + len];
+        for (y, row) in pixels.chunks_exact_mut(frame.stride as usize).enumerate() {
+            for (x, pixel) in row
+                .chunks_exact_mut(bpp)
+                .take(frame.width as usize)
+                .enumerate()
+            {
+                let (image_x, image_y) = image_position(
+                    output.transform,
+                    frame.y_invert,
+                    (frame.width, frame.height),
+                    (x as u32, y as u32),
+                );
+                let rgba = output.pixel(region_x + image_x, region_y + image_y);
+                encode(output.format, rgba, pixel);
+            }
+        }
+        Ok(())
+    }
+
+    /// Set up a screencopy frame for a region of an output given in logical coordinates.
+    fn create_frame(
+        &self,
+        frame: New<ZwlrScreencopyFrameV1>,
+        output: &WlOutput,
+        logical: Option<(i32, i32, i32, i32)>,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let index = *output.data::<usize>().expect("wl_output has an index");
+        let config = &self.outputs[index];
+        let (image_width, image_height) = config.physical_size();
+        let (x, y, width, height) = match logical {
+            Some((x, y, width, height)) => {
+                let scale = config.scale;
+                let left = (x * scale).clamp(0, image_width);
+                let top = (y * scale).clamp(0, image_height);
+                let right = ((x + width) * scale).clamp(left, image_width);
+                let bottom = ((y + height) * scale).clamp(top, image_height);
+                (left, top, right - left, bottom - top)
+            }
+            None => (0, 0, image_width, image_height),
+        };
+        let (buffer_width, buffer_height) = if is_rotated(config.transform) {
+            (height as u32, width as u32)
+        } else {
+            (width as u32, height as u32)
+        };
+        let bpp = config.format.bytes_per_pixel().unwrap_or(4);
+        let stride = buffer_width * bpp + config.stride_padding;
+
+        self.counters.live_frames.fetch_add(1, Ordering::Relaxed);
+        let frame = data_init.init(
+            frame,
+            ScreencopyFrame {
+                output: index,
+                layout: FrameData {
+                    region: (x as u32, y as u32, width as u32, height as u32),
+                    width: buffer_width,
+                    height: buffer_height,
+                    stride,
+                    y_invert: config.y_invert,
+                },
+            },
+        );
+        frame.buffer(
+            shm_format(config.format),
+            buffer_width,
+            buffer_height,
+            stride,
+        );
+        if frame.version() >= 3 {
+            if self.offer_dmabuf {
+                frame.linux_dmabuf(
+                    dmabuf::drm_fourcc(shm_format(config.format)),
+                    buffer_width,
+                    buffer_height,
+                );
+            }
+            frame.buffer_done();
+        }
+    }
+
+    fn copy_frame(&mut self, frame: &ZwlrScreencopyFrameV1, buffer: &WlBuffer, damage: bool) {
+        let Some(data) = frame.data::<ScreencopyFrame>() else {
+            return;
+        };
+        let Some(buffer_data) = buffer.data::<BufferData>() else {
+            frame.post_error(
+                zwlr_screencopy_frame_v1::Error::InvalidBuffer,
+                "not a wl_shm buffer",
+            );
+            return;
+        };
+        let output = &self.outputs[data.output];
+        if output.stall_captures {
+            return;
+        }
+        if output.fail_captures {
+            frame.failed();
+            return;
+        }
+        let mut layout = data.layout.clone();
+        if buffer_data.dmabuf {
+            self.counters.dmabuf_copies.fetch_add(1, Ordering::Relaxed);
+            if output.fail_dmabuf_copies {
+                frame.failed();
+                return;
+            }
+            // Stride padding only applies to the advertised `wl_shm` buffer.
+            layout.stride = buffer_data.stride;
+        }
+        if let Err(reason) = Self::render(output, &layout, buffer_data) {
+            frame.post_error(zwlr_screencopy_frame_v1::Error::InvalidBuffer, reason);
+            return;
+        }
+
+        let flags = if output.y_invert {
+            zwlr_screencopy_frame_v1::Flags::YInvert
+        } else {
+            zwlr_screencopy_frame_v1::Flags::empty()
+        };
+        frame.flags(flags);
+        if damage {
+            frame.damage(0, 0, layout.width, layout.height);
+        }
+        self.frames += 1;
+        frame.ready(0, self.frames, 0);
+    }
+}
+
+fn wl_transform(transform: Transform) -> wl_output::Transform {
+    match transform {
+        Transform::Normal => wl_output::Transform::Normal,
+        Transform::Rotated90 => wl_output::Transform::_90,
+        Transform::Rotated180 => wl_output::Transform::_180,
+        Transform::Rotated270 => wl_output::Transform::_270,
+        Transform::Flipped => wl_output::Transform::Flipped,
+        Transform::Flipped90 => wl_output::Transform::Flipped90,
+        Transform::Flipped180 => wl_output::Transform::Flipped180,
+        Transform::Flipped270 => wl_output::Transform::Flipped270,
+    }
+}
+
+impl GlobalDispatch<WlOutput, usize> for Server {
+    fn bind(
+        state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WlOutput>,
+        index: &usize,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let output = data_init.init(resource, *index);
+        send_output_state(&state.outputs[*index], &output);
+        if output.version() >= 2 {
+            output.done();
+        }
+        state.wl_outputs.push(output);
+    }
+}
+
+/// Send the `wl_output` events describing `config`, without the final `done`.
+fn send_output_state(config: &MockOutput, output: &WlOutput) {
+    output.geometry(
+        config.x,
+        config.y,
+        config.physical_size_mm.0,
+        config.physical_size_mm.1,
+        wl_output::Subpixel::Unknown,
+        "grim-rs".to_string(),
+        "mock".to_string(),
+        wl_transform(config.transform),
+    );
+    output.mode(
+        wl_output::Mode::Current | wl_output::Mode::Preferred,
+        config.width,
+        config.height,
+        60_000,
+    );
+    if output.version() >= 2 {
+        output.scale(config.scale);
+    }
+    if output.version() >= 4 {
+        output.name(config.name.clone());
+        output.description(config.description.clone());
+    }
+}
+
+/// Send the logical geometry of `config`; before version 3 this ends with `done`.
+fn send_xdg_output_state(config: &MockOutput, xdg_output: &ZxdgOutputV1) {
+    let (width, height) = config.logical_size();
+    xdg_output.logical_position(config.x, config.y);
+    xdg_output.logical_size(width, height);
+    if xdg_output.version() < 3 {
+        xdg_output.done();
+    }
+}
+
+impl Dispatch<WlOutput, usize> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &WlOutput,
+        _request: wl_output::Request,
+        _data: &usize,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<WlShm, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WlShm>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let shm = data_init.init(resource, ());
+        shm.format(Format::Argb8888);
+        shm.format(Format::Xrgb8888);
+    }
+}
+
+impl Dispatch<WlShm, ()> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &WlShm,
+        request: wl_shm::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_shm::Request::CreatePool { id, fd, size } = request {
+            state.counters.shm_pools.fetch_add(1, Ordering::Relaxed);
+            let pool = Pool {
+                file: File::from(fd),
+                size: size.max(0) as usize,
+            };
+            data_init.init(id, Arc::new(Mutex::new(pool)));
+        }
+    }
+}
+
+impl Dispatch<WlShmPool, Arc<Mutex<Pool>>> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &WlShmPool,
+        request: wl_shm_pool::Request,
+        pool: &Arc<Mutex<Pool>>,
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_shm_pool::Request::CreateBuffer {
+                id,
+                offset,
+                width,
+                height,
+                stride,
+                format,
+            } => {
+                state.counters.shm_buffers.fetch_add(1, Ordering::Relaxed);
+                data_init.init(
+                    id,
+                    BufferData {
+                        pool: pool.clone(),
+                        offset: offset.max(0) as usize,
+                        width: width.max(0) as u32,
+                        height: height.max(0) as u32,
+                        stride: stride.max(0) as u32,
+                        format,
+                        dmabuf: false,
+                    },
+                );
+            }
+            wl_shm_pool::Request::Resize { size } => {
+                if let Ok(mut pool) = pool.lock() {
+                    pool.size = pool.size.max(size.max(0) as usize);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlBuffer, BufferData> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &WlBuffer,
+        _request: wl_buffer::Request,
+        _data: &BufferData,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<ZxdgOutputManagerV1, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZxdgOutputManagerV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZxdgOutputManagerV1, ()> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ZxdgOutputManagerV1,
+        request: zxdg_output_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zxdg_output_manager_v1::Request::GetXdgOutput { id, output } = request {
+            let index = *output.data::<usize>().expect("wl_output has an index");
+            let config = &state.outputs[index];
+            let xdg_output = data_init.init(id, ());
+            if xdg_output.version() >= 2 {
+                xdg_output.name(config.name.clone());
+                xdg_output.description(config.description.clone());
+            }
+            send_xdg_output_state(config, &xdg_output);
+            if xdg_output.version() >= 3 {
+                output.done();
+            }
+            state.xdg_outputs.push((xdg_output, output));
+        }
+    }
+}
+
+impl Dispatch<ZxdgOutputV1, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ZxdgOutputV1,
+        _request: zxdg_output_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<ZwlrScreencopyManagerV1, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrScreencopyManagerV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrScreencopyManagerV1, ()> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrScreencopyManagerV1,
+        request: zwlr_screencopy_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_screencopy_manager_v1::Request::CaptureOutput { frame, output, .. } => {
+                state.create_frame(frame, &output, None, data_init);
+            }
+            zwlr_screencopy_manager_v1::Request::CaptureOutputRegion {
+                frame,
+                output,
+                x,
+                y,
+                width,
+                height,
+                ..
+            } => {
+                state.create_frame(frame, &output, Some((x, y, width, height)), data_init);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, ScreencopyFrame> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrScreencopyFrameV1,
+        request: zwlr_screencopy_frame_v1::Request,
+        _data: &ScreencopyFrame,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_screencopy_frame_v1::Request::Copy { buffer } => {
+                state.copy_frame(resource, &buffer, false);
+            }
+            zwlr_screencopy_frame_v1::Request::CopyWithDamage { buffer } => {
+                state.copy_frame(resource, &buffer, true);
+            }
+            _ => {}
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_server::backend::ClientId,
+        _resource: &ZwlrScreencopyFrameV1,
+        _data: &ScreencopyFrame,
+    ) {
+        state.counters.live_frames.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,185 @@
+//! linux-dmabuf, just enough to import single-plane linear buffers.
+//!
+//! The mock has no GPU: an imported plane is mapped like a `wl_shm` pool, so any fd that
+//! can be memory-mapped imports, and wlr-screencopy renders into it the same way.
+
+use std::fs::File;
+use std::os::fd::OwnedFd;
+use std::sync::{Arc, Mutex};
+
+use wayland_server::protocol::wl_buffer::WlBuffer;
+use wayland_server::protocol::wl_shm::Format;
+use wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+
+use wayland_protocols::wp::linux_dmabuf::zv1::server::{
+    zwp_linux_buffer_params_v1::{self, ZwpLinuxBufferParamsV1},
+    zwp_linux_dmabuf_v1::{self, ZwpLinuxDmabufV1},
+};
+
+use super::{BufferData, Pool, Server};
+
+const fn fourcc_code(a: u8, b: u8, c: u8, d: u8) -> u32 {
+    (a as u32) | ((b as u32) << 8) | ((c as u32) << 16) | ((d as u32) << 24)
+}
+
+const DRM_FORMAT_XRGB8888: u32 = fourcc_code(b'X', b'R', b'2', b'4');
+const DRM_FORMAT_ARGB8888: u32 = fourcc_code(b'A', b'R', b'2', b'4');
+
+/// Plane added to a `zwp_linux_buffer_params_v1`.
+struct Plane {
+    fd: OwnedFd,
+    offset: u32,
+    stride: u32,
+}
+
+pub(super) fn create_global(handle: &mut DisplayHandle) {
+    handle.create_global::<Server, ZwpLinuxDmabufV1, ()>(3, ());
+}
+
+/// DRM fourcc with the same memory layout as `format`.
+///
+/// Apart from the two formats every compositor supports, `wl_shm` codes are fourccs.
+pub(super) fn drm_fourcc(format: Format) -> u32 {
+    match format {
+        Format::Argb8888 => DRM_FORMAT_ARGB8888,
+        Format::Xrgb8888 => DRM_FORMAT_XRGB8888,
+        other => other.into(),
+    }
+}
+
+fn shm_format(fourcc: u32) -> WEnum<Format> {
+    match fourcc {
+        DRM_FORMAT_ARGB8888 => WEnum::Value(Format::Argb8888),
+        DRM_FORMAT_XRGB8888 => WEnum::Value(Format::Xrgb8888),
+        other => WEnum::from(other),
+    }
+}
+
+/// Map the plane like a `wl_shm` pool, or explain why it cannot be imported.
+fn import(
+    plane: Option<Plane>,
+    width: i32,
+    height: i32,
+    fourcc: u32,
+) -> std::result::Result<BufferData, String> {
+    let plane = plane.ok_or("no plane was added")?;
+    let file = File::from(plane.fd);
+    let size = file.metadata().map_err(|e| e.to_string())?.len() as usize;
+    let buffer = BufferData {
+        pool: Arc::new(Mutex::new(Pool { file, size })),
+        offset: plane.offset as usize,
+        width: width.max(0) as u32,
+        height: height.max(0) as u32,
+        stride: plane.stride,
+        format: shm_format(fourcc),
+        dmabuf: true,
+    };
+    if buffer.offset + buffer.stride as usize * buffer.height as usize > size {
+        return Err("plane is smaller than the buffer".to_string());
+    }
+    Ok(buffer)
+}
+
+impl GlobalDispatch<ZwpLinuxDmabufV1, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpLinuxDmabufV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let dmabuf = data_init.init(resource, ());
+        for format in [DRM_FORMAT_ARGB8888, DRM_FORMAT_XRGB8888] {
+            // DRM_FORMAT_MOD_LINEAR.
+            dmabuf.modifier(format, 0, 0);
+        }
+    }
+}
+
+impl Dispatch<ZwpLinuxDmabufV1, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ZwpLinuxDmabufV1,
+        request: zwp_linux_dmabuf_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwp_linux_dmabuf_v1::Request::CreateParams { params_id } = request {
+            data_init.init(params_id, Mutex::new(None));
+        }
+    }
+}
+
+impl Dispatch<ZwpLinuxBufferParamsV1, Mutex<Option<Plane>>> for Server {
+    fn request(
+        _state: &mut Self,
+        client: &Client,
+        resource: &ZwpLinuxBufferParamsV1,
+        request: zwp_linux_buffer_params_v1::Request,
+        plane: &Mutex<Option<Plane>>,
+        handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let take_plane = || plane.lock().ok().and_then(|mut plane| plane.take());
+        match request {
+            zwp_linux_buffer_params_v1::Request::Add {
+                fd,
+                plane_idx,
+                offset,
+                stride,
+                ..
+            } => {
+                if plane_idx != 0 {
+                    resource.post_error(
+                        zwp_linux_buffer_params_v1::Error::PlaneIdx,
+                        "only single-plane buffers are supported",
+                    );
+                } else if let Ok(mut plane) = plane.lock() {
+                    *plane = Some(Plane { fd, offset, stride });
+                }
+            }
+            zwp_linux_buffer_params_v1::Request::Create {
+                width,
+                height,
+                format,
+                ..
+            } => match import(take_plane(), width, height, format) {
+                Ok(data) => {
+                    match client.create_resource::<WlBuffer, BufferData, Self>(handle, 1, data) {
+                        Ok(buffer) => resource.created(&buffer),
+                        Err(e) => {
+                            log::error!("Mock compositor failed to create dmabuf buffer: {}", e);
+                            resource.failed();
+                        }
+                    }
+                }
+                Err(reason) => {
+                    log::debug!("Mock compositor failed to import dmabuf: {}", reason);
+                    resource.failed();
+                }
+            },
+            zwp_linux_buffer_params_v1::Request::CreateImmed {
+                buffer_id,
+                width,
+                height,
+                format,
+                ..
+            } => match import(take_plane(), width, height, format) {
+                Ok(data) => {
+                    data_init.init(buffer_id, data);
+                }
+                Err(reason) => data_init.post_error(
+                    buffer_id,
+                    zwp_linux_buffer_params_v1::Error::InvalidWlBuffer,
+                    reason,
+                ),
+            },
+            _ => {}
+        }
+    }
+}
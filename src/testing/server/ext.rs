@@ -0,0 +1,310 @@
+//! ext-image-copy-capture with output and foreign toplevel capture sources.
+//!
+//! A session advertises the output's mode size and shm format, then every frame copies
+//! the whole output in buffer orientation and reports the output transform. The ext
+//! protocol has no Y-invert, so [`MockOutput::y_invert`](super::MockOutput::y_invert)
+//! only applies to wlr-screencopy frames. Toplevels are served the same way as an
+//! untransformed output of their size.
+
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use wayland_server::protocol::{wl_buffer::WlBuffer, wl_output::WlOutput};
+use wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource};
+
+use wayland_protocols::ext::image_capture_source::v1::server::{
+    ext_foreign_toplevel_image_capture_source_manager_v1::{
+        self, ExtForeignToplevelImageCaptureSourceManagerV1,
+    },
+    ext_image_capture_source_v1::{self, ExtImageCaptureSourceV1},
+    ext_output_image_capture_source_manager_v1::{self, ExtOutputImageCaptureSourceManagerV1},
+};
+use wayland_protocols::ext::image_copy_capture::v1::server::{
+    ext_image_copy_capture_frame_v1::{self, ExtImageCopyCaptureFrameV1, FailureReason},
+    ext_image_copy_capture_manager_v1::{self, ExtImageCopyCaptureManagerV1, Options},
+    ext_image_copy_capture_session_v1::{self, ExtImageCopyCaptureSessionV1},
+};
+
+use super::{shm_format, wl_transform, BufferData, FrameData, MockOutput, Server};
+
+/// What an `ext_image_capture_source_v1` shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Source {
+    /// Index into `Server::outputs`.
+    Output(usize),
+    /// Index into `Server::toplevels`.
+    Toplevel(usize),
+}
+
+/// An `ext_image_copy_capture_frame_v1` and the buffer attached to it.
+pub(super) struct ExtFrame {
+    source: Source,
+    buffer: Mutex<Option<WlBuffer>>,
+}
+
+pub(super) fn create_globals(handle: &mut DisplayHandle, toplevels: bool) {
+    handle.create_global::<Server, ExtImageCopyCaptureManagerV1, ()>(1, ());
+    handle.create_global::<Server, ExtOutputImageCaptureSourceManagerV1, ()>(1, ());
+    if toplevels {
+        handle.create_global::<Server, ExtForeignToplevelImageCaptureSourceManagerV1, ()>(1, ());
+    }
+}
+
+impl Server {
+    /// Configuration `source` is rendered from, `None` once its output was removed.
+    fn source_surface(&self, source: Source) -> Option<&MockOutput> {
+        match source {
+            Source::Output(index) => self.output_globals[index]
+                .as_ref()
+                .map(|_| &self.outputs[index]),
+            Source::Toplevel(index) => Some(&self.toplevels[index].surface),
+        }
+    }
+
+    /// Send the buffer constraints of `source`, or `stopped` if it no longer exists.
+    fn send_constraints(&self, session: &ExtImageCopyCaptureSessionV1, source: Source) {
+        let Some(config) = self.source_surface(source) else {
+            session.stopped();
+            return;
+        };
+        session.buffer_size(config.width as u32, config.height as u32);
+        session.shm_format(shm_format(config.format));
+        session.done();
+    }
+
+    fn capture_ext_frame(&mut self, frame: &ExtImageCopyCaptureFrameV1, data: &ExtFrame) {
+        let Some(buffer) = data.buffer.lock().ok().and_then(|buffer| buffer.clone()) else {
+            frame.post_error(
+                ext_image_copy_capture_frame_v1::Error::NoBuffer,
+                "capture without an attached buffer",
+            );
+            return;
+        };
+        let Some(buffer_data) = buffer.data::<BufferData>() else {
+            frame.failed(FailureReason::BufferConstraints);
+            return;
+        };
+        let Some(output) = self.source_surface(data.source) else {
+            frame.failed(FailureReason::Stopped);
+            return;
+        };
+        if output.stall_captures {
+            return;
+        }
+        if output.fail_captures {
+            frame.failed(FailureReason::Unknown);
+            return;
+        }
+
+        let (width, height) = output.physical_size();
+        let (buffer_width, buffer_height) = (output.width as u32, output.height as u32);
+        let frame_data = FrameData {
+            region: (0, 0, width as u32, height as u32),
+            width: buffer_width,
+            height: buffer_height,
+            // The client picks the stride; anything that holds a row is fine.
+            stride: buffer_data
+                .stride
+                .max(buffer_width * output.format.bytes_per_pixel().unwrap_or(4)),
+            y_invert: false,
+        };
+        if let Err(reason) = Self::render(output, &frame_data, buffer_data) {
+            log::debug!("Mock compositor rejected ext buffer: {}", reason);
+            frame.failed(FailureReason::BufferConstraints);
+            return;
+        }
+
+        frame.transform(wl_transform(output.transform));
+        frame.damage(0, 0, buffer_width as i32, buffer_height as i32);
+        self.frames += 1;
+        frame.presentation_time(0, self.frames, 0);
+        frame.ready();
+    }
+}
+
+impl GlobalDispatch<ExtImageCopyCaptureManagerV1, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtImageCopyCaptureManagerV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureManagerV1, ()> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ExtImageCopyCaptureManagerV1,
+        request: ext_image_copy_capture_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let ext_image_copy_capture_manager_v1::Request::CreateSession {
+            session,
+            source,
+            options,
+        } = request
+        {
+            if options
+                .into_result()
+                .is_ok_and(|options| options.contains(Options::PaintCursors))
+            {
+                state
+                    .counters
+                    .cursor_sessions
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            let source = *source.data::<Source>().expect("source has a target");
+            let session = data_init.init(session, source);
+            state.send_constraints(&session, source);
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, Source> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ExtImageCopyCaptureSessionV1,
+        request: ext_image_copy_capture_session_v1::Request,
+        source: &Source,
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let ext_image_copy_capture_session_v1::Request::CreateFrame { frame } = request {
+            state.counters.live_frames.fetch_add(1, Ordering::Relaxed);
+            data_init.init(
+                frame,
+                ExtFrame {
+                    source: *source,
+                    buffer: Mutex::new(None),
+                },
+            );
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, ExtFrame> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ExtImageCopyCaptureFrameV1,
+        request: ext_image_copy_capture_frame_v1::Request,
+        data: &ExtFrame,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            ext_image_copy_capture_frame_v1::Request::AttachBuffer { buffer } => {
+                if let Ok(mut attached) = data.buffer.lock() {
+                    *attached = Some(buffer);
+                }
+            }
+            ext_image_copy_capture_frame_v1::Request::Capture => {
+                state.capture_ext_frame(resource, data);
+            }
+            _ => {}
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_server::backend::ClientId,
+        _resource: &ExtImageCopyCaptureFrameV1,
+        _data: &ExtFrame,
+    ) {
+        state.counters.live_frames.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl GlobalDispatch<ExtOutputImageCaptureSourceManagerV1, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtOutputImageCaptureSourceManagerV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ExtOutputImageCaptureSourceManagerV1, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ExtOutputImageCaptureSourceManagerV1,
+        request: ext_output_image_capture_source_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let ext_output_image_capture_source_manager_v1::Request::CreateSource {
+            source,
+            output,
+        } = request
+        {
+            data_init.init(source, Source::Output(output_index(&output)));
+        }
+    }
+}
+
+impl GlobalDispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtForeignToplevelImageCaptureSourceManagerV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ExtForeignToplevelImageCaptureSourceManagerV1,
+        request: ext_foreign_toplevel_image_capture_source_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let ext_foreign_toplevel_image_capture_source_manager_v1::Request::CreateSource {
+            source,
+            toplevel_handle,
+        } = request
+        {
+            let index = *toplevel_handle
+                .data::<usize>()
+                .expect("toplevel handle has an index");
+            data_init.init(source, Source::Toplevel(index));
+        }
+    }
+}
+
+fn output_index(output: &WlOutput) -> usize {
+    *output.data::<usize>().expect("wl_output has an index")
+}
+
+impl Dispatch<ExtImageCaptureSourceV1, Source> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ExtImageCaptureSourceV1,
+        _request: ext_image_capture_source_v1::Request,
+        _data: &Source,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
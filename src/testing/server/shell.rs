@@ -0,0 +1,490 @@
+//! Surfaces, the seat and wlr-layer-shell, enough for clients that map overlays.
+//!
+//! Layer surfaces are configured to the logical size of their output on the first
+//! commit and count as mapped once a buffer is committed; committed buffers are released
+//! right away. The pointer sits at a position in the global logical space set by the
+//! test, enters the mapped layer surface whose output contains it and reports motion
+//! and button events there.
+
+use std::sync::atomic::Ordering;
+
+use wayland_server::protocol::{
+    wl_buffer::WlBuffer,
+    wl_callback::WlCallback,
+    wl_compositor::{self, WlCompositor},
+    wl_keyboard::{self, WlKeyboard},
+    wl_pointer::{self, WlPointer},
+    wl_region::{self, WlRegion},
+    wl_seat::{self, WlSeat},
+    wl_surface::{self, WlSurface},
+    wl_touch::{self, WlTouch},
+};
+use wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource};
+
+use wayland_protocols_wlr::layer_shell::v1::server::{
+    zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, ZwlrLayerSurfaceV1},
+};
+
+use super::{MockOutput, Server};
+
+/// A `zwlr_layer_surface_v1` and the surface it gives the role to.
+pub(super) struct LayerSurface {
+    surface: WlSurface,
+    layer_surface: ZwlrLayerSurfaceV1,
+    /// Index into `Server::outputs`.
+    output: usize,
+    configured: bool,
+    /// Buffer from `attach`, applied on the next commit.
+    pending_buffer: Option<Option<WlBuffer>>,
+    mapped: bool,
+}
+
+pub(super) fn create_globals(handle: &mut DisplayHandle) {
+    handle.create_global::<Server, WlCompositor, ()>(4, ());
+    handle.create_global::<Server, WlSeat, ()>(5, ());
+    handle.create_global::<Server, ZwlrLayerShellV1, ()>(4, ());
+}
+
+impl Server {
+    fn next_serial(&mut self) -> u32 {
+        self.serial = self.serial.wrapping_add(1);
+        self.serial
+    }
+
+    /// Move the pointer to `(x, y)` in the global logical space.
+    pub(super) fn move_pointer(&mut self, x: i32, y: i32) {
+        self.pointer = (x, y);
+        let before = self.pointer_focus.clone();
+        self.update_pointer_focus();
+        if before.is_some() && before == self.pointer_focus {
+            self.send_pointer_motion();
+        }
+    }
+
+    /// Press or release `button` over the focused surface.
+    pub(super) fn pointer_button(&mut self, button: u32, pressed: bool) {
+        let Some(surface) = self.pointer_focus.clone() else {
+            return;
+        };
+        let serial = self.next_serial();
+        let state = if pressed {
+            wl_pointer::ButtonState::Pressed
+        } else {
+            wl_pointer::ButtonState::Released
+        };
+        for pointer in &self.pointers {
+            if pointer.id().same_client_as(&surface.id()) {
+                pointer.button(serial, 0, button, state);
+                if pointer.version() >= 5 {
+                    pointer.frame();
+                }
+            }
+        }
+    }
+
+    /// Report the pointer position to the clients of the focused surface.
+    fn send_pointer_motion(&mut self) {
+        let Some(surface) = self.pointer_focus.clone() else {
+            return;
+        };
+        let Some((x, y)) = self
+            .layer_surfaces
+            .iter()
+            .find(|layer| layer.surface == surface)
+            .and_then(|layer| self.pointer_position_on(layer.output))
+        else {
+            return;
+        };
+        for pointer in &self.pointers {
+            if pointer.id().same_client_as(&surface.id()) {
+                pointer.motion(0, x, y);
+                if pointer.version() >= 5 {
+                    pointer.frame();
+                }
+            }
+        }
+    }
+
+    /// Pointer position relative to `output`, if the pointer is inside its logical area
+    /// and the output still exists.
+    fn pointer_position_on(&self, output: usize) -> Option<(f64, f64)> {
+        self.output_globals.get(output)?.as_ref()?;
+        let config = &self.outputs[output];
+        let (width, height) = config.logical_size();
+        let (x, y) = (self.pointer.0 - config.x, self.pointer.1 - config.y);
+        ((0..width).contains(&x) && (0..height).contains(&y)).then_some((x as f64, y as f64))
+    }
+
+    /// Send `leave`/`enter` if the pointer is now over a different mapped surface.
+    fn update_pointer_focus(&mut self) {
+        self.pointers.retain(|pointer| pointer.is_alive());
+        let target = self
+            .layer_surfaces
+            .iter()
+            .filter(|layer| layer.mapped)
+            .find_map(|layer| {
+                let position = self.pointer_position_on(layer.output)?;
+                Some((layer.surface.clone(), position))
+            });
+        if target.as_ref().map(|(surface, _)| surface) == self.pointer_focus.as_ref() {
+            return;
+        }
+
+        if let Some(old) = self.pointer_focus.take() {
+            let serial = self.next_serial();
+            for pointer in &self.pointers {
+                if pointer.id().same_client_as(&old.id()) {
+                    pointer.leave(serial, &old);
+                    if pointer.version() >= 5 {
+                        pointer.frame();
+                    }
+                }
+            }
+        }
+        if let Some((surface, _)) = target {
+            self.pointer_focus = Some(surface);
+            for pointer in self.pointers.clone() {
+                self.send_pointer_enter(&pointer);
+            }
+        }
+    }
+
+    /// Tell `pointer` about the focused surface, if it belongs to the same client.
+    fn send_pointer_enter(&mut self, pointer: &WlPointer) {
+        let Some(surface) = self.pointer_focus.clone() else {
+            return;
+        };
+        if !pointer.id().same_client_as(&surface.id()) {
+            return;
+        }
+        let Some((x, y)) = self
+            .layer_surfaces
+            .iter()
+            .find(|layer| layer.surface == surface)
+            .and_then(|layer| self.pointer_position_on(layer.output))
+        else {
+            return;
+        };
+        let serial = self.next_serial();
+        pointer.enter(serial, &surface, x, y);
+        if pointer.version() >= 5 {
+            pointer.frame();
+        }
+    }
+
+    fn commit_surface(&mut self, surface: &WlSurface) {
+        let Some(index) = self
+            .layer_surfaces
+            .iter()
+            .position(|layer| layer.surface == *surface)
+        else {
+            return;
+        };
+        if !self.layer_surfaces[index].configured {
+            let (width, height) = self
+                .outputs
+                .get(self.layer_surfaces[index].output)
+                .map_or((0, 0), MockOutput::logical_size);
+            let serial = self.next_serial();
+            let layer = &mut self.layer_surfaces[index];
+            layer.configured = true;
+            layer
+                .layer_surface
+                .configure(serial, width.max(0) as u32, height.max(0) as u32);
+            return;
+        }
+        if let Some(buffer) = self.layer_surfaces[index].pending_buffer.take() {
+            self.layer_surfaces[index].mapped = buffer.is_some();
+            // Nothing is ever drawn, so the contents are never needed again.
+            if let Some(buffer) = buffer {
+                buffer.release();
+            }
+            self.count_mapped_overlays();
+            self.update_pointer_focus();
+        }
+    }
+
+    fn count_mapped_overlays(&self) {
+        let mapped = self
+            .layer_surfaces
+            .iter()
+            .filter(|layer| layer.mapped)
+            .count();
+        self.counters
+            .mapped_overlays
+            .store(mapped, Ordering::Relaxed);
+    }
+
+    fn forget_surface(&mut self, surface: &WlSurface) {
+        self.layer_surfaces
+            .retain(|layer| layer.surface != *surface);
+        self.count_mapped_overlays();
+        if self.pointer_focus.as_ref() == Some(surface) {
+            self.pointer_focus = None;
+            self.update_pointer_focus();
+        }
+    }
+}
+
+impl GlobalDispatch<WlCompositor, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WlCompositor>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<WlCompositor, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &WlCompositor,
+        request: wl_compositor::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_compositor::Request::CreateSurface { id } => {
+                data_init.init(id, ());
+            }
+            wl_compositor::Request::CreateRegion { id } => {
+                data_init.init(id, ());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlSurface, ()> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &WlSurface,
+        request: wl_surface::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_surface::Request::Attach { buffer, .. } => {
+                if let Some(layer) = state
+                    .layer_surfaces
+                    .iter_mut()
+                    .find(|layer| layer.surface == *resource)
+                {
+                    layer.pending_buffer = Some(buffer);
+                }
+            }
+            wl_surface::Request::Frame { callback } => {
+                // Nothing is ever drawn, so every frame is done right away.
+                data_init.init(callback, ()).done(0);
+            }
+            wl_surface::Request::Commit => state.commit_surface(resource),
+            _ => {}
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_server::backend::ClientId,
+        resource: &WlSurface,
+        _data: &(),
+    ) {
+        state.forget_surface(resource);
+    }
+}
+
+impl Dispatch<WlRegion, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &WlRegion,
+        _request: wl_region::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlCallback, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &WlCallback,
+        _request: wayland_server::protocol::wl_callback::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<WlSeat, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<WlSeat>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let seat = data_init.init(resource, ());
+        seat.capabilities(wl_seat::Capability::Pointer);
+        if seat.version() >= 2 {
+            seat.name("seat0".to_string());
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &WlSeat,
+        request: wl_seat::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_seat::Request::GetPointer { id } => {
+                let pointer = data_init.init(id, ());
+                state.send_pointer_enter(&pointer);
+                state.pointers.push(pointer);
+            }
+            // Inert: the seat never has these capabilities.
+            wl_seat::Request::GetKeyboard { id } => {
+                data_init.init(id, ());
+            }
+            wl_seat::Request::GetTouch { id } => {
+                data_init.init(id, ());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlPointer, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &WlPointer,
+        _request: wl_pointer::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlKeyboard, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &WlKeyboard,
+        _request: wl_keyboard::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlTouch, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &WlTouch,
+        _request: wl_touch::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl GlobalDispatch<ZwlrLayerShellV1, ()> for Server {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrLayerShellV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrLayerShellV1, ()> for Server {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrLayerShellV1,
+        request: zwlr_layer_shell_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_layer_shell_v1::Request::GetLayerSurface {
+            id,
+            surface,
+            output,
+            ..
+        } = request
+        {
+            let layer_surface = data_init.init(id, ());
+            // Without an output the compositor picks one; the mock takes the first.
+            let output = output
+                .and_then(|output| output.data::<usize>().copied())
+                .or_else(|| state.output_globals.iter().position(Option::is_some))
+                .unwrap_or(0);
+            state.layer_surfaces.push(LayerSurface {
+                surface,
+                layer_surface,
+                output,
+                configured: false,
+                pending_buffer: None,
+                mapped: false,
+            });
+        }
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrLayerSurfaceV1,
+        _request: zwlr_layer_surface_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: wayland_server::backend::ClientId,
+        resource: &ZwlrLayerSurfaceV1,
+        _data: &(),
+    ) {
+        let Some(surface) = state
+            .layer_surfaces
+            .iter()
+            .find(|layer| layer.layer_surface == *resource)
+            .map(|layer| layer.surface.clone())
+        else {
+            return;
+        };
+        state.forget_surface(&surface);
+    }
+}
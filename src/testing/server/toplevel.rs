@@ -0,0 +1,77 @@
+//! ext-foreign-toplevel-list.
+//!
+//! Every client that binds the list gets a handle for each toplevel, with its
+//! identifier, title and app ID, followed by `done`. The list never changes afterwards.
+
+use wayland_server::{Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource};
+
+use wayland_protocols::ext::foreign_toplevel_list::v1::server::{
+    ext_foreign_toplevel_handle_v1::{self, ExtForeignToplevelHandleV1},
+    ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+};
+
+use super::Server;
+
+pub(super) fn create_global(handle: &mut DisplayHandle) {
+    handle.create_global::<Server, ExtForeignToplevelListV1, ()>(1, ());
+}
+
+impl GlobalDispatch<ExtForeignToplevelListV1, ()> for Server {
+    fn bind(
+        state: &mut Self,
+        handle: &DisplayHandle,
+        client: &Client,
+        resource: New<ExtForeignToplevelListV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let list = data_init.init(resource, ());
+        for (index, config) in state.toplevels.iter().enumerate() {
+            let toplevel = match client.create_resource::<ExtForeignToplevelHandleV1, usize, Self>(
+                handle,
+                list.version(),
+                index,
+            ) {
+                Ok(toplevel) => toplevel,
+                Err(e) => {
+                    log::error!("Mock compositor failed to create toplevel handle: {}", e);
+                    return;
+                }
+            };
+            list.toplevel(&toplevel);
+            toplevel.identifier(config.identifier.clone());
+            toplevel.title(config.title.clone());
+            toplevel.app_id(config.app_id.clone());
+            toplevel.done();
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        resource: &ExtForeignToplevelListV1,
+        request: ext_foreign_toplevel_list_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let ext_foreign_toplevel_list_v1::Request::Stop = request {
+            resource.finished();
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, usize> for Server {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ExtForeignToplevelHandleV1,
+        _request: ext_foreign_toplevel_handle_v1::Request,
+        _data: &usize,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
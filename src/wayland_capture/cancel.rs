@@ -0,0 +1,96 @@
+use super::*;
+
+use std::os::fd::{AsFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::Waker;
+
+/// Aborts captures from another thread or task.
+///
+/// Install a token with [`crate::Grim::set_cancellation_token`]. Once
+/// [`CancellationToken::cancel`] is called, a capture waiting on the compositor stops
+/// with [`Error::Cancelled`] and destroys its pending frames and buffers. A cancelled
+/// token stays cancelled: later captures fail the same way until it is replaced.
+///
+/// Clones share the same state, so one clone can be handed to the code that cancels.
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Debug)]
+struct CancelState {
+    cancelled: AtomicBool,
+    /// eventfd that becomes readable on cancellation, polled next to the connection.
+    event: OwnedFd,
+    /// Async captures to wake on cancellation.
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Io` if the eventfd used to interrupt blocking waits cannot be
+    /// created.
+    pub fn new() -> Result<Self> {
+        // SAFETY: eventfd has no memory-safety preconditions.
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self {
+            inner: Arc::new(CancelState {
+                cancelled: AtomicBool::new(false),
+                // SAFETY: `fd` was just created and is owned by nobody else.
+                event: unsafe { OwnedFd::from_raw_fd(fd) },
+                wakers: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Cancel every capture using this token, now and in the future.
+    pub fn cancel(&self) {
+        if self.inner.cancelled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        // The counter is never read back, so the eventfd stays readable for every poller.
+        let one = 1u64.to_ne_bytes();
+        // SAFETY: writes 8 bytes from a valid buffer to an eventfd we own.
+        unsafe { libc::write(self.inner.event.as_raw_fd(), one.as_ptr().cast(), one.len()) };
+        let wakers = std::mem::take(
+            &mut *self
+                .inner
+                .wakers
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Whether [`CancellationToken::cancel`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Wake `waker` when the token is cancelled.
+    #[cfg(feature = "async")]
+    pub(super) fn register(&self, waker: &Waker) {
+        let mut wakers = self
+            .inner
+            .wakers
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if !wakers.iter().any(|known| known.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+impl AsFd for CancellationToken {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.event.as_fd()
+    }
+}
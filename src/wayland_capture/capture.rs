@@ -1,27 +1,54 @@
-use super::transform::{apply_image_transform, flip_vertical};
+use super::dmabuf::DmabufCopy;
+use super::frame::{FramePixels, RawFrame};
 use super::*;
 
 impl WaylandCapture {
-    fn refresh_outputs(&mut self) -> Result<()> {
+    pub(super) async fn refresh_outputs(&mut self) -> Result<()> {
+        self.start_deadline(self.timeout);
+        let mut event_queue = self._connection.new_event_queue();
+        self.bind_outputs(&mut event_queue).await?;
+        if self.globals.output_info.is_empty() {
+            return Err(Error::NoOutputs);
+        }
+        Ok(())
+    }
+
+    /// Forget all outputs and toplevels and bind them again through a new registry on
+    /// `event_queue`, which then receives their events.
+    pub(super) async fn bind_outputs(
+        &mut self,
+        event_queue: &mut wayland_client::EventQueue<Self>,
+    ) -> Result<()> {
+        for toplevel in self.globals.toplevels.drain(..) {
+            toplevel.destroy();
+        }
+        self.globals.toplevel_info.clear();
+        if let Some(list) = self.globals.foreign_toplevel_list.take() {
+            list.destroy();
+        }
+        if let Some(seat) = self.globals.seat.take() {
+            if seat.version() >= 5 {
+                seat.release();
+            }
+        }
         self.globals.outputs.clear();
         self.globals.output_info.clear();
         self.globals.output_xdg_map.clear();
 
-        let mut event_queue = self._connection.new_event_queue();
         let qh = event_queue.handle();
-
         let _registry = self._connection.display().get_registry(&qh, ());
 
-        event_queue.roundtrip(self).map_err(|e| {
-            Error::WaylandConnection(format!("Failed to refresh Wayland globals: {}", e))
+        self.roundtrip(event_queue).await.map_err(|e| {
+            e.into_error(|e| {
+                Error::WaylandConnection(format!("Failed to refresh Wayland globals: {}", e))
+            })
         })?;
-        if self.globals.output_info.is_empty() {
-            return Err(Error::NoOutputs);
-        }
 
         for _ in 0..2 {
-            event_queue.roundtrip(self).map_err(|e| {
-                Error::WaylandConnection(format!("Failed to process output events: {}", e))
+            self.roundtrip(event_queue).await.map_err(|e| {
+                e.into_error(|e| {
+                    Error::WaylandConnection(format!("Failed to process output events: {}", e))
+                })
             })?;
         }
 
@@ -36,7 +63,7 @@ impl WaylandCapture {
         Ok(())
     }
 
-    fn collect_outputs_snapshot(&self) -> Vec<(WlOutput, OutputInfo)> {
+    pub(super) fn collect_outputs_snapshot(&self) -> Vec<(WlOutput, OutputInfo)> {
         self.globals
             .outputs
             .iter()
@@ -51,11 +78,12 @@ impl WaylandCapture {
             .collect()
     }
 
-    fn capture_region_for_output(
+    async fn capture_region_for_output(
         &mut self,
         output: &WlOutput,
         region: Box,
         overlay_cursor: bool,
+        dmabuf: bool,
     ) -> Result<CaptureResult> {
         if region.width() <= 0 || region.height() <= 0 {
             return Err(Error::InvalidRegion(
@@ -68,6 +96,49 @@ impl WaylandCapture {
             ));
         }
 
+        match self.backend {
+            CaptureBackend::ExtImageCopyCapture => {
+                self.capture_region_ext(output, region, overlay_cursor)
+                    .await
+            }
+            CaptureBackend::WlrScreencopy | CaptureBackend::Auto => {
+                self.capture_region_wlr(output, region, overlay_cursor, dmabuf)
+                    .await
+            }
+        }
+    }
+
+    async fn capture_region_wlr(
+        &mut self,
+        output: &WlOutput,
+        region: Box,
+        overlay_cursor: bool,
+        dmabuf: bool,
+    ) -> Result<CaptureResult> {
+        let (raw, pixels) = self
+            .copy_region_wlr(output, region, overlay_cursor, dmabuf)
+            .await?;
+        match pixels {
+            FramePixels::Pool => {
+                let data = self
+                    .shm_pool
+                    .as_ref()
+                    .ok_or_else(|| Error::BufferCreation("shm pool was not created".to_string()))?
+                    .slice(0, raw.size())?;
+                raw.into_capture(data)
+            }
+            FramePixels::Owned(data) => raw.into_capture(&data),
+        }
+    }
+
+    /// Copy a region of an output with wlr-screencopy without processing the pixels.
+    pub(super) async fn copy_region_wlr(
+        &mut self,
+        output: &WlOutput,
+        region: Box,
+        overlay_cursor: bool,
+        dmabuf: bool,
+    ) -> Result<(RawFrame, FramePixels)> {
         let screencopy_manager =
             self.globals
                 .screencopy_manager
@@ -77,15 +148,8 @@ impl WaylandCapture {
                 ))?;
         let mut event_queue = self._connection.new_event_queue();
         let qh = event_queue.handle();
-        let frame_state = Arc::new(Mutex::new(FrameState {
-            buffer: None,
-            width: 0,
-            height: 0,
-            format: None,
-            ready: false,
-            flags: 0,
-        }));
-        let frame = screencopy_manager.capture_output_region(
+        let frame_state = Arc::new(Mutex::new(FrameState::default()));
+        let frame = DestroyOnDrop::new(screencopy_manager.capture_output_region(
             if overlay_cursor { 1 } else { 0 },
             output,
             region.x(),
@@ -94,9 +158,8 @@ impl WaylandCapture {
             region.height(),
             &qh,
             frame_state.clone(),
-        );
+        ));
 
-        let mut attempts = 0;
         loop {
             {
                 let state = lock_frame_state(&frame_state)?;
@@ -109,65 +172,53 @@ impl WaylandCapture {
                     break;
                 }
             }
-            if attempts >= MAX_ATTEMPTS {
-                return Err(Error::FrameCapture(
-                    "Timeout waiting for frame buffer".to_string(),
-                ));
-            }
-            event_queue.blocking_dispatch(self).map_err(|e| {
-                Error::FrameCapture(format!("Failed to dispatch frame events: {}", e))
-            })?;
-            attempts += 1;
+            self.dispatch(&mut event_queue)
+                .await
+                .map_err(WaitError::into_frame_error)?;
         }
 
-        let shm = self
-            .globals
-            .shm
-            .as_ref()
-            .ok_or_else(|| Error::UnsupportedProtocol("wl_shm not available".to_string()))?;
-
-        let (width, height, stride, size, format) = {
-            let state = lock_frame_state(&frame_state)?;
-            if state.width == 0 || state.height == 0 {
-                return Err(Error::CaptureFailed);
+        if dmabuf {
+            // Since version 3 the compositor lists every buffer type before `buffer_done`.
+            if frame.version() >= 3 {
+                while !lock_frame_state(&frame_state)?.buffer_done {
+                    self.dispatch(&mut event_queue)
+                        .await
+                        .map_err(WaitError::into_frame_error)?;
+                }
             }
-            let width = state.width;
-            let height = state.height;
-            let stride = width * 4;
-            let size = checked_buffer_size(width, height, 4, Some(stride))?;
-            let format = state.format.unwrap_or(ShmFormat::Xrgb8888);
-            (width, height, stride, size, format)
-        };
-
-        let mut tmp_file = tempfile::NamedTempFile::new().map_err(|e| {
-            Error::BufferCreation(format!("failed to create temporary file: {}", e))
-        })?;
-        tmp_file.as_file_mut().set_len(size as u64).map_err(|e| {
-            Error::BufferCreation(format!("failed to resize buffer to {} bytes: {}", size, e))
-        })?;
-        let mmap = unsafe {
-            memmap2::MmapMut::map_mut(&tmp_file)
-                .map_err(|e| Error::BufferCreation(format!("failed to memory-map buffer: {}", e)))?
-        };
-
-        let pool = shm.create_pool(
-            unsafe { BorrowedFd::borrow_raw(tmp_file.as_file().as_raw_fd()) },
-            size as i32,
-            &qh,
-            (),
-        );
-        let buffer = pool.create_buffer(
-            0,
-            width as i32,
-            height as i32,
-            stride as i32,
-            format,
-            &qh,
-            (),
+            match self
+                .copy_frame_dmabuf(&mut event_queue, &frame, &frame_state)
+                .await?
+            {
+                DmabufCopy::Copied { data, layout } => {
+                    let raw =
+                        self.wlr_raw_frame(output.id().protocol_id(), &frame_state, layout)?;
+                    return Ok((raw, FramePixels::Owned(data)));
+                }
+                DmabufCopy::Unavailable(reason) => {
+                    log::debug!("dmabuf capture unavailable, using wl_shm: {}", reason);
+                }
+                DmabufCopy::Rejected => {
+                    log::debug!("Compositor rejected the dmabuf copy, retrying with wl_shm");
+                    // Boxed because an async fn cannot contain itself.
+                    return std::boxed::Box::pin(self.copy_region_wlr(
+                        output,
+                        region,
+                        overlay_cursor,
+                        false,
+                    ))
+                    .await;
+                }
+            }
+        }
+
+        let layout = lock_frame_state(&frame_state)?.shm_layout()?;
+        let buffer = DestroyOnDrop::new(
+            self.shm_pool(&qh, layout.size()?)?
+                .create_buffer(0, layout, &qh),
         );
         frame.copy(&buffer);
 
-        let mut attempts = 0;
         loop {
             {
                 let state = lock_frame_state(&frame_state)?;
@@ -180,58 +231,23 @@ impl WaylandCapture {
                     break;
                 }
             }
-            if attempts >= MAX_ATTEMPTS {
-                return Err(Error::FrameCapture(
-                    "Timeout waiting for frame capture completion".to_string(),
-                ));
-            }
-            event_queue.blocking_dispatch(self).map_err(|e| {
-                Error::FrameCapture(format!("Failed to dispatch frame events: {}", e))
-            })?;
-            attempts += 1;
-        }
-
-        let mut buffer_data = mmap.to_vec();
-        convert_shm_to_rgba(&mut buffer_data, format);
-        let output_id = output.id().protocol_id();
-        let mut final_data = buffer_data;
-        let mut final_width = width;
-        let mut final_height = height;
-
-        if let Some(info) = self.globals.output_info.get(&output_id) {
-            if !matches!(
-                info.transform,
-                wayland_client::protocol::wl_output::Transform::Normal
-            ) {
-                let (transformed_data, new_width, new_height) =
-                    apply_image_transform(&final_data, final_width, final_height, info.transform);
-                final_data = transformed_data;
-                final_width = new_width;
-                final_height = new_height;
-            }
+            self.dispatch(&mut event_queue)
+                .await
+                .map_err(WaitError::into_frame_error)?;
         }
+        drop(buffer);
 
-        let flags = {
-            let state = lock_frame_state(&frame_state)?;
-            state.flags
-        };
-
-        if (flags & ZWLR_SCREENCOPY_FRAME_V1_FLAGS_Y_INVERT) != 0 {
-            let (inverted_data, inv_width, inv_height) =
-                flip_vertical(&final_data, final_width, final_height);
-            final_data = inverted_data;
-            final_width = inv_width;
-            final_height = inv_height;
+        if lock_frame_state(&frame_state)?.failed {
+            return Err(Error::FrameCapture(
+                "Compositor failed to copy frame".to_string(),
+            ));
         }
 
-        Ok(CaptureResult {
-            data: final_data,
-            width: final_width,
-            height: final_height,
-        })
+        let raw = self.wlr_raw_frame(output.id().protocol_id(), &frame_state, layout)?;
+        Ok((raw, FramePixels::Pool))
     }
 
-    fn composite_region(
+    async fn composite_region(
         &mut self,
         region: Box,
         outputs: &[(WlOutput, OutputInfo)],
@@ -278,8 +294,9 @@ impl WaylandCapture {
                     intersection.width(),
                     intersection.height(),
                 );
-                let mut capture =
-                    self.capture_region_for_output(output, local_region, overlay_cursor)?;
+                let mut capture = self
+                    .capture_region_for_output(output, local_region, overlay_cursor, false)
+                    .await?;
 
                 if scale != 1.0 {
                     capture = self.scale_image_data(capture, 1.0 / scale)?;
@@ -306,37 +323,19 @@ impl WaylandCapture {
             ));
         }
 
-        Ok(CaptureResult {
-            data: dest,
-            width: region.width() as u32,
-            height: region.height() as u32,
-        })
+        Ok(CaptureResult::new(
+            dest,
+            region.width() as u32,
+            region.height() as u32,
+        ))
     }
 
-    pub fn get_outputs(&mut self) -> Result<Vec<Output>> {
-        self.refresh_outputs()?;
+    pub async fn get_outputs(&mut self) -> Result<Vec<Output>> {
+        self.refresh_outputs().await?;
         let snapshot = self.collect_outputs_snapshot();
         let outputs = snapshot
-            .into_iter()
-            .map(|(_, info)| {
-                let (x, y, width, height) = if info.logical_scale_known {
-                    (
-                        info.logical_x,
-                        info.logical_y,
-                        info.logical_width,
-                        info.logical_height,
-                    )
-                } else {
-                    (info.x, info.y, info.width, info.height)
-                };
-
-                Output {
-                    name: info.name.clone(),
-                    geometry: Box::new(x, y, width, height),
-                    scale: info.scale,
-                    description: info.description.clone(),
-                }
-            })
+            .iter()
+            .map(|(_, info)| info.to_output())
             .collect::<Vec<_>>();
         if outputs.is_empty() {
             return Err(Error::NoOutputs);
@@ -344,8 +343,8 @@ impl WaylandCapture {
         Ok(outputs)
     }
 
-    pub fn capture_all(&mut self) -> Result<CaptureResult> {
-        self.refresh_outputs()?;
+    pub async fn capture_all(&mut self) -> Result<CaptureResult> {
+        self.refresh_outputs().await?;
         let snapshot = self.collect_outputs_snapshot();
         if snapshot.is_empty() {
             return Err(Error::NoOutputs);
@@ -365,16 +364,16 @@ impl WaylandCapture {
         }
 
         let region = Box::new(min_x, min_y, max_x - min_x, max_y - min_y);
-        self.composite_region(region, &snapshot, false)
+        self.composite_region(region, &snapshot, false).await
     }
 
-    pub fn capture_all_with_scale(&mut self, scale: f64) -> Result<CaptureResult> {
-        let result = self.capture_all()?;
+    pub async fn capture_all_with_scale(&mut self, scale: f64) -> Result<CaptureResult> {
+        let result = self.capture_all().await?;
         self.scale_image_data(result, scale)
     }
 
-    pub fn capture_output(&mut self, output_name: &str) -> Result<CaptureResult> {
-        self.refresh_outputs()?;
+    pub async fn capture_output(&mut self, output_name: &str) -> Result<CaptureResult> {
+        self.refresh_outputs().await?;
         let snapshot = self.collect_outputs_snapshot();
         let (output_handle, info) = snapshot
             .into_iter()
@@ -382,34 +381,45 @@ impl WaylandCapture {
             .ok_or_else(|| Error::OutputNotFound(output_name.to_string()))?;
 
         let local_region = Box::new(0, 0, info.logical_width, info.logical_height);
-        self.capture_region_for_output(&output_handle, local_region, false)
+        self.capture_region_for_output(&output_handle, local_region, false, false)
+            .await
     }
 
-    pub fn capture_output_with_scale(
+    pub async fn capture_output_with_scale(
         &mut self,
         output_name: &str,
         scale: f64,
     ) -> Result<CaptureResult> {
-        let result = self.capture_output(output_name)?;
+        let result = self.capture_output(output_name).await?;
         self.scale_image_data(result, scale)
     }
 
-    pub fn capture_region(&mut self, region: Box) -> Result<CaptureResult> {
-        self.refresh_outputs()?;
+    pub async fn capture_region(&mut self, region: Box) -> Result<CaptureResult> {
+        self.refresh_outputs().await?;
         let snapshot = self.collect_outputs_snapshot();
-        self.composite_region(region, &snapshot, false)
+        self.composite_region(region, &snapshot, false).await
     }
 
-    pub fn capture_region_with_scale(&mut self, region: Box, scale: f64) -> Result<CaptureResult> {
-        let result = self.capture_region(region)?;
+    pub async fn capture_region_with_scale(
+        &mut self,
+        region: Box,
+        scale: f64,
+    ) -> Result<CaptureResult> {
+        let result = self.capture_region(region).await?;
         self.scale_image_data(result, scale)
     }
 
-    pub fn capture_outputs(
+    pub async fn capture_outputs(
         &mut self,
         parameters: Vec<CaptureParameters>,
     ) -> Result<MultiOutputCaptureResult> {
-        self.refresh_outputs()?;
+        self.refresh_outputs().await?;
+
+        if self.backend == CaptureBackend::ExtImageCopyCapture
+            || parameters.iter().any(CaptureParameters::dmabuf_enabled)
+        {
+            return self.capture_outputs_sequential(parameters).await;
+        }
 
         let screencopy_manager =
             self.globals
@@ -421,7 +431,12 @@ impl WaylandCapture {
         let mut event_queue = self._connection.new_event_queue();
         let qh = event_queue.handle();
         let mut frame_states: HashMap<String, Arc<Mutex<FrameState>>> = HashMap::new();
-        let mut frames: HashMap<String, ZwlrScreencopyFrameV1> = HashMap::new();
+        let mut frames: HashMap<String, DestroyOnDrop<ZwlrScreencopyFrameV1>> = HashMap::new();
+        // The frames are in flight together, so the strictest timeout bounds all of them.
+        let timeout = parameters
+            .iter()
+            .filter_map(|param| param.timeout_duration().or(self.timeout))
+            .min();
 
         for param in &parameters {
             let (output_id, output_info) = self
@@ -453,14 +468,7 @@ impl WaylandCapture {
             } else {
                 Box::new(0, 0, output_info.logical_width, output_info.logical_height)
             };
-            let frame_state = Arc::new(Mutex::new(FrameState {
-                buffer: None,
-                width: 0,
-                height: 0,
-                format: None,
-                ready: false,
-                flags: 0,
-            }));
+            let frame_state = Arc::new(Mutex::new(FrameState::default()));
             let frame = screencopy_manager.capture_output_region(
                 if param.overlay_cursor_enabled() { 1 } else { 0 },
                 output,
@@ -472,12 +480,12 @@ impl WaylandCapture {
                 frame_state.clone(),
             );
             frame_states.insert(param.output_name().to_string(), frame_state);
-            frames.insert(param.output_name().to_string(), frame);
+            frames.insert(param.output_name().to_string(), DestroyOnDrop::new(frame));
         }
-        let mut attempts = 0;
+        self.start_deadline(timeout);
         let mut completed_frames = 0;
         let total_frames = parameters.len();
-        while completed_frames < total_frames && attempts < MAX_ATTEMPTS {
+        while completed_frames < total_frames {
             completed_frames = frame_states
                 .iter()
                 .filter(|(_, state)| {
@@ -490,15 +498,9 @@ impl WaylandCapture {
             if completed_frames >= total_frames {
                 break;
             }
-            event_queue.blocking_dispatch(self).map_err(|e| {
-                Error::FrameCapture(format!("Failed to dispatch frame events: {}", e))
-            })?;
-            attempts += 1;
-        }
-        if attempts >= MAX_ATTEMPTS {
-            return Err(Error::FrameCapture(
-                "Timeout waiting for frame buffers".to_string(),
-            ));
+            self.dispatch(&mut event_queue)
+                .await
+                .map_err(WaitError::into_frame_error)?;
         }
         for frame_state in frame_states.values() {
             let state = lock_frame_state(frame_state)?;
@@ -506,69 +508,29 @@ impl WaylandCapture {
                 return Err(Error::CaptureFailed);
             }
         }
-        let mut buffers: HashMap<String, (tempfile::NamedTempFile, memmap2::MmapMut)> =
-            HashMap::new();
+        // Every frame gets its own slice of the shared shm pool.
+        let mut total_size = 0usize;
+        let mut slices: HashMap<String, (usize, BufferLayout)> = HashMap::new();
         for (output_name, frame_state) in &frame_states {
-            let (width, height, stride, size) = {
-                let state = lock_frame_state(frame_state)?;
-                let width = state.width;
-                let height = state.height;
-                let stride = width * 4;
-                let size = checked_buffer_size(width, height, 4, Some(stride))?;
-                (width, height, stride, size)
-            };
-            let mut tmp_file = tempfile::NamedTempFile::new().map_err(|e| {
-                Error::BufferCreation(format!(
-                    "failed to create temporary file for output '{}': {}",
-                    output_name, e
-                ))
-            })?;
-            tmp_file.as_file_mut().set_len(size as u64).map_err(|e| {
-                Error::BufferCreation(format!(
-                    "failed to resize buffer for output '{}' to {} bytes: {}",
-                    output_name, size, e
-                ))
+            let layout = lock_frame_state(frame_state)?.shm_layout()?;
+            let size = layout.size()?;
+            slices.insert(output_name.clone(), (total_size, layout));
+            total_size = total_size.checked_add(size).ok_or_else(|| {
+                Error::BufferCreation("combined output buffer size overflow".to_string())
             })?;
-            let mmap = unsafe {
-                memmap2::MmapMut::map_mut(&tmp_file).map_err(|e| {
-                    Error::BufferCreation(format!(
-                        "failed to memory-map buffer for output '{}': {}",
-                        output_name, e
-                    ))
-                })?
-            };
-            let shm = self.globals.shm.as_ref().ok_or(Error::UnsupportedProtocol(
-                "wl_shm not available".to_string(),
-            ))?;
-            {
-                let format = {
-                    let state = lock_frame_state(frame_state)?;
-                    state.format.unwrap_or(ShmFormat::Xrgb8888)
-                };
-                let pool = shm.create_pool(
-                    unsafe { BorrowedFd::borrow_raw(tmp_file.as_file().as_raw_fd()) },
-                    size as i32,
-                    &qh,
-                    (),
-                );
-                let buffer = pool.create_buffer(
-                    0,
-                    width as i32,
-                    height as i32,
-                    stride as i32,
-                    format,
-                    &qh,
-                    (),
-                );
-                if let Some(frame) = frames.get(output_name) {
-                    frame.copy(&buffer);
-                }
+        }
+        let pool = self.shm_pool(&qh, total_size)?;
+        let mut buffers: Vec<DestroyOnDrop<WlBuffer>> = Vec::new();
+        for output_name in frame_states.keys() {
+            let (offset, layout) = slices[output_name];
+            let buffer = DestroyOnDrop::new(pool.create_buffer(offset, layout, &qh));
+            if let Some(frame) = frames.get(output_name) {
+                frame.copy(&buffer);
             }
-            buffers.insert(output_name.clone(), (tmp_file, mmap));
+            buffers.push(buffer);
         }
-        let mut attempts = 0;
         let mut completed_frames = 0;
-        while completed_frames < total_frames && attempts < MAX_ATTEMPTS {
+        while completed_frames < total_frames {
             completed_frames = frame_states
                 .iter()
                 .filter(|(_, state)| state.lock().ok().is_some_and(|s| s.ready))
@@ -576,18 +538,18 @@ impl WaylandCapture {
             if completed_frames >= total_frames {
                 break;
             }
-            event_queue.blocking_dispatch(self).map_err(|e| {
-                Error::FrameCapture(format!("Failed to dispatch frame events: {}", e))
-            })?;
-            attempts += 1;
-        }
-        if attempts >= MAX_ATTEMPTS {
-            return Err(Error::FrameCapture(
-                "Timeout waiting for frame capture completion".to_string(),
-            ));
+            self.dispatch(&mut event_queue)
+                .await
+                .map_err(WaitError::into_frame_error)?;
         }
+        drop(buffers);
         for frame_state in frame_states.values() {
             let state = lock_frame_state(frame_state)?;
+            if state.failed {
+                return Err(Error::FrameCapture(
+                    "Compositor failed to copy frame".to_string(),
+                ));
+            }
             if state.ready && state.buffer.is_none() {
                 return Err(Error::FrameCapture(
                     "Frame is ready but buffer was not received".to_string(),
@@ -595,74 +557,76 @@ impl WaylandCapture {
             }
         }
         let mut results: HashMap<String, CaptureResult> = HashMap::new();
-        for (output_name, (_tmp_file, mmap)) in buffers {
+        for (output_name, (offset, layout)) in slices {
             let frame_state = &frame_states[&output_name];
-            let (width, height) = {
-                let state = lock_frame_state(frame_state)?;
-                (state.width, state.height)
-            };
-            let format = {
-                let state = lock_frame_state(frame_state)?;
-                state.format.unwrap_or(ShmFormat::Xrgb8888)
-            };
-            let mut buffer_data = mmap.to_vec();
-            convert_shm_to_rgba(&mut buffer_data, format);
-            let mut final_data = buffer_data;
-            let mut final_width = width;
-            let mut final_height = height;
-
-            if let Some(info) = self
+            let output_id = self
                 .globals
                 .output_info
-                .values()
-                .find(|info| info.name == output_name)
-            {
-                if !matches!(
-                    info.transform,
-                    wayland_client::protocol::wl_output::Transform::Normal
-                ) {
-                    let (transformed_data, new_width, new_height) = apply_image_transform(
-                        &final_data,
-                        final_width,
-                        final_height,
-                        info.transform,
-                    );
-                    final_data = transformed_data;
-                    final_width = new_width;
-                    final_height = new_height;
-                }
-            }
+                .iter()
+                .find(|(_, info)| info.name == output_name)
+                .map(|(id, _)| *id)
+                .ok_or_else(|| Error::OutputNotFound(output_name.clone()))?;
+            let raw = self.wlr_raw_frame(output_id, frame_state, layout)?;
+            let data = self
+                .shm_pool(&qh, total_size)?
+                .slice(offset, layout.size()?)?;
+            results.insert(output_name, raw.into_capture(data)?);
+        }
+        Ok(MultiOutputCaptureResult::new(results))
+    }
+
+    /// Multi-output capture that captures outputs one after another.
+    ///
+    /// Used by the ext-image-copy-capture backend, where each output gets its own capture
+    /// session, and when dmabuf copies are requested, which need per-frame buffer negotiation.
+    async fn capture_outputs_sequential(
+        &mut self,
+        parameters: Vec<CaptureParameters>,
+    ) -> Result<MultiOutputCaptureResult> {
+        let snapshot = self.collect_outputs_snapshot();
+        let mut results: HashMap<String, CaptureResult> = HashMap::new();
 
-            let flags = {
-                let state = lock_frame_state(frame_state)?;
-                state.flags
+        for param in &parameters {
+            let (output, output_info) = snapshot
+                .iter()
+                .find(|(_, info)| info.name == param.output_name())
+                .ok_or_else(|| Error::OutputNotFound(param.output_name().to_string()))?;
+            let region = if let Some(region) = param.region_ref() {
+                if region.x() < 0
+                    || region.y() < 0
+                    || region.x() + region.width() > output_info.logical_width
+                    || region.y() + region.height() > output_info.logical_height
+                {
+                    return Err(Error::InvalidRegion(
+                        "Capture region extends outside output boundaries".to_string(),
+                    ));
+                }
+                *region
+            } else {
+                Box::new(0, 0, output_info.logical_width, output_info.logical_height)
             };
 
-            if (flags & ZWLR_SCREENCOPY_FRAME_V1_FLAGS_Y_INVERT) != 0 {
-                let (inverted_data, inv_width, inv_height) =
-                    flip_vertical(&final_data, final_width, final_height);
-                final_data = inverted_data;
-                final_width = inv_width;
-                final_height = inv_height;
-            }
-            results.insert(
-                output_name,
-                CaptureResult {
-                    data: final_data,
-                    width: final_width,
-                    height: final_height,
-                },
-            );
+            self.start_deadline(param.timeout_duration().or(self.timeout));
+            let capture = self
+                .capture_region_for_output(
+                    output,
+                    region,
+                    param.overlay_cursor_enabled(),
+                    param.dmabuf_enabled(),
+                )
+                .await?;
+            results.insert(param.output_name().to_string(), capture);
         }
+
         Ok(MultiOutputCaptureResult::new(results))
     }
 
-    pub fn capture_outputs_with_scale(
+    pub async fn capture_outputs_with_scale(
         &mut self,
         parameters: Vec<CaptureParameters>,
         default_scale: f64,
     ) -> Result<MultiOutputCaptureResult> {
-        let result = self.capture_outputs(parameters)?;
+        let result = self.capture_outputs(parameters).await?;
         let mut scaled_results = std::collections::HashMap::new();
 
         for (output_name, capture_result) in result.into_outputs() {
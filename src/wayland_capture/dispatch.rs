@@ -0,0 +1,230 @@
+use super::*;
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+use wayland_client::backend::WaylandError;
+use wayland_client::{DispatchError, EventQueue};
+
+/// Drive a capture future to completion on the calling thread.
+///
+/// Without an async `Readiness` installed, every wait inside the
+/// capture code is a blocking dispatch, so the future is always ready on its first poll.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    struct NoopWaker;
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let future = pin!(future);
+    match future.poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("blocking capture future returned Pending"),
+    }
+}
+
+/// Why waiting for the compositor stopped.
+pub(super) enum WaitError {
+    Dispatch(DispatchError),
+    Timeout(Duration),
+    Cancelled,
+}
+
+impl From<DispatchError> for WaitError {
+    fn from(error: DispatchError) -> Self {
+        Self::Dispatch(error)
+    }
+}
+
+impl From<WaylandError> for WaitError {
+    fn from(error: WaylandError) -> Self {
+        Self::Dispatch(DispatchError::Backend(error))
+    }
+}
+
+impl WaitError {
+    /// Convert into a crate error, describing dispatch failures with `describe`.
+    pub(super) fn into_error(self, describe: impl FnOnce(DispatchError) -> Error) -> Error {
+        match self {
+            Self::Dispatch(error) => describe(error),
+            Self::Timeout(timeout) => Error::Timeout(timeout),
+            Self::Cancelled => Error::Cancelled,
+        }
+    }
+
+    /// `into_error` for waits on a frame.
+    pub(super) fn into_frame_error(self) -> Error {
+        self.into_error(|e| Error::FrameCapture(format!("Failed to dispatch frame events: {}", e)))
+    }
+}
+
+fn would_block(error: &WaylandError) -> bool {
+    matches!(error, WaylandError::Io(e) if e.kind() == std::io::ErrorKind::WouldBlock)
+}
+
+impl WaylandCapture {
+    /// Start the wall-clock budget for the operation about to run; `None` waits forever.
+    pub(super) fn start_deadline(&mut self, timeout: Option<Duration>) {
+        self.deadline =
+            timeout.and_then(|timeout| Some((Instant::now().checked_add(timeout)?, timeout)));
+    }
+
+    /// Wait for events on `event_queue` and dispatch them.
+    ///
+    /// Blocking waits `poll` the connection together with the cancellation token and
+    /// give up at the deadline set by `start_deadline`. With async readiness sources
+    /// installed the future yields instead, until the connection is readable, the token
+    /// is cancelled or the deadline passes.
+    pub(super) async fn dispatch(
+        &mut self,
+        event_queue: &mut EventQueue<Self>,
+    ) -> std::result::Result<usize, WaitError> {
+        loop {
+            let dispatched = event_queue.dispatch_pending(self)?;
+            if dispatched > 0 {
+                return Ok(dispatched);
+            }
+            if self
+                .cancellation
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+            {
+                return Err(WaitError::Cancelled);
+            }
+            match event_queue.flush() {
+                Err(e) if would_block(&e) => {}
+                result => result?,
+            }
+            // `None` means another reader queued events for us in the meantime.
+            let Some(guard) = event_queue.prepare_read() else {
+                continue;
+            };
+
+            #[cfg(feature = "async")]
+            if self.readiness.is_some() {
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(e) if would_block(&e) => self.wait_readable().await?,
+                    Err(e) => return Err(e.into()),
+                }
+                continue;
+            }
+
+            let timeout = match self.deadline {
+                Some((deadline, timeout)) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Err(WaitError::Timeout(timeout));
+                    }
+                    Some(left)
+                }
+                None => None,
+            };
+            if self.poll_connection(guard.connection_fd().as_raw_fd(), timeout)? {
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(e) if would_block(&e) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+
+    /// Send a `wl_display.sync` and dispatch `event_queue` until the compositor answers,
+    /// like `EventQueue::roundtrip`.
+    pub(super) async fn roundtrip(
+        &mut self,
+        event_queue: &mut EventQueue<Self>,
+    ) -> std::result::Result<(), WaitError> {
+        let done = Arc::new(AtomicBool::new(false));
+        self._connection
+            .display()
+            .sync(&event_queue.handle(), done.clone());
+        while !done.load(Ordering::Acquire) {
+            self.dispatch(event_queue).await?;
+        }
+        Ok(())
+    }
+
+    /// Block until `fd` is readable, the cancellation token fires or `timeout` passes.
+    ///
+    /// Returns whether the connection is readable; `false` on timeout or interruption,
+    /// which the caller re-checks against the deadline.
+    fn poll_connection(
+        &self,
+        fd: std::os::fd::RawFd,
+        timeout: Option<Duration>,
+    ) -> std::result::Result<bool, WaitError> {
+        let token_fd = self
+            .cancellation
+            .as_ref()
+            .map_or(-1, |token| std::os::fd::AsFd::as_fd(token).as_raw_fd());
+        // poll() ignores entries with a negative fd.
+        let mut fds = [
+            libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: token_fd,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        // Round up so a sub-millisecond remainder does not turn into a busy loop.
+        let timeout_ms = timeout.map_or(-1, |timeout| {
+            i32::try_from(timeout.as_nanos().div_ceil(1_000_000)).unwrap_or(i32::MAX)
+        });
+        // SAFETY: `fds` is a valid array of two pollfd structs for the whole call.
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms) };
+        if ready < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(false);
+            }
+            return Err(WaylandError::Io(error).into());
+        }
+        if fds[1].revents != 0 {
+            return Err(WaitError::Cancelled);
+        }
+        Ok(fds[0].revents != 0)
+    }
+
+    /// Yield until the async readiness source reports the connection readable, the
+    /// cancellation token fires or the deadline passes.
+    #[cfg(feature = "async")]
+    async fn wait_readable(&mut self) -> std::result::Result<(), WaitError> {
+        let cancellation = self.cancellation.clone();
+        let deadline = self.deadline;
+        let readiness = self
+            .readiness
+            .as_mut()
+            .expect("readiness checked by the caller");
+        std::future::poll_fn(|cx| {
+            if let Some(token) = &cancellation {
+                // Register before checking so a concurrent cancel cannot be missed.
+                token.register(cx.waker());
+                if token.is_cancelled() {
+                    return Poll::Ready(Err(WaitError::Cancelled));
+                }
+            }
+            if let Some((deadline, timeout)) = deadline {
+                match readiness.timer.poll_expired(deadline, cx) {
+                    Poll::Ready(Ok(())) => return Poll::Ready(Err(WaitError::Timeout(timeout))),
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(WaylandError::Io(e).into())),
+                    Poll::Pending => {}
+                }
+            }
+            readiness
+                .connection
+                .poll_readable(cx)
+                .map_err(|e| WaylandError::Io(e).into())
+        })
+        .await
+    }
+}
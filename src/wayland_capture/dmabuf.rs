@@ -0,0 +1,298 @@
+use super::shm_pool::{create_memfd, seal_shrink};
+use super::*;
+
+use std::fs::File;
+use std::os::fd::{AsFd, FromRawFd, OwnedFd};
+use wayland_client::backend::WeakBackend;
+use wayland_protocols::wp::linux_dmabuf::zv1::client::zwp_linux_buffer_params_v1::Flags as DmabufFlags;
+
+/// `DRM_FORMAT_MOD_LINEAR`: plain row-major layout that the CPU can read back directly.
+const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+
+const fn fourcc_code(a: u8, b: u8, c: u8, d: u8) -> u32 {
+    (a as u32) | ((b as u32) << 8) | ((c as u32) << 16) | ((d as u32) << 24)
+}
+
+const DRM_FORMAT_XRGB8888: u32 = fourcc_code(b'X', b'R', b'2', b'4');
+const DRM_FORMAT_ARGB8888: u32 = fourcc_code(b'A', b'R', b'2', b'4');
+const DRM_FORMAT_XBGR8888: u32 = fourcc_code(b'X', b'B', b'2', b'4');
+const DRM_FORMAT_ABGR8888: u32 = fourcc_code(b'A', b'B', b'2', b'4');
+
+/// `UDMABUF_CREATE = _IOW('u', 0x42, struct udmabuf_create)`.
+const UDMABUF_CREATE: libc::c_ulong = 0x4018_7542;
+const UDMABUF_FLAGS_CLOEXEC: u32 = 0x01;
+
+/// `DMA_BUF_IOCTL_SYNC = _IOW('b', 0, struct dma_buf_sync)`.
+const DMA_BUF_IOCTL_SYNC: libc::c_ulong = 0x4008_6200;
+const DMA_BUF_SYNC_READ: u64 = 1 << 0;
+const DMA_BUF_SYNC_START: u64 = 0;
+const DMA_BUF_SYNC_END: u64 = 1 << 2;
+
+#[repr(C)]
+struct UdmabufCreate {
+    memfd: u32,
+    flags: u32,
+    offset: u64,
+    size: u64,
+}
+
+#[repr(C)]
+struct DmaBufSync {
+    flags: u64,
+}
+
+/// Map a DRM fourcc to the `wl_shm` format with the same memory layout.
+///
+/// Only the 32-bit formats understood by `process_frame()` are accepted.
+pub(super) fn drm_fourcc_to_shm(fourcc: u32) -> Option<ShmFormat> {
+    match fourcc {
+        DRM_FORMAT_XRGB8888 => Some(ShmFormat::Xrgb8888),
+        DRM_FORMAT_ARGB8888 => Some(ShmFormat::Argb8888),
+        DRM_FORMAT_XBGR8888 => Some(ShmFormat::Xbgr8888),
+        DRM_FORMAT_ABGR8888 => Some(ShmFormat::Abgr8888),
+        _ => None,
+    }
+}
+
+/// State of a `zwp_linux_buffer_params_v1` import request.
+#[derive(Debug, Default)]
+pub(super) struct DmabufParamsState {
+    pub(super) buffer: Option<WlBuffer>,
+    pub(super) failed: bool,
+}
+
+/// Outcome of trying to copy a screencopy frame into a dmabuf.
+pub(super) enum DmabufCopy {
+    /// The frame was copied; contains the raw (unconverted) pixel data.
+    Copied { data: Vec<u8>, layout: BufferLayout },
+    /// The dmabuf path could not be set up; the same frame can still be copied via `wl_shm`.
+    Unavailable(String),
+    /// The compositor failed the copy into our dmabuf; the frame is consumed.
+    Rejected,
+}
+
+/// A linear buffer allocated from a sealed memfd and exported as a dmabuf via `/dev/udmabuf`.
+///
+/// This gives a dmabuf that is plain system memory, so it works without a GPU and can be
+/// read back by mapping the memfd.
+struct UdmabufAllocation {
+    memfd: File,
+    dmabuf: OwnedFd,
+}
+
+/// Wrap the first `size` bytes of a sealed memfd in a dmabuf through `/dev/udmabuf`.
+fn export_udmabuf(memfd: &File, size: usize) -> Result<OwnedFd> {
+    let udmabuf_dev = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/udmabuf")
+        .map_err(|e| Error::IoWithContext {
+            operation: "opening /dev/udmabuf".to_string(),
+            source: e,
+        })?;
+    let create = UdmabufCreate {
+        memfd: memfd.as_raw_fd() as u32,
+        flags: UDMABUF_FLAGS_CLOEXEC,
+        offset: 0,
+        size: size as u64,
+    };
+    let dmabuf = unsafe {
+        let fd = libc::ioctl(udmabuf_dev.as_raw_fd(), UDMABUF_CREATE, &create);
+        if fd < 0 {
+            return Err(Error::BufferCreation(format!(
+                "UDMABUF_CREATE failed: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+        OwnedFd::from_raw_fd(fd)
+    };
+    Ok(dmabuf)
+}
+
+/// Connections whose compositor imports any mappable fd as a dmabuf.
+///
+/// Only [`MockCompositor`](crate::testing::MockCompositor) registers connections here, so
+/// tests can run the dmabuf path without `/dev/udmabuf`. Everywhere else a missing
+/// udmabuf means no dmabuf, and the capture falls back to `wl_shm`.
+static MEMFD_DMABUF_CONNECTIONS: Mutex<Vec<WeakBackend>> = Mutex::new(Vec::new());
+
+/// Let captures on `connection` offer a plain memfd when udmabuf is unavailable.
+#[cfg(feature = "testing")]
+pub(crate) fn accept_memfd_dmabufs(connection: &Connection) {
+    if let Ok(mut connections) = MEMFD_DMABUF_CONNECTIONS.lock() {
+        connections.retain(|weak| weak.upgrade().is_some());
+        connections.push(connection.backend().downgrade());
+    }
+}
+
+fn accepts_memfd_dmabufs(connection: &Connection) -> bool {
+    let backend = connection.backend();
+    MEMFD_DMABUF_CONNECTIONS.lock().is_ok_and(|connections| {
+        connections
+            .iter()
+            .any(|weak| weak.upgrade().as_ref() == Some(&backend))
+    })
+}
+
+impl UdmabufAllocation {
+    /// Allocate `size` bytes, offering the memfd itself without udmabuf if `memfd_fallback`.
+    fn new(size: usize, memfd_fallback: bool) -> Result<Self> {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        let page_size = if page_size > 0 {
+            page_size as usize
+        } else {
+            4096
+        };
+        let size = size
+            .checked_next_multiple_of(page_size)
+            .ok_or_else(|| Error::BufferCreation("dmabuf size overflow".to_string()))?;
+
+        let memfd = create_memfd(c"grim-rs-dmabuf")
+            .map_err(|e| Error::BufferCreation(format!("memfd_create failed: {}", e)))?;
+        memfd.set_len(size as u64).map_err(|e| {
+            Error::BufferCreation(format!("failed to resize dmabuf to {} bytes: {}", size, e))
+        })?;
+        // udmabuf refuses memfds that could shrink under it.
+        seal_shrink(&memfd)
+            .map_err(|e| Error::BufferCreation(format!("failed to seal memfd: {}", e)))?;
+
+        let dmabuf = match export_udmabuf(&memfd, size) {
+            Ok(dmabuf) => dmabuf,
+            Err(err) if memfd_fallback => {
+                log::debug!("{}; offering the memfd as the dmabuf", err);
+                memfd.try_clone().map(OwnedFd::from).map_err(|e| {
+                    Error::BufferCreation(format!("failed to duplicate memfd: {}", e))
+                })?
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self { memfd, dmabuf })
+    }
+
+    fn sync(&self, flags: u64) {
+        let sync = DmaBufSync { flags };
+        if unsafe { libc::ioctl(self.dmabuf.as_raw_fd(), DMA_BUF_IOCTL_SYNC, &sync) } < 0 {
+            log::debug!(
+                "DMA_BUF_IOCTL_SYNC failed: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    /// Copy the first `len` bytes of the buffer to the CPU.
+    fn read(&self, len: usize) -> Result<Vec<u8>> {
+        let mmap = unsafe {
+            memmap2::Mmap::map(&self.memfd)
+                .map_err(|e| Error::BufferCreation(format!("failed to memory-map dmabuf: {}", e)))?
+        };
+        if mmap.len() < len {
+            return Err(Error::BufferCreation(
+                "dmabuf is smaller than the frame".to_string(),
+            ));
+        }
+        self.sync(DMA_BUF_SYNC_START | DMA_BUF_SYNC_READ);
+        let data = mmap[..len].to_vec();
+        self.sync(DMA_BUF_SYNC_END | DMA_BUF_SYNC_READ);
+        Ok(data)
+    }
+}
+
+impl WaylandCapture {
+    /// Copy a screencopy frame into a linear udmabuf and read it back.
+    ///
+    /// Must be called after the frame's buffer events have been received and before
+    /// `copy` has been issued on the frame.
+    pub(super) async fn copy_frame_dmabuf(
+        &mut self,
+        event_queue: &mut wayland_client::EventQueue<Self>,
+        frame: &ZwlrScreencopyFrameV1,
+        frame_state: &Arc<Mutex<FrameState>>,
+    ) -> Result<DmabufCopy> {
+        let qh = event_queue.handle();
+        let Some(linux_dmabuf) = self.globals.linux_dmabuf.clone() else {
+            return Ok(DmabufCopy::Unavailable(
+                "zwp_linux_dmabuf_v1 not available".to_string(),
+            ));
+        };
+        let Some((fourcc, width, height)) = lock_frame_state(frame_state)?.dmabuf else {
+            return Ok(DmabufCopy::Unavailable(
+                "compositor did not offer a dmabuf buffer".to_string(),
+            ));
+        };
+        let Some(format) = drm_fourcc_to_shm(fourcc) else {
+            return Ok(DmabufCopy::Unavailable(format!(
+                "unsupported DRM format {:#010x}",
+                fourcc
+            )));
+        };
+
+        let stride = width * 4;
+        let size = checked_buffer_size(width, height, 4, Some(stride))?;
+        let memfd_fallback = accepts_memfd_dmabufs(&self._connection);
+        let allocation = match UdmabufAllocation::new(size, memfd_fallback) {
+            Ok(allocation) => allocation,
+            Err(err) => return Ok(DmabufCopy::Unavailable(err.to_string())),
+        };
+
+        let params_state = Arc::new(Mutex::new(DmabufParamsState::default()));
+        let params = DestroyOnDrop::new(linux_dmabuf.create_params(&qh, params_state.clone()));
+        params.add(
+            allocation.dmabuf.as_fd(),
+            0,
+            0,
+            stride,
+            (DRM_FORMAT_MOD_LINEAR >> 32) as u32,
+            DRM_FORMAT_MOD_LINEAR as u32,
+        );
+        params.create(width as i32, height as i32, fourcc, DmabufFlags::empty());
+
+        let buffer = loop {
+            {
+                let mut state = lock_frame_state(&params_state)?;
+                if let Some(buffer) = state.buffer.take() {
+                    break Some(buffer);
+                }
+                if state.failed {
+                    break None;
+                }
+            }
+            self.dispatch(event_queue).await.map_err(|e| {
+                e.into_error(|e| {
+                    Error::FrameCapture(format!("Failed to dispatch dmabuf events: {}", e))
+                })
+            })?;
+        };
+        drop(params);
+        let Some(buffer) = buffer.map(DestroyOnDrop::new) else {
+            return Ok(DmabufCopy::Unavailable(
+                "compositor failed to import the dmabuf".to_string(),
+            ));
+        };
+
+        frame.copy(&buffer);
+
+        loop {
+            {
+                let state = lock_frame_state(frame_state)?;
+                if state.ready {
+                    break;
+                }
+            }
+            self.dispatch(event_queue)
+                .await
+                .map_err(WaitError::into_frame_error)?;
+        }
+        drop(buffer);
+
+        if lock_frame_state(frame_state)?.failed {
+            return Ok(DmabufCopy::Rejected);
+        }
+
+        let data = allocation.read(size)?;
+        Ok(DmabufCopy::Copied {
+            data,
+            layout: BufferLayout::packed(width, height, format)?,
+        })
+    }
+}
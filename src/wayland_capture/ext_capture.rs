@@ -0,0 +1,289 @@
+use super::frame::RawFrame;
+use super::*;
+
+use wayland_protocols::ext::image_copy_capture::v1::client::ext_image_copy_capture_manager_v1::Options as ExtCaptureOptions;
+
+/// `wl_shm` formats that `process_frame()` understands, in order of preference.
+///
+/// 8-bit formats come first because they are what the RGBA output holds anyway; the
+/// high bit depth formats are used when the compositor offers nothing else, and the lossy
+/// 16-bit formats only as a last resort.
+const EXT_PREFERRED_SHM_FORMATS: [ShmFormat; 16] = [
+    ShmFormat::Xrgb8888,
+    ShmFormat::Argb8888,
+    ShmFormat::Xbgr8888,
+    ShmFormat::Abgr8888,
+    ShmFormat::Rgb888,
+    ShmFormat::Bgr888,
+    ShmFormat::Xrgb2101010,
+    ShmFormat::Argb2101010,
+    ShmFormat::Xbgr2101010,
+    ShmFormat::Abgr2101010,
+    ShmFormat::Xbgr16161616,
+    ShmFormat::Abgr16161616,
+    ShmFormat::Xbgr16161616f,
+    ShmFormat::Abgr16161616f,
+    ShmFormat::Rgb565,
+    ShmFormat::Bgr565,
+];
+
+impl WaylandCapture {
+    /// Capture a region of an output through `ext_image_copy_capture_v1`.
+    ///
+    /// The ext protocol always captures the whole output in buffer coordinates, so the
+    /// full frame is captured, converted and transformed first, and then the requested
+    /// logical region is cropped out of it at the output's effective scale. The returned
+    /// image therefore has the same dimensions as the wlr-screencopy path would produce.
+    pub(super) async fn capture_region_ext(
+        &mut self,
+        output: &WlOutput,
+        region: Box,
+        overlay_cursor: bool,
+    ) -> Result<CaptureResult> {
+        let output_id = output.id().protocol_id();
+        let (logical_width, logical_height) = self
+            .globals
+            .output_info
+            .get(&output_id)
+            .map(|info| (info.logical_width, info.logical_height))
+            .ok_or_else(|| Error::OutputNotFound(format!("wl_output@{}", output_id)))?;
+
+        let full = self.capture_output_ext(output, overlay_cursor).await?;
+
+        if region.x() == 0
+            && region.y() == 0
+            && region.width() == logical_width
+            && region.height() == logical_height
+        {
+            return Ok(full);
+        }
+
+        let scale_x = if logical_width > 0 {
+            full.width as f64 / logical_width as f64
+        } else {
+            1.0
+        };
+        let scale_y = if logical_height > 0 {
+            full.height as f64 / logical_height as f64
+        } else {
+            1.0
+        };
+        let x1 = (region.x() as f64 * scale_x).floor() as i32;
+        let y1 = (region.y() as f64 * scale_y).floor() as i32;
+        let x2 = ((region.x() + region.width()) as f64 * scale_x).ceil() as i32;
+        let y2 = ((region.y() + region.height()) as f64 * scale_y).ceil() as i32;
+
+        crop_capture(&full, Box::new(x1, y1, x2 - x1, y2 - y1))
+    }
+
+    /// Capture a whole output through `ext_image_copy_capture_v1`.
+    async fn capture_output_ext(
+        &mut self,
+        output: &WlOutput,
+        overlay_cursor: bool,
+    ) -> Result<CaptureResult> {
+        let raw = self.copy_output_ext(output, overlay_cursor).await?;
+        let data = self.read_pool_frame(&raw)?;
+        raw.into_capture(data)
+    }
+
+    /// Copy a whole output into the shm pool through `ext_image_copy_capture_v1`.
+    pub(super) async fn copy_output_ext(
+        &mut self,
+        output: &WlOutput,
+        overlay_cursor: bool,
+    ) -> Result<RawFrame> {
+        let copy_manager =
+            self.globals
+                .image_copy_capture_manager
+                .as_ref()
+                .ok_or(Error::UnsupportedProtocol(
+                    "ext_image_copy_capture_manager_v1 not available".to_string(),
+                ))?;
+        let source_manager = self
+            .globals
+            .output_image_capture_source_manager
+            .as_ref()
+            .ok_or(Error::UnsupportedProtocol(
+                "ext_output_image_capture_source_manager_v1 not available".to_string(),
+            ))?;
+
+        let mut event_queue = self._connection.new_event_queue();
+        let qh = event_queue.handle();
+
+        let source = DestroyOnDrop::new(source_manager.create_source(output, &qh, ()));
+        let session_state = Arc::new(Mutex::new(ExtSessionState::default()));
+        let options = if overlay_cursor {
+            ExtCaptureOptions::PaintCursors
+        } else {
+            ExtCaptureOptions::empty()
+        };
+        let session = DestroyOnDrop::new(copy_manager.create_session(
+            &source,
+            options,
+            &qh,
+            session_state.clone(),
+        ));
+
+        let mut raw = self
+            .copy_session_frame(&mut event_queue, &session, &session_state)
+            .await?;
+        raw.output_name = self
+            .globals
+            .output_info
+            .get(&output.id().protocol_id())
+            .map(|info| info.name.clone());
+        Ok(raw)
+    }
+
+    /// Capture a single foreign toplevel through `ext_image_copy_capture_v1`.
+    pub(super) async fn capture_toplevel_ext(
+        &mut self,
+        toplevel: &ExtForeignToplevelHandleV1,
+        overlay_cursor: bool,
+    ) -> Result<CaptureResult> {
+        let copy_manager =
+            self.globals
+                .image_copy_capture_manager
+                .as_ref()
+                .ok_or(Error::UnsupportedProtocol(
+                    "ext_image_copy_capture_manager_v1 not available".to_string(),
+                ))?;
+        let source_manager = self
+            .globals
+            .toplevel_image_capture_source_manager
+            .as_ref()
+            .ok_or(Error::UnsupportedProtocol(
+                "ext_foreign_toplevel_image_capture_source_manager_v1 not available".to_string(),
+            ))?;
+
+        let mut event_queue = self._connection.new_event_queue();
+        let qh = event_queue.handle();
+
+        let source = DestroyOnDrop::new(source_manager.create_source(toplevel, &qh, ()));
+        let session_state = Arc::new(Mutex::new(ExtSessionState::default()));
+        let options = if overlay_cursor {
+            ExtCaptureOptions::PaintCursors
+        } else {
+            ExtCaptureOptions::empty()
+        };
+        let session = DestroyOnDrop::new(copy_manager.create_session(
+            &source,
+            options,
+            &qh,
+            session_state.clone(),
+        ));
+
+        let raw = self
+            .copy_session_frame(&mut event_queue, &session, &session_state)
+            .await?;
+        drop(session);
+        drop(source);
+        let data = self.read_pool_frame(&raw)?;
+        raw.into_capture(data)
+    }
+
+    fn read_pool_frame(&self, raw: &RawFrame) -> Result<&[u8]> {
+        self.shm_pool
+            .as_ref()
+            .ok_or_else(|| Error::BufferCreation("shm pool was not created".to_string()))?
+            .slice(0, raw.size())
+    }
+
+    /// Negotiate buffer constraints on an open session and copy a single frame into the
+    /// shm pool.
+    async fn copy_session_frame(
+        &mut self,
+        event_queue: &mut wayland_client::EventQueue<Self>,
+        session: &ExtImageCopyCaptureSessionV1,
+        session_state: &Arc<Mutex<ExtSessionState>>,
+    ) -> Result<RawFrame> {
+        let qh = event_queue.handle();
+
+        loop {
+            {
+                let state = lock_frame_state(session_state)?;
+                if state.stopped {
+                    return Err(Error::FrameCapture(
+                        "Capture session stopped before buffer constraints were received"
+                            .to_string(),
+                    ));
+                }
+                if state.done {
+                    break;
+                }
+            }
+            self.dispatch(event_queue).await.map_err(|e| {
+                e.into_error(|e| {
+                    Error::FrameCapture(format!("Failed to dispatch session events: {}", e))
+                })
+            })?;
+        }
+
+        let (width, height, format) = {
+            let state = lock_frame_state(session_state)?;
+            if state.width == 0 || state.height == 0 {
+                return Err(Error::CaptureFailed);
+            }
+            let format = EXT_PREFERRED_SHM_FORMATS
+                .iter()
+                .copied()
+                .find(|format| state.shm_formats.contains(format))
+                .ok_or_else(|| {
+                    Error::FrameCapture(format!(
+                        "Compositor offered no supported shm format (got {:?})",
+                        state.shm_formats
+                    ))
+                })?;
+            (state.width, state.height, format)
+        };
+        let layout = BufferLayout::packed(width, height, format)?;
+        let buffer = DestroyOnDrop::new(
+            self.shm_pool(&qh, layout.size()?)?
+                .create_buffer(0, layout, &qh),
+        );
+
+        let frame_state = Arc::new(Mutex::new(ExtFrameState {
+            ready: false,
+            failed: None,
+            transform: wayland_client::protocol::wl_output::Transform::Normal,
+            damage: Vec::new(),
+            timestamp: None,
+        }));
+        let frame = DestroyOnDrop::new(session.create_frame(&qh, frame_state.clone()));
+        frame.attach_buffer(&buffer);
+        frame.damage_buffer(0, 0, width as i32, height as i32);
+        frame.capture();
+
+        while !lock_frame_state(&frame_state)?.ready {
+            self.dispatch(event_queue)
+                .await
+                .map_err(WaitError::into_frame_error)?;
+        }
+        drop(frame);
+        drop(buffer);
+
+        let (transform, damage, timestamp) = {
+            let state = lock_frame_state(&frame_state)?;
+            if let Some(reason) = &state.failed {
+                return Err(Error::FrameCapture(format!(
+                    "Compositor failed to capture frame: {}",
+                    reason
+                )));
+            }
+            (state.transform, state.damage.clone(), state.timestamp)
+        };
+
+        Ok(RawFrame {
+            width,
+            height,
+            stride: layout.stride,
+            format,
+            transform,
+            y_invert: false,
+            timestamp,
+            damage,
+            output_name: None,
+        })
+    }
+}
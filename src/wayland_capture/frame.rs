@@ -0,0 +1,213 @@
+use super::pipeline::process_frame;
+use super::transform::{flip_vertical_rect, transform_rect};
+use super::*;
+use crate::{PixelFormat, Transform};
+
+/// A copied frame as the compositor delivered it, before any pixel processing.
+///
+/// Holds everything needed to turn the raw buffer into an upright RGBA image; the pixels
+/// themselves live either in the shared shm pool or in an owned buffer (see `FramePixels`).
+#[derive(Debug, Clone)]
+pub(super) struct RawFrame {
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) stride: u32,
+    pub(super) format: ShmFormat,
+    pub(super) transform: wayland_client::protocol::wl_output::Transform,
+    pub(super) y_invert: bool,
+    pub(super) timestamp: Option<std::time::Duration>,
+    /// Damage in buffer coordinates.
+    pub(super) damage: Vec<Box>,
+    pub(super) output_name: Option<String>,
+}
+
+/// Where the pixels of a copied frame ended up.
+pub(super) enum FramePixels {
+    /// At offset 0 of the shared shm pool.
+    Pool,
+    /// Read back from a dmabuf.
+    Owned(Vec<u8>),
+}
+
+impl RawFrame {
+    /// Size of the buffer in bytes.
+    pub(super) fn size(&self) -> usize {
+        self.stride as usize * self.height as usize
+    }
+
+    /// Convert the raw buffer to RGBA, undo the transform and Y-invert, and attach the
+    /// frame metadata (with damage mapped to image coordinates).
+    pub(super) fn into_capture(self, buffer_data: &[u8]) -> Result<CaptureResult> {
+        let frame = process_frame(
+            buffer_data,
+            self.width,
+            self.height,
+            self.stride,
+            self.format.into(),
+            self.transform.into(),
+            self.y_invert,
+        )?;
+
+        let damage = self
+            .damage
+            .into_iter()
+            .map(|rect| {
+                let rect = transform_rect(rect, self.width, self.height, self.transform);
+                if self.y_invert {
+                    flip_vertical_rect(rect, frame.height)
+                } else {
+                    rect
+                }
+            })
+            .collect();
+
+        let mut result = CaptureResult::new(frame.data, frame.width, frame.height);
+        result.data16 = frame.data16;
+        result.timestamp = self.timestamp;
+        result.output_name = self.output_name;
+        result.format = Some(self.format.into());
+        result.transform = self.transform.into();
+        result.y_invert = self.y_invert;
+        result.damage = damage;
+        Ok(result)
+    }
+}
+
+impl WaylandCapture {
+    /// Describe a finished screencopy frame, taking the transform from the output it
+    /// was captured from.
+    pub(super) fn wlr_raw_frame(
+        &self,
+        output_id: u32,
+        frame_state: &Arc<Mutex<FrameState>>,
+        layout: BufferLayout,
+    ) -> Result<RawFrame> {
+        let info = self.globals.output_info.get(&output_id);
+        let state = lock_frame_state(frame_state)?;
+        Ok(RawFrame {
+            width: layout.width,
+            height: layout.height,
+            stride: layout.stride,
+            format: layout.format,
+            transform: info
+                .map(|info| info.transform)
+                .unwrap_or(wayland_client::protocol::wl_output::Transform::Normal),
+            y_invert: (state.flags & ZWLR_SCREENCOPY_FRAME_V1_FLAGS_Y_INVERT) != 0,
+            timestamp: state.timestamp,
+            damage: state.damage.clone(),
+            output_name: info.map(|info| info.name.clone()),
+        })
+    }
+
+    pub async fn capture_output_view(&mut self, output_name: &str) -> Result<FrameView<'_>> {
+        self.refresh_outputs().await?;
+        let (output, info) = self
+            .collect_outputs_snapshot()
+            .into_iter()
+            .find(|(_, info)| info.name == output_name)
+            .ok_or_else(|| Error::OutputNotFound(output_name.to_string()))?;
+
+        let raw = match self.backend {
+            CaptureBackend::ExtImageCopyCapture => self.copy_output_ext(&output, false).await?,
+            CaptureBackend::WlrScreencopy | CaptureBackend::Auto => {
+                let region = Box::new(0, 0, info.logical_width, info.logical_height);
+                match self.copy_region_wlr(&output, region, false, false).await? {
+                    (raw, FramePixels::Pool) => raw,
+                    (_, FramePixels::Owned(_)) => {
+                        return Err(Error::FrameCapture(
+                            "Frame was not copied into the shm pool".to_string(),
+                        ))
+                    }
+                }
+            }
+        };
+
+        let data = self
+            .shm_pool
+            .as_ref()
+            .ok_or_else(|| Error::BufferCreation("shm pool was not created".to_string()))?
+            .slice(0, raw.size())?;
+        Ok(FrameView { data, raw })
+    }
+}
+
+/// A captured frame that borrows the mapped shm buffer instead of copying it.
+///
+/// Created by [`crate::Grim::capture_output_view`]. The pixels are exactly what the
+/// compositor wrote: in [`FrameView::format`], `stride()` bytes per row, and still in
+/// buffer orientation (see [`FrameView::transform`] and [`FrameView::y_invert`]). Nothing
+/// is converted or allocated until [`FrameView::to_rgba`] is called.
+///
+/// The view borrows the capture connection, so it must be dropped before the next capture.
+pub struct FrameView<'a> {
+    data: &'a [u8],
+    raw: RawFrame,
+}
+
+impl FrameView<'_> {
+    /// Raw pixel bytes in the native format, `stride() * height()` bytes long.
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+
+    /// Buffer width in pixels (before the output transform is applied).
+    pub fn width(&self) -> u32 {
+        self.raw.width
+    }
+
+    /// Buffer height in pixels (before the output transform is applied).
+    pub fn height(&self) -> u32 {
+        self.raw.height
+    }
+
+    /// Number of bytes between the starts of two consecutive rows.
+    pub fn stride(&self) -> u32 {
+        self.raw.stride
+    }
+
+    /// Pixel format of [`FrameView::data`].
+    pub fn format(&self) -> PixelFormat {
+        self.raw.format.into()
+    }
+
+    /// Output transform that still has to be undone to get an upright image.
+    pub fn transform(&self) -> Transform {
+        self.raw.transform.into()
+    }
+
+    /// Whether the rows are stored bottom-up.
+    pub fn y_invert(&self) -> bool {
+        self.raw.y_invert
+    }
+
+    /// Presentation time of the frame, if the compositor reported one.
+    pub fn timestamp(&self) -> Option<std::time::Duration> {
+        self.raw.timestamp
+    }
+
+    /// Name of the output the frame was captured from.
+    pub fn output_name(&self) -> Option<&str> {
+        self.raw.output_name.as_deref()
+    }
+
+    /// Iterate over the rows of the buffer, without stride padding.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        let bytes_per_pixel = self.format().bytes_per_pixel().unwrap_or(4);
+        let row_len = self.raw.width as usize * bytes_per_pixel as usize;
+        self.data
+            .chunks(self.raw.stride as usize)
+            .map(move |row| &row[..row_len])
+    }
+
+    /// Convert the frame to an upright RGBA [`CaptureResult`].
+    ///
+    /// This performs the same conversion, transform and Y-invert as the regular capture
+    /// methods.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnsupportedFormat` if the format has no known layout.
+    pub fn to_rgba(&self) -> Result<CaptureResult> {
+        self.raw.clone().into_capture(self.data)
+    }
+}
@@ -0,0 +1,85 @@
+use super::*;
+
+use std::ops::Deref;
+
+/// Protocol object released with a destructor request.
+pub(super) trait Destroy: Proxy {
+    fn destroy(&self);
+}
+
+macro_rules! destroy_request {
+    ($($proxy:ty),* $(,)?) => {
+        $(
+            impl Destroy for $proxy {
+                fn destroy(&self) {
+                    <$proxy>::destroy(self);
+                }
+            }
+        )*
+    };
+}
+
+destroy_request!(
+    WlBuffer,
+    WlSurface,
+    ZwlrLayerSurfaceV1,
+    ZwlrScreencopyFrameV1,
+    ZwpLinuxBufferParamsV1,
+    ExtImageCaptureSourceV1,
+    ExtImageCopyCaptureSessionV1,
+    ExtImageCopyCaptureFrameV1,
+);
+
+impl Destroy for WlPointer {
+    fn destroy(&self) {
+        // Before version 3 a pointer cannot be released and lives as long as the client.
+        if self.version() >= 3 {
+            self.release();
+        }
+    }
+}
+
+#[cfg(feature = "select")]
+destroy_request!(WpCursorShapeDeviceV1);
+
+#[cfg(feature = "select")]
+impl Destroy for WlKeyboard {
+    fn destroy(&self) {
+        if self.version() >= 3 {
+            self.release();
+        }
+    }
+}
+
+/// Destroys the wrapped protocol object when dropped.
+///
+/// Frames, buffers and sessions are held in these guards so they are released however
+/// a capture ends: success, error, timeout, cancellation or a dropped async future.
+/// Destroying an object that is already gone is ignored by `wayland-client`.
+///
+/// The request is flushed right away: after a timeout or cancellation the connection may
+/// sit idle, and the compositor should stop working on the frame.
+pub(super) struct DestroyOnDrop<P: Destroy>(P);
+
+impl<P: Destroy> DestroyOnDrop<P> {
+    pub(super) fn new(proxy: P) -> Self {
+        Self(proxy)
+    }
+}
+
+impl<P: Destroy> Deref for DestroyOnDrop<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.0
+    }
+}
+
+impl<P: Destroy> Drop for DestroyOnDrop<P> {
+    fn drop(&mut self) {
+        self.0.destroy();
+        if let Some(backend) = self.0.backend().upgrade() {
+            let _ = backend.flush();
+        }
+    }
+}
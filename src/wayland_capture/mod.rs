@@ -1,35 +1,95 @@
 pub(super) use crate::{
-    Box, CaptureParameters, CaptureResult, Error, MultiOutputCaptureResult, Output, Result,
+    Box, CaptureBackend, CaptureParameters, CaptureResult, Error, MultiOutputCaptureResult, Output,
+    OutputEvent, Result, StreamFrame, StreamParameters, Toplevel,
 };
 pub(super) use std::collections::HashMap;
-pub(super) use std::os::fd::{AsRawFd, BorrowedFd};
+pub(super) use std::os::fd::AsRawFd;
 pub(super) use std::sync::{Arc, Mutex};
+#[cfg(feature = "select")]
+pub(super) use wayland_client::protocol::wl_keyboard::WlKeyboard;
 pub(super) use wayland_client::{
+    globals::GlobalList,
     protocol::{
         wl_buffer::WlBuffer,
         wl_compositor::WlCompositor,
         wl_output::WlOutput,
+        wl_pointer::WlPointer,
         wl_registry::WlRegistry,
+        wl_seat::WlSeat,
         wl_shm::{Format as ShmFormat, WlShm},
         wl_shm_pool::WlShmPool,
+        wl_surface::WlSurface,
     },
     Connection, Dispatch, Proxy, QueueHandle,
 };
+pub(super) use wayland_protocols::ext::foreign_toplevel_list::v1::client::{
+    ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    ext_foreign_toplevel_list_v1::ExtForeignToplevelListV1,
+};
+pub(super) use wayland_protocols::ext::image_capture_source::v1::client::{
+    ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1,
+    ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+    ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1,
+};
+pub(super) use wayland_protocols::ext::image_copy_capture::v1::client::{
+    ext_image_copy_capture_frame_v1::ExtImageCopyCaptureFrameV1,
+    ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1,
+    ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1,
+};
+#[cfg(feature = "select")]
+pub(super) use wayland_protocols::wp::cursor_shape::v1::client::{
+    wp_cursor_shape_device_v1::WpCursorShapeDeviceV1,
+    wp_cursor_shape_manager_v1::WpCursorShapeManagerV1,
+};
+pub(super) use wayland_protocols::wp::linux_dmabuf::zv1::client::{
+    zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1,
+};
 pub(super) use wayland_protocols::xdg::xdg_output::zv1::client::{
     zxdg_output_manager_v1::ZxdgOutputManagerV1, zxdg_output_v1::ZxdgOutputV1,
 };
+pub(super) use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::ZwlrLayerShellV1, zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+};
 pub(super) use wayland_protocols_wlr::screencopy::v1::client::{
     zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1,
     zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
 };
 
+mod cancel;
 mod capture;
+mod dispatch;
+mod dmabuf;
+mod ext_capture;
+mod frame;
+mod guard;
+mod pipeline;
+mod pointer;
 mod scaling;
+#[cfg(feature = "select")]
+mod select;
+mod shm_pool;
+mod stream;
+mod toplevel;
 mod transform;
+mod watch;
 mod wayland_events;
 
+pub use cancel::CancellationToken;
+pub(crate) use dispatch::block_on;
+#[cfg(feature = "testing")]
+pub(crate) use dmabuf::accept_memfd_dmabufs;
+pub use frame::FrameView;
+pub(crate) use pipeline::process_frame;
+pub use stream::OutputStream;
+pub use watch::OutputWatch;
+
+use dispatch::WaitError;
+use guard::DestroyOnDrop;
+use shm_pool::ShmPool;
+
 pub(super) const ZWLR_SCREENCOPY_FRAME_V1_FLAGS_Y_INVERT: u32 = 1;
-pub(super) const MAX_ATTEMPTS: usize = 100;
+/// Wall-clock limit for a capture unless the caller sets another one.
+pub(crate) const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
 /// Global upper bound for pixel count used by `checked_buffer_size()`.
 ///
@@ -44,14 +104,85 @@ pub(super) const MAX_ATTEMPTS: usize = 100;
 /// The goal is to prevent integer overflows and avoid OOM from extreme sizes.
 pub(super) const MAX_PIXELS: u64 = 134_217_728;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub(super) struct FrameState {
     buffer: Option<Vec<u8>>,
     width: u32,
     height: u32,
+    /// Row stride from the `buffer` event, in bytes.
+    stride: u32,
     format: Option<ShmFormat>,
+    /// Raw `wl_shm` code from the `buffer` event, kept for error messages when the code
+    /// is not a format `wayland-client` knows.
+    format_code: Option<u32>,
     ready: bool,
+    failed: bool,
     flags: u32,
+    /// `(drm_fourcc, width, height)` from the `linux_dmabuf` event, if offered.
+    dmabuf: Option<(u32, u32, u32)>,
+    buffer_done: bool,
+    /// Regions reported by `damage` events, in buffer coordinates.
+    damage: Vec<Box>,
+    /// Presentation time from the `ready` event (`CLOCK_MONOTONIC`).
+    timestamp: Option<std::time::Duration>,
+}
+
+/// Constraints advertised by an `ext_image_copy_capture_session_v1`.
+///
+/// Filled by the session's `buffer_size`/`shm_format` events; `done` marks the end of
+/// a constraint batch and `stopped` means the source went away before we could capture.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ExtSessionState {
+    width: u32,
+    height: u32,
+    shm_formats: Vec<ShmFormat>,
+    done: bool,
+    stopped: bool,
+}
+
+/// Per-frame state for an `ext_image_copy_capture_frame_v1`.
+#[derive(Debug, Clone)]
+pub(super) struct ExtFrameState {
+    ready: bool,
+    failed: Option<String>,
+    transform: wayland_client::protocol::wl_output::Transform,
+    /// Regions reported by `damage` events, in buffer coordinates.
+    damage: Vec<Box>,
+    /// Presentation time from the `presentation_time` event (`CLOCK_MONOTONIC`).
+    timestamp: Option<std::time::Duration>,
+}
+
+/// State of an overlay surface, updated by its `zwlr_layer_surface_v1` events.
+#[derive(Debug, Clone, Default)]
+pub(super) struct OverlayState {
+    /// Size from the latest `configure`, already acknowledged.
+    size: Option<(u32, u32)>,
+    closed: bool,
+}
+
+/// Input received while selecting a region, in the order the compositor sent it.
+#[cfg(feature = "select")]
+#[derive(Debug, Clone)]
+pub(super) enum SelectInput {
+    /// The pointer entered `surface` at surface-local `(x, y)`.
+    Enter {
+        surface: wayland_client::backend::ObjectId,
+        serial: u32,
+        x: f64,
+        y: f64,
+    },
+    Motion {
+        x: f64,
+        y: f64,
+    },
+    Leave,
+    /// A pointer button (evdev code) was pressed or released.
+    Button {
+        button: u32,
+        pressed: bool,
+    },
+    /// A key (evdev code) was pressed.
+    Key(u32),
 }
 
 /// Compute a safe buffer size in bytes for image-like data.
@@ -104,58 +235,145 @@ pub(super) fn checked_buffer_size(
     usize::try_from(bytes).map_err(|_| Error::BufferCreation("Buffer size overflow".to_string()))
 }
 
-/// Safely lock a FrameState mutex, converting poisoned mutex errors to Result.
+/// Safely lock a frame/session state mutex, converting poisoned mutex errors to Result.
 ///
 /// This helper function provides proper error handling for mutex locks instead of panicking.
-pub(super) fn lock_frame_state(
-    frame_state: &Arc<Mutex<FrameState>>,
-) -> Result<std::sync::MutexGuard<'_, FrameState>> {
+pub(super) fn lock_frame_state<T>(
+    frame_state: &Arc<Mutex<T>>,
+) -> Result<std::sync::MutexGuard<'_, T>> {
     frame_state
         .lock()
         .map_err(|e| Error::FrameCapture(format!("Frame state mutex poisoned: {}", e)))
 }
 
-/// `wl_shm` 32-bit frame bytes to the crate's internal RGBA layout.
-///
-/// Why this exists:
-/// - `CaptureResult` data is exposed as RGBA.
-/// - `wl_shm` formats (`Xrgb8888`, `Argb8888`, etc.) are word-defined and on
-///   little-endian systems their byte order in memory is not always RGBA.
+/// Bytes per pixel of a `wl_shm` format the pixel pipeline can convert.
 ///
-/// Where this is used:
-/// - `capture_region_for_output()` after reading the mmap buffer.
-/// - `capture_outputs()` for each captured output buffer.
-///
-/// Notes:
-/// - Conversion is done in-place (no extra allocation).
-/// - Unsupported formats are left unchanged.
-pub(super) fn convert_shm_to_rgba(buffer_data: &mut [u8], format: ShmFormat) {
-    match format {
-        // x:R:G:B -> bytes in memory are B,G,R,x on little-endian.
-        ShmFormat::Xrgb8888 => {
-            for chunk in buffer_data.chunks_exact_mut(4) {
-                chunk.swap(0, 2);
-                chunk[3] = 255;
-            }
+/// Returns `Error::UnsupportedFormat` for anything else, so such buffers are rejected
+/// before they are copied instead of being handed back as corrupt data.
+pub(super) fn shm_bytes_per_pixel(format: ShmFormat) -> Result<u32> {
+    crate::PixelFormat::from(format)
+        .bytes_per_pixel()
+        .ok_or_else(|| Error::UnsupportedFormat(format!("wl_shm format {:?}", format)))
+}
+
+/// Size, row stride and format of a `wl_shm` buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct BufferLayout {
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) stride: u32,
+    pub(super) format: ShmFormat,
+}
+
+impl BufferLayout {
+    /// Layout with tightly packed rows, for buffers whose stride the client chooses.
+    pub(super) fn packed(width: u32, height: u32, format: ShmFormat) -> Result<Self> {
+        let stride = width
+            .checked_mul(shm_bytes_per_pixel(format)?)
+            .ok_or_else(|| Error::BufferCreation("Row stride overflow".to_string()))?;
+        Ok(Self {
+            width,
+            height,
+            stride,
+            format,
+        })
+    }
+
+    /// Size of the buffer in bytes.
+    pub(super) fn size(&self) -> Result<usize> {
+        checked_buffer_size(
+            self.width,
+            self.height,
+            shm_bytes_per_pixel(self.format)?,
+            Some(self.stride),
+        )
+    }
+}
+
+impl FrameState {
+    /// The `wl_shm` buffer layout the compositor asked for in its `buffer` event.
+    ///
+    /// Fails if no buffer was advertised, if the format cannot be converted, or if the
+    /// stride is too short for a row.
+    pub(super) fn shm_layout(&self) -> Result<BufferLayout> {
+        if self.width == 0 || self.height == 0 {
+            return Err(Error::CaptureFailed);
         }
-        // A:R:G:B -> bytes in memory are B,G,R,A on little-endian.
-        ShmFormat::Argb8888 => {
-            for chunk in buffer_data.chunks_exact_mut(4) {
-                chunk.swap(0, 2);
+        let format = match (self.format, self.format_code) {
+            (Some(format), _) => format,
+            (None, Some(code)) => {
+                return Err(Error::UnsupportedFormat(format!(
+                    "unknown wl_shm format {:#010x}",
+                    code
+                )))
             }
-        }
-        // x:B:G:R -> bytes in memory are R,G,B,x on little-endian.
-        ShmFormat::Xbgr8888 => {
-            for chunk in buffer_data.chunks_exact_mut(4) {
-                chunk[3] = 255;
+            (None, None) => {
+                return Err(Error::FrameCapture(
+                    "Compositor did not advertise a buffer format".to_string(),
+                ))
             }
+        };
+        let packed = BufferLayout::packed(self.width, self.height, format)?;
+        if self.stride < packed.stride {
+            return Err(Error::BufferCreation(format!(
+                "stride {} is too short for {} pixels of {:?}",
+                self.stride, self.width, format
+            )));
         }
-        // A:B:G:R -> bytes in memory are R,G,B,A on little-endian.
-        ShmFormat::Abgr8888 => {}
-        _ => {}
+        Ok(BufferLayout {
+            stride: self.stride,
+            ..packed
+        })
     }
 }
 
+impl From<ShmFormat> for crate::PixelFormat {
+    fn from(format: ShmFormat) -> Self {
+        match format {
+            ShmFormat::Argb8888 => Self::Argb8888,
+            ShmFormat::Xrgb8888 => Self::Xrgb8888,
+            ShmFormat::Abgr8888 => Self::Abgr8888,
+            ShmFormat::Xbgr8888 => Self::Xbgr8888,
+            ShmFormat::Rgb888 => Self::Rgb888,
+            ShmFormat::Bgr888 => Self::Bgr888,
+            ShmFormat::Rgb565 => Self::Rgb565,
+            ShmFormat::Bgr565 => Self::Bgr565,
+            ShmFormat::Xrgb2101010 => Self::Xrgb2101010,
+            ShmFormat::Argb2101010 => Self::Argb2101010,
+            ShmFormat::Xbgr2101010 => Self::Xbgr2101010,
+            ShmFormat::Abgr2101010 => Self::Abgr2101010,
+            ShmFormat::Xbgr16161616 => Self::Xbgr16161616,
+            ShmFormat::Abgr16161616 => Self::Abgr16161616,
+            ShmFormat::Xbgr16161616f => Self::Xbgr16161616f,
+            ShmFormat::Abgr16161616f => Self::Abgr16161616f,
+            other => Self::Other(other.into()),
+        }
+    }
+}
+
+/// Carry the capture metadata of `source` over to an image derived from it.
+///
+/// Damage boxes are remapped with `map_damage`; boxes for which it returns `None`
+/// (e.g. outside a crop) are dropped.
+pub(super) fn with_metadata_of(
+    mut result: CaptureResult,
+    source: &CaptureResult,
+    map_damage: impl Fn(Box) -> Option<Box>,
+) -> CaptureResult {
+    result.timestamp = source.timestamp;
+    result.output_name = source.output_name.clone();
+    result.format = source.format;
+    result.transform = source.transform;
+    result.y_invert = source.y_invert;
+    result.damage = source
+        .damage
+        .iter()
+        .copied()
+        .filter_map(map_damage)
+        .collect();
+    result
+}
+
 /// Guess logical geometry from physical geometry when xdg_output is not available.
 pub(super) fn guess_output_logical_geometry(info: &mut OutputInfo) {
     info.logical_x = info.x;
@@ -169,6 +387,7 @@ pub(super) fn guess_output_logical_geometry(info: &mut OutputInfo) {
         &mut info.logical_height,
     );
     info.logical_scale_known = true;
+    info.logical_guessed = true;
     update_logical_scale(info);
 }
 
@@ -226,8 +445,72 @@ pub(super) fn blit_capture(
     }
 }
 
+/// Copy a `width`×`height` block at `(x, y)` out of an RGBA image `src_width` pixels wide.
+fn crop_rows<T: Copy>(
+    src: &[T],
+    src_width: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> Vec<T> {
+    let mut data = Vec::with_capacity(width * height * 4);
+    for row in y..y + height {
+        let start = (row * src_width + x) * 4;
+        data.extend_from_slice(&src[start..start + width * 4]);
+    }
+    data
+}
+
+/// Copy a sub-rectangle out of an RGBA capture.
+///
+/// Used by the ext-image-copy-capture backend, which can only capture whole outputs:
+/// the requested region is cut out of the full (already transformed) output image.
+/// The crop box is clamped to the capture bounds.
+pub(super) fn crop_capture(capture: &CaptureResult, crop: Box) -> Result<CaptureResult> {
+    let src_width = capture.width as i64;
+    let src_height = capture.height as i64;
+    let x1 = (crop.x() as i64).clamp(0, src_width);
+    let y1 = (crop.y() as i64).clamp(0, src_height);
+    let x2 = (crop.x() as i64 + crop.width() as i64).clamp(0, src_width);
+    let y2 = (crop.y() as i64 + crop.height() as i64).clamp(0, src_height);
+    if x2 <= x1 || y2 <= y1 {
+        return Err(Error::InvalidRegion(
+            "Capture region does not intersect with the captured output".to_string(),
+        ));
+    }
+
+    let (x1, y1) = (x1 as usize, y1 as usize);
+    let width = (x2 as usize) - x1;
+    let height = (y2 as usize) - y1;
+    checked_buffer_size(width as u32, height as u32, 4, None)?;
+    let src_width = capture.width as usize;
+    let data = crop_rows(&capture.data, src_width, x1, y1, width, height);
+
+    let crop_box = Box::new(x1 as i32, y1 as i32, width as i32, height as i32);
+    let mut result = CaptureResult::new(data, width as u32, height as u32);
+    result.data16 = capture
+        .data16
+        .as_ref()
+        .map(|data16| crop_rows(data16, src_width, x1, y1, width, height));
+    Ok(with_metadata_of(result, capture, |rect| {
+        rect.intersection(&crop_box).map(|rect| {
+            Box::new(
+                rect.x() - crop_box.x(),
+                rect.y() - crop_box.y(),
+                rect.width(),
+                rect.height(),
+            )
+        })
+    }))
+}
+
 #[derive(Clone)]
 pub(super) struct OutputInfo {
+    /// Registry name of the `wl_output` global, stable for the output's lifetime.
+    global_name: u32,
+    /// Whether the last batch of changes was completed by a `done` event.
+    settled: bool,
     name: String,
     width: i32,
     height: i32,
@@ -241,53 +524,200 @@ pub(super) struct OutputInfo {
     logical_height: i32,
     logical_scale_known: bool,
     logical_scale: f64,
+    /// Whether the logical geometry was guessed from the mode and scale, without xdg-output.
+    logical_guessed: bool,
     description: Option<String>,
+    make: String,
+    model: String,
+    physical_width_mm: i32,
+    physical_height_mm: i32,
+    /// Refresh rate of the current mode in mHz, 0 if unknown.
+    refresh: i32,
+}
+
+impl OutputInfo {
+    /// Public view of the output, in logical coordinates when they are known.
+    pub(super) fn to_output(&self) -> Output {
+        let (x, y, width, height) = if self.logical_scale_known {
+            (
+                self.logical_x,
+                self.logical_y,
+                self.logical_width,
+                self.logical_height,
+            )
+        } else {
+            (self.x, self.y, self.width, self.height)
+        };
+
+        let (mut physical_width, mut physical_height) = (self.width, self.height);
+        transform::apply_output_transform(
+            self.transform,
+            &mut physical_width,
+            &mut physical_height,
+        );
+
+        Output {
+            name: self.name.clone(),
+            geometry: Box::new(x, y, width, height),
+            physical_geometry: Box::new(self.x, self.y, physical_width, physical_height),
+            mode_size: (self.width, self.height),
+            scale: self.scale,
+            logical_scale: self.logical_scale,
+            logical_scale_inferred: self.logical_guessed || !self.logical_scale_known,
+            transform: self.transform.into(),
+            description: self.description.clone(),
+            make: self.make.clone(),
+            model: self.model.clone(),
+            physical_size_mm: (self.physical_width_mm > 0 && self.physical_height_mm > 0)
+                .then_some((self.physical_width_mm, self.physical_height_mm)),
+            refresh_mhz: u32::try_from(self.refresh)
+                .ok()
+                .filter(|&refresh| refresh > 0),
+        }
+    }
+}
+
+/// Window metadata collected from `ext_foreign_toplevel_handle_v1` events.
+#[derive(Clone, Default)]
+pub(super) struct ToplevelInfo {
+    identifier: String,
+    title: String,
+    app_id: String,
 }
 
 pub(super) struct WaylandGlobals {
     compositor: Option<WlCompositor>,
     shm: Option<WlShm>,
+    linux_dmabuf: Option<ZwpLinuxDmabufV1>,
     screencopy_manager: Option<ZwlrScreencopyManagerV1>,
+    image_copy_capture_manager: Option<ExtImageCopyCaptureManagerV1>,
+    output_image_capture_source_manager: Option<ExtOutputImageCaptureSourceManagerV1>,
+    toplevel_image_capture_source_manager: Option<ExtForeignToplevelImageCaptureSourceManagerV1>,
+    foreign_toplevel_list: Option<ExtForeignToplevelListV1>,
     xdg_output_manager: Option<ZxdgOutputManagerV1>,
+    layer_shell: Option<ZwlrLayerShellV1>,
+    /// First seat advertised, and whether it currently has a pointer and a keyboard.
+    seat: Option<WlSeat>,
+    seat_has_pointer: bool,
+    #[cfg(feature = "select")]
+    seat_has_keyboard: bool,
+    #[cfg(feature = "select")]
+    cursor_shape_manager: Option<WpCursorShapeManagerV1>,
     outputs: Vec<WlOutput>,
     output_info: HashMap<u32, OutputInfo>,
     output_xdg_map: HashMap<u32, ZxdgOutputV1>,
+    toplevels: Vec<ExtForeignToplevelHandleV1>,
+    toplevel_info: HashMap<u32, ToplevelInfo>,
 }
 
 pub struct WaylandCapture {
     _connection: Connection,
     globals: WaylandGlobals,
+    backend: CaptureBackend,
+    /// shm pool reused by every capture on this connection; created on first use.
+    shm_pool: Option<ShmPool>,
+    /// Wall-clock limit for each operation; `None` waits forever.
+    timeout: Option<std::time::Duration>,
+    /// When the running operation gives up, and the timeout it was derived from.
+    deadline: Option<(std::time::Instant, std::time::Duration)>,
+    cancellation: Option<CancellationToken>,
+    /// Wakes async captures when the connection becomes readable or the deadline passes;
+    /// `None` for blocking use.
+    #[cfg(feature = "async")]
+    readiness: Option<crate::async_capture::AsyncWait>,
 }
 
 impl WaylandCapture {
-    pub fn new() -> Result<Self> {
+    pub fn new(backend: CaptureBackend) -> Result<Self> {
         let connection = Connection::connect_to_env().map_err(|e| {
             Error::WaylandConnection(format!("Failed to connect to Wayland: {}", e))
         })?;
+        Self::from_connection(connection, backend)
+    }
+
+    /// Set up capture on an already established connection.
+    pub fn from_connection(connection: Connection, backend: CaptureBackend) -> Result<Self> {
+        Self::initialize(connection, backend, |instance, qh| {
+            let _registry = instance._connection.display().get_registry(qh, ());
+        })
+    }
+
+    /// Set up capture from the globals an application already collected with
+    /// `registry_queue_init`, without a registry round trip of our own.
+    pub fn from_globals(
+        connection: &Connection,
+        globals: &GlobalList,
+        backend: CaptureBackend,
+    ) -> Result<Self> {
+        Self::initialize(connection.clone(), backend, |instance, qh| {
+            for global in globals.contents().clone_list() {
+                instance.bind_global(
+                    globals.registry(),
+                    global.name,
+                    &global.interface,
+                    global.version,
+                    qh,
+                );
+            }
+        })
+    }
+
+    /// Capture state for `connection` with nothing bound yet.
+    fn unbound(connection: Connection, backend: CaptureBackend) -> Self {
         let globals = WaylandGlobals {
             compositor: None,
             shm: None,
+            linux_dmabuf: None,
             screencopy_manager: None,
+            image_copy_capture_manager: None,
+            output_image_capture_source_manager: None,
+            toplevel_image_capture_source_manager: None,
+            foreign_toplevel_list: None,
             xdg_output_manager: None,
+            layer_shell: None,
+            seat: None,
+            seat_has_pointer: false,
+            #[cfg(feature = "select")]
+            seat_has_keyboard: false,
+            #[cfg(feature = "select")]
+            cursor_shape_manager: None,
             outputs: Vec::new(),
             output_info: HashMap::new(),
             output_xdg_map: HashMap::new(),
+            toplevels: Vec::new(),
+            toplevel_info: HashMap::new(),
         };
-        let mut event_queue = connection.new_event_queue();
-        let qh = event_queue.handle();
-        let _registry = connection.display().get_registry(&qh, ());
-        let mut instance = Self {
+        Self {
             _connection: connection,
             globals,
-        };
-        event_queue.roundtrip(&mut instance).map_err(|e| {
-            Error::WaylandConnection(format!("Failed to initialize Wayland globals: {}", e))
-        })?;
-        if instance.globals.screencopy_manager.is_none() {
-            return Err(Error::UnsupportedProtocol(
-                "zwlr_screencopy_manager_v1 not available".to_string(),
-            ));
+            backend,
+            shm_pool: None,
+            timeout: Some(DEFAULT_TIMEOUT),
+            deadline: None,
+            cancellation: None,
+            #[cfg(feature = "async")]
+            readiness: None,
         }
+    }
+
+    /// Create the capture state, let `bind` bind the globals onto a private queue, and
+    /// wait for their initial events.
+    fn initialize(
+        connection: Connection,
+        backend: CaptureBackend,
+        bind: impl FnOnce(&mut Self, &QueueHandle<Self>),
+    ) -> Result<Self> {
+        let mut event_queue = connection.new_event_queue();
+        let qh = event_queue.handle();
+        let mut instance = Self::unbound(connection, backend);
+        bind(&mut instance, &qh);
+        instance.start_deadline(instance.timeout);
+        block_on(instance.roundtrip(&mut event_queue)).map_err(|e| {
+            e.into_error(|e| {
+                Error::WaylandConnection(format!("Failed to initialize Wayland globals: {}", e))
+            })
+        })?;
+        instance.backend = instance.resolve_backend(backend)?;
         if instance.globals.shm.is_none() {
             return Err(Error::UnsupportedProtocol(
                 "wl_shm not available".to_string(),
@@ -295,4 +725,63 @@ impl WaylandCapture {
         }
         Ok(instance)
     }
+
+    /// Pick the capture protocol to use for this connection.
+    ///
+    /// `Auto` prefers the standardized ext-image-copy-capture protocol (matching upstream
+    /// grim) and falls back to wlr-screencopy. An explicitly requested backend must be
+    /// advertised by the compositor.
+    fn resolve_backend(&self, requested: CaptureBackend) -> Result<CaptureBackend> {
+        let has_ext = self.globals.image_copy_capture_manager.is_some()
+            && self.globals.output_image_capture_source_manager.is_some();
+        let has_wlr = self.globals.screencopy_manager.is_some();
+
+        match requested {
+            CaptureBackend::Auto if has_ext => Ok(CaptureBackend::ExtImageCopyCapture),
+            CaptureBackend::Auto if has_wlr => Ok(CaptureBackend::WlrScreencopy),
+            CaptureBackend::Auto => Err(Error::UnsupportedProtocol(
+                "neither ext_image_copy_capture_manager_v1 nor zwlr_screencopy_manager_v1 available"
+                    .to_string(),
+            )),
+            CaptureBackend::WlrScreencopy if has_wlr => Ok(CaptureBackend::WlrScreencopy),
+            CaptureBackend::WlrScreencopy => Err(Error::UnsupportedProtocol(
+                "zwlr_screencopy_manager_v1 not available".to_string(),
+            )),
+            CaptureBackend::ExtImageCopyCapture if has_ext => {
+                Ok(CaptureBackend::ExtImageCopyCapture)
+            }
+            CaptureBackend::ExtImageCopyCapture => Err(Error::UnsupportedProtocol(
+                "ext_image_copy_capture_manager_v1 or ext_output_image_capture_source_manager_v1 not available"
+                    .to_string(),
+            )),
+        }
+    }
+
+    pub fn backend(&self) -> CaptureBackend {
+        self.backend
+    }
+
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// The connection captures are dispatched on.
+    #[cfg(feature = "async")]
+    pub(crate) fn connection(&self) -> &Connection {
+        &self._connection
+    }
+
+    /// Install or remove the readiness sources used to wait without blocking.
+    #[cfg(feature = "async")]
+    pub(crate) fn set_readiness(&mut self, readiness: Option<crate::async_capture::AsyncWait>) {
+        self.readiness = readiness;
+    }
 }
@@ -0,0 +1,332 @@
+use crate::{Error, PixelFormat, Result, Transform};
+
+/// Number of source rows processed together for the 90/270 degree transforms.
+const TILE: usize = 64;
+
+/// Where source pixel `(x, y)` ends up in the destination, in pixels:
+/// `origin + x * step_x + y * step_y`.
+///
+/// For transforms that keep rows intact `step_x` is ±1; for the 90/270 variants `step_y`
+/// is ±1, so a source column becomes a destination row.
+struct Mapping {
+    width: u32,
+    height: u32,
+    rotated: bool,
+    origin: isize,
+    step_x: isize,
+    step_y: isize,
+}
+
+impl Mapping {
+    fn new(width: u32, height: u32, transform: Transform, y_invert: bool) -> Self {
+        let (w, h) = (width as isize, height as isize);
+        // dst_x = x0 + ax * x + bx * y, dst_y = y0 + ay * x + by * y
+        let (x0, ax, bx, y0, ay, by) = match transform {
+            Transform::Normal => (0, 1, 0, 0, 0, 1),
+            Transform::Rotated90 => (h - 1, 0, -1, 0, 1, 0),
+            Transform::Rotated180 => (w - 1, -1, 0, h - 1, 0, -1),
+            Transform::Rotated270 => (0, 0, 1, w - 1, -1, 0),
+            Transform::Flipped => (w - 1, -1, 0, 0, 0, 1),
+            Transform::Flipped90 => (h - 1, 0, -1, w - 1, -1, 0),
+            Transform::Flipped180 => (0, 1, 0, h - 1, 0, -1),
+            Transform::Flipped270 => (0, 0, 1, 0, 1, 0),
+        };
+        let rotated = ax == 0;
+        let (out_width, out_height) = if rotated {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let (y0, ay, by) = if y_invert {
+            (out_height as isize - 1 - y0, -ay, -by)
+        } else {
+            (y0, ay, by)
+        };
+        let row = out_width as isize;
+        Self {
+            width: out_width,
+            height: out_height,
+            rotated,
+            origin: y0 * row + x0,
+            step_x: ay * row + ax,
+            step_y: by * row + bx,
+        }
+    }
+
+    /// Element range of `len` destination pixels starting at pixel `start` and walking in
+    /// direction `step` (±1), in increasing address order.
+    fn span(start: isize, step: isize, len: usize) -> std::ops::Range<usize> {
+        let first = if step < 0 {
+            start - (len as isize - 1)
+        } else {
+            start
+        } as usize;
+        first * 4..(first + len) * 4
+    }
+}
+
+/// An upright RGBA image produced by `process_frame()`.
+pub(crate) struct ProcessedFrame {
+    pub(crate) data: Vec<u8>,
+    /// RGBA with 16 bits per channel, for formats with more than 8 bits per channel.
+    pub(crate) data16: Option<Vec<u16>>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+/// Convert a raw `width`×`height` frame with `stride` bytes per row into upright RGBA.
+///
+/// Channel conversion, the output transform and Y-invert are folded into one mapping from
+/// source to destination pixel, so every pixel is read and written exactly once. Transforms
+/// that keep rows intact are processed row by row; the 90/270 variants, which turn source
+/// rows into destination columns, are processed in bands of rows so both sides of the copy
+/// stay in cache.
+///
+/// 10-bit and 16-bit formats are converted to 16 bits per channel first, and the 8-bit
+/// image is derived from that. The final width and height are swapped for the 90/270
+/// degree transforms. `data` must hold at least
+/// `stride * (height - 1) + width * bytes_per_pixel` bytes.
+///
+/// Returns `Error::UnsupportedFormat` for [`PixelFormat::Other`], which has no known layout.
+pub(crate) fn process_frame(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: PixelFormat,
+    transform: Transform,
+    y_invert: bool,
+) -> Result<ProcessedFrame> {
+    if let PixelFormat::Other(code) = format {
+        return Err(Error::UnsupportedFormat(format!(
+            "wl_shm format {:#010x} has no known layout",
+            code
+        )));
+    }
+    let mapping = Mapping::new(width, height, transform, y_invert);
+    let src = Source {
+        data,
+        width: width as usize,
+        height: height as usize,
+        stride: stride as usize,
+    };
+    let len = width as usize * height as usize * 4;
+
+    let (data, data16) = if format.is_high_bit_depth() {
+        let mut out = vec![0u16; len];
+        if len > 0 {
+            match format {
+                PixelFormat::Xrgb2101010 => src.copy(&mut out, &mapping, |p: &[u8; 4]| {
+                    let v = u32::from_le_bytes(*p);
+                    [ten_bit(v, 20), ten_bit(v, 10), ten_bit(v, 0), u16::MAX]
+                }),
+                PixelFormat::Argb2101010 => src.copy(&mut out, &mapping, |p: &[u8; 4]| {
+                    let v = u32::from_le_bytes(*p);
+                    [ten_bit(v, 20), ten_bit(v, 10), ten_bit(v, 0), two_bit(v)]
+                }),
+                PixelFormat::Xbgr2101010 => src.copy(&mut out, &mapping, |p: &[u8; 4]| {
+                    let v = u32::from_le_bytes(*p);
+                    [ten_bit(v, 0), ten_bit(v, 10), ten_bit(v, 20), u16::MAX]
+                }),
+                PixelFormat::Abgr2101010 => src.copy(&mut out, &mapping, |p: &[u8; 4]| {
+                    let v = u32::from_le_bytes(*p);
+                    [ten_bit(v, 0), ten_bit(v, 10), ten_bit(v, 20), two_bit(v)]
+                }),
+                PixelFormat::Xbgr16161616 => src.copy(&mut out, &mapping, |p: &[u8; 8]| {
+                    [word(p, 0), word(p, 1), word(p, 2), u16::MAX]
+                }),
+                PixelFormat::Abgr16161616 => src.copy(&mut out, &mapping, |p: &[u8; 8]| {
+                    [word(p, 0), word(p, 1), word(p, 2), word(p, 3)]
+                }),
+                PixelFormat::Xbgr16161616f => src.copy(&mut out, &mapping, |p: &[u8; 8]| {
+                    [half(p, 0), half(p, 1), half(p, 2), u16::MAX]
+                }),
+                _ => src.copy(&mut out, &mapping, |p: &[u8; 8]| {
+                    [half(p, 0), half(p, 1), half(p, 2), half(p, 3)]
+                }),
+            }
+        }
+        (narrow_to_8bit(&out), Some(out))
+    } else {
+        let mut out = vec![0u8; len];
+        if len > 0 {
+            match format {
+                // x:R:G:B -> bytes in memory are B,G,R,x on little-endian.
+                PixelFormat::Xrgb8888 => {
+                    src.copy(&mut out, &mapping, |p: &[u8; 4]| [p[2], p[1], p[0], 255])
+                }
+                // A:R:G:B -> bytes in memory are B,G,R,A on little-endian.
+                PixelFormat::Argb8888 => {
+                    src.copy(&mut out, &mapping, |p: &[u8; 4]| [p[2], p[1], p[0], p[3]])
+                }
+                // x:B:G:R -> bytes in memory are R,G,B,x on little-endian.
+                PixelFormat::Xbgr8888 => {
+                    src.copy(&mut out, &mapping, |p: &[u8; 4]| [p[0], p[1], p[2], 255])
+                }
+                // A:B:G:R is already RGBA in memory.
+                PixelFormat::Abgr8888 => src.copy(&mut out, &mapping, |p: &[u8; 4]| *p),
+                // R:G:B -> bytes in memory are B,G,R.
+                PixelFormat::Rgb888 => {
+                    src.copy(&mut out, &mapping, |p: &[u8; 3]| [p[2], p[1], p[0], 255])
+                }
+                // B:G:R -> bytes in memory are R,G,B.
+                PixelFormat::Bgr888 => {
+                    src.copy(&mut out, &mapping, |p: &[u8; 3]| [p[0], p[1], p[2], 255])
+                }
+                PixelFormat::Rgb565 => src.copy(&mut out, &mapping, |p: &[u8; 2]| {
+                    let v = u16::from_le_bytes(*p);
+                    [five_bit(v, 11), six_bit(v, 5), five_bit(v, 0), 255]
+                }),
+                PixelFormat::Bgr565 => src.copy(&mut out, &mapping, |p: &[u8; 2]| {
+                    let v = u16::from_le_bytes(*p);
+                    [five_bit(v, 0), six_bit(v, 5), five_bit(v, 11), 255]
+                }),
+                _ => unreachable!("high bit depth and unknown formats are handled above"),
+            }
+        }
+        (out, None)
+    };
+
+    Ok(ProcessedFrame {
+        data,
+        data16,
+        width: mapping.width,
+        height: mapping.height,
+    })
+}
+
+/// Keep the 8 most significant bits of every 16-bit channel.
+pub(crate) fn narrow_to_8bit(data16: &[u16]) -> Vec<u8> {
+    data16.iter().map(|&value| (value >> 8) as u8).collect()
+}
+
+/// Extract the 5-bit channel at `shift` and widen it to 8 bits by bit replication.
+fn five_bit(value: u16, shift: u32) -> u8 {
+    let channel = ((value >> shift) & 0x1f) as u8;
+    (channel << 3) | (channel >> 2)
+}
+
+/// Extract the 6-bit channel at `shift` and widen it to 8 bits by bit replication.
+fn six_bit(value: u16, shift: u32) -> u8 {
+    let channel = ((value >> shift) & 0x3f) as u8;
+    (channel << 2) | (channel >> 4)
+}
+
+/// Extract the 10-bit channel at `shift` and widen it to 16 bits by bit replication.
+fn ten_bit(value: u32, shift: u32) -> u16 {
+    let channel = ((value >> shift) & 0x3ff) as u16;
+    (channel << 6) | (channel >> 4)
+}
+
+/// Widen the 2-bit alpha channel of a 2:10:10:10 pixel to 16 bits.
+fn two_bit(value: u32) -> u16 {
+    (value >> 30) as u16 * 0x5555
+}
+
+/// The `index`-th little-endian 16-bit word of a pixel.
+fn word(pixel: &[u8; 8], index: usize) -> u16 {
+    u16::from_le_bytes([pixel[index * 2], pixel[index * 2 + 1]])
+}
+
+/// The `index`-th half-float channel of a pixel, clamped to `[0, 1]` and scaled to 16 bits.
+fn half(pixel: &[u8; 8], index: usize) -> u16 {
+    let value = half_to_f32(word(pixel, index));
+    // NaN saturates to 0 in the cast.
+    (value.clamp(0.0, 1.0) * 65535.0).round() as u16
+}
+
+/// Decode an IEEE 754 binary16 value.
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as u32;
+    let magnitude = match exponent {
+        0 => mantissa as f32 / (1u32 << 24) as f32,
+        0x1f if mantissa == 0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => f32::from_bits(((exponent as u32 + 112) << 23) | (mantissa << 13)),
+    };
+    sign * magnitude
+}
+
+struct Source<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+}
+
+impl Source<'_> {
+    fn pixel<const BPP: usize>(&self, x: usize, y: usize) -> &[u8; BPP] {
+        let start = y * self.stride + x * BPP;
+        self.data[start..start + BPP]
+            .try_into()
+            .expect("slice has BPP bytes")
+    }
+
+    fn copy<T, F, const BPP: usize>(&self, out: &mut [T], mapping: &Mapping, convert: F)
+    where
+        T: Copy,
+        F: Fn(&[u8; BPP]) -> [T; 4],
+    {
+        if mapping.rotated {
+            self.copy_columns(out, mapping, &convert);
+        } else {
+            self.copy_rows(out, mapping, &convert);
+        }
+    }
+
+    /// Every source row lands on one destination row, possibly reversed.
+    fn copy_rows<T, F, const BPP: usize>(&self, out: &mut [T], mapping: &Mapping, convert: &F)
+    where
+        T: Copy,
+        F: Fn(&[u8; BPP]) -> [T; 4],
+    {
+        let row_len = self.width * BPP;
+        for y in 0..self.height {
+            let src = &self.data[y * self.stride..y * self.stride + row_len];
+            let start = mapping.origin + y as isize * mapping.step_y;
+            let dst = &mut out[Mapping::span(start, mapping.step_x, self.width)];
+            let pixels = src
+                .chunks_exact(BPP)
+                .map(|p| convert(p.try_into().expect("chunk has BPP bytes")));
+            if mapping.step_x < 0 {
+                for (d, p) in dst.chunks_exact_mut(4).rev().zip(pixels) {
+                    d.copy_from_slice(&p);
+                }
+            } else {
+                for (d, p) in dst.chunks_exact_mut(4).zip(pixels) {
+                    d.copy_from_slice(&p);
+                }
+            }
+        }
+    }
+
+    /// Every source column lands on one destination row, possibly reversed. Works in
+    /// bands of `TILE` source rows, so the rows being read stay in cache while each column
+    /// of the band is written out as one contiguous run.
+    fn copy_columns<T, F, const BPP: usize>(&self, out: &mut [T], mapping: &Mapping, convert: &F)
+    where
+        T: Copy,
+        F: Fn(&[u8; BPP]) -> [T; 4],
+    {
+        for tile_y in (0..self.height).step_by(TILE) {
+            let end_y = (tile_y + TILE).min(self.height);
+            for x in 0..self.width {
+                let column = mapping.origin + x as isize * mapping.step_x;
+                let start = column + tile_y as isize * mapping.step_y;
+                let dst = &mut out[Mapping::span(start, mapping.step_y, end_y - tile_y)];
+                let pixels = (tile_y..end_y).map(|y| convert(self.pixel(x, y)));
+                if mapping.step_y < 0 {
+                    for (d, p) in dst.chunks_exact_mut(4).rev().zip(pixels) {
+                        d.copy_from_slice(&p);
+                    }
+                } else {
+                    for (d, p) in dst.chunks_exact_mut(4).zip(pixels) {
+                        d.copy_from_slice(&p);
+                    }
+                }
+            }
+        }
+    }
+}
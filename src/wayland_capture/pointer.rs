@@ -0,0 +1,142 @@
+use super::*;
+
+use wayland_client::backend::ObjectId;
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::Layer,
+    zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity},
+};
+
+/// Transparent layer surface covering one output.
+///
+/// Fields drop in declaration order, so the role object goes before its surface and the
+/// buffer last.
+struct Overlay {
+    output_name: String,
+    state: Arc<Mutex<OverlayState>>,
+    _layer_surface: DestroyOnDrop<ZwlrLayerSurfaceV1>,
+    surface: DestroyOnDrop<WlSurface>,
+    buffer: Option<DestroyOnDrop<WlBuffer>>,
+}
+
+impl WaylandCapture {
+    /// Name of the output under the pointer.
+    ///
+    /// Clients only learn the pointer position over their own surfaces, so an empty,
+    /// fully transparent overlay is mapped on every output with `zwlr_layer_shell_v1`,
+    /// and the output whose overlay receives `wl_pointer.enter` wins. The overlays take
+    /// no keyboard focus and are destroyed before this returns.
+    pub async fn pointer_output(&mut self) -> Result<String> {
+        self.refresh_outputs().await?;
+        let (compositor, layer_shell, seat) = self.overlay_globals()?;
+
+        let mut event_queue = self._connection.new_event_queue();
+        let qh = event_queue.handle();
+        let entered: Arc<Mutex<Option<ObjectId>>> = Arc::new(Mutex::new(None));
+        let _pointer = DestroyOnDrop::new(seat.get_pointer(&qh, entered.clone()));
+
+        let mut overlays = Vec::new();
+        for output in &self.globals.outputs {
+            let Some(info) = self.globals.output_info.get(&output.id().protocol_id()) else {
+                continue;
+            };
+            let state = Arc::new(Mutex::new(OverlayState::default()));
+            let surface = DestroyOnDrop::new(compositor.create_surface(&qh, ()));
+            let layer_surface = DestroyOnDrop::new(layer_shell.get_layer_surface(
+                &surface,
+                Some(output),
+                Layer::Overlay,
+                "grim-rs".to_string(),
+                &qh,
+                state.clone(),
+            ));
+            layer_surface.set_anchor(Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right);
+            layer_surface.set_exclusive_zone(-1);
+            layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+            surface.commit();
+            overlays.push(Overlay {
+                output_name: info.name.clone(),
+                state,
+                _layer_surface: layer_surface,
+                surface,
+                buffer: None,
+            });
+        }
+
+        loop {
+            for overlay in &mut overlays {
+                if overlay.buffer.is_some() {
+                    continue;
+                }
+                let size = lock_frame_state(&overlay.state)?.size;
+                if let Some(size) = size {
+                    overlay.buffer =
+                        Some(self.attach_transparent_buffer(&overlay.surface, size, &qh)?);
+                }
+            }
+
+            if let Some(ref surface) = *lock_frame_state(&entered)? {
+                if let Some(overlay) = overlays
+                    .iter()
+                    .find(|overlay| overlay.surface.id() == *surface)
+                {
+                    return Ok(overlay.output_name.clone());
+                }
+            }
+            let mut closed = true;
+            for overlay in &overlays {
+                closed &= lock_frame_state(&overlay.state)?.closed;
+            }
+            if closed {
+                return Err(Error::WaylandConnection(
+                    "Compositor closed the overlays before the pointer entered one".to_string(),
+                ));
+            }
+
+            self.dispatch(&mut event_queue).await.map_err(|e| {
+                e.into_error(|e| {
+                    Error::WaylandConnection(format!("Failed to dispatch pointer events: {}", e))
+                })
+            })?;
+        }
+    }
+
+    /// The globals needed to map overlays and follow the pointer over them.
+    pub(super) fn overlay_globals(&self) -> Result<(WlCompositor, ZwlrLayerShellV1, WlSeat)> {
+        let compositor =
+            self.globals.compositor.clone().ok_or_else(|| {
+                Error::UnsupportedProtocol("wl_compositor not available".to_string())
+            })?;
+        let layer_shell = self.globals.layer_shell.clone().ok_or_else(|| {
+            Error::UnsupportedProtocol("zwlr_layer_shell_v1 not available".to_string())
+        })?;
+        match self.globals.seat {
+            Some(ref seat) if self.globals.seat_has_pointer => {
+                Ok((compositor, layer_shell, seat.clone()))
+            }
+            _ => Err(Error::UnsupportedProtocol(
+                "no wl_seat with a pointer".to_string(),
+            )),
+        }
+    }
+
+    /// Give `surface` a fully transparent buffer of `width`×`height` and commit it.
+    fn attach_transparent_buffer(
+        &mut self,
+        surface: &WlSurface,
+        (width, height): (u32, u32),
+        qh: &QueueHandle<Self>,
+    ) -> Result<DestroyOnDrop<WlBuffer>> {
+        // Anchored to all edges the compositor always picks the size; guard against a
+        // zero anyway, since a buffer cannot be empty.
+        let layout = BufferLayout::packed(width.max(1), height.max(1), ShmFormat::Argb8888)?;
+        let size = layout.size()?;
+        let pool = self.shm_pool(qh, size)?;
+        // The pool may still hold an earlier capture; all overlays share these zeroes.
+        pool.slice_mut(0, size)?.fill(0);
+        let buffer = DestroyOnDrop::new(pool.create_buffer(0, layout, qh));
+        surface.attach(Some(&buffer), 0, 0);
+        surface.damage(0, 0, layout.width as i32, layout.height as i32);
+        surface.commit();
+        Ok(buffer)
+    }
+}
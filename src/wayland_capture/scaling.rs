@@ -30,14 +30,15 @@ impl WaylandCapture {
 
         use image::{imageops, ImageBuffer, Rgba};
 
-        let img =
-            ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(old_width, old_height, capture_result.data)
-                .ok_or_else(|| {
-                    Error::ScalingFailed(format!(
-                        "failed to create image buffer for scaling {}x{} -> {}x{}",
-                        old_width, old_height, new_width, new_height
-                    ))
-                })?;
+        let mut capture_result = capture_result;
+        let data = std::mem::take(&mut capture_result.data);
+        let img = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(old_width, old_height, data)
+            .ok_or_else(|| {
+                Error::ScalingFailed(format!(
+                    "failed to create image buffer for scaling {}x{} -> {}x{}",
+                    old_width, old_height, new_width, new_height
+                ))
+            })?;
 
         let filter = if scale > 1.0 {
             imageops::FilterType::Nearest
@@ -51,11 +52,12 @@ impl WaylandCapture {
 
         let scaled_img = imageops::resize(&img, new_width, new_height, filter);
 
-        Ok(CaptureResult {
-            data: scaled_img.into_raw(),
-            width: new_width,
-            height: new_height,
-        })
+        let scale_x = new_width as f64 / old_width as f64;
+        let scale_y = new_height as f64 / old_height as f64;
+        let result = CaptureResult::new(scaled_img.into_raw(), new_width, new_height);
+        Ok(with_metadata_of(result, &capture_result, |rect| {
+            scale_rect(rect, scale_x, scale_y)
+        }))
     }
 
     /// Fast scaling for integer multipliers (2x, 3x, 4x)
@@ -111,10 +113,27 @@ impl WaylandCapture {
             }
         }
 
-        Ok(CaptureResult::new(
-            new_data,
-            new_width as u32,
-            new_height as u32,
-        ))
+        let result = CaptureResult::new(new_data, new_width as u32, new_height as u32);
+        let factor = factor as i32;
+        Ok(with_metadata_of(result, &capture, |rect| {
+            Some(Box::new(
+                rect.x() * factor,
+                rect.y() * factor,
+                rect.width() * factor,
+                rect.height() * factor,
+            ))
+        }))
     }
 }
+
+/// Scale a damage box, rounding outwards so it still covers every changed pixel.
+fn scale_rect(rect: Box, scale_x: f64, scale_y: f64) -> Option<Box> {
+    let x1 = (rect.x() as f64 * scale_x).floor() as i32;
+    let y1 = (rect.y() as f64 * scale_y).floor() as i32;
+    let x2 = ((rect.x() + rect.width()) as f64 * scale_x).ceil() as i32;
+    let y2 = ((rect.y() + rect.height()) as f64 * scale_y).ceil() as i32;
+    if x2 <= x1 || y2 <= y1 {
+        return None;
+    }
+    Some(Box::new(x1, y1, x2 - x1, y2 - y1))
+}
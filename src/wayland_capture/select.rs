@@ -0,0 +1,275 @@
+use super::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::Layer,
+    zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity},
+};
+
+use crate::select::Selection;
+
+/// evdev codes from `linux/input-event-codes.h`.
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const KEY_ESC: u32 = 1;
+
+/// An overlay's buffer and where it lives in the shm pool.
+struct OverlayBuffer {
+    buffer: DestroyOnDrop<WlBuffer>,
+    /// Set while the compositor holds the buffer, until it sends `release`.
+    busy: Arc<AtomicBool>,
+    offset: usize,
+    layout: BufferLayout,
+}
+
+/// Shaded layer surface covering one output.
+///
+/// Fields drop in declaration order, so the role object goes before its surface and the
+/// buffer last.
+struct SelectOverlay {
+    /// Global logical position of the output.
+    origin: (i32, i32),
+    state: Arc<Mutex<OverlayState>>,
+    _layer_surface: DestroyOnDrop<ZwlrLayerSurfaceV1>,
+    surface: DestroyOnDrop<WlSurface>,
+    buffer: Option<OverlayBuffer>,
+    /// Whether the buffer shows an outdated highlight.
+    dirty: bool,
+}
+
+impl WaylandCapture {
+    /// Let the user select a region on a shaded overlay.
+    ///
+    /// Maps a fullscreen `zwlr_layer_shell_v1` overlay on every output and feeds pointer
+    /// input to a [`Selection`] over the outputs and `windows`, redrawing the overlays
+    /// into shm buffers as the highlight changes. Escape or the right button cancels.
+    ///
+    /// Waiting for the user is not subject to the timeout; a cancellation token still
+    /// aborts it. The overlays are gone from the compositor by the time this returns.
+    pub async fn select_region(&mut self, windows: Vec<Box>) -> Result<Box> {
+        self.refresh_outputs().await?;
+        let result = self.run_selection(windows).await;
+
+        // Make sure the compositor has unmapped the overlays before anything is captured.
+        self.start_deadline(self.timeout);
+        let mut event_queue = self._connection.new_event_queue();
+        self.roundtrip(&mut event_queue).await.map_err(|e| {
+            e.into_error(|e| {
+                Error::WaylandConnection(format!("Failed to remove selection overlays: {}", e))
+            })
+        })?;
+        result
+    }
+
+    async fn run_selection(&mut self, windows: Vec<Box>) -> Result<Box> {
+        let (compositor, layer_shell, seat) = self.overlay_globals()?;
+        self.start_deadline(None);
+
+        let mut event_queue = self._connection.new_event_queue();
+        let qh = event_queue.handle();
+        let input = Arc::new(Mutex::new(Vec::new()));
+        let pointer = DestroyOnDrop::new(seat.get_pointer(&qh, input.clone()));
+        let _keyboard = self
+            .globals
+            .seat_has_keyboard
+            .then(|| DestroyOnDrop::new(seat.get_keyboard(&qh, input.clone())));
+        let cursor_shape = self
+            .globals
+            .cursor_shape_manager
+            .as_ref()
+            .map(|manager| DestroyOnDrop::new(manager.get_pointer(&pointer, &qh, ())));
+
+        let mut outputs = Vec::new();
+        let mut overlays = Vec::new();
+        for output in &self.globals.outputs {
+            let Some(info) = self.globals.output_info.get(&output.id().protocol_id()) else {
+                continue;
+            };
+            outputs.push(Box::new(
+                info.logical_x,
+                info.logical_y,
+                info.logical_width,
+                info.logical_height,
+            ));
+            let state = Arc::new(Mutex::new(OverlayState::default()));
+            let surface = DestroyOnDrop::new(compositor.create_surface(&qh, ()));
+            let layer_surface = DestroyOnDrop::new(layer_shell.get_layer_surface(
+                &surface,
+                Some(output),
+                Layer::Overlay,
+                "selection".to_string(),
+                &qh,
+                state.clone(),
+            ));
+            layer_surface.set_anchor(Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right);
+            layer_surface.set_exclusive_zone(-1);
+            layer_surface.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+            surface.commit();
+            overlays.push(SelectOverlay {
+                origin: (info.logical_x, info.logical_y),
+                state,
+                _layer_surface: layer_surface,
+                surface,
+                buffer: None,
+                dirty: true,
+            });
+        }
+        let mut selection = Selection::new(outputs).windows(windows);
+
+        // Overlay the pointer is over, and the highlight the buffers were drawn with.
+        let mut focus = None;
+        let mut drawn = None;
+        let mut next_offset = 0;
+        loop {
+            let events = std::mem::take(&mut *lock_frame_state(&input)?);
+            for event in events {
+                match event {
+                    SelectInput::Enter {
+                        surface,
+                        serial,
+                        x,
+                        y,
+                    } => {
+                        if let Some(ref device) = cursor_shape {
+                            device.set_shape(serial, Shape::Crosshair);
+                        }
+                        focus = overlays
+                            .iter()
+                            .find(|overlay| overlay.surface.id() == surface)
+                            .map(|overlay| overlay.origin);
+                        if let Some(origin) = focus {
+                            selection.pointer_motion(origin.0 + x as i32, origin.1 + y as i32);
+                        }
+                    }
+                    SelectInput::Motion { x, y } => {
+                        if let Some(origin) = focus {
+                            selection.pointer_motion(origin.0 + x as i32, origin.1 + y as i32);
+                        }
+                    }
+                    SelectInput::Leave => {
+                        focus = None;
+                        selection.pointer_left();
+                    }
+                    SelectInput::Button {
+                        button: BTN_LEFT,
+                        pressed,
+                    } => {
+                        if pressed {
+                            selection.press();
+                        } else if let Some(region) = selection.release() {
+                            return Ok(region);
+                        }
+                    }
+                    SelectInput::Button {
+                        button: BTN_RIGHT,
+                        pressed: true,
+                    }
+                    | SelectInput::Key(KEY_ESC) => return Err(Error::SelectionCancelled),
+                    _ => {}
+                }
+            }
+
+            let highlight = selection.highlight();
+            if highlight != drawn {
+                drawn = highlight;
+                for overlay in &mut overlays {
+                    overlay.dirty = true;
+                }
+            }
+            for overlay in &mut overlays {
+                let Some((width, height)) = lock_frame_state(&overlay.state)?.size else {
+                    continue;
+                };
+                // Anchored to all edges the compositor always picks the size; guard
+                // against a zero anyway, since a buffer cannot be empty.
+                let layout =
+                    BufferLayout::packed(width.max(1), height.max(1), ShmFormat::Argb8888)?;
+                if overlay.buffer.as_ref().map(|buffer| buffer.layout) != Some(layout) {
+                    overlay.buffer = Some(self.overlay_buffer(layout, &mut next_offset, &qh)?);
+                    overlay.dirty = true;
+                }
+                self.draw_overlay(overlay, &selection, &qh)?;
+            }
+
+            let mut closed = true;
+            for overlay in &overlays {
+                closed &= lock_frame_state(&overlay.state)?.closed;
+            }
+            if closed {
+                return Err(Error::WaylandConnection(
+                    "Compositor closed the selection overlays".to_string(),
+                ));
+            }
+
+            self.dispatch(&mut event_queue).await.map_err(|e| {
+                e.into_error(|e| {
+                    Error::WaylandConnection(format!("Failed to dispatch selection events: {}", e))
+                })
+            })?;
+        }
+    }
+
+    /// Create a buffer with `layout` at `next_offset` in the shm pool and move the offset
+    /// past it. Overlays never share memory, so one can be redrawn while the compositor
+    /// still holds another.
+    fn overlay_buffer(
+        &mut self,
+        layout: BufferLayout,
+        next_offset: &mut usize,
+        qh: &QueueHandle<Self>,
+    ) -> Result<OverlayBuffer> {
+        let offset = *next_offset;
+        let end = offset
+            .checked_add(layout.size()?)
+            .ok_or_else(|| Error::BufferCreation("Overlay buffers overflow".to_string()))?;
+        let pool = self.shm_pool(qh, end)?;
+        let busy = Arc::new(AtomicBool::new(false));
+        let buffer = DestroyOnDrop::new(pool.create_buffer_with(offset, layout, qh, busy.clone()));
+        *next_offset = end;
+        Ok(OverlayBuffer {
+            buffer,
+            busy,
+            offset,
+            layout,
+        })
+    }
+
+    /// Redraw `overlay` if it is outdated and the compositor has released its buffer.
+    fn draw_overlay(
+        &mut self,
+        overlay: &mut SelectOverlay,
+        selection: &Selection,
+        qh: &QueueHandle<Self>,
+    ) -> Result<()> {
+        let Some(ref buffer) = overlay.buffer else {
+            return Ok(());
+        };
+        if !overlay.dirty || buffer.busy.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let layout = buffer.layout;
+        let size = layout.size()?;
+        let area = Box::new(
+            overlay.origin.0,
+            overlay.origin.1,
+            layout.width as i32,
+            layout.height as i32,
+        );
+        let pool = self.shm_pool(qh, buffer.offset + size)?;
+        selection.render(
+            area,
+            pool.slice_mut(buffer.offset, size)?,
+            layout.stride as usize,
+        );
+        buffer.busy.store(true, Ordering::Release);
+        overlay.surface.attach(Some(&buffer.buffer), 0, 0);
+        overlay
+            .surface
+            .damage(0, 0, layout.width as i32, layout.height as i32);
+        overlay.surface.commit();
+        overlay.dirty = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use super::*;
+
+use std::fs::File;
+use std::os::fd::{AsFd, FromRawFd};
+
+/// Create an anonymous, sealable memfd.
+pub(super) fn create_memfd(name: &std::ffi::CStr) -> std::io::Result<File> {
+    let fd =
+        unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Add `F_SEAL_SHRINK` so the file can never shrink under a mapping held by the compositor.
+pub(super) fn seal_shrink(file: &File) -> std::io::Result<()> {
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, libc::F_SEAL_SHRINK) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Open the backing file for the shm pool.
+///
+/// Prefers a sealed memfd, which never touches the filesystem. If `memfd_create` is not
+/// available, falls back to an unlinked file in `XDG_RUNTIME_DIR`, which is a per-user
+/// tmpfs on systemd and elogind systems.
+fn open_backing_file() -> Result<File> {
+    match create_memfd(c"grim-rs-shm") {
+        Ok(file) => {
+            if let Err(e) = seal_shrink(&file) {
+                log::debug!("Failed to seal shm memfd: {}", e);
+            }
+            return Ok(file);
+        }
+        Err(e) => log::debug!(
+            "memfd_create failed ({}), falling back to XDG_RUNTIME_DIR",
+            e
+        ),
+    }
+
+    runtime_dir_file(std::env::var_os("XDG_RUNTIME_DIR"))
+}
+
+/// Create an unlinked file in `runtime_dir`, the value of `XDG_RUNTIME_DIR`.
+fn runtime_dir_file(runtime_dir: Option<std::ffi::OsString>) -> Result<File> {
+    let runtime_dir = runtime_dir.ok_or_else(|| {
+        Error::BufferCreation(
+            "memfd_create is unavailable and XDG_RUNTIME_DIR is not set".to_string(),
+        )
+    })?;
+    tempfile::tempfile_in(&runtime_dir).map_err(|e| {
+        Error::BufferCreation(format!(
+            "failed to create shm file in {}: {}",
+            std::path::Path::new(&runtime_dir).display(),
+            e
+        ))
+    })
+}
+
+/// A `wl_shm_pool` owned by `WaylandCapture` and reused across captures.
+///
+/// The pool only ever grows: `wl_shm_pool.resize` cannot shrink, and keeping the largest
+/// size seen avoids reallocating when the same outputs are captured repeatedly.
+pub(super) struct ShmPool {
+    file: File,
+    mmap: memmap2::MmapMut,
+    pool: WlShmPool,
+    size: usize,
+}
+
+impl ShmPool {
+    fn new(shm: &WlShm, qh: &QueueHandle<WaylandCapture>, size: usize) -> Result<Self> {
+        let file = open_backing_file()?;
+        file.set_len(size as u64).map_err(|e| {
+            Error::BufferCreation(format!("failed to resize buffer to {} bytes: {}", size, e))
+        })?;
+        let mmap = unsafe {
+            memmap2::MmapMut::map_mut(&file)
+                .map_err(|e| Error::BufferCreation(format!("failed to memory-map buffer: {}", e)))?
+        };
+        let pool = shm.create_pool(file.as_fd(), pool_size(size)?, qh, ());
+        Ok(Self {
+            file,
+            mmap,
+            pool,
+            size,
+        })
+    }
+
+    fn grow(&mut self, size: usize) -> Result<()> {
+        if size <= self.size {
+            return Ok(());
+        }
+        self.file.set_len(size as u64).map_err(|e| {
+            Error::BufferCreation(format!("failed to resize buffer to {} bytes: {}", size, e))
+        })?;
+        self.mmap = unsafe {
+            memmap2::MmapMut::map_mut(&self.file)
+                .map_err(|e| Error::BufferCreation(format!("failed to memory-map buffer: {}", e)))?
+        };
+        self.pool.resize(pool_size(size)?);
+        self.size = size;
+        Ok(())
+    }
+
+    /// Create a `wl_buffer` at `offset` bytes into the pool.
+    pub(super) fn create_buffer(
+        &self,
+        offset: usize,
+        layout: BufferLayout,
+        qh: &QueueHandle<WaylandCapture>,
+    ) -> WlBuffer {
+        self.create_buffer_with(offset, layout, qh, ())
+    }
+
+    /// Like `create_buffer`, with `data` to receive the buffer's `release` events.
+    pub(super) fn create_buffer_with<U: Send + Sync + 'static>(
+        &self,
+        offset: usize,
+        layout: BufferLayout,
+        qh: &QueueHandle<WaylandCapture>,
+        data: U,
+    ) -> WlBuffer
+    where
+        WaylandCapture: Dispatch<WlBuffer, U>,
+    {
+        self.pool.create_buffer(
+            offset as i32,
+            layout.width as i32,
+            layout.height as i32,
+            layout.stride as i32,
+            layout.format,
+            qh,
+            data,
+        )
+    }
+
+    /// Borrow `len` bytes starting at `offset` from the pool mapping.
+    pub(super) fn slice(&self, offset: usize, len: usize) -> Result<&[u8]> {
+        self.mmap
+            .get(offset..offset + len)
+            .ok_or_else(|| Error::BufferCreation("shm pool is smaller than the frame".to_string()))
+    }
+
+    /// Mutably borrow `len` bytes starting at `offset` from the pool mapping.
+    pub(super) fn slice_mut(&mut self, offset: usize, len: usize) -> Result<&mut [u8]> {
+        self.mmap
+            .get_mut(offset..offset + len)
+            .ok_or_else(|| Error::BufferCreation("shm pool is smaller than the buffer".to_string()))
+    }
+}
+
+impl Drop for ShmPool {
+    fn drop(&mut self) {
+        self.pool.destroy();
+    }
+}
+
+fn pool_size(size: usize) -> Result<i32> {
+    i32::try_from(size)
+        .map_err(|_| Error::BufferCreation(format!("shm pool size {} exceeds i32", size)))
+}
+
+impl WaylandCapture {
+    /// Return the shared shm pool, creating it or growing it to at least `size` bytes.
+    pub(super) fn shm_pool(&mut self, qh: &QueueHandle<Self>, size: usize) -> Result<&mut ShmPool> {
+        match self.shm_pool {
+            Some(ref mut pool) => pool.grow(size)?,
+            None => {
+                let shm = self.globals.shm.as_ref().ok_or_else(|| {
+                    Error::UnsupportedProtocol("wl_shm not available".to_string())
+                })?;
+                self.shm_pool = Some(ShmPool::new(shm, qh, size)?);
+            }
+        }
+        self.shm_pool
+            .as_mut()
+            .ok_or_else(|| Error::BufferCreation("shm pool was not created".to_string()))
+    }
+
+    /// Destroy the shared shm pool; the next capture creates a new one.
+    pub fn release_shm_pool(&mut self) {
+        self.shm_pool = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::runtime_dir_file;
+    use crate::Error;
+    use std::io::{Read, Seek, Write};
+
+    #[test]
+    fn runtime_dir_fallback_leaves_no_file_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = runtime_dir_file(Some(dir.path().into())).unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        file.set_len(64).unwrap();
+        file.write_all(b"pixels").unwrap();
+        file.rewind().unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents.len(), 64);
+        assert_eq!(&contents[..6], b"pixels");
+    }
+
+    #[test]
+    fn runtime_dir_fallback_needs_the_variable() {
+        assert!(matches!(
+            runtime_dir_file(None),
+            Err(Error::BufferCreation(_))
+        ));
+        let missing = tempfile::tempdir().unwrap().path().join("missing");
+        assert!(matches!(
+            runtime_dir_file(Some(missing.into())),
+            Err(Error::BufferCreation(_))
+        ));
+    }
+}
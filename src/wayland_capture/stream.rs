@@ -0,0 +1,189 @@
+use super::*;
+
+/// `wl_buffer` in the shared shm pool that is reused for every frame of a stream.
+struct StreamBuffer {
+    buffer: DestroyOnDrop<WlBuffer>,
+    layout: BufferLayout,
+}
+
+/// Iterator over frames of a single output, created by [`crate::Grim::stream_output`].
+///
+/// Every call to `next()` requests a new frame with `copy_with_damage` and blocks until
+/// the compositor has redrawn something on the output. The `wl_buffer` is created on the
+/// first frame and reused until the output changes size, stride or format.
+///
+/// With [`crate::StreamParameters::frame_timeout`] every frame, including the wait for
+/// damage, must arrive in time. Otherwise the capture timeout only bounds the buffer
+/// negotiation and waiting for damage is only interrupted by the cancellation token.
+pub struct OutputStream<'a> {
+    capture: &'a mut WaylandCapture,
+    event_queue: wayland_client::EventQueue<WaylandCapture>,
+    manager: ZwlrScreencopyManagerV1,
+    output: WlOutput,
+    region: Box,
+    overlay_cursor: bool,
+    frame_timeout: Option<std::time::Duration>,
+    buffer: Option<StreamBuffer>,
+    finished: bool,
+}
+
+impl OutputStream<'_> {
+    fn capture_frame(&mut self) -> Result<StreamFrame> {
+        let qh = self.event_queue.handle();
+        let frame_state = Arc::new(Mutex::new(FrameState::default()));
+        let frame = DestroyOnDrop::new(self.manager.capture_output_region(
+            if self.overlay_cursor { 1 } else { 0 },
+            &self.output,
+            self.region.x(),
+            self.region.y(),
+            self.region.width(),
+            self.region.height(),
+            &qh,
+            frame_state.clone(),
+        ));
+
+        self.capture
+            .start_deadline(self.frame_timeout.or(self.capture.timeout));
+        loop {
+            {
+                let state = lock_frame_state(&frame_state)?;
+                if state.buffer.is_some() || state.ready {
+                    if state.ready && state.buffer.is_none() {
+                        return Err(Error::FrameCapture(
+                            "Frame is ready but buffer was not received".to_string(),
+                        ));
+                    }
+                    break;
+                }
+            }
+            block_on(self.capture.dispatch(&mut self.event_queue))
+                .map_err(WaitError::into_frame_error)?;
+        }
+
+        let layout = lock_frame_state(&frame_state)?.shm_layout()?;
+        let size = layout.size()?;
+
+        if !self
+            .buffer
+            .as_ref()
+            .is_some_and(|buffer| buffer.layout == layout)
+        {
+            self.buffer = None;
+            let buffer = DestroyOnDrop::new(
+                self.capture
+                    .shm_pool(&qh, size)?
+                    .create_buffer(0, layout, &qh),
+            );
+            self.buffer = Some(StreamBuffer { buffer, layout });
+        }
+        let Some(buffer) = self.buffer.as_ref() else {
+            return Err(Error::CaptureFailed);
+        };
+
+        // Blocks until the compositor reports damage, so only a frame timeout applies.
+        frame.copy_with_damage(&buffer.buffer);
+        if self.frame_timeout.is_none() {
+            self.capture.start_deadline(None);
+        }
+        while !lock_frame_state(&frame_state)?.ready {
+            block_on(self.capture.dispatch(&mut self.event_queue))
+                .map_err(WaitError::into_frame_error)?;
+        }
+
+        if lock_frame_state(&frame_state)?.failed {
+            return Err(Error::FrameCapture(
+                "Compositor failed to copy frame".to_string(),
+            ));
+        }
+
+        let raw =
+            self.capture
+                .wlr_raw_frame(self.output.id().protocol_id(), &frame_state, layout)?;
+        let capture = raw.into_capture(self.capture.shm_pool(&qh, size)?.slice(0, size)?)?;
+        let damage = capture.damage().to_vec();
+        let timestamp = capture.timestamp().unwrap_or_default();
+
+        Ok(StreamFrame {
+            capture,
+            damage,
+            timestamp,
+        })
+    }
+}
+
+impl Iterator for OutputStream<'_> {
+    type Item = Result<StreamFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let result = self.capture_frame();
+        if result.is_err() {
+            self.finished = true;
+        }
+        Some(result)
+    }
+}
+
+impl WaylandCapture {
+    pub async fn stream_output(
+        &mut self,
+        output_name: &str,
+        parameters: StreamParameters,
+    ) -> Result<OutputStream<'_>> {
+        self.refresh_outputs().await?;
+
+        let manager = self
+            .globals
+            .screencopy_manager
+            .clone()
+            .ok_or(Error::UnsupportedProtocol(
+                "zwlr_screencopy_manager_v1 not available".to_string(),
+            ))?;
+        if manager.version() < 2 {
+            return Err(Error::UnsupportedProtocol(
+                "zwlr_screencopy_manager_v1 version 2 is required for copy_with_damage".to_string(),
+            ));
+        }
+
+        let (output, info) = self
+            .collect_outputs_snapshot()
+            .into_iter()
+            .find(|(_, info)| info.name == output_name)
+            .ok_or_else(|| Error::OutputNotFound(output_name.to_string()))?;
+
+        let region = if let Some(region) = parameters.region_ref() {
+            if region.width() <= 0 || region.height() <= 0 {
+                return Err(Error::InvalidRegion(
+                    "Capture region must have positive width and height".to_string(),
+                ));
+            }
+            if region.x() < 0
+                || region.y() < 0
+                || region.x() + region.width() > info.logical_width
+                || region.y() + region.height() > info.logical_height
+            {
+                return Err(Error::InvalidRegion(
+                    "Capture region extends outside output boundaries".to_string(),
+                ));
+            }
+            *region
+        } else {
+            Box::new(0, 0, info.logical_width, info.logical_height)
+        };
+
+        let event_queue = self._connection.new_event_queue();
+        Ok(OutputStream {
+            capture: self,
+            event_queue,
+            manager,
+            output,
+            region,
+            overlay_cursor: parameters.overlay_cursor_enabled(),
+            frame_timeout: parameters.frame_timeout_duration(),
+            buffer: None,
+            finished: false,
+        })
+    }
+}
@@ -0,0 +1,68 @@
+use super::*;
+
+impl WaylandCapture {
+    fn collect_toplevels_snapshot(&self) -> Vec<(ExtForeignToplevelHandleV1, ToplevelInfo)> {
+        self.globals
+            .toplevels
+            .iter()
+            .filter_map(|toplevel| {
+                let id = toplevel.id().protocol_id();
+                self.globals
+                    .toplevel_info
+                    .get(&id)
+                    .cloned()
+                    .map(|info| (toplevel.clone(), info))
+            })
+            .collect()
+    }
+
+    pub async fn list_toplevels(&mut self) -> Result<Vec<Toplevel>> {
+        self.refresh_outputs().await?;
+        if self.globals.foreign_toplevel_list.is_none() {
+            return Err(Error::UnsupportedProtocol(
+                "ext_foreign_toplevel_list_v1 not available".to_string(),
+            ));
+        }
+
+        Ok(self
+            .collect_toplevels_snapshot()
+            .into_iter()
+            .map(|(_, info)| Toplevel {
+                identifier: info.identifier,
+                title: info.title,
+                app_id: info.app_id,
+            })
+            .collect())
+    }
+
+    pub async fn capture_toplevel(
+        &mut self,
+        toplevel: &Toplevel,
+        overlay_cursor: bool,
+    ) -> Result<CaptureResult> {
+        self.refresh_outputs().await?;
+        if self.globals.foreign_toplevel_list.is_none() {
+            return Err(Error::UnsupportedProtocol(
+                "ext_foreign_toplevel_list_v1 not available".to_string(),
+            ));
+        }
+
+        let (handle, _) = self
+            .collect_toplevels_snapshot()
+            .into_iter()
+            .find(|(_, info)| info.identifier == toplevel.identifier)
+            .ok_or_else(|| Error::ToplevelNotFound(toplevel.identifier.clone()))?;
+
+        self.capture_toplevel_ext(&handle, overlay_cursor).await
+    }
+
+    pub async fn capture_toplevel_with_scale(
+        &mut self,
+        toplevel: &Toplevel,
+        scale: f64,
+        overlay_cursor: bool,
+    ) -> Result<CaptureResult> {
+        let result = self.capture_toplevel(toplevel, overlay_cursor).await?;
+        self.scale_image_data(result, scale)
+    }
+}
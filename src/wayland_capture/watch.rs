@@ -0,0 +1,162 @@
+use super::*;
+
+use std::collections::VecDeque;
+
+/// Iterator over output hotplug and configuration changes, created by
+/// [`crate::Grim::watch_outputs`].
+///
+/// The watch runs on its own event queue and `wl_registry` on the connection of the
+/// `Grim` it was created from, so that instance stays free for captures. It only binds
+/// `wl_output` and `zxdg_output_manager_v1`. Every call to
+/// `next()` blocks until an output is added, removed or reconfigured; the outputs that
+/// exist when the watch starts are available from [`OutputWatch::outputs`] and are not
+/// reported as added.
+///
+/// The watch waits without a timeout. Cancelling the [`CancellationToken`] that was set
+/// on the `Grim` ends it with `Error::Cancelled`.
+pub struct OutputWatch {
+    capture: WaylandCapture,
+    event_queue: wayland_client::EventQueue<WaylandCapture>,
+    /// Outputs reported so far, by registry name, in the order they appeared.
+    known: Vec<(u32, Output)>,
+    pending: VecDeque<OutputEvent>,
+    finished: bool,
+}
+
+impl OutputWatch {
+    /// Outputs as of the last event returned by `next()`.
+    pub fn outputs(&self) -> Vec<Output> {
+        self.known
+            .iter()
+            .map(|(_, output)| output.clone())
+            .collect()
+    }
+
+    /// Compare the settled outputs with the ones reported so far and queue the differences.
+    fn collect_changes(&mut self) {
+        if self.capture.globals.xdg_output_manager.is_none() {
+            // The guess is refreshed on every change, since no logical geometry arrives.
+            for info in self.capture.globals.output_info.values_mut() {
+                guess_output_logical_geometry(info);
+            }
+        }
+        let infos = &self.capture.globals.output_info;
+
+        let mut removed = Vec::new();
+        self.known.retain(|(global_name, output)| {
+            let present = infos.values().any(|info| info.global_name == *global_name);
+            if !present {
+                removed.push(OutputEvent::Removed(output.clone()));
+            }
+            present
+        });
+        self.pending.extend(removed);
+
+        let mut settled: Vec<&OutputInfo> = infos.values().filter(|info| info.settled).collect();
+        settled.sort_by_key(|info| info.global_name);
+        for info in settled {
+            let after = info.to_output();
+            match self
+                .known
+                .iter_mut()
+                .find(|(global_name, _)| *global_name == info.global_name)
+            {
+                Some((_, before)) if *before != after => {
+                    let before = std::mem::replace(before, after.clone());
+                    self.pending
+                        .push_back(OutputEvent::Changed { before, after });
+                }
+                Some(_) => {}
+                None => {
+                    self.known.push((info.global_name, after.clone()));
+                    self.pending.push_back(OutputEvent::Added(after));
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for OutputWatch {
+    type Item = Result<OutputEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if let Err(e) = block_on(self.capture.dispatch(&mut self.event_queue)) {
+                self.finished = true;
+                return Some(Err(e.into_error(|e| {
+                    Error::WaylandConnection(format!("Failed to dispatch output events: {}", e))
+                })));
+            }
+            self.collect_changes();
+        }
+    }
+}
+
+impl WaylandCapture {
+    pub fn watch_outputs(&self) -> Result<OutputWatch> {
+        let mut capture = WaylandCapture::unbound(self._connection.clone(), self.backend);
+        capture.timeout = self.timeout;
+        capture.cancellation = self.cancellation.clone();
+
+        let mut event_queue = self._connection.new_event_queue();
+        let _registry = self
+            ._connection
+            .display()
+            .get_registry(&event_queue.handle(), OutputRegistry);
+        capture.start_deadline(capture.timeout);
+        // Globals, then the outputs' events, then the xdg-output events they trigger.
+        for _ in 0..3 {
+            block_on(capture.roundtrip(&mut event_queue)).map_err(|e| {
+                e.into_error(|e| {
+                    Error::WaylandConnection(format!("Failed to process output events: {}", e))
+                })
+            })?;
+        }
+        capture.start_deadline(None);
+
+        let mut watch = OutputWatch {
+            capture,
+            event_queue,
+            known: Vec::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        };
+        watch.collect_changes();
+        watch.pending.clear();
+        Ok(watch)
+    }
+}
+
+/// User data of the watch's `wl_registry`, which only binds `wl_output` and
+/// `zxdg_output_manager_v1`.
+struct OutputRegistry;
+
+impl Dispatch<WlRegistry, OutputRegistry> for WaylandCapture {
+    fn event(
+        state: &mut Self,
+        registry: &WlRegistry,
+        event: <WlRegistry as Proxy>::Event,
+        _: &OutputRegistry,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        use wayland_client::protocol::wl_registry::Event;
+        match event {
+            Event::Global {
+                name,
+                interface,
+                version,
+            } if matches!(interface.as_str(), "wl_output" | "zxdg_output_manager_v1") => {
+                state.bind_global(registry, name, &interface, version, qh)
+            }
+            Event::GlobalRemove { name } => state.remove_global(name),
+            _ => {}
+        }
+    }
+}
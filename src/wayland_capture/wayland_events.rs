@@ -1,5 +1,9 @@
+use super::dmabuf::DmabufParamsState;
 use super::*;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use wayland_client::protocol::wl_callback::WlCallback;
+
 impl Dispatch<WlRegistry, ()> for WaylandCapture {
     fn event(
         state: &mut Self,
@@ -10,72 +14,175 @@ impl Dispatch<WlRegistry, ()> for WaylandCapture {
         qh: &QueueHandle<Self>,
     ) {
         use wayland_client::protocol::wl_registry::Event;
-        if let Event::Global {
-            name,
-            interface,
-            version,
-        } = event
-        {
-            match interface.as_str() {
-                "wl_compositor" => {
-                    state.globals.compositor =
-                        Some(registry.bind::<WlCompositor, _, _>(name, version, qh, ()));
-                }
-                "wl_shm" => {
-                    state.globals.shm = Some(registry.bind::<WlShm, _, _>(name, version, qh, ()));
-                }
-                "zwlr_screencopy_manager_v1" => {
-                    state.globals.screencopy_manager =
-                        Some(registry.bind::<ZwlrScreencopyManagerV1, _, _>(name, version, qh, ()));
-                }
-                "zxdg_output_manager_v1" => {
-                    state.globals.xdg_output_manager =
-                        Some(registry.bind::<ZxdgOutputManagerV1, _, _>(name, version, qh, ()));
-
-                    if let Some(ref xdg_output_manager) = state.globals.xdg_output_manager {
-                        for output in &state.globals.outputs {
-                            let xdg_output = xdg_output_manager.get_xdg_output(output, qh, ());
-                            let output_id = output.id().protocol_id();
-                            state.globals.output_xdg_map.insert(output_id, xdg_output);
-                        }
+        match event {
+            Event::Global {
+                name,
+                interface,
+                version,
+            } => state.bind_global(registry, name, &interface, version, qh),
+            Event::GlobalRemove { name } => state.remove_global(name),
+            _ => {}
+        }
+    }
+}
+
+impl WaylandCapture {
+    /// Bind a global advertised by `registry` if grim-rs uses its interface.
+    ///
+    /// The new objects are dispatched on `qh`, which does not have to be the queue
+    /// `registry` belongs to.
+    pub(super) fn bind_global(
+        &mut self,
+        registry: &WlRegistry,
+        name: u32,
+        interface: &str,
+        version: u32,
+        qh: &QueueHandle<Self>,
+    ) {
+        match interface {
+            "wl_compositor" => {
+                self.globals.compositor =
+                    Some(registry.bind::<WlCompositor, _, _>(name, version, qh, ()));
+            }
+            "wl_shm" => {
+                self.globals.shm = Some(registry.bind::<WlShm, _, _>(name, version, qh, ()));
+            }
+            "zwp_linux_dmabuf_v1" => {
+                self.globals.linux_dmabuf =
+                    Some(registry.bind::<ZwpLinuxDmabufV1, _, _>(name, version.min(3), qh, ()));
+            }
+            "zwlr_screencopy_manager_v1" => {
+                self.globals.screencopy_manager =
+                    Some(registry.bind::<ZwlrScreencopyManagerV1, _, _>(name, version, qh, ()));
+            }
+            "ext_image_copy_capture_manager_v1" => {
+                self.globals.image_copy_capture_manager = Some(
+                    registry.bind::<ExtImageCopyCaptureManagerV1, _, _>(name, version, qh, ()),
+                );
+            }
+            "ext_output_image_capture_source_manager_v1" => {
+                self.globals.output_image_capture_source_manager =
+                    Some(registry.bind::<ExtOutputImageCaptureSourceManagerV1, _, _>(
+                        name,
+                        version,
+                        qh,
+                        (),
+                    ));
+            }
+            "ext_foreign_toplevel_image_capture_source_manager_v1" => {
+                self.globals.toplevel_image_capture_source_manager = Some(
+                    registry.bind::<ExtForeignToplevelImageCaptureSourceManagerV1, _, _>(
+                        name,
+                        version,
+                        qh,
+                        (),
+                    ),
+                );
+            }
+            "ext_foreign_toplevel_list_v1" => {
+                self.globals.foreign_toplevel_list =
+                    Some(registry.bind::<ExtForeignToplevelListV1, _, _>(name, version, qh, ()));
+            }
+            "zxdg_output_manager_v1" => {
+                self.globals.xdg_output_manager =
+                    Some(registry.bind::<ZxdgOutputManagerV1, _, _>(name, version, qh, ()));
+
+                if let Some(ref xdg_output_manager) = self.globals.xdg_output_manager {
+                    for output in &self.globals.outputs {
+                        let xdg_output = xdg_output_manager.get_xdg_output(output, qh, ());
+                        let output_id = output.id().protocol_id();
+                        self.globals.output_xdg_map.insert(output_id, xdg_output);
                     }
                 }
-                "wl_output" => {
-                    let output = registry.bind::<WlOutput, _, _>(name, version, qh, ());
-                    let output_id = output.id().protocol_id();
-
-                    state.globals.output_info.insert(
-                        output_id,
-                        OutputInfo {
-                            name: format!("output-{}", name),
-                            width: 0,
-                            height: 0,
-                            x: 0,
-                            y: 0,
-                            scale: 1,
-                            transform: wayland_client::protocol::wl_output::Transform::Normal,
-                            logical_x: 0,
-                            logical_y: 0,
-                            logical_width: 0,
-                            logical_height: 0,
-                            logical_scale_known: false,
-                            logical_scale: 1.0,
-                            description: None,
-                        },
-                    );
-                    let output_idx = state.globals.outputs.len();
-                    state.globals.outputs.push(output.clone());
-
-                    if let Some(ref xdg_output_manager) = state.globals.xdg_output_manager {
-                        let output_to_use = &state.globals.outputs[output_idx];
-                        let xdg_output = xdg_output_manager.get_xdg_output(output_to_use, qh, ());
-                        let output_id = output_to_use.id().protocol_id();
-                        state.globals.output_xdg_map.insert(output_id, xdg_output);
-                    }
+            }
+            "zwlr_layer_shell_v1" => {
+                self.globals.layer_shell =
+                    Some(registry.bind::<ZwlrLayerShellV1, _, _>(name, version.min(4), qh, ()));
+            }
+            #[cfg(feature = "select")]
+            "wp_cursor_shape_manager_v1" => {
+                self.globals.cursor_shape_manager =
+                    Some(registry.bind::<WpCursorShapeManagerV1, _, _>(name, 1, qh, ()));
+            }
+            "wl_seat" if self.globals.seat.is_none() => {
+                self.globals.seat_has_pointer = false;
+                #[cfg(feature = "select")]
+                {
+                    self.globals.seat_has_keyboard = false;
                 }
-                _ => {}
+                self.globals.seat =
+                    Some(registry.bind::<WlSeat, _, _>(name, version.min(5), qh, ()));
             }
+            "wl_output" => {
+                let output = registry.bind::<WlOutput, _, _>(name, version, qh, ());
+                let output_id = output.id().protocol_id();
+
+                self.globals.output_info.insert(
+                    output_id,
+                    OutputInfo {
+                        global_name: name,
+                        // Without `done` (version 1) every event stands on its own.
+                        settled: version < 2,
+                        name: format!("output-{}", name),
+                        width: 0,
+                        height: 0,
+                        x: 0,
+                        y: 0,
+                        scale: 1,
+                        transform: wayland_client::protocol::wl_output::Transform::Normal,
+                        logical_x: 0,
+                        logical_y: 0,
+                        logical_width: 0,
+                        logical_height: 0,
+                        logical_scale_known: false,
+                        logical_scale: 1.0,
+                        logical_guessed: false,
+                        description: None,
+                        make: String::new(),
+                        model: String::new(),
+                        physical_width_mm: 0,
+                        physical_height_mm: 0,
+                        refresh: 0,
+                    },
+                );
+                let output_idx = self.globals.outputs.len();
+                self.globals.outputs.push(output.clone());
+
+                if let Some(ref xdg_output_manager) = self.globals.xdg_output_manager {
+                    let output_to_use = &self.globals.outputs[output_idx];
+                    let xdg_output = xdg_output_manager.get_xdg_output(output_to_use, qh, ());
+                    let output_id = output_to_use.id().protocol_id();
+                    self.globals.output_xdg_map.insert(output_id, xdg_output);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Forget an output whose global the compositor removed, e.g. an unplugged monitor.
+    pub(super) fn remove_global(&mut self, name: u32) {
+        let Some(output_id) = self
+            .globals
+            .output_info
+            .iter()
+            .find(|(_, info)| info.global_name == name)
+            .map(|(id, _)| *id)
+        else {
+            return;
+        };
+        self.globals.output_info.remove(&output_id);
+        if let Some(xdg_output) = self.globals.output_xdg_map.remove(&output_id) {
+            xdg_output.destroy();
         }
+        self.globals.outputs.retain(|output| {
+            if output.id().protocol_id() != output_id {
+                return true;
+            }
+            if output.version() >= 3 {
+                output.release();
+            }
+            false
+        });
     }
 }
 
@@ -90,20 +197,28 @@ impl Dispatch<WlOutput, ()> for WaylandCapture {
     ) {
         use wayland_client::protocol::wl_output::Event;
         let output_id = output.id().protocol_id();
+        if let Some(info) = state.globals.output_info.get_mut(&output_id) {
+            // Changes are applied atomically when `done` arrives.
+            info.settled = matches!(event, Event::Done);
+        }
         match event {
             Event::Geometry {
                 x,
                 y,
-                physical_width: _,
-                physical_height: _,
+                physical_width,
+                physical_height,
                 subpixel: _,
-                make: _,
-                model: _,
+                make,
+                model,
                 transform,
             } => {
                 if let Some(info) = state.globals.output_info.get_mut(&output_id) {
                     info.x = x;
                     info.y = y;
+                    info.physical_width_mm = physical_width;
+                    info.physical_height_mm = physical_height;
+                    info.make = make;
+                    info.model = model;
                     if let wayland_client::WEnum::Value(t) = transform {
                         info.transform = t;
                     }
@@ -116,11 +231,18 @@ impl Dispatch<WlOutput, ()> for WaylandCapture {
                 }
             }
             Event::Mode {
-                flags: _,
+                flags,
                 width,
                 height,
-                refresh: _,
+                refresh,
             } => {
+                // Only the current mode describes the output; older compositors list
+                // every supported mode.
+                if let wayland_client::WEnum::Value(flags) = flags {
+                    if !flags.contains(wayland_client::protocol::wl_output::Mode::Current) {
+                        return;
+                    }
+                }
                 log::debug!(
                     "Mode event for output_id {}: {}x{}",
                     output_id,
@@ -130,6 +252,7 @@ impl Dispatch<WlOutput, ()> for WaylandCapture {
                 if let Some(info) = state.globals.output_info.get_mut(&output_id) {
                     info.width = width;
                     info.height = height;
+                    info.refresh = refresh;
                     log::debug!("Updated output info: {}x{}", info.width, info.height);
                     if !info.logical_scale_known {
                         info.logical_width = width;
@@ -225,8 +348,16 @@ impl Dispatch<ZwlrScreencopyFrameV1, Arc<Mutex<FrameState>>> for WaylandCapture
                 };
                 state.width = width;
                 state.height = height;
-                if let wayland_client::WEnum::Value(val) = format {
-                    state.format = Some(val);
+                state.stride = stride;
+                match format {
+                    wayland_client::WEnum::Value(val) => {
+                        state.format = Some(val);
+                        state.format_code = Some(val.into());
+                    }
+                    wayland_client::WEnum::Unknown(code) => {
+                        log::warn!("Compositor offered unknown wl_shm format {:#010x}", code);
+                        state.format_code = Some(code);
+                    }
                 }
                 match checked_buffer_size(width, height, 4, Some(stride)) {
                     Ok(size) => {
@@ -259,9 +390,9 @@ impl Dispatch<ZwlrScreencopyFrameV1, Arc<Mutex<FrameState>>> for WaylandCapture
                 }
             }
             Event::Ready {
-                tv_sec_hi: _,
-                tv_sec_lo: _,
-                tv_nsec: _,
+                tv_sec_hi,
+                tv_sec_lo,
+                tv_nsec,
             } => {
                 let mut state = match lock_frame_state(frame_state) {
                     Ok(state) => state,
@@ -273,9 +404,27 @@ impl Dispatch<ZwlrScreencopyFrameV1, Arc<Mutex<FrameState>>> for WaylandCapture
                         return;
                     }
                 };
+                let secs = ((tv_sec_hi as u64) << 32) | tv_sec_lo as u64;
+                state.timestamp = Some(std::time::Duration::new(secs, tv_nsec));
                 state.ready = true;
                 frame.destroy();
             }
+            Event::Damage {
+                x,
+                y,
+                width,
+                height,
+            } => match lock_frame_state(frame_state) {
+                Ok(mut state) => {
+                    state
+                        .damage
+                        .push(Box::new(x as i32, y as i32, width as i32, height as i32))
+                }
+                Err(err) => log::error!(
+                    "Dropping screencopy Damage event due to mutex error: {}",
+                    err
+                ),
+            },
             Event::Failed => {
                 let mut state = match lock_frame_state(frame_state) {
                     Ok(state) => state,
@@ -287,6 +436,7 @@ impl Dispatch<ZwlrScreencopyFrameV1, Arc<Mutex<FrameState>>> for WaylandCapture
                         return;
                     }
                 };
+                state.failed = true;
                 state.ready = true;
             }
             Event::LinuxDmabuf {
@@ -294,17 +444,29 @@ impl Dispatch<ZwlrScreencopyFrameV1, Arc<Mutex<FrameState>>> for WaylandCapture
                 width,
                 height,
             } => {
-                // TODO:Обработка LinuxDmabuf - альтернативный способ передачи данных
-                // Пока не поддерживаем, но логируем для отладки
                 log::debug!(
-                    "Received LinuxDmabuf: format={}, width={}, height={}",
+                    "Received LinuxDmabuf: format={:#010x}, width={}, height={}",
                     format,
                     width,
                     height
                 );
+                match lock_frame_state(frame_state) {
+                    Ok(mut state) => state.dmabuf = Some((format, width, height)),
+                    Err(err) => log::error!(
+                        "Dropping screencopy LinuxDmabuf event due to mutex error: {}",
+                        err
+                    ),
+                }
             }
             Event::BufferDone => {
                 log::debug!("Buffer copy completed");
+                match lock_frame_state(frame_state) {
+                    Ok(mut state) => state.buffer_done = true,
+                    Err(err) => log::error!(
+                        "Dropping screencopy BufferDone event due to mutex error: {}",
+                        err
+                    ),
+                }
             }
             _ => {
                 log::warn!("Received unknown event: {:?}", event);
@@ -336,17 +498,21 @@ impl Dispatch<ZxdgOutputV1, ()> for WaylandCapture {
 
         if let Some(wl_output_id) = found_output_id {
             if let Some(info) = state.globals.output_info.get_mut(&wl_output_id) {
+                // Since version 3 the changes are applied by `wl_output.done` instead.
+                info.settled = xdg_output.version() < 3 && matches!(event, Event::Done);
                 match event {
                     Event::LogicalPosition { x, y } => {
                         info.logical_x = x;
                         info.logical_y = y;
                         info.logical_scale_known = true;
+                        info.logical_guessed = false;
                         update_logical_scale(info);
                     }
                     Event::LogicalSize { width, height } => {
                         info.logical_width = width;
                         info.logical_height = height;
                         info.logical_scale_known = true;
+                        info.logical_guessed = false;
                         update_logical_scale(info);
                     }
                     Event::Name { name }
@@ -380,6 +546,21 @@ impl Dispatch<WlBuffer, ()> for WaylandCapture {
     }
 }
 
+impl Dispatch<WlCallback, Arc<AtomicBool>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlCallback,
+        event: <WlCallback as Proxy>::Event,
+        done: &Arc<AtomicBool>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wayland_client::protocol::wl_callback::Event::Done { .. } = event {
+            done.store(true, Ordering::Release);
+        }
+    }
+}
+
 impl Dispatch<WlShmPool, ()> for WaylandCapture {
     fn event(
         _state: &mut Self,
@@ -403,3 +584,480 @@ impl Dispatch<ZxdgOutputManagerV1, ()> for WaylandCapture {
     ) {
     }
 }
+
+impl Dispatch<ExtImageCopyCaptureManagerV1, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtImageCopyCaptureManagerV1,
+        _event: <ExtImageCopyCaptureManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtOutputImageCaptureSourceManagerV1, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtOutputImageCaptureSourceManagerV1,
+        _event: <ExtOutputImageCaptureSourceManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtImageCaptureSourceV1, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtImageCaptureSourceV1,
+        _event: <ExtImageCaptureSourceV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, Arc<Mutex<ExtSessionState>>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _session: &ExtImageCopyCaptureSessionV1,
+        event: <ExtImageCopyCaptureSessionV1 as Proxy>::Event,
+        session_state: &Arc<Mutex<ExtSessionState>>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_protocols::ext::image_copy_capture::v1::client::ext_image_copy_capture_session_v1::Event;
+        let mut state = match lock_frame_state(session_state) {
+            Ok(state) => state,
+            Err(err) => {
+                log::error!(
+                    "Dropping image copy capture session event due to mutex error: {}",
+                    err
+                );
+                return;
+            }
+        };
+        match event {
+            Event::BufferSize { width, height } => {
+                state.width = width;
+                state.height = height;
+            }
+            Event::ShmFormat { format } => {
+                if let wayland_client::WEnum::Value(val) = format {
+                    state.shm_formats.push(val);
+                }
+            }
+            Event::Done => {
+                state.done = true;
+            }
+            Event::Stopped => {
+                state.stopped = true;
+            }
+            Event::DmabufDevice { .. } | Event::DmabufFormat { .. } => {}
+            _ => {
+                log::warn!("Received unknown session event: {:?}", event);
+            }
+        }
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, Arc<Mutex<ExtFrameState>>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _frame: &ExtImageCopyCaptureFrameV1,
+        event: <ExtImageCopyCaptureFrameV1 as Proxy>::Event,
+        frame_state: &Arc<Mutex<ExtFrameState>>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_protocols::ext::image_copy_capture::v1::client::ext_image_copy_capture_frame_v1::Event;
+        let mut state = match lock_frame_state(frame_state) {
+            Ok(state) => state,
+            Err(err) => {
+                log::error!(
+                    "Dropping image copy capture frame event due to mutex error: {}",
+                    err
+                );
+                return;
+            }
+        };
+        match event {
+            Event::Transform { transform } => {
+                if let wayland_client::WEnum::Value(val) = transform {
+                    state.transform = val;
+                }
+            }
+            Event::Ready => {
+                state.ready = true;
+            }
+            Event::Failed { reason } => {
+                state.failed = Some(format!("{:?}", reason));
+                state.ready = true;
+            }
+            Event::Damage {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                state.damage.push(Box::new(x, y, width, height));
+            }
+            Event::PresentationTime {
+                tv_sec_hi,
+                tv_sec_lo,
+                tv_nsec,
+            } => {
+                let secs = ((tv_sec_hi as u64) << 32) | tv_sec_lo as u64;
+                state.timestamp = Some(std::time::Duration::new(secs, tv_nsec));
+            }
+            _ => {
+                log::warn!("Received unknown frame event: {:?}", event);
+            }
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtForeignToplevelImageCaptureSourceManagerV1,
+        _event: <ExtForeignToplevelImageCaptureSourceManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for WaylandCapture {
+    fn event(
+        state: &mut Self,
+        _list: &ExtForeignToplevelListV1,
+        event: <ExtForeignToplevelListV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_list_v1::Event;
+        match event {
+            Event::Toplevel { toplevel } => {
+                let toplevel_id = toplevel.id().protocol_id();
+                state
+                    .globals
+                    .toplevel_info
+                    .insert(toplevel_id, ToplevelInfo::default());
+                state.globals.toplevels.push(toplevel);
+            }
+            Event::Finished => {
+                log::debug!("Foreign toplevel list finished");
+            }
+            _ => {}
+        }
+    }
+
+    wayland_client::event_created_child!(WaylandCapture, ExtForeignToplevelListV1, [
+        wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for WaylandCapture {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: <ExtForeignToplevelHandleV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::Event;
+        let toplevel_id = handle.id().protocol_id();
+        match event {
+            Event::Closed => {
+                state.globals.toplevel_info.remove(&toplevel_id);
+                state
+                    .globals
+                    .toplevels
+                    .retain(|toplevel| toplevel.id().protocol_id() != toplevel_id);
+                handle.destroy();
+            }
+            Event::Title { title } => {
+                if let Some(info) = state.globals.toplevel_info.get_mut(&toplevel_id) {
+                    info.title = title;
+                }
+            }
+            Event::AppId { app_id } => {
+                if let Some(info) = state.globals.toplevel_info.get_mut(&toplevel_id) {
+                    info.app_id = app_id;
+                }
+            }
+            Event::Identifier { identifier } => {
+                if let Some(info) = state.globals.toplevel_info.get_mut(&toplevel_id) {
+                    info.identifier = identifier;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwpLinuxDmabufV1, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpLinuxDmabufV1,
+        _event: <ZwpLinuxDmabufV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwpLinuxBufferParamsV1, Arc<Mutex<DmabufParamsState>>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _params: &ZwpLinuxBufferParamsV1,
+        event: <ZwpLinuxBufferParamsV1 as Proxy>::Event,
+        params_state: &Arc<Mutex<DmabufParamsState>>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_protocols::wp::linux_dmabuf::zv1::client::zwp_linux_buffer_params_v1::Event;
+        let mut state = match lock_frame_state(params_state) {
+            Ok(state) => state,
+            Err(err) => {
+                log::error!("Dropping dmabuf params event due to mutex error: {}", err);
+                return;
+            }
+        };
+        match event {
+            Event::Created { buffer } => {
+                state.buffer = Some(buffer);
+            }
+            Event::Failed => {
+                state.failed = true;
+            }
+            _ => {}
+        }
+    }
+
+    wayland_client::event_created_child!(WaylandCapture, ZwpLinuxBufferParamsV1, [
+        wayland_protocols::wp::linux_dmabuf::zv1::client::zwp_linux_buffer_params_v1::EVT_CREATED_OPCODE => (WlBuffer, ()),
+    ]);
+}
+
+impl Dispatch<WlSeat, ()> for WaylandCapture {
+    fn event(
+        state: &mut Self,
+        seat: &WlSeat,
+        event: <WlSeat as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_client::protocol::wl_seat::{Capability, Event};
+        if let Event::Capabilities {
+            capabilities: wayland_client::WEnum::Value(capabilities),
+        } = event
+        {
+            if state.globals.seat.as_ref() == Some(seat) {
+                state.globals.seat_has_pointer = capabilities.contains(Capability::Pointer);
+                #[cfg(feature = "select")]
+                {
+                    state.globals.seat_has_keyboard = capabilities.contains(Capability::Keyboard);
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<WlPointer, Arc<Mutex<Option<wayland_client::backend::ObjectId>>>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlPointer,
+        event: <WlPointer as Proxy>::Event,
+        entered: &Arc<Mutex<Option<wayland_client::backend::ObjectId>>>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_client::protocol::wl_pointer::Event;
+        let Ok(mut entered) = lock_frame_state(entered) else {
+            return;
+        };
+        match event {
+            Event::Enter { surface, .. } => *entered = Some(surface.id()),
+            Event::Leave { .. } => *entered = None,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlSurface, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlSurface,
+        _event: <WlSurface as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrLayerShellV1, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwlrLayerShellV1,
+        _event: <ZwlrLayerShellV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, Arc<Mutex<OverlayState>>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: <ZwlrLayerSurfaceV1 as Proxy>::Event,
+        overlay_state: &Arc<Mutex<OverlayState>>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1::Event;
+        let Ok(mut overlay) = lock_frame_state(overlay_state) else {
+            return;
+        };
+        match event {
+            Event::Configure {
+                serial,
+                width,
+                height,
+            } => {
+                layer_surface.ack_configure(serial);
+                overlay.size = Some((width, height));
+            }
+            Event::Closed => overlay.closed = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "select")]
+impl Dispatch<WlBuffer, Arc<AtomicBool>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlBuffer,
+        event: <WlBuffer as Proxy>::Event,
+        busy: &Arc<AtomicBool>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wayland_client::protocol::wl_buffer::Event::Release = event {
+            busy.store(false, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(feature = "select")]
+impl Dispatch<WlPointer, Arc<Mutex<Vec<SelectInput>>>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlPointer,
+        event: <WlPointer as Proxy>::Event,
+        input: &Arc<Mutex<Vec<SelectInput>>>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_client::protocol::wl_pointer::{ButtonState, Event};
+        let Ok(mut input) = lock_frame_state(input) else {
+            return;
+        };
+        match event {
+            Event::Enter {
+                serial,
+                surface,
+                surface_x,
+                surface_y,
+            } => input.push(SelectInput::Enter {
+                surface: surface.id(),
+                serial,
+                x: surface_x,
+                y: surface_y,
+            }),
+            Event::Motion {
+                surface_x,
+                surface_y,
+                ..
+            } => input.push(SelectInput::Motion {
+                x: surface_x,
+                y: surface_y,
+            }),
+            Event::Leave { .. } => input.push(SelectInput::Leave),
+            Event::Button {
+                button,
+                state: wayland_client::WEnum::Value(state),
+                ..
+            } => input.push(SelectInput::Button {
+                button,
+                pressed: state == ButtonState::Pressed,
+            }),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "select")]
+impl Dispatch<WlKeyboard, Arc<Mutex<Vec<SelectInput>>>> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlKeyboard,
+        event: <WlKeyboard as Proxy>::Event,
+        input: &Arc<Mutex<Vec<SelectInput>>>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        use wayland_client::protocol::wl_keyboard::{Event, KeyState};
+        // The keymap fd is closed when the event is dropped; only raw keycodes are used.
+        if let Event::Key {
+            key,
+            state: wayland_client::WEnum::Value(KeyState::Pressed),
+            ..
+        } = event
+        {
+            if let Ok(mut input) = lock_frame_state(input) {
+                input.push(SelectInput::Key(key));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "select")]
+impl Dispatch<WpCursorShapeManagerV1, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpCursorShapeManagerV1,
+        _event: <WpCursorShapeManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+#[cfg(feature = "select")]
+impl Dispatch<WpCursorShapeDeviceV1, ()> for WaylandCapture {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpCursorShapeDeviceV1,
+        _event: <WpCursorShapeDeviceV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
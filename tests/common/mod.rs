@@ -0,0 +1,36 @@
+//! Fixtures shared by the encoder tests. Each test crate uses a subset of them.
+#![allow(dead_code)]
+
+use grim_rs::encode::{self, EncodeOptions};
+use grim_rs::CaptureResult;
+#[cfg(feature = "testing")]
+use grim_rs::{
+    testing::{MockCompositor, MockOutput},
+    Grim,
+};
+
+/// A `Grim` on a mock compositor with one 16x8 output, for tests that capture, save,
+/// register encoders or run the CLI. Encoding alone needs neither.
+#[cfg(feature = "testing")]
+pub fn grim() -> (MockCompositor, Grim) {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 16, 8)]).unwrap();
+    let grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    (compositor, grim)
+}
+
+/// Encode `result` with the built-in encoders.
+pub fn encode_capture(result: &CaptureResult, options: EncodeOptions) -> grim_rs::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    encode::encode(result, &options, &mut output)?;
+    Ok(output)
+}
+
+/// Encode raw RGBA `data` with the built-in encoders.
+pub fn encode(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    options: EncodeOptions,
+) -> grim_rs::Result<Vec<u8>> {
+    encode_capture(&CaptureResult::new(data.to_vec(), width, height), options)
+}
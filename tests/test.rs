@@ -1,28 +1,31 @@
-use grim_rs::{Box as GrimBox, CaptureParameters, CaptureResult, Error, Grim};
+// Exercises the deprecated per-format encoding methods as well.
+#![allow(deprecated)]
+
+#[cfg(feature = "testing")]
+use grim_rs::{
+    testing::{MockCompositor, MockOutput},
+    Grim,
+};
+use grim_rs::{Box as GrimBox, CaptureParameters, CaptureResult, PixelFormat, Transform};
 use std::collections::HashMap;
-use wayland_client::protocol::wl_shm::Format as ShmFormat;
-
-fn convert_shm_to_rgba_for_test(buffer_data: &mut [u8], format: ShmFormat) {
-    match format {
-        ShmFormat::Xrgb8888 => {
-            for chunk in buffer_data.chunks_exact_mut(4) {
-                chunk.swap(0, 2);
-                chunk[3] = 255;
-            }
-        }
-        ShmFormat::Argb8888 => {
-            for chunk in buffer_data.chunks_exact_mut(4) {
-                chunk.swap(0, 2);
-            }
-        }
-        ShmFormat::Xbgr8888 => {
-            for chunk in buffer_data.chunks_exact_mut(4) {
-                chunk[3] = 255;
-            }
-        }
-        ShmFormat::Abgr8888 => {}
-        _ => {}
-    }
+
+/// A `Grim` connected to an in-process mock compositor with two outputs.
+#[cfg(feature = "testing")]
+fn mock_grim() -> (MockCompositor, Grim) {
+    let compositor = MockCompositor::new(vec![
+        MockOutput::new("DP-1", 8, 6),
+        MockOutput::new("DP-2", 4, 4).position(8, 0),
+    ])
+    .unwrap();
+    let grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    (compositor, grim)
+}
+
+/// Convert one pixel of `format` to RGBA through the library's pipeline.
+fn convert_pixel(pixel: [u8; 4], format: PixelFormat) -> Vec<u8> {
+    CaptureResult::from_raw(&pixel, 1, 1, 4, format, Transform::Normal, false)
+        .unwrap()
+        .into_data()
 }
 
 #[test]
@@ -141,27 +144,27 @@ fn test_image_data_format() {
 
 #[test]
 fn test_convert_xrgb8888_to_rgba() {
-    let mut pixel_data = vec![10, 20, 30, 99];
-    convert_shm_to_rgba_for_test(&mut pixel_data, ShmFormat::Xrgb8888);
+    let pixel_data = convert_pixel([10, 20, 30, 99], PixelFormat::Xrgb8888);
     assert_eq!(pixel_data, vec![30, 20, 10, 255]);
 }
 
 #[test]
 fn test_convert_argb8888_to_rgba_preserves_alpha() {
-    let mut pixel_data = vec![10, 20, 30, 40];
-    convert_shm_to_rgba_for_test(&mut pixel_data, ShmFormat::Argb8888);
+    let pixel_data = convert_pixel([10, 20, 30, 40], PixelFormat::Argb8888);
     assert_eq!(pixel_data, vec![30, 20, 10, 40]);
 }
 
+#[cfg(feature = "testing")]
 #[test]
 fn test_png_compression_levels() {
     let test_data = vec![255u8; 100 * 100 * 4]; // 100x100 image
 
-    if let Ok(grim) = Grim::new() {
-        let _ = grim.to_png(&test_data, 100, 100);
-        let _ = grim.to_png_with_compression(&test_data, 100, 100, 0);
-        let _ = grim.to_png_with_compression(&test_data, 100, 100, 6);
-        let _ = grim.to_png_with_compression(&test_data, 100, 100, 9);
+    let (_compositor, grim) = mock_grim();
+    assert!(grim.to_png(&test_data, 100, 100).is_ok());
+    for level in [0, 6, 9] {
+        assert!(grim
+            .to_png_with_compression(&test_data, 100, 100, level)
+            .is_ok());
     }
 }
 
@@ -175,32 +178,27 @@ fn test_capture_parameters_default_behavior() {
     assert!(params.scale_factor().is_none());
 }
 
-#[cfg(feature = "jpeg")]
+#[cfg(all(feature = "jpeg", feature = "testing"))]
 #[test]
 fn test_jpeg_functionality_available() {
     let test_data = vec![255u8; 10 * 10 * 4];
 
-    if let Ok(grim) = Grim::new() {
-        let jpeg_result = grim.to_jpeg(&test_data, 10, 10);
-        assert!(jpeg_result.is_ok());
+    let (_compositor, grim) = mock_grim();
+    let jpeg_result = grim.to_jpeg(&test_data, 10, 10);
+    assert!(jpeg_result.is_ok());
 
-        let jpeg_result_with_quality = grim.to_jpeg_with_quality(&test_data, 10, 10, 85);
-        assert!(jpeg_result_with_quality.is_ok());
-    }
+    let jpeg_result_with_quality = grim.to_jpeg_with_quality(&test_data, 10, 10, 85);
+    assert!(jpeg_result_with_quality.is_ok());
 }
 
-#[cfg(not(feature = "jpeg"))]
+#[cfg(all(not(feature = "jpeg"), feature = "testing"))]
 #[test]
 fn test_jpeg_functionality_unavailable() {
     let test_data = vec![255u8; 10 * 10 * 4];
 
-    match Grim::new() {
-        Ok(grim) => {
-            let jpeg_result = grim.to_jpeg(&test_data, 10, 10);
-            assert!(jpeg_result.is_err());
-        }
-        Err(_) => {}
-    }
+    let (_compositor, grim) = mock_grim();
+    let jpeg_result = grim.to_jpeg(&test_data, 10, 10);
+    assert!(jpeg_result.is_err());
 }
 
 #[test]
@@ -510,52 +508,40 @@ mod y_invert_tests {
     }
 }
 
+#[cfg(feature = "testing")]
 #[test]
 fn test_mock_capture() {
-    let result = std::panic::catch_unwind(|| {
-        let mut grim = Grim::new().unwrap();
-        grim.capture_all()
-    });
-
-    match result {
-        Ok(capture_result) => {
-            if let Ok(capture) = capture_result {
-                assert_eq!(
-                    capture.data().len(),
-                    (capture.width() * capture.height() * 4) as usize
-                );
-            } else {
-                assert!(matches!(capture_result, Err(Error::NoOutputs)));
-            }
-        }
-        Err(_) => {
-            panic!("Test panicked unexpectedly");
-        }
-    }
+    let (_compositor, mut grim) = mock_grim();
+    let capture = grim.capture_all().unwrap();
+    assert_eq!((capture.width(), capture.height()), (12, 6));
+    assert_eq!(
+        capture.data().len(),
+        (capture.width() * capture.height() * 4) as usize
+    );
 }
 
+#[cfg(all(feature = "png", feature = "testing"))]
 #[test]
-#[cfg(feature = "png")]
 fn test_to_png() {
-    let grim = Grim::new().unwrap();
+    let (_compositor, grim) = mock_grim();
     let test_data = vec![255u8; 64];
     let png_data = grim.to_png(&test_data, 4, 4).unwrap();
     assert!(!png_data.is_empty());
 }
 
+#[cfg(all(feature = "jpeg", feature = "testing"))]
 #[test]
-#[cfg(feature = "jpeg")]
 fn test_to_jpeg() {
-    let grim = Grim::new().unwrap();
+    let (_compositor, grim) = mock_grim();
     let test_data = vec![255u8; 64];
     let jpeg_data = grim.to_jpeg(&test_data, 4, 4).unwrap();
     assert!(!jpeg_data.is_empty());
 }
 
+#[cfg(all(not(feature = "jpeg"), feature = "testing"))]
 #[test]
-#[cfg(not(feature = "jpeg"))]
 fn test_jpeg_disabled() {
-    let grim = Grim::new().unwrap();
+    let (_compositor, grim) = mock_grim();
     let test_data = vec![255u8; 16];
     let jpeg_result = grim.to_jpeg(&test_data, 4, 4);
     assert!(jpeg_result.is_err());
@@ -573,13 +559,12 @@ fn test_read_region_from_stdin() {
     assert_eq!(region.height(), 400);
 }
 
+#[cfg(feature = "testing")]
 #[test]
 fn test_scale_functionality() {
-    let mut grim = Grim::new().unwrap();
-    let test_capture = grim.capture_all_with_scale(1.0);
-    match test_capture {
-        Ok(_) => {}
-        Err(Error::NoOutputs) => {}
-        Err(e) => panic!("Unexpected error: {:?}", e),
-    }
+    let (_compositor, mut grim) = mock_grim();
+    let full = grim.capture_all_with_scale(1.0).unwrap();
+    assert_eq!((full.width(), full.height()), (12, 6));
+    let half = grim.capture_all_with_scale(0.5).unwrap();
+    assert_eq!((half.width(), half.height()), (6, 3));
 }
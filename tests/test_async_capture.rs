@@ -0,0 +1,175 @@
+#![cfg(all(feature = "testing", feature = "async"))]
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{Box, Error, Grim};
+
+fn expected_image(output: &MockOutput, width: u32, height: u32) -> Vec<u8> {
+    (0..height)
+        .flat_map(|y| (0..width).flat_map(move |x| output.pixel(x, y)))
+        .collect()
+}
+
+#[cfg(feature = "tokio")]
+mod tokio_runtime {
+    use super::*;
+    use grim_rs::async_capture::AsyncGrim;
+
+    fn connect(compositor: &MockCompositor) -> AsyncGrim {
+        AsyncGrim::tokio(Grim::from_connection(compositor.connect().unwrap()).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn capture_output_matches_the_mock() {
+        let output = MockOutput::new("DP-1", 8, 4);
+        let compositor = MockCompositor::new(vec![output.clone()]).unwrap();
+        let mut grim = connect(&compositor);
+
+        let result = grim.capture_output("DP-1").await.unwrap();
+        assert_eq!((result.width(), result.height()), (8, 4));
+        assert_eq!(result.data(), expected_image(&output, 8, 4).as_slice());
+    }
+
+    #[tokio::test]
+    async fn outputs_all_and_regions() {
+        let compositor = MockCompositor::new(vec![
+            MockOutput::new("DP-1", 4, 2),
+            MockOutput::new("DP-2", 4, 2).position(4, 0),
+        ])
+        .unwrap();
+        let mut grim = connect(&compositor);
+
+        assert_eq!(grim.get_outputs().await.unwrap().len(), 2);
+        let all = grim.capture_all().await.unwrap();
+        assert_eq!((all.width(), all.height()), (8, 2));
+        let region = grim.capture_region(Box::new(2, 0, 4, 2)).await.unwrap();
+        assert_eq!((region.width(), region.height()), (4, 2));
+    }
+
+    #[tokio::test]
+    async fn concurrent_captures_share_one_thread() {
+        let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 16, 16)]).unwrap();
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let mut grim = connect(&compositor);
+                tokio::spawn(async move { grim.capture_output("DP-1").await })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap().width(), 16);
+        }
+    }
+
+    #[tokio::test]
+    async fn failures_are_reported() {
+        let compositor =
+            MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4).fail_captures(true)]).unwrap();
+        let mut grim = connect(&compositor);
+        assert!(matches!(
+            grim.capture_output("DP-1").await,
+            Err(Error::FrameCapture(_))
+        ));
+        assert!(matches!(
+            grim.capture_output("HDMI-A-1").await,
+            Err(Error::OutputNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn into_inner_blocks_again() {
+        let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4)]).unwrap();
+        let mut grim = connect(&compositor).into_inner();
+        assert_eq!(grim.capture_output("DP-1").unwrap().width(), 4);
+    }
+}
+
+#[cfg(feature = "calloop")]
+mod calloop_loop {
+    use super::*;
+    use grim_rs::async_capture::AsyncGrim;
+    use std::time::Duration;
+
+    #[test]
+    fn capture_runs_on_the_event_loop() {
+        let output = MockOutput::new("DP-1", 6, 3);
+        let compositor = MockCompositor::new(vec![output.clone()]).unwrap();
+        let mut event_loop =
+            calloop::EventLoop::<Option<grim_rs::Result<Vec<u8>>>>::try_new().unwrap();
+        let (executor, scheduler) = calloop::futures::executor().unwrap();
+        event_loop
+            .handle()
+            .insert_source(executor, |result, _, done| *done = Some(result))
+            .unwrap();
+
+        let grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+        let mut grim = AsyncGrim::calloop(grim, &event_loop.handle()).unwrap();
+        scheduler
+            .schedule(async move {
+                grim.capture_output("DP-1")
+                    .await
+                    .map(|result| result.into_data())
+            })
+            .unwrap();
+
+        let mut done = None;
+        for _ in 0..500 {
+            event_loop
+                .dispatch(Some(Duration::from_millis(10)), &mut done)
+                .unwrap();
+            if done.is_some() {
+                break;
+            }
+        }
+        assert_eq!(
+            done.expect("capture did not finish").unwrap(),
+            expected_image(&output, 6, 3)
+        );
+    }
+
+    #[test]
+    fn into_inner_restores_blocking_captures() {
+        let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4)]).unwrap();
+        let mut event_loop = calloop::EventLoop::<()>::try_new().unwrap();
+        let grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+        let grim = AsyncGrim::calloop(grim, &event_loop.handle()).unwrap();
+        let mut grim = grim.into_inner();
+
+        assert_eq!(grim.capture_output("DP-1").unwrap().width(), 4);
+        event_loop
+            .dispatch(Some(Duration::from_millis(10)), &mut ())
+            .unwrap();
+    }
+
+    #[test]
+    fn timeout_wakes_the_event_loop() {
+        let compositor =
+            MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4).stall_captures(true)]).unwrap();
+        let mut event_loop = calloop::EventLoop::<Option<grim_rs::Result<u32>>>::try_new().unwrap();
+        let (executor, scheduler) = calloop::futures::executor().unwrap();
+        event_loop
+            .handle()
+            .insert_source(executor, |result, _, done| *done = Some(result))
+            .unwrap();
+
+        let grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+        let mut grim = AsyncGrim::calloop(grim, &event_loop.handle()).unwrap();
+        grim.set_timeout(Some(Duration::from_millis(50)));
+        scheduler
+            .schedule(async move { grim.capture_output("DP-1").await.map(|r| r.width()) })
+            .unwrap();
+
+        let mut done = None;
+        // Nothing else wakes the loop: the capture only finishes if the timer fires.
+        for _ in 0..10 {
+            event_loop
+                .dispatch(Some(Duration::from_secs(1)), &mut done)
+                .unwrap();
+            if done.is_some() {
+                break;
+            }
+        }
+        assert!(matches!(
+            done.expect("capture did not time out"),
+            Err(Error::Timeout(t)) if t == Duration::from_millis(50)
+        ));
+    }
+}
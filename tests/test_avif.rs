@@ -0,0 +1,285 @@
+//! AVIF encoding. Encoding needs only the `avif` feature; the tests that capture, save
+//! or run the CLI also need `testing` for the mock compositor.
+
+mod common;
+
+use common::encode;
+#[cfg(feature = "testing")]
+use common::grim;
+use grim_rs::{EncodeOptions, ImageFormat};
+
+/// Gradient with a few sharp edges.
+#[cfg(feature = "avif")]
+fn pattern(width: u32, height: u32) -> Vec<u8> {
+    (0..height)
+        .flat_map(|y| {
+            (0..width).flat_map(move |x| {
+                let edge = if x % 16 < 8 { 0x30 } else { 0xd0 };
+                [edge, (x * 255 / width) as u8, (y * 255 / height) as u8, 255]
+            })
+        })
+        .collect()
+}
+
+/// Split `data` into ISOBMFF boxes: `(type, payload)`.
+#[cfg(feature = "avif")]
+fn boxes(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+    while !data.is_empty() {
+        let size = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        let kind = data[4..8].try_into().unwrap();
+        let (header, size) = match size {
+            0 => (8, data.len()),
+            1 => (
+                16,
+                u64::from_be_bytes(data[8..16].try_into().unwrap()) as usize,
+            ),
+            size => (8, size),
+        };
+        boxes.push((kind, &data[header..size]));
+        data = &data[size..];
+    }
+    boxes
+}
+
+#[cfg(feature = "avif")]
+fn find<'a>(boxes: &[([u8; 4], &'a [u8])], kind: &[u8; 4]) -> Vec<&'a [u8]> {
+    boxes
+        .iter()
+        .filter(|(k, _)| k == kind)
+        .map(|(_, payload)| *payload)
+        .collect()
+}
+
+/// Big-endian bit reader for the AV1 sequence header.
+#[cfg(feature = "avif")]
+struct Bits<'a>(&'a [u8], usize);
+
+#[cfg(feature = "avif")]
+impl Bits<'_> {
+    fn read(&mut self, n: usize) -> u32 {
+        (0..n).fold(0, |value, _| {
+            let bit = self.0[self.1 / 8] >> (7 - self.1 % 8) & 1;
+            self.1 += 1;
+            value << 1 | bit as u32
+        })
+    }
+}
+
+/// `max_frame_width` and `max_frame_height` from an AV1 sequence header OBU payload.
+#[cfg(feature = "avif")]
+fn sequence_size(header: &[u8]) -> (usize, usize) {
+    let mut bits = Bits(header, 0);
+    let _profile = bits.read(3);
+    assert_eq!(bits.read(1), 1, "still_picture");
+    // Still images use the reduced header: a single operating point and no timing info.
+    assert_eq!(bits.read(1), 1, "reduced_still_picture_header");
+    let _level = bits.read(5);
+    let width_bits = bits.read(4) as usize + 1;
+    let height_bits = bits.read(4) as usize + 1;
+    let width = bits.read(width_bits) as usize + 1;
+    let height = bits.read(height_bits) as usize + 1;
+    (width, height)
+}
+
+/// Walk the AV1 OBUs in `mdat`, returning the size from every sequence header and the
+/// number of frame OBUs.
+#[cfg(feature = "avif")]
+fn obus(mut data: &[u8]) -> (Vec<(usize, usize)>, usize) {
+    let (mut sizes, mut frames) = (Vec::new(), 0);
+    while !data.is_empty() {
+        let header = data[0];
+        assert_eq!(header & 0x80, 0, "forbidden bit");
+        assert_ne!(header & 0x02, 0, "OBUs in AVIF carry their size");
+        let mut at = if header & 0x04 != 0 { 2 } else { 1 };
+        let mut size = 0;
+        for shift in (0..).step_by(7) {
+            let byte = data[at];
+            at += 1;
+            size |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let payload = &data[at..at + size];
+        match header >> 3 & 0x0f {
+            1 => sizes.push(sequence_size(payload)),
+            6 => {
+                assert!(!payload.is_empty());
+                frames += 1;
+            }
+            _ => {}
+        }
+        data = &data[at + size..];
+    }
+    (sizes, frames)
+}
+
+/// Read the output back: an AV1 HEIF image whose `ispe` box and AV1 sequence headers
+/// agree on the size, with coded frames in `mdat`.
+///
+/// There is no AV1 decoder among the dev-dependencies: the AVIF decoding crates bind
+/// dav1d, libaom or libavif and need their headers at build time, which a plain
+/// `cargo test` cannot assume. So the pixels are not compared; the bitstream is checked
+/// as far as it goes without decoding tiles.
+#[cfg(feature = "avif")]
+fn decode_size(avif: &[u8]) -> (usize, usize) {
+    let top = boxes(avif);
+    assert_eq!(top[0].0, *b"ftyp");
+    assert_eq!(&top[0].1[..4], b"avif");
+    assert_eq!(
+        imagesize::image_type(avif).unwrap(),
+        imagesize::ImageType::Heif(imagesize::Compression::Av1)
+    );
+
+    let meta = find(&top, b"meta");
+    assert_eq!(meta.len(), 1);
+    // `meta` is a full box: skip the version and flags.
+    let meta = boxes(&meta[0][4..]);
+    let iprp = boxes(find(&meta, b"iprp")[0]);
+    let ipco = boxes(find(&iprp, b"ipco")[0]);
+    let ispe = find(&ipco, b"ispe")[0];
+    let size = (
+        u32::from_be_bytes(ispe[4..8].try_into().unwrap()) as usize,
+        u32::from_be_bytes(ispe[8..12].try_into().unwrap()) as usize,
+    );
+    let configs = find(&ipco, b"av1C");
+    assert!(!configs.is_empty());
+    for config in &configs {
+        // marker and version 1
+        assert_eq!(config[0], 0x81);
+    }
+
+    let mdat = find(&top, b"mdat");
+    assert_eq!(mdat.len(), 1);
+    let (sizes, frames) = obus(mdat[0]);
+    // One sequence header and frame per image item: the colour and, if present, alpha.
+    assert_eq!(sizes.len(), configs.len());
+    assert_eq!(frames, configs.len());
+    assert!(
+        sizes.iter().all(|&s| s == size),
+        "{:?} vs {:?}",
+        sizes,
+        size
+    );
+    size
+}
+
+#[test]
+#[cfg(feature = "avif")]
+fn avif_keeps_the_dimensions() {
+    let data = pattern(70, 30);
+    assert_eq!(
+        decode_size(&encode(&data, 70, 30, EncodeOptions::new(ImageFormat::Avif)).unwrap()),
+        (70, 30)
+    );
+    let avif = encode(
+        &data,
+        70,
+        30,
+        EncodeOptions::new(ImageFormat::Avif).quality(50).speed(10),
+    )
+    .unwrap();
+    assert_eq!(decode_size(&avif), (70, 30));
+}
+
+#[test]
+#[cfg(feature = "avif")]
+fn lower_avif_quality_gives_smaller_files() {
+    let data = pattern(128, 64);
+    let high = encode(
+        &data,
+        128,
+        64,
+        EncodeOptions::new(ImageFormat::Avif).quality(95).speed(10),
+    )
+    .unwrap();
+    let low = encode(
+        &data,
+        128,
+        64,
+        EncodeOptions::new(ImageFormat::Avif).quality(10).speed(10),
+    )
+    .unwrap();
+    assert!(low.len() < high.len(), "{} >= {}", low.len(), high.len());
+    // Out-of-range knobs are clamped rather than rejected.
+    let clamped = encode(
+        &data,
+        128,
+        64,
+        EncodeOptions::new(ImageFormat::Avif).quality(0).speed(200),
+    )
+    .unwrap();
+    assert_eq!(decode_size(&clamped), (128, 64));
+}
+
+#[test]
+#[cfg(feature = "avif")]
+fn avif_rejects_mismatched_input() {
+    assert!(encode(&[0; 15], 2, 2, EncodeOptions::new(ImageFormat::Avif)).is_err());
+    assert!(encode(
+        &[0; 12],
+        2,
+        2,
+        EncodeOptions::new(ImageFormat::Avif).quality(80)
+    )
+    .is_err());
+}
+
+#[test]
+#[cfg(all(feature = "avif", feature = "testing"))]
+fn captured_output_saves_as_avif() {
+    let (_compositor, mut grim) = grim();
+    let result = grim.capture_output("DP-1").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("capture.avif");
+    grim.save(&result, &path, &EncodeOptions::default())
+        .unwrap();
+    assert_eq!(decode_size(&std::fs::read(&path).unwrap()), (16, 8));
+}
+
+#[test]
+#[cfg(not(feature = "avif"))]
+fn avif_is_unavailable_without_the_feature() {
+    assert!(encode(&[0; 16], 2, 2, EncodeOptions::new(ImageFormat::Avif)).is_err());
+    assert!(encode(
+        &[0; 16],
+        2,
+        2,
+        EncodeOptions::new(ImageFormat::Avif).quality(80)
+    )
+    .is_err());
+}
+
+#[test]
+#[cfg(all(feature = "avif", feature = "testing"))]
+fn cli_writes_avif() {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::process::Command;
+
+    let (compositor, _grim) = grim();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("capture.avif");
+    let socket: UnixStream = compositor.connect_socket().unwrap();
+    let fd = socket.as_raw_fd();
+    // SAFETY: `fd` is owned by `socket`, which outlives the child spawn.
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        assert_eq!(libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC), 0);
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_grim-rs"))
+        .args(["-t", "avif", "-q", "60"])
+        .arg(&file)
+        .env("WAYLAND_SOCKET", fd.to_string())
+        .env_remove("WAYLAND_DISPLAY")
+        .output()
+        .unwrap();
+    drop(socket);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(decode_size(&std::fs::read(&file).unwrap()), (16, 8));
+}
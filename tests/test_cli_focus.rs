@@ -0,0 +1,216 @@
+#![cfg(feature = "testing")]
+
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::process::Command;
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+
+/// Run the CLI against `compositor` through `WAYLAND_SOCKET`, with `env` set on top of
+/// an environment that names no compositor IPC socket.
+fn run_cli(
+    compositor: &MockCompositor,
+    args: &[&str],
+    env: &[(&str, &Path)],
+) -> std::process::Output {
+    let socket: UnixStream = compositor.connect_socket().unwrap();
+    let fd = socket.as_raw_fd();
+    // SAFETY: `fd` is owned by `socket`, which outlives the child spawn.
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        assert!(flags >= 0);
+        assert_eq!(libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC), 0);
+    }
+    let mut command = Command::new(env!("CARGO_BIN_EXE_grim-rs"));
+    command
+        .args(args)
+        .env("WAYLAND_SOCKET", fd.to_string())
+        .env_remove("WAYLAND_DISPLAY")
+        .env_remove("SWAYSOCK")
+        .env_remove("HYPRLAND_INSTANCE_SIGNATURE");
+    for (key, value) in env {
+        command.env(key, value);
+    }
+    let output = command.output().expect("failed to run grim-rs binary");
+    drop(socket);
+    output
+}
+
+fn compositor() -> MockCompositor {
+    MockCompositor::new(vec![
+        MockOutput::new("DP-1", 8, 4),
+        MockOutput::new("HDMI-A-1", 6, 2).position(8, 0),
+    ])
+    .unwrap()
+}
+
+fn image_size(path: &Path) -> (u32, u32) {
+    image::image_dimensions(path).unwrap()
+}
+
+#[test]
+fn output_cursor_captures_the_output_under_the_pointer() {
+    let compositor = compositor();
+    compositor.move_pointer(9, 1).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("cursor.png");
+
+    let output = run_cli(&compositor, &["-o", "cursor", file.to_str().unwrap()], &[]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(image_size(&file), (6, 2));
+}
+
+#[test]
+fn output_focused_asks_sway() {
+    let compositor = compositor();
+    // The pointer is on DP-1, so a sway answer of HDMI-A-1 must come from IPC.
+    compositor.move_pointer(1, 1).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("sway-ipc.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header).unwrap();
+        let reply = br#"[{"name":"DP-1","focused":false},{"name":"HDMI-A-1","focused":true}]"#;
+        let mut response = b"i3-ipc".to_vec();
+        response.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+        response.extend_from_slice(&header[10..14]);
+        response.extend_from_slice(reply);
+        stream.write_all(&response).unwrap();
+    });
+    let file = dir.path().join("focused.png");
+
+    let output = run_cli(
+        &compositor,
+        &["-o", "focused", file.to_str().unwrap()],
+        &[("SWAYSOCK", &socket)],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    server.join().unwrap();
+    assert_eq!(image_size(&file), (6, 2));
+}
+
+#[test]
+fn output_focused_falls_back_to_the_pointer() {
+    let compositor = compositor();
+    compositor.move_pointer(2, 2).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("focused.png");
+
+    let output = run_cli(&compositor, &["-o", "focused", file.to_str().unwrap()], &[]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(image_size(&file), (8, 4));
+}
+
+#[cfg(feature = "ipc")]
+#[test]
+fn geometry_focused_window_asks_sway() {
+    let compositor = compositor();
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("sway-ipc.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header).unwrap();
+        // A floating window straddling both outputs.
+        let reply = br#"{"type":"root","focused":false,"nodes":[],"floating_nodes":[
+            {"type":"floating_con","focused":true,"rect":{"x":6,"y":1,"width":5,"height":2}}]}"#;
+        let mut response = b"i3-ipc".to_vec();
+        response.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+        response.extend_from_slice(&header[10..14]);
+        response.extend_from_slice(reply);
+        stream.write_all(&response).unwrap();
+        u32::from_ne_bytes(header[10..14].try_into().unwrap())
+    });
+    let file = dir.path().join("window.png");
+
+    let output = run_cli(
+        &compositor,
+        &["-g", "focused-window", file.to_str().unwrap()],
+        &[("SWAYSOCK", &socket)],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // GET_TREE.
+    assert_eq!(server.join().unwrap(), 4);
+    assert_eq!(image_size(&file), (5, 2));
+}
+
+#[cfg(feature = "ipc")]
+#[test]
+fn geometry_active_workspace_asks_hyprland() {
+    let compositor = compositor();
+    let dir = tempfile::tempdir().unwrap();
+    let instance = dir.path().join("hypr").join("abc");
+    std::fs::create_dir_all(&instance).unwrap();
+    let listener = UnixListener::bind(instance.join(".socket.sock")).unwrap();
+    let server = std::thread::spawn(move || {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut command = [0u8; 64];
+            let read = stream.read(&mut command).unwrap();
+            let reply: &[u8] = match &command[..read] {
+                b"j/activeworkspace" => br#"{"id":2,"monitor":"HDMI-A-1"}"#,
+                b"j/monitors" => {
+                    br#"[{"name":"DP-1","x":0,"y":0,"width":8,"height":4,"scale":1},
+                    {"name":"HDMI-A-1","x":8,"y":0,"width":6,"height":2,"scale":1}]"#
+                }
+                _ => b"unknown request",
+            };
+            stream.write_all(reply).unwrap();
+        }
+    });
+    let file = dir.path().join("workspace.png");
+
+    let output = run_cli(
+        &compositor,
+        &["-g", "active-workspace", file.to_str().unwrap()],
+        &[
+            ("XDG_RUNTIME_DIR", dir.path()),
+            ("HYPRLAND_INSTANCE_SIGNATURE", Path::new("abc")),
+        ],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    server.join().unwrap();
+    assert_eq!(image_size(&file), (6, 2));
+}
+
+#[cfg(feature = "ipc")]
+#[test]
+fn geometry_focused_window_needs_a_compositor_ipc() {
+    let compositor = compositor();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("window.png");
+
+    let output = run_cli(
+        &compositor,
+        &["-g", "focused-window", file.to_str().unwrap()],
+        &[],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("sway or Hyprland"));
+    assert!(!file.exists());
+}
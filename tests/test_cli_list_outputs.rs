@@ -0,0 +1,83 @@
+#![cfg(feature = "testing")]
+
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::process::Command;
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::Transform;
+
+/// Run the CLI against `compositor`, handing it a connection through `WAYLAND_SOCKET`.
+fn run_cli(compositor: &MockCompositor, args: &[&str]) -> std::process::Output {
+    let socket: UnixStream = compositor.connect_socket().unwrap();
+    let fd = socket.as_raw_fd();
+    // SAFETY: `fd` is owned by `socket`, which outlives the child spawn.
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        assert!(flags >= 0);
+        assert_eq!(libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC), 0);
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_grim-rs"))
+        .args(args)
+        .env("WAYLAND_SOCKET", fd.to_string())
+        .env_remove("WAYLAND_DISPLAY")
+        .output()
+        .expect("failed to run grim-rs binary");
+    drop(socket);
+    output
+}
+
+fn compositor() -> MockCompositor {
+    MockCompositor::new(vec![
+        MockOutput::new("DP-1", 1920, 1080).description("Dell \"U2720Q\""),
+        MockOutput::new("HDMI-A-1", 1280, 1024)
+            .position(1920, 0)
+            .transform(Transform::Rotated90),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn list_outputs_prints_a_table() {
+    let compositor = compositor();
+    let output = run_cli(&compositor, &["--list-outputs"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("NAME"));
+    assert!(lines[0].contains("GEOMETRY"));
+    assert!(lines[1].starts_with("DP-1 "));
+    assert!(lines[1].contains("0,0 1920x1080"));
+    assert!(lines[1].ends_with("Dell \"U2720Q\""));
+    assert!(lines[2].starts_with("HDMI-A-1 "));
+    assert!(lines[2].contains("1920,0 1024x1280"));
+    assert!(lines[2].contains(" 90 "));
+}
+
+#[test]
+fn list_outputs_prints_json() {
+    let compositor = compositor();
+    let output = run_cli(&compositor, &["--list-outputs", "--json"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.trim_end(),
+        concat!(
+            "[{\"name\":\"DP-1\",\"description\":\"Dell \\\"U2720Q\\\"\",",
+            "\"x\":0,\"y\":0,\"width\":1920,\"height\":1080,",
+            "\"scale\":1,\"logical_scale\":1,\"transform\":\"normal\"},",
+            "{\"name\":\"HDMI-A-1\",\"description\":\"Mock output HDMI-A-1\",",
+            "\"x\":1920,\"y\":0,\"width\":1024,\"height\":1280,",
+            "\"scale\":1,\"logical_scale\":1,\"transform\":\"90\"}]"
+        )
+    );
+}
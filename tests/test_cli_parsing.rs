@@ -32,7 +32,7 @@ fn cli_help_long_prints_usage_without_wayland() {
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("Usage: grim [options...] [output-file]"));
-    assert!(stdout.contains("-t png|jpeg"));
+    assert!(stdout.contains("-t png|jpeg|webp"));
 }
 
 #[test]
@@ -67,7 +67,17 @@ fn cli_fails_for_invalid_filetype() {
 fn cli_fails_for_invalid_quality_range() {
     let output = run_cli(&["-q", "101"]);
     assert!(!output.status.success());
-    assert_stderr_contains(&output, "Error: JPEG quality must be between 0 and 100");
+    assert_stderr_contains(&output, "Error: quality must be between 0 and 100");
+}
+
+#[test]
+fn cli_rejects_quality_for_jxl() {
+    let output = run_cli(&["-t", "jxl", "-q", "50"]);
+    assert!(!output.status.success());
+    assert_stderr_contains(
+        &output,
+        "Error: -q is not supported for JPEG XL, which is always lossless",
+    );
 }
 
 #[test]
@@ -110,3 +120,41 @@ fn cli_fails_for_too_many_positional_arguments() {
     assert!(!output.status.success());
     assert_stderr_contains(&output, "Error: too many arguments");
 }
+
+#[test]
+fn cli_fails_when_toplevel_argument_is_missing() {
+    let output = run_cli(&["-T"]);
+    assert!(!output.status.success());
+    assert_stderr_contains(&output, "Error: -T requires an argument");
+}
+
+#[test]
+fn cli_fails_when_toplevel_is_combined_with_output() {
+    let output = run_cli(&["-T", "toplevel-1", "-o", "DP-1"]);
+    assert!(!output.status.success());
+    assert_stderr_contains(&output, "Error: -T cannot be combined with -g or -o");
+}
+
+#[test]
+fn cli_help_lists_toplevel_option() {
+    let output = run_cli(&["-h"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("-T <identifier>"));
+}
+
+#[test]
+fn cli_help_lists_output_listing_flags() {
+    let output = run_cli(&["--help"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--list-outputs"));
+    assert!(stdout.contains("--json"));
+}
+
+#[test]
+fn cli_fails_when_json_is_used_without_list_outputs() {
+    let output = run_cli(&["--json"]);
+    assert!(!output.status.success());
+    assert_stderr_contains(&output, "--json requires --list-outputs");
+}
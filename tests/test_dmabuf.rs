@@ -0,0 +1,109 @@
+#![cfg(feature = "testing")]
+
+use grim_rs::testing::{MockCompositor, MockOptions, MockOutput};
+use grim_rs::{CaptureParameters, CaptureResult, Grim, Transform};
+use wayland_client::Connection;
+
+fn outputs() -> Vec<MockOutput> {
+    vec![
+        MockOutput::new("DP-1", 9, 5).stride_padding(12),
+        MockOutput::new("DP-2", 6, 4)
+            .position(9, 0)
+            .transform(Transform::Rotated90)
+            .y_invert(true),
+    ]
+}
+
+fn capture(compositor: &MockCompositor, name: &str, dmabuf: bool) -> CaptureResult {
+    let mut grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    grim.capture_outputs(vec![CaptureParameters::new(name).dmabuf(dmabuf)])
+        .unwrap()
+        .into_outputs()
+        .remove(name)
+        .unwrap()
+}
+
+fn expected_image(output: &MockOutput) -> Vec<u8> {
+    let (width, height) = output.physical_size();
+    (0..height as u32)
+        .flat_map(|y| (0..width as u32).flat_map(move |x| output.pixel(x, y)))
+        .collect()
+}
+
+#[test]
+fn dmabuf_falls_back_to_shm_without_linux_dmabuf() {
+    let compositor = MockCompositor::new(outputs()).unwrap();
+    for output in outputs() {
+        let plain = capture(&compositor, output.name(), false);
+        let dmabuf = capture(&compositor, output.name(), true);
+        assert_eq!(
+            (dmabuf.width(), dmabuf.height()),
+            (plain.width(), plain.height())
+        );
+        assert_eq!(dmabuf.data(), plain.data(), "{}", output.name());
+        assert_eq!(dmabuf.data(), expected_image(&output).as_slice());
+        assert_eq!(dmabuf.transform(), plain.transform());
+    }
+    assert_eq!(compositor.dmabuf_copies(), 0);
+}
+
+#[test]
+fn dmabuf_copies_match_shm() {
+    let compositor =
+        MockCompositor::with_options(outputs(), MockOptions::new().linux_dmabuf(true)).unwrap();
+    for (copies, output) in outputs().into_iter().enumerate() {
+        let plain = capture(&compositor, output.name(), false);
+        assert_eq!(compositor.dmabuf_copies(), copies);
+        let dmabuf = capture(&compositor, output.name(), true);
+        assert_eq!(compositor.dmabuf_copies(), copies + 1);
+        assert_eq!(dmabuf.data(), plain.data(), "{}", output.name());
+        assert_eq!(dmabuf.data(), expected_image(&output).as_slice());
+    }
+}
+
+#[test]
+fn rejected_dmabuf_copies_retry_with_shm() {
+    let outputs: Vec<_> = outputs()
+        .into_iter()
+        .map(|output| output.fail_dmabuf_copies(true))
+        .collect();
+    let compositor =
+        MockCompositor::with_options(outputs.clone(), MockOptions::new().linux_dmabuf(true))
+            .unwrap();
+    for (rejected, output) in outputs.iter().enumerate() {
+        let plain = capture(&compositor, output.name(), false);
+        let retried = capture(&compositor, output.name(), true);
+        assert_eq!(compositor.dmabuf_copies(), rejected + 1);
+        assert_eq!(retried.data(), plain.data(), "{}", output.name());
+        assert_eq!(retried.data(), expected_image(output).as_slice());
+    }
+}
+
+#[test]
+fn dmabuf_without_udmabuf_falls_back_to_shm_outside_the_mock_connection() {
+    if std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/udmabuf")
+        .is_ok()
+    {
+        // A real udmabuf would be imported; this test is about its absence.
+        return;
+    }
+    let compositor =
+        MockCompositor::with_options(outputs(), MockOptions::new().linux_dmabuf(true)).unwrap();
+    // A connection the mock did not hand out gets the same allocation as on a real
+    // compositor: no udmabuf means no dmabuf.
+    let connection = Connection::from_socket(compositor.connect_socket().unwrap()).unwrap();
+    let mut grim = Grim::from_connection(connection).unwrap();
+    for output in outputs() {
+        let result = grim
+            .capture_outputs(vec![CaptureParameters::new(output.name()).dmabuf(true)])
+            .unwrap()
+            .into_outputs()
+            .remove(output.name())
+            .unwrap();
+        assert_eq!(result.data(), expected_image(&output).as_slice());
+    }
+    assert_eq!(compositor.dmabuf_copies(), 0);
+}
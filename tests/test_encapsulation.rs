@@ -1,5 +1,8 @@
 use grim_rs::geometry::Box;
-use grim_rs::{CaptureParameters, CaptureResult, MultiOutputCaptureResult};
+use grim_rs::{
+    CaptureBackend, CaptureParameters, CaptureResult, MultiOutputCaptureResult, StreamFrame,
+    StreamParameters, Toplevel, Transform,
+};
 use std::collections::HashMap;
 
 #[test]
@@ -56,6 +59,7 @@ fn capture_parameters_minimal_construction() {
     assert_eq!(params.region_ref(), None);
     assert!(!params.overlay_cursor_enabled());
     assert_eq!(params.scale_factor(), None);
+    assert!(!params.dmabuf_enabled());
 }
 
 #[test]
@@ -83,6 +87,14 @@ fn capture_parameters_partial_builder() {
     assert_eq!(params.scale_factor(), None);
 }
 
+#[test]
+fn capture_parameters_dmabuf_opt_in() {
+    let params = CaptureParameters::new("DP-1").dmabuf(true);
+
+    assert!(params.dmabuf_enabled());
+    assert!(!params.dmabuf(false).dmabuf_enabled());
+}
+
 #[test]
 fn multi_output_capture_result_get_works() {
     let mut outputs = HashMap::new();
@@ -160,3 +172,63 @@ fn box_encapsulation_prevents_direct_field_access() {
     assert_eq!(b.width(), 300);
     assert_eq!(b.height(), 400);
 }
+
+#[test]
+fn capture_backend_defaults_to_auto() {
+    assert_eq!(CaptureBackend::default(), CaptureBackend::Auto);
+    assert_ne!(
+        CaptureBackend::WlrScreencopy,
+        CaptureBackend::ExtImageCopyCapture
+    );
+}
+
+#[test]
+fn toplevel_accessors_work() {
+    let toplevel = Toplevel::new("toplevel-1", "Terminal", "foot");
+
+    assert_eq!(toplevel.identifier(), "toplevel-1");
+    assert_eq!(toplevel.title(), "Terminal");
+    assert_eq!(toplevel.app_id(), "foot");
+}
+
+#[test]
+fn stream_parameters_builder_works() {
+    let region = Box::new(0, 0, 320, 240);
+
+    let params = StreamParameters::new().region(region).overlay_cursor(true);
+
+    assert_eq!(params.region_ref(), Some(&region));
+    assert!(params.overlay_cursor_enabled());
+    assert_eq!(StreamParameters::new().region_ref(), None);
+    assert_eq!(StreamParameters::new().frame_timeout_duration(), None);
+    assert_eq!(
+        StreamParameters::new()
+            .frame_timeout(std::time::Duration::from_millis(250))
+            .frame_timeout_duration(),
+        Some(std::time::Duration::from_millis(250))
+    );
+}
+
+#[test]
+fn stream_frame_accessors_work() {
+    let capture = CaptureResult::new(vec![0u8; 16], 2, 2);
+    let damage = vec![Box::new(0, 0, 1, 1)];
+    let frame = StreamFrame::new(capture, damage, std::time::Duration::from_millis(16));
+
+    assert_eq!(frame.damage(), &[Box::new(0, 0, 1, 1)]);
+    assert_eq!(frame.timestamp(), std::time::Duration::from_millis(16));
+    assert_eq!(frame.capture().width(), 2);
+    assert_eq!(frame.into_capture().data().len(), 16);
+}
+
+#[test]
+fn capture_result_metadata_defaults_to_empty() {
+    let result = CaptureResult::new(vec![0u8; 4], 1, 1);
+
+    assert_eq!(result.timestamp(), None);
+    assert_eq!(result.output_name(), None);
+    assert_eq!(result.format(), None);
+    assert_eq!(result.transform(), Transform::Normal);
+    assert!(!result.y_invert());
+    assert!(result.damage().is_empty());
+}
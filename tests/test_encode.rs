@@ -0,0 +1,270 @@
+//! Tests for the encoder registry on `Grim`, which needs the mock compositor behind the
+//! `testing` feature. Encoding without a `Grim` is covered by the per-codec test files.
+#![cfg(feature = "testing")]
+
+mod common;
+
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use common::grim;
+use grim_rs::encode::{Encoder, ImageRef};
+use grim_rs::{CaptureResult, EncodeOptions, ImageFormat, PixelFormat, Transform};
+
+fn capture(width: u32, height: u32) -> CaptureResult {
+    let data = (0..width * height)
+        .flat_map(|i| [(i * 5) as u8, (i / 2) as u8, 0x60, 0xff])
+        .collect();
+    CaptureResult::new(data, width, height)
+}
+
+/// Writes a header line and the raw pixels, counting its calls.
+struct Raw(Arc<AtomicUsize>);
+
+impl Encoder for Raw {
+    fn encode(
+        &self,
+        image: ImageRef<'_>,
+        options: &EncodeOptions,
+        writer: &mut dyn Write,
+    ) -> grim_rs::Result<()> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        writeln!(
+            writer,
+            "raw {}x{} q{:?}",
+            image.width(),
+            image.height(),
+            options.quality_value()
+        )?;
+        writer.write_all(image.data())?;
+        Ok(())
+    }
+}
+
+#[test]
+fn formats_parse_from_names_and_extensions() {
+    assert_eq!("png".parse::<ImageFormat>().unwrap(), ImageFormat::Png);
+    assert_eq!("jpeg".parse::<ImageFormat>().unwrap(), ImageFormat::Jpeg);
+    assert_eq!(ImageFormat::from_extension("JPG"), Some(ImageFormat::Jpeg));
+    assert_eq!(ImageFormat::from_extension("jxl"), Some(ImageFormat::Jxl));
+    assert_eq!(ImageFormat::from_extension("bmp"), None);
+    assert!("bmp".parse::<ImageFormat>().is_err());
+
+    assert_eq!(ImageFormat::Jpeg.extension(), "jpeg");
+    assert_eq!(
+        ImageFormat::custom("PPM"),
+        ImageFormat::Custom("ppm".to_string())
+    );
+    assert_eq!(ImageFormat::custom("PPM").to_string(), "ppm");
+}
+
+#[test]
+fn options_fall_back_to_format_defaults() {
+    let options = EncodeOptions::default();
+    assert_eq!(options.format_ref(), None);
+    assert_eq!(options.compression_level(), 6);
+    assert_eq!(options.quality_value(), None);
+
+    let options = EncodeOptions::new(ImageFormat::Jxl)
+        .effort(7)
+        .compression(1);
+    assert_eq!(options.format_ref(), Some(&ImageFormat::Jxl));
+    assert_eq!(options.effort_value(), Some(7));
+    assert_eq!(options.compression_level(), 1);
+    assert_eq!(options.speed_value(), None);
+}
+
+#[test]
+fn image_ref_checks_the_data_length() {
+    assert!(ImageRef::new(&[0; 16], 2, 2).is_ok());
+    assert!(ImageRef::new(&[0; 15], 2, 2).is_err());
+    assert!(ImageRef::new(&[0; 12], 2, 2).is_err());
+    assert!(ImageRef::new(&[], 0, 0).is_err());
+    assert!(ImageRef::new(&[0; 16], 2, 2)
+        .unwrap()
+        .with_data16(&[0; 8])
+        .is_err());
+}
+
+#[test]
+#[allow(deprecated)]
+fn encode_writes_png_by_default() {
+    let (_compositor, grim) = grim();
+    let result = capture(7, 5);
+    let mut png = Vec::new();
+    grim.encode(&result, &EncodeOptions::default(), &mut png)
+        .unwrap();
+    assert_eq!(
+        png,
+        grim.to_png(result.data(), result.width(), result.height())
+            .unwrap()
+    );
+
+    let decoded = image::load_from_memory(&png).unwrap().into_rgba8();
+    assert_eq!(decoded.dimensions(), (7, 5));
+    assert_eq!(decoded.into_raw(), result.data());
+}
+
+#[test]
+fn encode_keeps_16_bit_captures() {
+    let (_compositor, grim) = grim();
+    let words: [u16; 4] = [0x1234, 0x5678, 0x9abc, 0xffff];
+    let data: Vec<u8> = std::iter::repeat_n(words, 6)
+        .flatten()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    let result = CaptureResult::from_raw(
+        &data,
+        3,
+        2,
+        24,
+        PixelFormat::Abgr16161616,
+        Transform::Normal,
+        false,
+    )
+    .unwrap();
+    assert!(result.data16().is_some());
+
+    let mut png = Vec::new();
+    grim.encode(&result, &EncodeOptions::new(ImageFormat::Png), &mut png)
+        .unwrap();
+    let decoded = image::load_from_memory(&png).unwrap();
+    assert_eq!(decoded.color(), image::ColorType::Rgba16);
+    assert_eq!(
+        decoded.into_rgba16().into_raw(),
+        result.data16().unwrap().to_vec()
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn save_infers_the_format_from_the_extension() {
+    let (_compositor, grim) = grim();
+    let result = capture(8, 8);
+    let dir = tempfile::tempdir().unwrap();
+
+    let png = dir.path().join("capture.PNG");
+    grim.save(&result, &png, &EncodeOptions::default()).unwrap();
+    assert_eq!(&std::fs::read(&png).unwrap()[1..4], b"PNG");
+
+    #[cfg(feature = "jpeg")]
+    {
+        let jpeg = dir.path().join("capture.jpg");
+        grim.save(&result, &jpeg, &EncodeOptions::default().quality(50))
+            .unwrap();
+        assert_eq!(
+            std::fs::read(&jpeg).unwrap(),
+            grim.to_jpeg_with_quality(result.data(), 8, 8, 50).unwrap()
+        );
+    }
+
+    // The format in the options wins over the extension.
+    let forced = dir.path().join("capture.jpg");
+    grim.save(&result, &forced, &EncodeOptions::new(ImageFormat::Png))
+        .unwrap();
+    assert_eq!(&std::fs::read(&forced).unwrap()[1..4], b"PNG");
+}
+
+#[test]
+fn save_rejects_unknown_extensions_without_writing() {
+    let (_compositor, grim) = grim();
+    let result = capture(4, 4);
+    let dir = tempfile::tempdir().unwrap();
+
+    for name in ["capture.bmp", "capture"] {
+        let path = dir.path().join(name);
+        assert!(grim
+            .save(&result, &path, &EncodeOptions::default())
+            .is_err());
+        assert!(!path.exists());
+    }
+}
+
+#[test]
+#[cfg(not(feature = "qoi"))]
+fn disabled_formats_are_reported() {
+    let (_compositor, grim) = grim();
+    let result = capture(4, 4);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("capture.qoi");
+    assert!(grim
+        .save(&result, &path, &EncodeOptions::default())
+        .is_err());
+    assert!(!path.exists());
+    assert!(grim
+        .encode(&result, &EncodeOptions::new(ImageFormat::Qoi), Vec::new())
+        .is_err());
+}
+
+#[test]
+fn registered_encoders_add_formats() {
+    let (_compositor, mut grim) = grim();
+    let calls = Arc::new(AtomicUsize::new(0));
+    grim.register_encoder(ImageFormat::custom("raw"), Raw(calls.clone()));
+    let result = capture(3, 2);
+    let dir = tempfile::tempdir().unwrap();
+
+    let path = dir.path().join("capture.RAW");
+    grim.save(&result, &path, &EncodeOptions::default().quality(9))
+        .unwrap();
+    let mut expected = b"raw 3x2 qSome(9)\n".to_vec();
+    expected.extend_from_slice(result.data());
+    assert_eq!(std::fs::read(&path).unwrap(), expected);
+
+    let mut encoded = Vec::new();
+    grim.encode(
+        &result,
+        &EncodeOptions::new(ImageFormat::custom("raw")).quality(9),
+        &mut encoded,
+    )
+    .unwrap();
+    assert_eq!(encoded, expected);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+#[allow(deprecated)]
+fn registered_encoders_replace_built_in_ones() {
+    let (_compositor, mut grim) = grim();
+    let calls = Arc::new(AtomicUsize::new(0));
+    grim.register_encoder(ImageFormat::Png, Raw(calls.clone()));
+    let result = capture(2, 2);
+
+    let mut encoded = Vec::new();
+    grim.encode(&result, &EncodeOptions::default(), &mut encoded)
+        .unwrap();
+    assert!(encoded.starts_with(b"raw 2x2 qNone\n"));
+    // The format-specific methods go through the registry too.
+    let png = grim.to_png(result.data(), 2, 2).unwrap();
+    assert_eq!(png, encoded);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn closures_are_encoders() {
+    let (_compositor, mut grim) = grim();
+    grim.register_encoder(
+        ImageFormat::custom("size"),
+        |image: ImageRef<'_>, _: &EncodeOptions, writer: &mut dyn Write| {
+            Ok(write!(writer, "{}x{}", image.width(), image.height())?)
+        },
+    );
+    let mut encoded = Vec::new();
+    grim.encode(
+        &capture(5, 3),
+        &EncodeOptions::new(ImageFormat::custom("size")),
+        &mut encoded,
+    )
+    .unwrap();
+    assert_eq!(encoded, b"5x3");
+}
+
+#[test]
+fn encoders_receive_validated_captures() {
+    let (_compositor, grim) = grim();
+    let result = CaptureResult::new(vec![0; 15], 2, 2);
+    assert!(grim
+        .encode(&result, &EncodeOptions::default(), Vec::new())
+        .is_err());
+}
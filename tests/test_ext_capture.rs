@@ -0,0 +1,271 @@
+#![cfg(feature = "testing")]
+
+use grim_rs::testing::{MockCompositor, MockOptions, MockOutput, MockToplevel};
+use grim_rs::{Box, CaptureBackend, CaptureParameters, Error, Grim, Transform};
+
+fn connect(
+    outputs: Vec<MockOutput>,
+    options: MockOptions,
+    backend: CaptureBackend,
+) -> (MockCompositor, Result<Grim, Error>) {
+    let compositor = MockCompositor::with_options(outputs, options).unwrap();
+    let grim = Grim::from_connection_with_backend(compositor.connect().unwrap(), backend);
+    (compositor, grim)
+}
+
+fn both() -> MockOptions {
+    MockOptions::new().ext_image_copy_capture(true)
+}
+
+fn ext_only() -> MockOptions {
+    MockOptions::new()
+        .wlr_screencopy(false)
+        .ext_image_copy_capture(true)
+}
+
+fn expected_image(output: &MockOutput, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+    (y..y + height)
+        .flat_map(|py| (x..x + width).flat_map(move |px| output.pixel(px, py)))
+        .collect()
+}
+
+#[test]
+fn auto_prefers_ext_and_falls_back_to_wlr() {
+    let output = || vec![MockOutput::new("DP-1", 4, 4)];
+    for (options, expected) in [
+        (both(), CaptureBackend::ExtImageCopyCapture),
+        (ext_only(), CaptureBackend::ExtImageCopyCapture),
+        (MockOptions::new(), CaptureBackend::WlrScreencopy),
+    ] {
+        let (_compositor, grim) = connect(output(), options, CaptureBackend::Auto);
+        assert_eq!(grim.unwrap().backend(), expected);
+    }
+
+    let (_compositor, grim) = connect(
+        output(),
+        MockOptions::new().wlr_screencopy(false),
+        CaptureBackend::Auto,
+    );
+    assert!(matches!(grim, Err(Error::UnsupportedProtocol(_))));
+}
+
+#[test]
+fn forced_backends_must_be_advertised() {
+    let output = || vec![MockOutput::new("DP-1", 4, 4)];
+    let (_compositor, grim) = connect(output(), both(), CaptureBackend::WlrScreencopy);
+    assert_eq!(grim.unwrap().backend(), CaptureBackend::WlrScreencopy);
+    let (_compositor, grim) = connect(output(), both(), CaptureBackend::ExtImageCopyCapture);
+    assert_eq!(grim.unwrap().backend(), CaptureBackend::ExtImageCopyCapture);
+
+    let (_compositor, grim) = connect(
+        output(),
+        MockOptions::new(),
+        CaptureBackend::ExtImageCopyCapture,
+    );
+    assert!(matches!(grim, Err(Error::UnsupportedProtocol(_))));
+    let (_compositor, grim) = connect(output(), ext_only(), CaptureBackend::WlrScreencopy);
+    assert!(matches!(grim, Err(Error::UnsupportedProtocol(_))));
+}
+
+#[test]
+fn both_backends_capture_the_same_pixels() {
+    for transform in [
+        Transform::Normal,
+        Transform::Rotated90,
+        Transform::Flipped180,
+    ] {
+        let output = MockOutput::new("DP-1", 7, 5).transform(transform);
+        let (width, height) = output.physical_size();
+        let expected = expected_image(&output, 0, 0, width as u32, height as u32);
+        for backend in [
+            CaptureBackend::ExtImageCopyCapture,
+            CaptureBackend::WlrScreencopy,
+        ] {
+            let (_compositor, grim) = connect(vec![output.clone()], both(), backend);
+            let mut grim = grim.unwrap();
+            let result = grim.capture_output("DP-1").unwrap();
+            assert_eq!(
+                (result.width() as i32, result.height() as i32),
+                (width, height),
+                "{:?} {:?}",
+                backend,
+                transform
+            );
+            assert_eq!(
+                result.data(),
+                expected.as_slice(),
+                "{:?} {:?}",
+                backend,
+                transform
+            );
+            assert_eq!(result.transform(), transform);
+            assert_eq!(result.output_name(), Some("DP-1"));
+            assert!(result.timestamp().is_some());
+        }
+    }
+}
+
+#[test]
+fn ext_crops_regions_at_the_output_scale() {
+    // 16x12 physical pixels, 8x6 logical.
+    let output = MockOutput::new("HDMI-A-1", 16, 12).scale(2);
+    let region = Box::new(1, 2, 5, 3);
+    let mut results = Vec::new();
+    for backend in [
+        CaptureBackend::ExtImageCopyCapture,
+        CaptureBackend::WlrScreencopy,
+    ] {
+        let (_compositor, grim) = connect(vec![output.clone()], both(), backend);
+        let mut grim = grim.unwrap();
+        let result = grim
+            .capture_outputs(vec![CaptureParameters::new("HDMI-A-1").region(region)])
+            .unwrap()
+            .into_outputs()
+            .remove("HDMI-A-1")
+            .unwrap();
+        assert_eq!((result.width(), result.height()), (10, 6), "{:?}", backend);
+        assert_eq!(
+            result.data(),
+            expected_image(&output, 2, 4, 10, 6).as_slice(),
+            "{:?}",
+            backend
+        );
+        results.push(result.data().to_vec());
+    }
+    assert_eq!(results[0], results[1]);
+}
+
+#[test]
+fn ext_capture_region_matches_wlr_on_a_rotated_scaled_output() {
+    let outputs = vec![
+        MockOutput::new("DP-1", 8, 6),
+        MockOutput::new("DP-2", 12, 8)
+            .position(8, 0)
+            .scale(2)
+            .transform(Transform::Rotated270),
+    ];
+    let region = Box::new(5, 1, 6, 4);
+    let capture = |backend| {
+        let (_compositor, grim) = connect(outputs.clone(), both(), backend);
+        grim.unwrap().capture_region(region).unwrap()
+    };
+    let ext = capture(CaptureBackend::ExtImageCopyCapture);
+    let wlr = capture(CaptureBackend::WlrScreencopy);
+    assert_eq!((ext.width(), ext.height()), (6, 4));
+    assert_eq!(ext.data(), wlr.data());
+}
+
+#[test]
+fn ext_reports_failed_frames() {
+    let output = MockOutput::new("DP-1", 4, 4).fail_captures(true);
+    let (_compositor, grim) = connect(vec![output], ext_only(), CaptureBackend::Auto);
+    assert!(matches!(
+        grim.unwrap().capture_output("DP-1"),
+        Err(Error::FrameCapture(_))
+    ));
+}
+
+fn toplevels() -> Vec<MockToplevel> {
+    vec![
+        MockToplevel::new("toplevel-1", 6, 4)
+            .title("Terminal")
+            .app_id("foot"),
+        MockToplevel::new("toplevel-2", 9, 5)
+            .title("Editor")
+            .app_id("org.example.Editor")
+            .content(|x, y| [0x10 + x as u8, 0x80 + y as u8, 0x33, 0xff]),
+    ]
+}
+
+#[test]
+fn lists_toplevels() {
+    let (_compositor, grim) = connect(
+        vec![MockOutput::new("DP-1", 4, 4)],
+        MockOptions::new().toplevels(toplevels()),
+        CaptureBackend::Auto,
+    );
+    let listed = grim.unwrap().list_toplevels().unwrap();
+    let listed: Vec<_> = listed
+        .iter()
+        .map(|toplevel| (toplevel.identifier(), toplevel.title(), toplevel.app_id()))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("toplevel-1", "Terminal", "foot"),
+            ("toplevel-2", "Editor", "org.example.Editor"),
+        ]
+    );
+
+    let (_compositor, grim) = connect(
+        vec![MockOutput::new("DP-1", 4, 4)],
+        MockOptions::new(),
+        CaptureBackend::Auto,
+    );
+    assert!(matches!(
+        grim.unwrap().list_toplevels(),
+        Err(Error::UnsupportedProtocol(_))
+    ));
+}
+
+#[test]
+fn captures_a_toplevel() {
+    let (_compositor, grim) = connect(
+        vec![MockOutput::new("DP-1", 4, 4)],
+        both().toplevels(toplevels()),
+        CaptureBackend::Auto,
+    );
+    let mut grim = grim.unwrap();
+    let listed = grim.list_toplevels().unwrap();
+    for (toplevel, mock) in listed.iter().zip(toplevels()) {
+        let result = grim.capture_toplevel(toplevel).unwrap();
+        let (width, height) = mock.size();
+        assert_eq!(
+            (result.width() as i32, result.height() as i32),
+            (width, height),
+            "{}",
+            mock.identifier()
+        );
+        let mock = &mock;
+        let expected: Vec<u8> = (0..height as u32)
+            .flat_map(|y| (0..width as u32).flat_map(move |x| mock.pixel(x, y)))
+            .collect();
+        assert_eq!(result.data(), expected.as_slice(), "{}", mock.identifier());
+    }
+
+    let missing = grim_rs::Toplevel::new("gone", "", "");
+    assert!(matches!(
+        grim.capture_toplevel(&missing),
+        Err(Error::ToplevelNotFound(_))
+    ));
+}
+
+#[test]
+fn cursor_option_reaches_the_session() {
+    let (compositor, grim) = connect(
+        vec![MockOutput::new("DP-1", 4, 4)],
+        ext_only().toplevels(toplevels()),
+        CaptureBackend::Auto,
+    );
+    let mut grim = grim.unwrap();
+    let toplevel = grim.list_toplevels().unwrap().remove(0);
+
+    grim.capture_toplevel(&toplevel).unwrap();
+    grim.capture_output("DP-1").unwrap();
+    assert_eq!(compositor.cursor_sessions(), 0);
+
+    let plain = grim.capture_toplevel(&toplevel).unwrap();
+    let with_cursor = grim.capture_toplevel_with_cursor(&toplevel, None).unwrap();
+    assert_eq!(compositor.cursor_sessions(), 1);
+    assert_eq!(with_cursor.data(), plain.data());
+
+    let scaled = grim
+        .capture_toplevel_with_cursor(&toplevel, Some(0.5))
+        .unwrap();
+    assert_eq!((scaled.width(), scaled.height()), (3, 2));
+    assert_eq!(compositor.cursor_sessions(), 2);
+
+    grim.capture_outputs(vec![CaptureParameters::new("DP-1").overlay_cursor(true)])
+        .unwrap();
+    assert_eq!(compositor.cursor_sessions(), 3);
+}
@@ -0,0 +1,228 @@
+#![cfg(feature = "testing")]
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use grim_rs::focus::{
+    FocusResolver, HyprlandResolver, PointerResolver, ResolverChain, SwayResolver,
+};
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{Error, Grim, Result};
+
+fn compositor() -> MockCompositor {
+    MockCompositor::new(vec![
+        MockOutput::new("DP-1", 8, 4),
+        MockOutput::new("HDMI-A-1", 6, 2).position(8, 0),
+    ])
+    .unwrap()
+}
+
+fn connect(compositor: &MockCompositor) -> Grim {
+    Grim::from_connection(compositor.connect().unwrap()).unwrap()
+}
+
+/// Stand-in for sway's IPC socket that answers one request with `reply` and hands back
+/// the request it received.
+fn fake_sway(dir: &tempfile::TempDir, reply: &str) -> (PathBuf, JoinHandle<(u32, u32)>) {
+    let path = dir.path().join("sway-ipc.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let reply = reply.to_string();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header).unwrap();
+        assert_eq!(&header[..6], b"i3-ipc");
+        let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+        let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+
+        let mut response = b"i3-ipc".to_vec();
+        response.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+        response.extend_from_slice(&message_type.to_ne_bytes());
+        response.extend_from_slice(reply.as_bytes());
+        stream.write_all(&response).unwrap();
+        (length, message_type)
+    });
+    (path, server)
+}
+
+/// Stand-in for Hyprland's `.socket.sock`: answers one command and closes.
+fn fake_hyprland(dir: &tempfile::TempDir, reply: &str) -> (PathBuf, JoinHandle<String>) {
+    let path = dir.path().join(".socket.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let reply = reply.to_string();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut command = [0u8; 64];
+        let read = stream.read(&mut command).unwrap();
+        stream.write_all(reply.as_bytes()).unwrap();
+        String::from_utf8_lossy(&command[..read]).into_owned()
+    });
+    (path, server)
+}
+
+const SWAY_OUTPUTS: &str = r#"[
+    {"name": "DP-1", "make": "Dell \"U2720Q\"", "focused": false,
+     "rect": {"x": 0, "y": 0, "width": 8, "height": 4}, "modes": []},
+    {"name": "HDMI-A-1", "make": "Écran 🖥", "focused": true,
+     "scale": 1.0, "current_workspace": null}
+]"#;
+
+#[test]
+fn sway_resolver_reads_the_focused_output() {
+    let compositor = compositor();
+    let mut grim = connect(&compositor);
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_sway(&dir, SWAY_OUTPUTS);
+
+    let output = grim
+        .focused_output_with(&mut SwayResolver::new(&socket))
+        .unwrap();
+    assert_eq!(output.name(), "HDMI-A-1");
+    assert_eq!(output.geometry().x(), 8);
+    // GET_OUTPUTS with an empty payload.
+    assert_eq!(server.join().unwrap(), (0, 3));
+}
+
+#[test]
+fn hyprland_resolver_reads_the_focused_monitor() {
+    let compositor = compositor();
+    let mut grim = connect(&compositor);
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_hyprland(
+        &dir,
+        r#"[{"id": 0, "name": "DP-1", "focused": true}, {"id": 1, "name": "HDMI-A-1", "focused": false}]"#,
+    );
+
+    let result = grim
+        .capture_focused_output_with(&mut HyprlandResolver::new(&socket))
+        .unwrap();
+    assert_eq!((result.width(), result.height()), (8, 4));
+    assert_eq!(server.join().unwrap(), "j/monitors");
+}
+
+#[test]
+fn resolver_errors_are_reported() {
+    let compositor = compositor();
+    let mut grim = connect(&compositor);
+
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_sway(&dir, r#"[{"name": "DP-1", "focused": false}]"#);
+    assert!(matches!(
+        grim.focused_output_with(&mut SwayResolver::new(&socket)),
+        Err(Error::NoFocusedOutput)
+    ));
+    server.join().unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_sway(&dir, r#"[{"name": "eDP-1", "focused": true}]"#);
+    assert!(matches!(
+        grim.focused_output_with(&mut SwayResolver::new(&socket)),
+        Err(Error::OutputNotFound(name)) if name == "eDP-1"
+    ));
+    server.join().unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_hyprland(&dir, "unknown request");
+    assert!(matches!(
+        grim.focused_output_with(&mut HyprlandResolver::new(&socket)),
+        Err(Error::Ipc(_))
+    ));
+    server.join().unwrap();
+
+    let missing = dir.path().join("missing.sock");
+    assert!(matches!(
+        grim.focused_output_with(&mut SwayResolver::new(missing)),
+        Err(Error::IoWithContext { .. })
+    ));
+}
+
+#[test]
+fn ipc_requests_respect_the_timeout() {
+    let compositor = compositor();
+    let mut grim = connect(&compositor);
+    grim.set_timeout(Some(Duration::from_millis(50)));
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("sway-ipc.sock");
+    // Accepts the connection (into the backlog) but never answers.
+    let _listener = UnixListener::bind(&socket).unwrap();
+
+    match grim.focused_output_with(&mut SwayResolver::new(&socket)) {
+        Err(Error::IoWithContext { source, .. }) => assert!(matches!(
+            source.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        )),
+        other => panic!(
+            "expected a timeout, got {:?}",
+            other.map(|o| o.name().to_string())
+        ),
+    }
+}
+
+#[test]
+fn pointer_resolver_finds_the_output_under_the_pointer() {
+    let compositor = compositor();
+    compositor.move_pointer(10, 1).unwrap();
+    let mut grim = connect(&compositor);
+
+    let output = grim.focused_output_with(&mut PointerResolver).unwrap();
+    assert_eq!(output.name(), "HDMI-A-1");
+
+    compositor.move_pointer(3, 3).unwrap();
+    let result = grim
+        .capture_focused_output_with(&mut PointerResolver)
+        .unwrap();
+    assert_eq!((result.width(), result.height()), (8, 4));
+}
+
+#[test]
+fn pointer_resolver_times_out_when_the_pointer_is_off_screen() {
+    let compositor = compositor();
+    compositor.move_pointer(-100, -100).unwrap();
+    let mut grim = connect(&compositor);
+    grim.set_timeout(Some(Duration::from_millis(100)));
+
+    assert!(matches!(
+        grim.focused_output_with(&mut PointerResolver),
+        Err(Error::Timeout(_))
+    ));
+    // The overlays are gone and the connection still works.
+    assert_eq!(grim.capture_output("DP-1").unwrap().width(), 8);
+}
+
+/// Resolver that never knows, to check that the chain moves on.
+struct Unknown;
+
+impl FocusResolver for Unknown {
+    fn focused_output(&mut self, _grim: &mut Grim) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+#[test]
+fn resolver_chain_uses_the_first_answer() {
+    let compositor = compositor();
+    compositor.move_pointer(10, 1).unwrap();
+    let mut grim = connect(&compositor);
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_sway(&dir, SWAY_OUTPUTS);
+
+    let mut chain = ResolverChain::new(vec![
+        Box::new(Unknown),
+        Box::new(SwayResolver::new(&socket)),
+        Box::new(PointerResolver),
+    ]);
+    assert_eq!(
+        grim.focused_output_with(&mut chain).unwrap().name(),
+        "HDMI-A-1"
+    );
+    server.join().unwrap();
+
+    let mut chain = ResolverChain::new(vec![Box::new(Unknown)]);
+    assert!(matches!(
+        grim.focused_output_with(&mut chain),
+        Err(Error::NoFocusedOutput)
+    ));
+}
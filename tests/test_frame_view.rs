@@ -0,0 +1,93 @@
+#![cfg(feature = "testing")]
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{Grim, PixelFormat, Transform};
+
+fn expected_image(output: &MockOutput) -> Vec<u8> {
+    let (width, height) = output.physical_size();
+    (0..height as u32)
+        .flat_map(|y| (0..width as u32).flat_map(move |x| output.pixel(x, y)))
+        .collect()
+}
+
+#[test]
+fn view_rows_skip_the_stride_padding() {
+    let output = MockOutput::new("DP-1", 5, 3).stride_padding(12);
+    let compositor = MockCompositor::new(vec![output.clone()]).unwrap();
+    let mut grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    let view = grim.capture_output_view("DP-1").unwrap();
+
+    assert_eq!((view.width(), view.height()), (5, 3));
+    assert_eq!(view.format(), PixelFormat::Xrgb8888);
+    assert_eq!(view.stride(), 5 * 4 + 12);
+    assert_eq!(view.data().len(), view.stride() as usize * 3);
+
+    let rows: Vec<&[u8]> = view.rows().collect();
+    assert_eq!(rows.len(), 3);
+    for (y, row) in rows.iter().enumerate() {
+        let start = y * view.stride() as usize;
+        assert_eq!(*row, &view.data()[start..start + 20]);
+        // Xrgb8888 is stored as little-endian B, G, R, X.
+        let expected: Vec<u8> = (0..5)
+            .flat_map(|x| {
+                let [r, g, b, _] = output.pixel(x, y as u32);
+                [b, g, r]
+            })
+            .collect();
+        let colors: Vec<u8> = row
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+        assert_eq!(colors, expected, "row {}", y);
+    }
+
+    let rgba = view.to_rgba().unwrap();
+    assert_eq!((rgba.width(), rgba.height()), (5, 3));
+    assert_eq!(rgba.data(), expected_image(&output).as_slice());
+}
+
+#[test]
+fn view_matches_the_copying_capture() {
+    for (output, padding) in [
+        (MockOutput::new("DP-1", 6, 4).format(PixelFormat::Rgb888), 7),
+        (
+            MockOutput::new("DP-1", 6, 4)
+                .transform(Transform::Rotated90)
+                .y_invert(true),
+            4,
+        ),
+        (
+            MockOutput::new("DP-1", 5, 3)
+                .format(PixelFormat::Rgb565)
+                .transform(Transform::Flipped270),
+            6,
+        ),
+    ] {
+        let output = output.stride_padding(padding);
+        let compositor = MockCompositor::new(vec![output.clone()]).unwrap();
+        let mut grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+        let copied = grim.capture_output("DP-1").unwrap();
+
+        let view = grim.capture_output_view("DP-1").unwrap();
+        let bpp = view.format().bytes_per_pixel().unwrap();
+        assert_eq!(view.stride(), view.width() * bpp + padding, "{:?}", output);
+        assert_eq!(view.data().len(), (view.stride() * view.height()) as usize);
+        assert_eq!(view.rows().count(), view.height() as usize);
+        for (y, row) in view.rows().enumerate() {
+            let start = y * view.stride() as usize;
+            assert_eq!(
+                row,
+                &view.data()[start..start + (view.width() * bpp) as usize]
+            );
+        }
+        assert_eq!(view.transform(), copied.transform());
+        assert_eq!(view.y_invert(), copied.y_invert());
+
+        let rgba = view.to_rgba().unwrap();
+        assert_eq!(
+            (rgba.width(), rgba.height()),
+            (copied.width(), copied.height())
+        );
+        assert_eq!(rgba.data(), copied.data(), "{:?}", output);
+    }
+}
@@ -0,0 +1,148 @@
+use grim_rs::{CaptureResult, Grim, PixelFormat, Transform};
+
+fn pack_2101010(high: u32, mid: u32, low: u32, alpha: u32) -> [u8; 4] {
+    ((alpha << 30) | (high << 20) | (mid << 10) | low).to_le_bytes()
+}
+
+fn pack_16(words: [u16; 4]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+fn convert(data: &[u8], width: u32, format: PixelFormat) -> CaptureResult {
+    let stride = width * format.bytes_per_pixel().unwrap();
+    CaptureResult::from_raw(
+        data,
+        width,
+        data.len() as u32 / stride,
+        stride,
+        format,
+        Transform::Normal,
+        false,
+    )
+    .unwrap()
+}
+
+#[test]
+fn pixel_format_sizes() {
+    assert_eq!(PixelFormat::Xrgb8888.bytes_per_pixel(), Some(4));
+    assert_eq!(PixelFormat::Xrgb2101010.bytes_per_pixel(), Some(4));
+    assert_eq!(PixelFormat::Abgr16161616.bytes_per_pixel(), Some(8));
+    assert_eq!(PixelFormat::Abgr16161616f.bytes_per_pixel(), Some(8));
+    assert_eq!(PixelFormat::Other(0).bytes_per_pixel(), None);
+
+    assert!(!PixelFormat::Argb8888.is_high_bit_depth());
+    assert!(PixelFormat::Xbgr2101010.is_high_bit_depth());
+    assert!(PixelFormat::Xbgr16161616f.is_high_bit_depth());
+}
+
+#[test]
+fn eight_bit_formats_have_no_16_bit_data() {
+    let result = convert(&[1, 2, 3, 4], 1, PixelFormat::Argb8888);
+    assert!(result.data16().is_none());
+}
+
+#[test]
+fn xrgb2101010_widens_by_bit_replication() {
+    let result = convert(&pack_2101010(1023, 512, 0, 0), 1, PixelFormat::Xrgb2101010);
+    assert_eq!(result.data16().unwrap(), &[65535, 32800, 0, 65535]);
+    assert_eq!(result.data(), &[255, 128, 0, 255]);
+}
+
+#[test]
+fn xbgr2101010_reads_red_from_low_bits() {
+    let result = convert(&pack_2101010(0, 512, 1023, 0), 1, PixelFormat::Xbgr2101010);
+    assert_eq!(result.data16().unwrap(), &[65535, 32800, 0, 65535]);
+}
+
+#[test]
+fn argb2101010_expands_two_bit_alpha() {
+    let data: Vec<u8> = (0..4)
+        .flat_map(|alpha| pack_2101010(0, 0, 0, alpha))
+        .collect();
+    let result = convert(&data, 4, PixelFormat::Argb2101010);
+    let alphas: Vec<u16> = result.data16().unwrap().chunks(4).map(|p| p[3]).collect();
+    assert_eq!(alphas, vec![0, 0x5555, 0xAAAA, 0xFFFF]);
+    let alphas: Vec<u8> = result.data().chunks(4).map(|p| p[3]).collect();
+    assert_eq!(alphas, vec![0, 0x55, 0xAA, 0xFF]);
+}
+
+#[test]
+fn abgr16161616_keeps_every_bit() {
+    let result = convert(
+        &pack_16([0x1234, 0xABCD, 0x00FF, 0x8001]),
+        1,
+        PixelFormat::Abgr16161616,
+    );
+    assert_eq!(result.data16().unwrap(), &[0x1234, 0xABCD, 0x00FF, 0x8001]);
+    assert_eq!(result.data(), &[0x12, 0xAB, 0x00, 0x80]);
+
+    let opaque = convert(&pack_16([1, 2, 3, 0]), 1, PixelFormat::Xbgr16161616);
+    assert_eq!(opaque.data16().unwrap(), &[1, 2, 3, 65535]);
+}
+
+#[test]
+fn abgr16161616f_clamps_to_unit_range() {
+    // 1.0, 0.5, -1.0, 2.0 as binary16
+    let result = convert(
+        &pack_16([0x3C00, 0x3800, 0xBC00, 0x4000]),
+        1,
+        PixelFormat::Abgr16161616f,
+    );
+    assert_eq!(result.data16().unwrap(), &[65535, 32768, 0, 65535]);
+
+    // NaN and a subnormal
+    let result = convert(
+        &pack_16([0x7E00, 0x0001, 0x0000, 0x3C00]),
+        1,
+        PixelFormat::Xbgr16161616f,
+    );
+    assert_eq!(result.data16().unwrap(), &[0, 0, 0, 65535]);
+}
+
+#[test]
+fn sixteen_bit_formats_are_transformed() {
+    // 2x1 image rotated by 90 degrees becomes 1x2.
+    let mut data = pack_16([1, 2, 3, 4]);
+    data.extend(pack_16([5, 6, 7, 8]));
+    let result = CaptureResult::from_raw(
+        &data,
+        2,
+        1,
+        16,
+        PixelFormat::Abgr16161616,
+        Transform::Rotated90,
+        true,
+    )
+    .unwrap();
+    assert_eq!((result.width(), result.height()), (1, 2));
+    assert_eq!(result.data16().unwrap(), &[5, 6, 7, 8, 1, 2, 3, 4]);
+}
+
+#[test]
+fn sixteen_bit_rows_need_eight_bytes_per_pixel() {
+    let data = vec![0u8; 32];
+    assert!(CaptureResult::from_raw(
+        &data,
+        4,
+        1,
+        16,
+        PixelFormat::Abgr16161616,
+        Transform::Normal,
+        false
+    )
+    .is_err());
+}
+
+#[test]
+#[allow(deprecated)]
+fn png16_round_trips() {
+    if let Ok(grim) = Grim::new() {
+        let data16: Vec<u16> = (0..2 * 3 * 4).map(|i| i * 2731).collect();
+        let png = grim.to_png16(&data16, 2, 3).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap();
+        assert_eq!(decoded.color(), image::ColorType::Rgba16);
+        assert_eq!(decoded.to_rgba16().into_raw(), data16);
+
+        assert!(grim.to_png16(&data16, 3, 3).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+#![cfg(feature = "ipc")]
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use grim_rs::ipc::{CompositorIpc, HyprlandIpc, SwayIpc};
+use grim_rs::{Box, Error};
+
+/// Stand-in for sway's IPC socket that answers one request per connection, picking the
+/// reply by message type, and hands back the types it was asked for.
+fn fake_sway(
+    dir: &tempfile::TempDir,
+    replies: &[(u32, &str)],
+    connections: usize,
+) -> (PathBuf, JoinHandle<Vec<u32>>) {
+    let path = dir.path().join("sway-ipc.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let replies: Vec<(u32, String)> = replies
+        .iter()
+        .map(|(message_type, reply)| (*message_type, reply.to_string()))
+        .collect();
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for _ in 0..connections {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut header = [0u8; 14];
+            stream.read_exact(&mut header).unwrap();
+            assert_eq!(&header[..6], b"i3-ipc");
+            let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+            let reply = replies
+                .iter()
+                .find(|(t, _)| *t == message_type)
+                .map_or("[]", |(_, reply)| reply.as_str());
+
+            let mut response = b"i3-ipc".to_vec();
+            response.extend_from_slice(&(reply.len() as u32).to_ne_bytes());
+            response.extend_from_slice(&message_type.to_ne_bytes());
+            response.extend_from_slice(reply.as_bytes());
+            stream.write_all(&response).unwrap();
+            requests.push(message_type);
+        }
+        requests
+    });
+    (path, server)
+}
+
+/// Stand-in for Hyprland's `.socket.sock`: answers one command per connection and hands
+/// back the commands it received.
+fn fake_hyprland(
+    dir: &tempfile::TempDir,
+    replies: &[(&str, &str)],
+    connections: usize,
+) -> (PathBuf, JoinHandle<Vec<String>>) {
+    let path = dir.path().join(".socket.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let replies: Vec<(String, String)> = replies
+        .iter()
+        .map(|(command, reply)| (command.to_string(), reply.to_string()))
+        .collect();
+    let server = std::thread::spawn(move || {
+        let mut commands = Vec::new();
+        for _ in 0..connections {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut command = [0u8; 64];
+            let read = stream.read(&mut command).unwrap();
+            let command = String::from_utf8_lossy(&command[..read]).into_owned();
+            let reply = replies
+                .iter()
+                .find(|(c, _)| *c == command)
+                .map_or("unknown request", |(_, reply)| reply.as_str());
+            stream.write_all(reply.as_bytes()).unwrap();
+            commands.push(command);
+        }
+        commands
+    });
+    (path, server)
+}
+
+const SWAY_TREE: &str = r#"{
+    "id": 1, "type": "root", "focused": false,
+    "rect": {"x": 0, "y": 0, "width": 3840, "height": 1080},
+    "nodes": [
+        {"id": 3, "type": "output", "name": "DP-1", "focused": false,
+         "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+         "nodes": [
+            {"id": 4, "type": "workspace", "name": "1", "focused": false,
+             "rect": {"x": 0, "y": 30, "width": 1920, "height": 1050},
+             "nodes": [
+                {"id": 5, "type": "con", "name": "term", "focused": false,
+                 "rect": {"x": 0, "y": 30, "width": 960, "height": 1050}, "nodes": []}
+             ],
+             "floating_nodes": [
+                {"id": 6, "type": "floating_con", "name": "Ünïcode \"dialog\"", "focused": true,
+                 "rect": {"x": 700, "y": 400, "width": 520, "height": 310},
+                 "window_rect": {"x": 2, "y": 24, "width": 516, "height": 284},
+                 "nodes": [], "floating_nodes": []}
+             ]}
+         ]}
+    ],
+    "floating_nodes": []
+}"#;
+
+const SWAY_WORKSPACES: &str = r#"[
+    {"num": 1, "name": "1", "focused": false, "output": "DP-1",
+     "rect": {"x": 0, "y": 30, "width": 1920, "height": 1050}},
+    {"num": 2, "name": "2", "focused": true, "output": "HDMI-A-1",
+     "rect": {"x": 1920, "y": 0, "width": 1920, "height": 1080}}
+]"#;
+
+#[test]
+fn sway_focused_window_includes_decorations() {
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_sway(&dir, &[(4, SWAY_TREE)], 1);
+
+    let window = SwayIpc::new(&socket).focused_window().unwrap();
+    assert_eq!(window, Some(Box::new(700, 400, 520, 310)));
+    // GET_TREE.
+    assert_eq!(server.join().unwrap(), vec![4]);
+}
+
+#[test]
+fn sway_focused_window_is_none_on_an_empty_workspace() {
+    let dir = tempfile::tempdir().unwrap();
+    let tree = r#"{"type": "root", "focused": false, "nodes": [
+        {"type": "workspace", "focused": true, "rect": {"x": 0, "y": 0, "width": 8, "height": 4}}
+    ]}"#;
+    let (socket, server) = fake_sway(&dir, &[(4, tree)], 1);
+
+    assert_eq!(SwayIpc::new(&socket).focused_window().unwrap(), None);
+    server.join().unwrap();
+}
+
+#[test]
+fn sway_active_workspace_and_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let outputs = r#"[{"name": "DP-1", "focused": false}, {"name": "HDMI-A-1", "focused": true}]"#;
+    let (socket, server) = fake_sway(&dir, &[(1, SWAY_WORKSPACES), (3, outputs)], 2);
+
+    let ipc = SwayIpc::new(&socket);
+    assert_eq!(
+        ipc.active_workspace().unwrap(),
+        Some(Box::new(1920, 0, 1920, 1080))
+    );
+    assert_eq!(ipc.focused_output().unwrap().as_deref(), Some("HDMI-A-1"));
+    // GET_WORKSPACES, then GET_OUTPUTS.
+    assert_eq!(server.join().unwrap(), vec![1, 3]);
+}
+
+#[test]
+fn sway_errors_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let tree = r#"{"type": "con", "focused": true, "rect": {"x": 0}}"#;
+    let (socket, server) = fake_sway(&dir, &[(4, tree), (1, "{not json")], 2);
+
+    let ipc = SwayIpc::new(&socket);
+    assert!(matches!(ipc.focused_window(), Err(Error::Ipc(_))));
+    assert!(matches!(ipc.active_workspace(), Err(Error::Ipc(_))));
+    server.join().unwrap();
+
+    let missing = SwayIpc::new(dir.path().join("missing.sock"));
+    assert!(matches!(
+        missing.focused_window(),
+        Err(Error::IoWithContext { .. })
+    ));
+}
+
+#[test]
+fn sway_requests_respect_the_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("sway-ipc.sock");
+    // Accepts the connection (into the backlog) but never answers.
+    let _listener = UnixListener::bind(&socket).unwrap();
+
+    let mut ipc = SwayIpc::new(&socket);
+    assert_eq!(ipc.timeout(), Some(Duration::from_secs(10)));
+    ipc.set_timeout(Some(Duration::from_millis(50)));
+    assert!(matches!(
+        ipc.focused_window(),
+        Err(Error::IoWithContext { .. })
+    ));
+}
+
+#[test]
+fn hyprland_focused_window() {
+    let dir = tempfile::tempdir().unwrap();
+    let window = r#"{"address": "0x55d0", "mapped": true, "at": [1930, 42], "size": [800, 600],
+        "workspace": {"id": 2, "name": "2"}, "title": "grim — éditeur"}"#;
+    let (socket, server) = fake_hyprland(&dir, &[("j/activewindow", window)], 1);
+
+    assert_eq!(
+        HyprlandIpc::new(&socket).focused_window().unwrap(),
+        Some(Box::new(1930, 42, 800, 600))
+    );
+    assert_eq!(server.join().unwrap(), vec!["j/activewindow"]);
+}
+
+#[test]
+fn hyprland_focused_window_is_none_without_a_window() {
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_hyprland(&dir, &[("j/activewindow", "{}")], 1);
+
+    assert_eq!(HyprlandIpc::new(&socket).focused_window().unwrap(), None);
+    server.join().unwrap();
+}
+
+#[test]
+fn hyprland_active_workspace_uses_the_logical_monitor_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let workspace = r#"{"id": 3, "name": "3", "monitor": "DP-2", "windows": 1}"#;
+    let monitors = r#"[
+        {"id": 0, "name": "eDP-1", "x": 0, "y": 0, "width": 2880, "height": 1800,
+         "scale": 2.0, "transform": 0, "focused": false},
+        {"id": 1, "name": "DP-2", "x": 1440, "y": 0, "width": 3840, "height": 2160,
+         "scale": 1.5, "transform": 1, "focused": true}
+    ]"#;
+    let (socket, server) = fake_hyprland(
+        &dir,
+        &[("j/activeworkspace", workspace), ("j/monitors", monitors)],
+        3,
+    );
+
+    let ipc = HyprlandIpc::new(&socket);
+    // Rotated by 90 degrees and scaled by 1.5.
+    assert_eq!(
+        ipc.active_workspace().unwrap(),
+        Some(Box::new(1440, 0, 1440, 2560))
+    );
+    assert_eq!(ipc.focused_output().unwrap().as_deref(), Some("DP-2"));
+    assert_eq!(
+        server.join().unwrap(),
+        vec!["j/activeworkspace", "j/monitors", "j/monitors"]
+    );
+}
+
+#[test]
+fn hyprland_errors_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let workspace = r#"{"id": 3, "monitor": "DP-9"}"#;
+    let monitors = r#"[{"name": "DP-1", "x": 0, "y": 0, "width": 8, "height": 4}]"#;
+    let (socket, server) = fake_hyprland(
+        &dir,
+        &[("j/activeworkspace", workspace), ("j/monitors", monitors)],
+        3,
+    );
+
+    let ipc = HyprlandIpc::new(&socket);
+    assert!(matches!(
+        ipc.active_workspace(),
+        Err(Error::OutputNotFound(name)) if name == "DP-9"
+    ));
+    assert!(matches!(ipc.focused_window(), Err(Error::Ipc(_))));
+    server.join().unwrap();
+}
+
+#[test]
+fn compositor_ipc_dispatches_to_the_backend() {
+    let dir = tempfile::tempdir().unwrap();
+    let (socket, server) = fake_sway(&dir, &[(4, SWAY_TREE)], 1);
+
+    let mut ipc = CompositorIpc::Sway(SwayIpc::new(&socket));
+    ipc.set_timeout(Some(Duration::from_secs(1)));
+    assert_eq!(
+        ipc.focused_window().unwrap(),
+        Some(Box::new(700, 400, 520, 310))
+    );
+    server.join().unwrap();
+}
+
+#[test]
+fn sway_windows_lists_visible_leaf_windows() {
+    let dir = tempfile::tempdir().unwrap();
+    let tree = r#"{"type": "root", "nodes": [
+        {"type": "workspace", "visible": true, "rect": {"x": 0, "y": 0, "width": 100, "height": 50},
+         "nodes": [
+            {"type": "con", "layout": "splith", "visible": true,
+             "rect": {"x": 0, "y": 0, "width": 100, "height": 50}, "nodes": [
+                {"type": "con", "visible": true, "rect": {"x": 0, "y": 0, "width": 50, "height": 50}, "nodes": []},
+                {"type": "con", "visible": true, "rect": {"x": 50, "y": 0, "width": 50, "height": 50}, "nodes": []}
+             ]}
+         ],
+         "floating_nodes": [
+            {"type": "floating_con", "visible": true, "rect": {"x": 20, "y": 10, "width": 30, "height": 20}}
+         ]},
+        {"type": "workspace", "visible": false, "nodes": [
+            {"type": "con", "visible": false, "rect": {"x": 0, "y": 0, "width": 100, "height": 50}, "nodes": []}
+        ]}
+    ]}"#;
+    let (socket, server) = fake_sway(&dir, &[(4, tree)], 1);
+
+    assert_eq!(
+        SwayIpc::new(&socket).windows().unwrap(),
+        vec![
+            Box::new(0, 0, 50, 50),
+            Box::new(50, 0, 50, 50),
+            Box::new(20, 10, 30, 20)
+        ]
+    );
+    server.join().unwrap();
+}
+
+#[test]
+fn hyprland_windows_lists_mapped_windows_on_shown_workspaces() {
+    let dir = tempfile::tempdir().unwrap();
+    let monitors = r#"[
+        {"name": "DP-1", "activeWorkspace": {"id": 1, "name": "1"}},
+        {"name": "DP-2", "activeWorkspace": {"id": 4, "name": "4"}}
+    ]"#;
+    let clients = r#"[
+        {"at": [0, 0], "size": [10, 10], "mapped": true, "hidden": false, "workspace": {"id": 1}},
+        {"at": [5, 5], "size": [10, 10], "mapped": true, "hidden": true, "workspace": {"id": 1}},
+        {"at": [9, 9], "size": [10, 10], "mapped": true, "hidden": false, "workspace": {"id": 2}},
+        {"at": [30, 0], "size": [20, 10], "mapped": true, "hidden": false, "workspace": {"id": 4}}
+    ]"#;
+    let (socket, server) =
+        fake_hyprland(&dir, &[("j/monitors", monitors), ("j/clients", clients)], 2);
+
+    let mut ipc = CompositorIpc::Hyprland(HyprlandIpc::new(&socket));
+    ipc.set_timeout(Some(Duration::from_secs(1)));
+    assert_eq!(
+        ipc.windows().unwrap(),
+        vec![Box::new(0, 0, 10, 10), Box::new(30, 0, 20, 10)]
+    );
+    assert_eq!(server.join().unwrap(), vec!["j/monitors", "j/clients"]);
+}
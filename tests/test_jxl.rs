@@ -0,0 +1,156 @@
+//! JPEG XL encoding. Encoding needs only the `jxl` feature; the tests that capture, save
+//! or run the CLI also need `testing` for the mock compositor.
+
+mod common;
+
+use common::encode;
+#[cfg(feature = "testing")]
+use common::grim;
+use grim_rs::{EncodeOptions, ImageFormat};
+
+/// Test pattern with sharp edges, a gradient and varying alpha.
+#[cfg(feature = "jxl")]
+fn pattern(width: u32, height: u32) -> Vec<u8> {
+    (0..height)
+        .flat_map(|y| {
+            (0..width).flat_map(move |x| {
+                let edge = if (x / 4 + y / 4) % 2 == 0 { 0x20 } else { 0xe0 };
+                [
+                    edge,
+                    (x * 255 / width) as u8,
+                    (y * 7) as u8,
+                    255 - (x % 3) as u8,
+                ]
+            })
+        })
+        .collect()
+}
+
+/// Decode `jxl` into its size and 8-bit samples, with alpha if the image has it.
+#[cfg(feature = "jxl")]
+fn decode(jxl: &[u8]) -> (u32, u32, Vec<u8>) {
+    let image = jxl_oxide::JxlImage::builder()
+        .read(jxl)
+        .expect("invalid JPEG XL");
+    let render = image.render_frame(0).unwrap();
+    let mut stream = render.stream();
+    let mut samples = vec![0u8; (stream.width() * stream.height() * stream.channels()) as usize];
+    stream.write_to_buffer(&mut samples);
+    (image.width(), image.height(), samples)
+}
+
+#[test]
+#[cfg(feature = "jxl")]
+fn jxl_round_trips_exactly() {
+    let data = pattern(300, 20);
+    let jxl = encode(&data, 300, 20, EncodeOptions::new(ImageFormat::Jxl)).unwrap();
+    assert_eq!(&jxl[..2], &[0xff, 0x0a]);
+    assert_eq!(decode(&jxl), (300, 20, data));
+}
+
+#[test]
+#[cfg(feature = "jxl")]
+fn every_effort_is_lossless() {
+    let data = pattern(64, 40);
+    for effort in [0, 1, 5, 9, 200] {
+        let jxl = encode(
+            &data,
+            64,
+            40,
+            EncodeOptions::new(ImageFormat::Jxl).effort(effort),
+        )
+        .unwrap();
+        assert_eq!(decode(&jxl), (64, 40, data.clone()), "effort {}", effort);
+    }
+}
+
+#[test]
+#[cfg(feature = "jxl")]
+fn jxl_rejects_mismatched_input() {
+    assert!(encode(&[0; 15], 2, 2, EncodeOptions::new(ImageFormat::Jxl)).is_err());
+    assert!(encode(
+        &[0; 12],
+        2,
+        2,
+        EncodeOptions::new(ImageFormat::Jxl).effort(3)
+    )
+    .is_err());
+}
+
+#[test]
+#[cfg(all(feature = "jxl", feature = "testing"))]
+fn captured_output_round_trips_through_jxl() {
+    let (_compositor, mut grim) = grim();
+    let result = grim.capture_output("DP-1").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("capture.jxl");
+    grim.save(&result, &path, &EncodeOptions::default())
+        .unwrap();
+    let (width, height, samples) = decode(&std::fs::read(&path).unwrap());
+    assert_eq!((width, height), (16, 8));
+    // Opaque captures decode without an alpha channel or with an opaque one.
+    let rgb: Vec<u8> = result
+        .data()
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let channels = samples.len() / (16 * 8);
+    let decoded: Vec<u8> = samples
+        .chunks_exact(channels)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    assert_eq!(decoded, rgb);
+}
+
+#[test]
+#[cfg(not(feature = "jxl"))]
+fn jxl_is_unavailable_without_the_feature() {
+    assert!(encode(&[0; 16], 2, 2, EncodeOptions::new(ImageFormat::Jxl)).is_err());
+    assert!(encode(
+        &[0; 16],
+        2,
+        2,
+        EncodeOptions::new(ImageFormat::Jxl).effort(3)
+    )
+    .is_err());
+}
+
+#[test]
+#[cfg(all(feature = "jxl", feature = "testing"))]
+fn cli_writes_jxl() {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::process::Command;
+
+    let (compositor, _grim) = grim();
+    let dir = tempfile::tempdir().unwrap();
+    for (args, succeeds) in [(&[][..], true), (&["-q", "100"][..], false)] {
+        let file = dir.path().join("capture.jxl");
+        let socket: UnixStream = compositor.connect_socket().unwrap();
+        let fd = socket.as_raw_fd();
+        // SAFETY: `fd` is owned by `socket`, which outlives the child spawn.
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            assert_eq!(libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC), 0);
+        }
+        let output = Command::new(env!("CARGO_BIN_EXE_grim-rs"))
+            .args(["-t", "jxl"])
+            .args(args)
+            .arg(&file)
+            .env("WAYLAND_SOCKET", fd.to_string())
+            .env_remove("WAYLAND_DISPLAY")
+            .output()
+            .unwrap();
+        drop(socket);
+        assert_eq!(
+            output.status.success(),
+            succeeds,
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        if succeeds {
+            let (width, height, _) = decode(&std::fs::read(&file).unwrap());
+            assert_eq!((width, height), (16, 8));
+        }
+    }
+}
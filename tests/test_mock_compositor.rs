@@ -0,0 +1,261 @@
+#![cfg(feature = "testing")]
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{Box, Error, Grim, PixelFormat, Transform};
+
+const TRANSFORMS: [Transform; 8] = [
+    Transform::Normal,
+    Transform::Rotated90,
+    Transform::Rotated180,
+    Transform::Rotated270,
+    Transform::Flipped,
+    Transform::Flipped90,
+    Transform::Flipped180,
+    Transform::Flipped270,
+];
+
+fn connect(outputs: Vec<MockOutput>) -> (MockCompositor, Grim) {
+    let compositor = MockCompositor::new(outputs).unwrap();
+    let grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    (compositor, grim)
+}
+
+fn expected_image(output: &MockOutput, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+    (y..y + height)
+        .flat_map(|py| (x..x + width).flat_map(move |px| output.pixel(px, py)))
+        .collect()
+}
+
+#[test]
+fn outputs_report_mock_geometry() {
+    let (_compositor, mut grim) = connect(vec![
+        MockOutput::new("DP-1", 64, 32),
+        MockOutput::new("HDMI-A-1", 40, 80)
+            .position(64, 0)
+            .scale(2)
+            .transform(Transform::Rotated90),
+    ]);
+    let mut outputs = grim.get_outputs().unwrap();
+    outputs.sort_by(|a, b| a.name().cmp(b.name()));
+
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0].name(), "DP-1");
+    assert_eq!(*outputs[0].geometry(), Box::new(0, 0, 64, 32));
+    assert_eq!(outputs[1].name(), "HDMI-A-1");
+    assert_eq!(*outputs[1].geometry(), Box::new(64, 0, 40, 20));
+    assert_eq!(outputs[1].scale(), 2);
+}
+
+#[test]
+fn outputs_report_mode_and_monitor_metadata() {
+    let (_compositor, mut grim) = connect(vec![MockOutput::new("DP-1", 2560, 1440)
+        .position(100, 0)
+        .scale(2)
+        .transform(Transform::Rotated270)
+        .physical_size_mm(600, 340)]);
+    let outputs = grim.get_outputs().unwrap();
+    let output = &outputs[0];
+
+    assert_eq!(output.make(), "grim-rs");
+    assert_eq!(output.model(), "mock");
+    assert_eq!(output.transform(), Transform::Rotated270);
+    assert_eq!(output.mode_size(), (2560, 1440));
+    assert_eq!(*output.physical_geometry(), Box::new(100, 0, 1440, 2560));
+    assert_eq!(*output.geometry(), Box::new(100, 0, 720, 1280));
+    assert_eq!(output.logical_scale(), 2.0);
+    assert!(!output.logical_scale_inferred());
+    assert_eq!(output.refresh_mhz(), Some(60_000));
+    assert_eq!(output.physical_size_mm(), Some((600, 340)));
+    let dpi = output.dpi().unwrap();
+    assert!((dpi - 108.37).abs() < 0.01, "dpi {}", dpi);
+
+    let (_compositor, mut grim) = connect(vec![MockOutput::new("DP-2", 64, 32)]);
+    let output = &grim.get_outputs().unwrap()[0];
+    assert_eq!(output.physical_size_mm(), None);
+    assert_eq!(output.dpi(), None);
+}
+
+#[test]
+fn capture_undoes_every_transform_and_y_invert() {
+    for transform in TRANSFORMS {
+        for y_invert in [false, true] {
+            let output = MockOutput::new("DP-1", 7, 5)
+                .transform(transform)
+                .y_invert(y_invert);
+            let (width, height) = output.physical_size();
+            let (_compositor, mut grim) = connect(vec![output.clone()]);
+
+            let result = grim.capture_output("DP-1").unwrap();
+            assert_eq!(
+                (result.width() as i32, result.height() as i32),
+                (width, height),
+                "{:?} y_invert={}",
+                transform,
+                y_invert
+            );
+            assert_eq!(
+                result.data(),
+                expected_image(&output, 0, 0, width as u32, height as u32).as_slice(),
+                "{:?} y_invert={}",
+                transform,
+                y_invert
+            );
+            assert_eq!(result.transform(), transform);
+            assert_eq!(result.y_invert(), y_invert);
+        }
+    }
+}
+
+#[test]
+fn capture_converts_shm_formats() {
+    let lossless = [
+        PixelFormat::Argb8888,
+        PixelFormat::Xrgb8888,
+        PixelFormat::Abgr8888,
+        PixelFormat::Xbgr8888,
+        PixelFormat::Rgb888,
+        PixelFormat::Bgr888,
+        PixelFormat::Xrgb2101010,
+        PixelFormat::Xbgr2101010,
+        PixelFormat::Xbgr16161616,
+        PixelFormat::Abgr16161616,
+    ];
+    for format in lossless {
+        let output = MockOutput::new("DP-1", 6, 3)
+            .format(format)
+            .content(|x, y| [x as u8 * 40, y as u8 * 80, 7, 255]);
+        let (_compositor, mut grim) = connect(vec![output.clone()]);
+        let result = grim.capture_output("DP-1").unwrap();
+        assert_eq!(result.format(), Some(format));
+        assert_eq!(
+            result.data(),
+            expected_image(&output, 0, 0, 6, 3).as_slice(),
+            "{:?}",
+            format
+        );
+        assert_eq!(result.data16().is_some(), format.is_high_bit_depth());
+    }
+}
+
+#[test]
+fn capture_converts_rgb565() {
+    // Values whose low bits survive the 5:6:5 round trip.
+    let output = MockOutput::new("DP-1", 2, 1)
+        .format(PixelFormat::Rgb565)
+        .content(|x, _| {
+            if x == 0 {
+                [255, 0, 0, 255]
+            } else {
+                [0, 255, 255, 255]
+            }
+        });
+    let (_compositor, mut grim) = connect(vec![output]);
+    let result = grim.capture_output("DP-1").unwrap();
+    assert_eq!(result.data(), &[255, 0, 0, 255, 0, 255, 255, 255]);
+}
+
+#[test]
+fn capture_honors_compositor_stride() {
+    let output = MockOutput::new("DP-1", 5, 4)
+        .format(PixelFormat::Rgb888)
+        .stride_padding(9);
+    let (_compositor, mut grim) = connect(vec![output.clone()]);
+    let result = grim.capture_output("DP-1").unwrap();
+    assert_eq!(
+        result.data(),
+        expected_image(&output, 0, 0, 5, 4).as_slice()
+    );
+}
+
+#[test]
+fn failed_frames_are_reported() {
+    let (_compositor, mut grim) = connect(vec![MockOutput::new("DP-1", 4, 4).fail_captures(true)]);
+    assert!(matches!(
+        grim.capture_output("DP-1"),
+        Err(Error::FrameCapture(_))
+    ));
+}
+
+#[test]
+fn unsupported_formats_are_rejected() {
+    // YUYV is a valid wl_shm format the pipeline cannot convert.
+    let output = MockOutput::new("DP-1", 4, 4).format(PixelFormat::Other(0x5659_5559));
+    let (_compositor, mut grim) = connect(vec![output]);
+    assert!(matches!(
+        grim.capture_output("DP-1"),
+        Err(Error::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn capture_all_composites_outputs() {
+    let left = MockOutput::new("DP-1", 4, 3).content(|x, y| [x as u8, y as u8, 1, 255]);
+    let right = MockOutput::new("DP-2", 2, 3)
+        .position(4, 0)
+        .content(|x, y| [x as u8, y as u8, 2, 255]);
+    let (_compositor, mut grim) = connect(vec![left.clone(), right.clone()]);
+
+    let result = grim.capture_all().unwrap();
+    assert_eq!((result.width(), result.height()), (6, 3));
+    for y in 0..3 {
+        for x in 0..6 {
+            let expected = if x < 4 {
+                left.pixel(x, y)
+            } else {
+                right.pixel(x - 4, y)
+            };
+            let start = ((y * 6 + x) * 4) as usize;
+            assert_eq!(
+                &result.data()[start..start + 4],
+                &expected,
+                "({}, {})",
+                x,
+                y
+            );
+        }
+    }
+}
+
+#[test]
+fn capture_region_crops_across_outputs() {
+    let left = MockOutput::new("DP-1", 4, 3).content(|x, y| [x as u8, y as u8, 1, 255]);
+    let right = MockOutput::new("DP-2", 4, 3)
+        .position(4, 0)
+        .transform(Transform::Rotated180)
+        .content(|x, y| [x as u8, y as u8, 2, 255]);
+    let (_compositor, mut grim) = connect(vec![left.clone(), right.clone()]);
+
+    let result = grim.capture_region(Box::new(2, 1, 4, 2)).unwrap();
+    assert_eq!((result.width(), result.height()), (4, 2));
+    let mut expected = Vec::new();
+    for y in 1..3 {
+        for x in 2..6 {
+            if x < 4 {
+                expected.extend(left.pixel(x, y));
+            } else {
+                expected.extend(right.pixel(x - 4, y));
+            }
+        }
+    }
+    assert_eq!(result.data(), expected.as_slice());
+}
+
+#[test]
+fn capture_reports_frame_metadata() {
+    let (_compositor, mut grim) = connect(vec![MockOutput::new("DP-1", 4, 4)]);
+    let first = grim.capture_output("DP-1").unwrap();
+    let second = grim.capture_output("DP-1").unwrap();
+    assert_eq!(first.output_name(), Some("DP-1"));
+    assert!(first.timestamp().unwrap() < second.timestamp().unwrap());
+}
+
+#[test]
+fn compositor_serves_several_clients() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4)]).unwrap();
+    let mut first = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    let mut second = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    assert_eq!(
+        first.capture_output("DP-1").unwrap().data(),
+        second.capture_output("DP-1").unwrap().data()
+    );
+}
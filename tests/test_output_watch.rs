@@ -0,0 +1,135 @@
+#![cfg(feature = "testing")]
+
+use std::time::Duration;
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{CancellationToken, Error, Grim, OutputEvent, Transform};
+
+fn connect(compositor: &MockCompositor) -> Grim {
+    Grim::from_connection(compositor.connect().unwrap()).unwrap()
+}
+
+#[test]
+fn initial_outputs_are_not_reported_as_added() {
+    let compositor = MockCompositor::new(vec![
+        MockOutput::new("DP-1", 4, 2),
+        MockOutput::new("DP-2", 4, 2).position(4, 0),
+    ])
+    .unwrap();
+    let watch = connect(&compositor).watch_outputs().unwrap();
+    let names: Vec<String> = watch
+        .outputs()
+        .iter()
+        .map(|output| output.name().to_string())
+        .collect();
+    assert_eq!(names, ["DP-1", "DP-2"]);
+}
+
+#[test]
+fn hotplug_is_reported() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 2)]).unwrap();
+    let grim = connect(&compositor);
+    let mut watch = grim.watch_outputs().unwrap();
+
+    compositor
+        .add_output(MockOutput::new("HDMI-A-1", 8, 6).position(4, 0))
+        .unwrap();
+    match watch.next().unwrap().unwrap() {
+        OutputEvent::Added(output) => {
+            assert_eq!(output.name(), "HDMI-A-1");
+            assert_eq!((output.geometry().x(), output.geometry().width()), (4, 8));
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert_eq!(watch.outputs().len(), 2);
+
+    compositor.remove_output("DP-1").unwrap();
+    match watch.next().unwrap().unwrap() {
+        OutputEvent::Removed(output) => assert_eq!(output.name(), "DP-1"),
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert_eq!(watch.outputs().len(), 1);
+}
+
+#[test]
+fn reconfiguration_is_one_change() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 8, 4)]).unwrap();
+    let mut watch = connect(&compositor).watch_outputs().unwrap();
+
+    compositor
+        .update_output(
+            MockOutput::new("DP-1", 8, 4)
+                .transform(Transform::Rotated90)
+                .scale(2),
+        )
+        .unwrap();
+    match watch.next().unwrap().unwrap() {
+        OutputEvent::Changed { before, after } => {
+            assert_eq!(
+                (before.geometry().width(), before.geometry().height()),
+                (8, 4)
+            );
+            assert_eq!(before.scale(), 1);
+            assert_eq!(
+                (after.geometry().width(), after.geometry().height()),
+                (2, 4)
+            );
+            assert_eq!(after.scale(), 2);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert_eq!(watch.outputs()[0].scale(), 2);
+}
+
+#[test]
+fn captures_continue_while_watching() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 2)]).unwrap();
+    let mut grim = connect(&compositor);
+    let watch = grim.watch_outputs().unwrap();
+    let watcher = std::thread::spawn(move || watch.take(1).collect::<Vec<_>>());
+
+    compositor
+        .add_output(MockOutput::new("DP-2", 6, 2).position(4, 0))
+        .unwrap();
+    let events = watcher.join().unwrap();
+    assert!(matches!(&events[..], [Ok(OutputEvent::Added(output))] if output.name() == "DP-2"));
+
+    assert_eq!(grim.get_outputs().unwrap().len(), 2);
+    assert_eq!(grim.capture_output("DP-2").unwrap().width(), 6);
+}
+
+#[test]
+fn captures_after_unplug_report_the_missing_output() {
+    let compositor = MockCompositor::new(vec![
+        MockOutput::new("DP-1", 4, 2),
+        MockOutput::new("DP-2", 4, 2).position(4, 0),
+    ])
+    .unwrap();
+    let mut grim = connect(&compositor);
+    let mut watch = grim.watch_outputs().unwrap();
+
+    compositor.remove_output("DP-2").unwrap();
+    assert!(matches!(watch.next(), Some(Ok(OutputEvent::Removed(_)))));
+    assert!(matches!(
+        grim.capture_output("DP-2"),
+        Err(Error::OutputNotFound(_))
+    ));
+    assert_eq!(grim.capture_all().unwrap().width(), 4);
+}
+
+#[test]
+fn cancellation_ends_the_watch() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 2)]).unwrap();
+    let mut grim = connect(&compositor);
+    let token = CancellationToken::new().unwrap();
+    grim.set_cancellation_token(Some(token.clone()));
+    let mut watch = grim.watch_outputs().unwrap();
+
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        token.cancel();
+    });
+    assert!(matches!(watch.next(), Some(Err(Error::Cancelled))));
+    assert!(watch.next().is_none());
+    canceller.join().unwrap();
+}
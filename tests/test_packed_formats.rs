@@ -0,0 +1,129 @@
+use grim_rs::{CaptureResult, Error, PixelFormat, Transform};
+
+fn convert(data: &[u8], width: u32, height: u32, stride: u32, format: PixelFormat) -> Vec<u8> {
+    CaptureResult::from_raw(
+        data,
+        width,
+        height,
+        stride,
+        format,
+        Transform::Normal,
+        false,
+    )
+    .unwrap()
+    .data()
+    .to_vec()
+}
+
+#[test]
+fn packed_format_sizes() {
+    assert_eq!(PixelFormat::Rgb888.bytes_per_pixel(), Some(3));
+    assert_eq!(PixelFormat::Bgr888.bytes_per_pixel(), Some(3));
+    assert_eq!(PixelFormat::Rgb565.bytes_per_pixel(), Some(2));
+    assert_eq!(PixelFormat::Bgr565.bytes_per_pixel(), Some(2));
+    assert!(!PixelFormat::Rgb565.is_high_bit_depth());
+}
+
+#[test]
+fn rgb888_and_bgr888_channel_order() {
+    let data = [1, 2, 3, 4, 5, 6];
+    assert_eq!(
+        convert(&data, 2, 1, 6, PixelFormat::Rgb888),
+        [3, 2, 1, 255, 6, 5, 4, 255]
+    );
+    assert_eq!(
+        convert(&data, 2, 1, 6, PixelFormat::Bgr888),
+        [1, 2, 3, 255, 4, 5, 6, 255]
+    );
+}
+
+#[test]
+fn rgb565_expands_by_bit_replication() {
+    // Pure red, pure green, pure blue, and a mid value in every channel.
+    let words: [u16; 4] = [0xF800, 0x07E0, 0x001F, (0x10 << 11) | (0x20 << 5) | 0x10];
+    let data: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    assert_eq!(
+        convert(&data, 4, 1, 8, PixelFormat::Rgb565),
+        [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 132, 130, 132, 255]
+    );
+    assert_eq!(
+        convert(&data[..2], 1, 1, 2, PixelFormat::Bgr565),
+        [0, 0, 255, 255]
+    );
+}
+
+#[test]
+fn packed_formats_honor_stride_padding() {
+    // Rows of 3 Rgb888 pixels padded to 12 bytes, as compositors align strides to 4.
+    let (width, height, stride) = (3u32, 2u32, 12u32);
+    let mut padded = vec![0xEE; (stride * height) as usize];
+    let mut packed = Vec::new();
+    for y in 0..height as usize {
+        let row: Vec<u8> = (0..9).map(|i| (y * 9 + i) as u8).collect();
+        padded[y * stride as usize..y * stride as usize + 9].copy_from_slice(&row);
+        packed.extend(row);
+    }
+    for transform in [
+        Transform::Normal,
+        Transform::Rotated90,
+        Transform::Flipped270,
+    ] {
+        let from_padded = CaptureResult::from_raw(
+            &padded,
+            width,
+            height,
+            stride,
+            PixelFormat::Rgb888,
+            transform,
+            true,
+        )
+        .unwrap();
+        let from_packed = CaptureResult::from_raw(
+            &packed,
+            width,
+            height,
+            width * 3,
+            PixelFormat::Rgb888,
+            transform,
+            true,
+        )
+        .unwrap();
+        assert_eq!(from_padded.data(), from_packed.data(), "{:?}", transform);
+    }
+}
+
+#[test]
+fn packed_formats_are_transformed() {
+    // 2x1 Rgb565 row rotated by 90 degrees becomes 1x2.
+    let data: Vec<u8> = [0xF800u16, 0x001F]
+        .iter()
+        .flat_map(|w| w.to_le_bytes())
+        .collect();
+    let result = CaptureResult::from_raw(
+        &data,
+        2,
+        1,
+        4,
+        PixelFormat::Rgb565,
+        Transform::Rotated90,
+        false,
+    )
+    .unwrap();
+    assert_eq!((result.width(), result.height()), (1, 2));
+    assert_eq!(result.data(), &[255, 0, 0, 255, 0, 0, 255, 255]);
+}
+
+#[test]
+fn unknown_formats_are_rejected() {
+    let data = [0u8; 16];
+    let result = CaptureResult::from_raw(
+        &data,
+        2,
+        2,
+        8,
+        PixelFormat::Other(0x3432_4752),
+        Transform::Normal,
+        false,
+    );
+    assert!(matches!(result, Err(Error::UnsupportedFormat(_))));
+}
@@ -0,0 +1,281 @@
+use grim_rs::{CaptureResult, Error, PixelFormat, Transform};
+use proptest::prelude::*;
+
+const TRANSFORMS: [Transform; 8] = [
+    Transform::Normal,
+    Transform::Rotated90,
+    Transform::Rotated180,
+    Transform::Rotated270,
+    Transform::Flipped,
+    Transform::Flipped90,
+    Transform::Flipped180,
+    Transform::Flipped270,
+];
+
+const FORMATS: [PixelFormat; 5] = [
+    PixelFormat::Xrgb8888,
+    PixelFormat::Argb8888,
+    PixelFormat::Xbgr8888,
+    PixelFormat::Abgr8888,
+    PixelFormat::Other(0x3432_4752),
+];
+
+// The multi-pass chain the capture path used before the fused pipeline:
+// convert in place, then transform, then flip for Y-invert.
+
+fn convert_in_place(data: &mut [u8], format: PixelFormat) {
+    for chunk in data.chunks_exact_mut(4) {
+        match format {
+            PixelFormat::Xrgb8888 => {
+                chunk.swap(0, 2);
+                chunk[3] = 255;
+            }
+            PixelFormat::Argb8888 => chunk.swap(0, 2),
+            PixelFormat::Xbgr8888 => chunk[3] = 255,
+            _ => {}
+        }
+    }
+}
+
+fn remap(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    swap: bool,
+    map: impl Fn(u32, u32) -> (u32, u32),
+) -> (Vec<u8>, u32, u32) {
+    let (new_width, new_height) = if swap {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let mut out = vec![0u8; data.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let src = ((y * width + x) * 4) as usize;
+            let (new_x, new_y) = map(x, y);
+            let dst = ((new_y * new_width + new_x) * 4) as usize;
+            out[dst..dst + 4].copy_from_slice(&data[src..src + 4]);
+        }
+    }
+    (out, new_width, new_height)
+}
+
+fn rotate_90(data: &[u8], w: u32, h: u32) -> (Vec<u8>, u32, u32) {
+    remap(data, w, h, true, |x, y| (h - 1 - y, x))
+}
+
+fn rotate_180(data: &[u8], w: u32, h: u32) -> (Vec<u8>, u32, u32) {
+    remap(data, w, h, false, |x, y| (w - 1 - x, h - 1 - y))
+}
+
+fn rotate_270(data: &[u8], w: u32, h: u32) -> (Vec<u8>, u32, u32) {
+    remap(data, w, h, true, |x, y| (y, w - 1 - x))
+}
+
+fn flip_horizontal(data: &[u8], w: u32, h: u32) -> (Vec<u8>, u32, u32) {
+    remap(data, w, h, false, |x, y| (w - 1 - x, y))
+}
+
+fn flip_vertical(data: &[u8], w: u32, h: u32) -> (Vec<u8>, u32, u32) {
+    remap(data, w, h, false, |x, y| (x, h - 1 - y))
+}
+
+fn multi_pass(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    transform: Transform,
+    y_invert: bool,
+) -> (Vec<u8>, u32, u32) {
+    let mut converted = data.to_vec();
+    convert_in_place(&mut converted, format);
+    let (data, w, h) = match transform {
+        Transform::Normal => (converted, width, height),
+        Transform::Rotated90 => rotate_90(&converted, width, height),
+        Transform::Rotated180 => rotate_180(&converted, width, height),
+        Transform::Rotated270 => rotate_270(&converted, width, height),
+        Transform::Flipped => flip_horizontal(&converted, width, height),
+        Transform::Flipped90 => {
+            let (flipped, w, h) = flip_horizontal(&converted, width, height);
+            rotate_90(&flipped, w, h)
+        }
+        Transform::Flipped180 => flip_vertical(&converted, width, height),
+        Transform::Flipped270 => {
+            let (flipped, w, h) = flip_horizontal(&converted, width, height);
+            rotate_270(&flipped, w, h)
+        }
+    };
+    if y_invert {
+        flip_vertical(&data, w, h)
+    } else {
+        (data, w, h)
+    }
+}
+
+fn test_frame(width: u32, height: u32) -> Vec<u8> {
+    (0..width * height * 4)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+        .collect()
+}
+
+fn assert_matches_multi_pass(width: u32, height: u32) {
+    let data = test_frame(width, height);
+    for format in FORMATS {
+        for transform in TRANSFORMS {
+            for y_invert in [false, true] {
+                let fused = CaptureResult::from_raw(
+                    &data,
+                    width,
+                    height,
+                    width * 4,
+                    format,
+                    transform,
+                    y_invert,
+                );
+                // Unknown formats have no layout to convert from.
+                if let PixelFormat::Other(_) = format {
+                    assert!(matches!(fused, Err(Error::UnsupportedFormat(_))));
+                    continue;
+                }
+                let fused = fused.unwrap();
+                let (expected, w, h) =
+                    multi_pass(&data, width, height, format, transform, y_invert);
+                assert_eq!(
+                    (fused.width(), fused.height()),
+                    (w, h),
+                    "{}x{} {:?} {:?} y_invert={}",
+                    width,
+                    height,
+                    format,
+                    transform,
+                    y_invert
+                );
+                assert!(
+                    fused.data() == expected.as_slice(),
+                    "{}x{} {:?} {:?} y_invert={}",
+                    width,
+                    height,
+                    format,
+                    transform,
+                    y_invert
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn fused_pipeline_matches_multi_pass_chain() {
+    assert_matches_multi_pass(7, 5);
+}
+
+#[test]
+fn fused_pipeline_matches_multi_pass_chain_across_tiles() {
+    // Larger than one tile in both directions, with partial tiles at the edges.
+    assert_matches_multi_pass(150, 67);
+}
+
+#[test]
+fn fused_pipeline_handles_single_row_and_column() {
+    assert_matches_multi_pass(9, 1);
+    assert_matches_multi_pass(1, 9);
+    assert_matches_multi_pass(1, 1);
+}
+
+#[test]
+fn fused_pipeline_skips_stride_padding() {
+    let (width, height, stride) = (5u32, 4u32, 32u32);
+    let packed = test_frame(width, height);
+    let mut padded = vec![0xAA; (stride * height) as usize];
+    for (row, chunk) in packed.chunks(width as usize * 4).enumerate() {
+        let start = row * stride as usize;
+        padded[start..start + chunk.len()].copy_from_slice(chunk);
+    }
+
+    for transform in TRANSFORMS {
+        let from_padded = CaptureResult::from_raw(
+            &padded,
+            width,
+            height,
+            stride,
+            PixelFormat::Xrgb8888,
+            transform,
+            true,
+        )
+        .unwrap();
+        let from_packed = CaptureResult::from_raw(
+            &packed,
+            width,
+            height,
+            width * 4,
+            PixelFormat::Xrgb8888,
+            transform,
+            true,
+        )
+        .unwrap();
+        assert_eq!(from_padded.data(), from_packed.data(), "{:?}", transform);
+    }
+}
+
+#[test]
+fn from_raw_records_metadata() {
+    let data = test_frame(3, 2);
+    let result = CaptureResult::from_raw(
+        &data,
+        3,
+        2,
+        12,
+        PixelFormat::Argb8888,
+        Transform::Flipped270,
+        true,
+    )
+    .unwrap();
+    assert_eq!(result.format(), Some(PixelFormat::Argb8888));
+    assert_eq!(result.transform(), Transform::Flipped270);
+    assert!(result.y_invert());
+    assert!(result.damage().is_empty());
+}
+
+#[test]
+fn from_raw_rejects_short_buffers() {
+    let data = test_frame(4, 4);
+    assert!(CaptureResult::from_raw(
+        &data,
+        4,
+        4,
+        12,
+        PixelFormat::Xrgb8888,
+        Transform::Normal,
+        false
+    )
+    .is_err());
+    assert!(CaptureResult::from_raw(
+        &data[..data.len() - 1],
+        4,
+        4,
+        16,
+        PixelFormat::Xrgb8888,
+        Transform::Normal,
+        false
+    )
+    .is_err());
+}
+
+proptest! {
+    #[test]
+    fn fused_pipeline_matches_multi_pass_for_any_size(
+        width in 1u32..80, height in 1u32..80, transform_index in 0usize..8, y_invert: bool
+    ) {
+        let data = test_frame(width, height);
+        let transform = TRANSFORMS[transform_index];
+        let fused = CaptureResult::from_raw(
+            &data, width, height, width * 4, PixelFormat::Xrgb8888, transform, y_invert,
+        ).unwrap();
+        let (expected, w, h) =
+            multi_pass(&data, width, height, PixelFormat::Xrgb8888, transform, y_invert);
+        prop_assert_eq!((fused.width(), fused.height()), (w, h));
+        prop_assert!(fused.data() == expected.as_slice());
+    }
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use common::encode_capture;
+#[cfg(feature = "testing")]
+use common::grim;
+use grim_rs::encode::{PngCompression, PngFilter};
+use grim_rs::{CaptureResult, EncodeOptions, ImageFormat};
+
+/// Screenshot-like frame: a gradient header, flat panels, noisy "text" blocks and a
+/// photo-like quadrant.
+fn screenshot(width: u32, height: u32) -> CaptureResult {
+    let mut seed = 0x1234_5678u32;
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let pixel = if y < height / 4 {
+                [(x * 255 / width) as u8, (y * 4) as u8, 0x80, 0xff]
+            } else if (x / 8 + y / 12) % 5 == 0 {
+                let value = if seed >> 29 == 0 { 0x20 } else { 0xf0 };
+                [value, value, value, 0xff]
+            } else if x > width / 2 && y > height / 2 {
+                [(seed >> 24) as u8 & 0x3f | 0x40, 0x90, (x ^ y) as u8, 0xff]
+            } else {
+                [0xf5, 0xf5, 0xf5, 0xff]
+            };
+            data.extend_from_slice(&pixel);
+        }
+    }
+    CaptureResult::new(data, width, height)
+}
+
+/// Encode `result` and check that the PNG decodes back to it.
+fn encode(result: &CaptureResult, options: EncodeOptions) -> Vec<u8> {
+    let png = encode_capture(result, options.clone()).unwrap();
+    let decoded = image::load_from_memory(&png).unwrap().into_rgba8();
+    assert_eq!(decoded.dimensions(), (result.width(), result.height()));
+    assert_eq!(decoded.into_raw(), result.data(), "{:?}", options);
+    png
+}
+
+fn png() -> EncodeOptions {
+    EncodeOptions::new(ImageFormat::Png)
+}
+
+#[test]
+fn higher_levels_give_smaller_files() {
+    let result = screenshot(256, 160);
+    let sizes: Vec<usize> = (0..=9)
+        .map(|level| encode(&result, png().compression(level)).len())
+        .collect();
+
+    // zlib does not promise every step shrinks the output, only the trend.
+    for (level, pair) in sizes.windows(2).enumerate() {
+        assert!(
+            pair[1] * 100 <= pair[0] * 102,
+            "level {} is {} bytes, level {} only {}: {:?}",
+            level + 1,
+            pair[1],
+            level,
+            pair[0],
+            sizes
+        );
+    }
+    assert!(sizes[0] > sizes[1] && sizes[1] > sizes[6] && sizes[6] > sizes[9]);
+    assert_eq!(encode(&result, png()).len(), sizes[6]);
+}
+
+#[test]
+fn level_0_stores_the_pixels() {
+    let result = screenshot(64, 40);
+    let stored = encode(&result, png().compression(0));
+    // One filter byte per row on top of the pixels, plus chunk and zlib headers.
+    let raw = result.data().len() + 40;
+    assert!(
+        (raw..raw + 128).contains(&stored.len()),
+        "{} bytes",
+        stored.len()
+    );
+}
+
+#[test]
+fn adaptive_filtering_beats_unfiltered_rows() {
+    let result = screenshot(256, 160);
+    for level in [1, 6, 9] {
+        let unfiltered = encode(
+            &result,
+            png().compression(level).png_filter(PngFilter::NoFilter),
+        );
+        let adaptive = encode(&result, png().compression(level));
+        assert!(
+            adaptive.len() < unfiltered.len(),
+            "level {}: {} >= {}",
+            level,
+            adaptive.len(),
+            unfiltered.len()
+        );
+    }
+}
+
+#[test]
+fn every_filter_round_trips() {
+    let result = screenshot(33, 17);
+    for filter in [
+        PngFilter::NoFilter,
+        PngFilter::Sub,
+        PngFilter::Up,
+        PngFilter::Avg,
+        PngFilter::Paeth,
+        PngFilter::Adaptive,
+    ] {
+        encode(&result, png().png_filter(filter));
+        encode(
+            &result,
+            png()
+                .png_compression(PngCompression::Fast)
+                .png_filter(filter),
+        );
+    }
+}
+
+#[test]
+fn fast_preset_compresses() {
+    let result = screenshot(256, 160);
+    let fast = encode(&result, png().png_compression(PngCompression::Fast));
+    let stored = encode(&result, png().compression(0));
+    assert!(
+        fast.len() * 2 < stored.len(),
+        "{} vs {}",
+        fast.len(),
+        stored.len()
+    );
+}
+
+#[test]
+fn fast_preset_counts_as_level_1() {
+    let fast = png().png_compression(PngCompression::Fast);
+    assert_eq!(fast.png_compression_value(), PngCompression::Fast);
+    assert_eq!(fast.compression_level(), 1);
+    assert_eq!(png().png_compression_value(), PngCompression::Level(6));
+    assert_eq!(png().compression(12).compression_level(), 9);
+}
+
+#[test]
+fn compression_picks_the_default_filter() {
+    assert_eq!(png().png_filter_value(), PngFilter::Adaptive);
+    assert_eq!(png().compression(0).png_filter_value(), PngFilter::NoFilter);
+    assert_eq!(
+        png()
+            .png_compression(PngCompression::Fast)
+            .png_filter_value(),
+        PngFilter::Up
+    );
+    assert_eq!(
+        png()
+            .compression(0)
+            .png_filter(PngFilter::Paeth)
+            .png_filter_value(),
+        PngFilter::Paeth
+    );
+}
+
+#[test]
+#[cfg(feature = "testing")]
+#[allow(deprecated)]
+fn legacy_methods_use_the_same_levels() {
+    let (_compositor, grim) = grim();
+    let result = screenshot(40, 24);
+    for level in [0, 1, 9] {
+        assert_eq!(
+            grim.to_png_with_compression(result.data(), 40, 24, level)
+                .unwrap(),
+            encode(&result, png().compression(level))
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "testing")]
+fn cli_accepts_the_fast_preset() {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::process::Command;
+
+    let (compositor, _grim) = grim();
+    let dir = tempfile::tempdir().unwrap();
+    for level in ["fast", "1", "9"] {
+        let file = dir.path().join(format!("capture-{}.png", level));
+        let socket: UnixStream = compositor.connect_socket().unwrap();
+        let fd = socket.as_raw_fd();
+        // SAFETY: `fd` is owned by `socket`, which outlives the child spawn.
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            assert_eq!(libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC), 0);
+        }
+        let output = Command::new(env!("CARGO_BIN_EXE_grim-rs"))
+            .args(["-l", level])
+            .arg(&file)
+            .env("WAYLAND_SOCKET", fd.to_string())
+            .env_remove("WAYLAND_DISPLAY")
+            .output()
+            .unwrap();
+        drop(socket);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let decoded = image::open(&file).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+    }
+}
@@ -0,0 +1,94 @@
+//! QOI encoding. Encoding needs only the `qoi` feature; the tests that capture, save
+//! or run the CLI also need `testing` for the mock compositor.
+
+mod common;
+
+use common::encode;
+#[cfg(feature = "testing")]
+use common::grim;
+use grim_rs::{EncodeOptions, ImageFormat};
+
+/// Decode `qoi` into its size and RGBA pixels.
+#[cfg(feature = "qoi")]
+fn decode(qoi: &[u8]) -> (u32, u32, Vec<u8>) {
+    let (header, pixels) = qoi::decode_to_vec(qoi).expect("invalid QOI");
+    assert_eq!(header.channels, qoi::Channels::Rgba);
+    (header.width, header.height, pixels)
+}
+
+#[test]
+#[cfg(feature = "qoi")]
+fn qoi_round_trips_exactly() {
+    let data: Vec<u8> = (0..23u32 * 11)
+        .flat_map(|i| [(i * 7) as u8, (i / 3) as u8, 0x40, (255 - i % 4) as u8])
+        .collect();
+    let qoi = encode(&data, 23, 11, EncodeOptions::new(ImageFormat::Qoi)).unwrap();
+    assert_eq!(&qoi[..4], b"qoif");
+    assert_eq!(decode(&qoi), (23, 11, data));
+}
+
+#[test]
+#[cfg(all(feature = "qoi", feature = "testing"))]
+fn captured_output_round_trips_through_qoi() {
+    let (_compositor, mut grim) = grim();
+    let result = grim.capture_output("DP-1").unwrap();
+    let mut qoi = Vec::new();
+    grim.encode(&result, &EncodeOptions::new(ImageFormat::Qoi), &mut qoi)
+        .unwrap();
+    assert_eq!(decode(&qoi), (16, 8, result.data().to_vec()));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("capture.qoi");
+    grim.save(&result, &path, &EncodeOptions::default())
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), qoi);
+}
+
+#[test]
+#[cfg(feature = "qoi")]
+fn qoi_rejects_input_that_is_not_rgba() {
+    assert!(encode(&[0; 15], 2, 2, EncodeOptions::new(ImageFormat::Qoi)).is_err());
+    // Three bytes per pixel would be valid RGB for the encoder.
+    assert!(encode(&[0; 12], 2, 2, EncodeOptions::new(ImageFormat::Qoi)).is_err());
+    assert!(encode(&[], 0, 0, EncodeOptions::new(ImageFormat::Qoi)).is_err());
+}
+
+#[test]
+#[cfg(not(feature = "qoi"))]
+fn qoi_is_unavailable_without_the_feature() {
+    assert!(encode(&[0; 16], 2, 2, EncodeOptions::new(ImageFormat::Qoi)).is_err());
+}
+
+#[test]
+#[cfg(all(feature = "qoi", feature = "testing"))]
+fn cli_writes_qoi() {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::process::Command;
+
+    let (compositor, _grim) = grim();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("capture.qoi");
+    let socket: UnixStream = compositor.connect_socket().unwrap();
+    let fd = socket.as_raw_fd();
+    // SAFETY: `fd` is owned by `socket`, which outlives the child spawn.
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        assert_eq!(libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC), 0);
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_grim-rs"))
+        .args(["-t", "qoi"])
+        .arg(&file)
+        .env("WAYLAND_SOCKET", fd.to_string())
+        .env_remove("WAYLAND_DISPLAY")
+        .output()
+        .unwrap();
+    drop(socket);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let (width, height, _) = decode(&std::fs::read(&file).unwrap());
+    assert_eq!((width, height), (16, 8));
+}
@@ -0,0 +1,291 @@
+#![cfg(feature = "select")]
+
+use grim_rs::select::{Selection, DEFAULT_SNAP_DISTANCE};
+use grim_rs::Box;
+
+/// Two outputs side by side, the second one shorter.
+fn layout() -> Vec<Box> {
+    vec![Box::new(0, 0, 100, 60), Box::new(100, 0, 80, 40)]
+}
+
+fn drag(selection: &mut Selection, from: (i32, i32), to: (i32, i32)) -> Option<Box> {
+    selection.pointer_motion(from.0, from.1);
+    selection.press();
+    selection.pointer_motion(to.0, to.1);
+    selection.release()
+}
+
+#[test]
+fn drag_selects_a_rectangle_in_any_direction() {
+    let mut selection = Selection::new(layout());
+    assert_eq!(
+        drag(&mut selection, (20, 15), (50, 50)),
+        Some(Box::new(20, 15, 30, 35))
+    );
+    assert_eq!(
+        drag(&mut selection, (50, 50), (20, 15)),
+        Some(Box::new(20, 15, 30, 35))
+    );
+    // Across both outputs.
+    assert_eq!(
+        drag(&mut selection, (60, 30), (130, 20)),
+        Some(Box::new(60, 20, 70, 10))
+    );
+}
+
+#[test]
+fn drag_snaps_to_output_edges() {
+    let mut selection = Selection::new(layout());
+    assert_eq!(DEFAULT_SNAP_DISTANCE, 8);
+    // Both corners within 8 pixels of an edge on both axes.
+    assert_eq!(
+        drag(&mut selection, (5, 7), (94, 53)),
+        Some(Box::new(0, 0, 100, 60))
+    );
+    // The edge between the outputs and the bottom of the shorter one.
+    assert_eq!(
+        drag(&mut selection, (30, 20), (103, 36)),
+        Some(Box::new(30, 20, 70, 20))
+    );
+    assert_eq!(selection.snap(50, 9), (50, 9));
+
+    let mut selection = Selection::new(layout()).snap_distance(0);
+    assert_eq!(
+        drag(&mut selection, (5, 7), (94, 53)),
+        Some(Box::new(5, 7, 89, 46))
+    );
+}
+
+#[test]
+fn click_selects_the_smallest_window_or_the_output() {
+    let windows = vec![Box::new(10, 10, 60, 40), Box::new(20, 20, 10, 10)];
+    let mut selection = Selection::new(layout()).windows(windows);
+
+    assert_eq!(
+        drag(&mut selection, (25, 25), (25, 25)),
+        Some(Box::new(20, 20, 10, 10))
+    );
+    assert_eq!(
+        drag(&mut selection, (12, 12), (12, 12)),
+        Some(Box::new(10, 10, 60, 40))
+    );
+    assert_eq!(
+        drag(&mut selection, (150, 5), (150, 5)),
+        Some(Box::new(100, 0, 80, 40))
+    );
+    // Below the shorter output nothing is there.
+    assert_eq!(drag(&mut selection, (150, 50), (150, 50)), None);
+}
+
+#[test]
+fn small_jitter_is_still_a_click() {
+    let mut selection = Selection::new(layout());
+    selection.pointer_motion(30, 30);
+    selection.press();
+    selection.pointer_motion(32, 29);
+    assert!(!selection.is_dragging());
+    assert_eq!(selection.release(), Some(Box::new(0, 0, 100, 60)));
+
+    selection.press();
+    selection.pointer_motion(35, 30);
+    assert!(selection.is_dragging());
+    assert_eq!(selection.release(), Some(Box::new(32, 29, 3, 1)));
+}
+
+#[test]
+fn release_without_a_press_or_position_selects_nothing() {
+    let mut selection = Selection::new(layout());
+    assert_eq!(selection.release(), None);
+    // No position yet, so the press is ignored.
+    selection.press();
+    selection.pointer_motion(10, 10);
+    assert_eq!(selection.release(), None);
+}
+
+#[test]
+fn highlight_follows_the_pointer_and_the_drag() {
+    let mut selection = Selection::new(layout()).windows(vec![Box::new(10, 10, 20, 20)]);
+    assert_eq!(selection.highlight(), None);
+
+    selection.pointer_motion(15, 15);
+    assert_eq!(selection.highlight(), Some(Box::new(10, 10, 20, 20)));
+    selection.pointer_motion(150, 15);
+    assert_eq!(selection.highlight(), Some(Box::new(100, 0, 80, 40)));
+    selection.pointer_left();
+    assert_eq!(selection.highlight(), None);
+
+    selection.pointer_motion(40, 40);
+    selection.press();
+    selection.pointer_motion(60, 50);
+    // A drag keeps its last position when the pointer leaves the overlays.
+    selection.pointer_left();
+    assert_eq!(selection.highlight(), Some(Box::new(40, 40, 20, 10)));
+}
+
+/// Render the overlay of `area` into a buffer with `padding` extra bytes per row.
+fn render(selection: &Selection, area: Box, padding: usize) -> (Vec<u8>, usize) {
+    let stride = area.width() as usize * 4 + padding;
+    let mut canvas = vec![0xaa; stride * area.height() as usize];
+    selection.render(area, &mut canvas, stride);
+    (canvas, stride)
+}
+
+fn pixel(canvas: &[u8], stride: usize, x: usize, y: usize) -> [u8; 4] {
+    let offset = y * stride + x * 4;
+    canvas[offset..offset + 4].try_into().unwrap()
+}
+
+const SHADE: [u8; 4] = [0, 0, 0, 0x80];
+const CLEAR: [u8; 4] = [0, 0, 0, 0];
+const BORDER: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+#[test]
+fn render_shades_everything_without_a_highlight() {
+    let selection = Selection::new(layout());
+    let (canvas, stride) = render(&selection, Box::new(0, 0, 100, 60), 8);
+    for y in 0..60 {
+        for x in 0..100 {
+            assert_eq!(pixel(&canvas, stride, x, y), SHADE);
+        }
+        // Row padding is left alone.
+        assert_eq!(&canvas[y * stride + 400..(y + 1) * stride], &[0xaa; 8]);
+    }
+}
+
+#[test]
+fn render_clears_the_selection_inside_a_border() {
+    let mut selection = Selection::new(layout());
+    selection.pointer_motion(20, 10);
+    selection.press();
+    selection.pointer_motion(40, 30);
+    let (canvas, stride) = render(&selection, Box::new(0, 0, 100, 60), 0);
+
+    assert_eq!(pixel(&canvas, stride, 19, 10), SHADE);
+    assert_eq!(pixel(&canvas, stride, 20, 10), BORDER);
+    assert_eq!(pixel(&canvas, stride, 21, 11), BORDER);
+    assert_eq!(pixel(&canvas, stride, 22, 12), CLEAR);
+    assert_eq!(pixel(&canvas, stride, 37, 27), CLEAR);
+    assert_eq!(pixel(&canvas, stride, 38, 28), BORDER);
+    assert_eq!(pixel(&canvas, stride, 39, 29), BORDER);
+    assert_eq!(pixel(&canvas, stride, 40, 30), SHADE);
+}
+
+#[test]
+fn render_draws_the_part_of_the_selection_on_each_output() {
+    let mut selection = Selection::new(layout()).snap_distance(0);
+    selection.pointer_motion(90, 10);
+    selection.press();
+    selection.pointer_motion(110, 20);
+
+    // The right output only shows the right half, so its left column is not a border.
+    let (canvas, stride) = render(&selection, Box::new(100, 0, 80, 40), 0);
+    assert_eq!(pixel(&canvas, stride, 0, 10), BORDER);
+    assert_eq!(pixel(&canvas, stride, 0, 15), CLEAR);
+    assert_eq!(pixel(&canvas, stride, 9, 15), BORDER);
+    assert_eq!(pixel(&canvas, stride, 10, 15), SHADE);
+
+    let (canvas, stride) = render(&selection, Box::new(0, 0, 100, 60), 0);
+    assert_eq!(pixel(&canvas, stride, 90, 15), BORDER);
+    assert_eq!(pixel(&canvas, stride, 99, 15), CLEAR);
+}
+
+#[test]
+fn render_tolerates_a_short_canvas() {
+    let selection = Selection::new(layout());
+    let mut canvas = vec![0u8; 10 * 4 + 3];
+    selection.render(Box::new(0, 0, 10, 5), &mut canvas, 10 * 4);
+    assert_eq!(&canvas[..4], &SHADE);
+    assert_eq!(&canvas[40..], &[0, 0, 0]);
+}
+
+#[cfg(feature = "testing")]
+mod overlay {
+    use std::time::{Duration, Instant};
+
+    use grim_rs::testing::{MockCompositor, MockOutput};
+    use grim_rs::{Box, Error, Grim};
+
+    const BTN_LEFT: u32 = 0x110;
+    const BTN_RIGHT: u32 = 0x111;
+
+    fn compositor() -> MockCompositor {
+        MockCompositor::new(vec![
+            MockOutput::new("DP-1", 80, 40),
+            MockOutput::new("HDMI-A-1", 60, 20).position(80, 0),
+        ])
+        .unwrap()
+    }
+
+    /// Run `select_region` on another thread, and return once its overlays are mapped.
+    fn start_selection(
+        compositor: &MockCompositor,
+        windows: Vec<Box>,
+    ) -> std::thread::JoinHandle<(Grim, grim_rs::Result<Box>)> {
+        let mut grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+        let selection = std::thread::spawn(move || {
+            let region = grim.select_region(&windows);
+            (grim, region)
+        });
+        let start = Instant::now();
+        while compositor.mapped_overlays() < 2 {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "overlays never mapped"
+            );
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        selection
+    }
+
+    #[test]
+    fn drag_across_outputs() {
+        let compositor = compositor();
+        let selection = start_selection(&compositor, Vec::new());
+        compositor.move_pointer(30, 10).unwrap();
+        compositor.pointer_button(BTN_LEFT, true).unwrap();
+        // Over the second output, close to its bottom edge.
+        compositor.move_pointer(100, 15).unwrap();
+        compositor.pointer_button(BTN_LEFT, false).unwrap();
+
+        let (mut grim, region) = selection.join().unwrap();
+        let region = region.unwrap();
+        assert_eq!(region, Box::new(30, 10, 70, 10));
+        assert_eq!(compositor.mapped_overlays(), 0);
+        let result = grim.capture_region(region).unwrap();
+        assert_eq!((result.width(), result.height()), (70, 10));
+    }
+
+    #[test]
+    fn click_selects_a_window_or_an_output() {
+        let compositor = compositor();
+        let window = Box::new(10, 10, 30, 20);
+        let selection = start_selection(&compositor, vec![window]);
+        compositor.move_pointer(20, 20).unwrap();
+        compositor.pointer_button(BTN_LEFT, true).unwrap();
+        compositor.pointer_button(BTN_LEFT, false).unwrap();
+        assert_eq!(selection.join().unwrap().1.unwrap(), window);
+
+        let selection = start_selection(&compositor, vec![window]);
+        compositor.move_pointer(90, 5).unwrap();
+        compositor.pointer_button(BTN_LEFT, true).unwrap();
+        compositor.pointer_button(BTN_LEFT, false).unwrap();
+        assert_eq!(
+            selection.join().unwrap().1.unwrap(),
+            Box::new(80, 0, 60, 20)
+        );
+    }
+
+    #[test]
+    fn right_button_cancels() {
+        let compositor = compositor();
+        let selection = start_selection(&compositor, Vec::new());
+        compositor.move_pointer(30, 10).unwrap();
+        compositor.pointer_button(BTN_RIGHT, true).unwrap();
+
+        let (mut grim, region) = selection.join().unwrap();
+        assert!(matches!(region, Err(Error::SelectionCancelled)));
+        assert_eq!(compositor.mapped_overlays(), 0);
+        // The connection still works.
+        assert_eq!(grim.capture_output("DP-1").unwrap().width(), 80);
+    }
+}
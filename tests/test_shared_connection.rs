@@ -0,0 +1,100 @@
+#![cfg(feature = "testing")]
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{CaptureBackend, Error, Grim};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry::{self, WlRegistry};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+
+/// Application state that owns the connection's main event queue.
+#[derive(Default)]
+struct App {
+    registry_events: usize,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for App {
+    fn event(
+        state: &mut Self,
+        _: &WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        state.registry_events += 1;
+    }
+}
+
+#[test]
+fn from_connection_uses_the_given_connection() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 2)]).unwrap();
+    let connection = compositor.connect().unwrap();
+    let mut grim = Grim::from_connection(connection.clone()).unwrap();
+    assert_eq!(grim.backend(), CaptureBackend::WlrScreencopy);
+    assert_eq!(grim.capture_output("DP-1").unwrap().width(), 4);
+
+    // The connection stays usable for the application.
+    let (globals, mut queue) = registry_queue_init::<App>(&connection).unwrap();
+    globals
+        .contents()
+        .with_list(|list| assert!(!list.is_empty()));
+    queue.roundtrip(&mut App::default()).unwrap();
+}
+
+#[test]
+fn from_connection_with_backend_checks_the_protocol() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 2)]).unwrap();
+    let grim = Grim::from_connection_with_backend(
+        compositor.connect().unwrap(),
+        CaptureBackend::WlrScreencopy,
+    )
+    .unwrap();
+    assert_eq!(grim.backend(), CaptureBackend::WlrScreencopy);
+
+    assert!(matches!(
+        Grim::from_connection_with_backend(
+            compositor.connect().unwrap(),
+            CaptureBackend::ExtImageCopyCapture,
+        ),
+        Err(Error::UnsupportedProtocol(_))
+    ));
+}
+
+#[test]
+fn from_globals_shares_the_application_registry() {
+    let output = MockOutput::new("DP-1", 6, 3);
+    let compositor = MockCompositor::new(vec![output.clone()]).unwrap();
+    let connection = compositor.connect().unwrap();
+    let (globals, mut queue) = registry_queue_init::<App>(&connection).unwrap();
+
+    let mut grim = Grim::from_globals(&connection, &globals).unwrap();
+    let result = grim.capture_output("DP-1").unwrap();
+    assert_eq!((result.width(), result.height()), (6, 3));
+    assert_eq!(&result.data()[..4], &output.pixel(0, 0));
+
+    // Capturing never dispatches the application's queue.
+    let mut app = App::default();
+    queue.roundtrip(&mut app).unwrap();
+    assert_eq!(app.registry_events, 0);
+}
+
+#[test]
+fn from_globals_reports_outputs() {
+    let compositor = MockCompositor::new(vec![
+        MockOutput::new("DP-1", 4, 2),
+        MockOutput::new("DP-2", 4, 2).position(4, 0),
+    ])
+    .unwrap();
+    let connection = compositor.connect().unwrap();
+    let (globals, _queue) = registry_queue_init::<App>(&connection).unwrap();
+
+    let mut grim = Grim::from_globals(&connection, &globals).unwrap();
+    let mut names: Vec<String> = grim
+        .get_outputs()
+        .unwrap()
+        .iter()
+        .map(|output| output.name().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["DP-1", "DP-2"]);
+}
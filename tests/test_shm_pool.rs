@@ -0,0 +1,82 @@
+#![cfg(feature = "testing")]
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{Box, CaptureParameters, CaptureResult, Grim, PixelFormat};
+
+fn outputs() -> Vec<MockOutput> {
+    vec![
+        MockOutput::new("DP-1", 4, 3),
+        MockOutput::new("DP-2", 16, 10)
+            .position(4, 0)
+            .stride_padding(20),
+        MockOutput::new("DP-3", 7, 5)
+            .position(20, 0)
+            .format(PixelFormat::Rgb888),
+    ]
+}
+
+fn expected_image(output: &MockOutput, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+    (y..y + height)
+        .flat_map(|py| (x..x + width).flat_map(move |px| output.pixel(px, py)))
+        .collect()
+}
+
+fn assert_output(result: &CaptureResult, output: &MockOutput, step: &str) {
+    let (width, height) = output.physical_size();
+    assert_eq!(
+        (result.width() as i32, result.height() as i32),
+        (width, height),
+        "{}",
+        step
+    );
+    assert_eq!(
+        result.data(),
+        expected_image(output, 0, 0, width as u32, height as u32).as_slice(),
+        "{}",
+        step
+    );
+}
+
+#[test]
+fn one_pool_serves_captures_of_every_size() {
+    let outputs = outputs();
+    let compositor = MockCompositor::new(outputs.clone()).unwrap();
+    let mut grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+
+    // Small, then larger (the pool grows), then small again (it does not shrink).
+    for (step, index) in [0, 1, 0, 2, 1, 0].into_iter().enumerate() {
+        let output = &outputs[index];
+        let result = grim.capture_output(output.name()).unwrap();
+        assert_output(&result, output, &format!("step {} {}", step, output.name()));
+    }
+
+    let result = grim.capture_region(Box::new(6, 2, 5, 4)).unwrap();
+    assert_eq!((result.width(), result.height()), (5, 4));
+    assert_eq!(
+        result.data(),
+        expected_image(&outputs[1], 2, 2, 5, 4).as_slice()
+    );
+
+    // A batch holds every frame in the pool at once.
+    let parameters = outputs
+        .iter()
+        .map(|output| CaptureParameters::new(output.name()))
+        .collect();
+    let batch = grim.capture_outputs(parameters).unwrap();
+    for output in &outputs {
+        assert_output(
+            batch.get(output.name()).unwrap(),
+            output,
+            &format!("batch {}", output.name()),
+        );
+    }
+
+    let result = grim.capture_output("DP-1").unwrap();
+    assert_output(&result, &outputs[0], "after batch");
+    assert_eq!(compositor.shm_pools(), 1);
+
+    grim.release_shm_pool();
+    let result = grim.capture_output("DP-3").unwrap();
+    assert_output(&result, &outputs[2], "after release");
+    assert_eq!(compositor.shm_pools(), 2);
+}
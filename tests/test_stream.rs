@@ -0,0 +1,76 @@
+#![cfg(feature = "testing")]
+
+use std::time::{Duration, Instant};
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{Box, Error, Grim, StreamParameters, Transform};
+
+fn expected_image(output: &MockOutput, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+    (y..y + height)
+        .flat_map(|py| (x..x + width).flat_map(move |px| output.pixel(px, py)))
+        .collect()
+}
+
+#[test]
+fn streams_frames_into_one_buffer() {
+    let output = MockOutput::new("DP-1", 10, 6).stride_padding(8);
+    let compositor = MockCompositor::new(vec![output.clone()]).unwrap();
+    let mut grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    let frames: Vec<_> = grim
+        .stream_output("DP-1", StreamParameters::new())
+        .unwrap()
+        .take(2)
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    for (index, frame) in frames.iter().enumerate() {
+        let capture = frame.capture();
+        assert_eq!((capture.width(), capture.height()), (10, 6));
+        assert_eq!(
+            capture.data(),
+            expected_image(&output, 0, 0, 10, 6).as_slice()
+        );
+        assert_eq!(frame.damage(), &[Box::new(0, 0, 10, 6)]);
+        // The mock counts copied frames as whole seconds.
+        assert_eq!(frame.timestamp(), Duration::from_secs(index as u64 + 1));
+        assert_eq!(capture.timestamp(), Some(frame.timestamp()));
+    }
+    assert!(frames[1].timestamp() > frames[0].timestamp());
+    assert_eq!(compositor.shm_buffers(), 1);
+}
+
+#[test]
+fn stream_damage_is_in_image_coordinates() {
+    let output = MockOutput::new("DP-1", 8, 4)
+        .scale(2)
+        .transform(Transform::Rotated90);
+    let compositor = MockCompositor::new(vec![output.clone()]).unwrap();
+    let mut grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    let parameters = StreamParameters::new().region(Box::new(0, 1, 2, 2));
+    for frame in grim.stream_output("DP-1", parameters).unwrap().take(2) {
+        let frame = frame.unwrap();
+        let capture = frame.capture();
+        assert_eq!((capture.width(), capture.height()), (4, 4));
+        assert_eq!(
+            capture.data(),
+            expected_image(&output, 0, 2, 4, 4).as_slice()
+        );
+        assert_eq!(frame.damage(), &[Box::new(0, 0, 4, 4)]);
+    }
+    assert_eq!(compositor.shm_buffers(), 1);
+}
+
+#[test]
+fn frame_timeout_bounds_the_wait_for_damage() {
+    let output = MockOutput::new("DP-1", 4, 4).stall_captures(true);
+    let compositor = MockCompositor::new(vec![output]).unwrap();
+    let mut grim = Grim::from_connection(compositor.connect().unwrap()).unwrap();
+    let timeout = Duration::from_millis(200);
+    let started = Instant::now();
+    let mut stream = grim
+        .stream_output("DP-1", StreamParameters::new().frame_timeout(timeout))
+        .unwrap();
+    assert!(matches!(stream.next(), Some(Err(Error::Timeout(t))) if t == timeout));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(stream.next().is_none());
+}
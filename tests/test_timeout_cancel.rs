@@ -0,0 +1,189 @@
+#![cfg(feature = "testing")]
+
+use std::time::{Duration, Instant};
+
+use grim_rs::testing::{MockCompositor, MockOutput};
+use grim_rs::{CancellationToken, CaptureParameters, Error, Grim};
+
+fn connect(compositor: &MockCompositor) -> Grim {
+    Grim::from_connection(compositor.connect().unwrap()).unwrap()
+}
+
+/// The compositor handles requests on its own thread, so give the destroy time to land.
+fn wait_for_no_frames(compositor: &MockCompositor) {
+    let start = Instant::now();
+    while compositor.live_frames() != 0 && start.elapsed() < Duration::from_secs(2) {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(compositor.live_frames(), 0);
+}
+
+#[test]
+fn default_timeout_is_set() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4)]).unwrap();
+    let mut grim = connect(&compositor);
+    assert_eq!(grim.timeout(), Some(Duration::from_secs(10)));
+    grim.set_timeout(None);
+    assert_eq!(grim.timeout(), None);
+    assert_eq!(grim.capture_output("DP-1").unwrap().width(), 4);
+}
+
+#[test]
+fn stalled_capture_times_out_and_destroys_the_frame() {
+    let compositor =
+        MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4).stall_captures(true)]).unwrap();
+    let mut grim = connect(&compositor);
+    grim.set_timeout(Some(Duration::from_millis(100)));
+
+    let start = Instant::now();
+    let result = grim.capture_output("DP-1");
+    assert!(matches!(result, Err(Error::Timeout(t)) if t == Duration::from_millis(100)));
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // The connection is still usable after a timeout.
+    grim.get_outputs().unwrap();
+    wait_for_no_frames(&compositor);
+}
+
+#[test]
+fn parameter_timeout_overrides_the_instance() {
+    let compositor = MockCompositor::new(vec![
+        MockOutput::new("DP-1", 4, 4),
+        MockOutput::new("DP-2", 4, 4)
+            .position(4, 0)
+            .stall_captures(true),
+    ])
+    .unwrap();
+    let mut grim = connect(&compositor);
+    grim.set_timeout(None);
+
+    let parameters = vec![
+        CaptureParameters::new("DP-1"),
+        CaptureParameters::new("DP-2").timeout(Duration::from_millis(50)),
+    ];
+    assert_eq!(
+        parameters[1].timeout_duration(),
+        Some(Duration::from_millis(50))
+    );
+    assert!(matches!(
+        grim.capture_outputs(parameters),
+        Err(Error::Timeout(t)) if t == Duration::from_millis(50)
+    ));
+    wait_for_no_frames(&compositor);
+
+    let result = grim
+        .capture_outputs(vec![
+            CaptureParameters::new("DP-1").timeout(Duration::from_millis(50))
+        ])
+        .unwrap();
+    assert!(result.get("DP-1").is_some());
+}
+
+#[test]
+fn cancel_from_another_thread_aborts_the_capture() {
+    let compositor =
+        MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4).stall_captures(true)]).unwrap();
+    let mut grim = connect(&compositor);
+    grim.set_timeout(None);
+    let token = CancellationToken::new().unwrap();
+    grim.set_cancellation_token(Some(token.clone()));
+
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        token.cancel();
+    });
+    assert!(matches!(grim.capture_output("DP-1"), Err(Error::Cancelled)));
+    canceller.join().unwrap();
+    wait_for_no_frames(&compositor);
+}
+
+#[test]
+fn cancelled_token_fails_until_replaced() {
+    let compositor = MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4)]).unwrap();
+    let mut grim = connect(&compositor);
+    let token = CancellationToken::new().unwrap();
+    token.cancel();
+    assert!(token.is_cancelled());
+    grim.set_cancellation_token(Some(token));
+
+    assert!(matches!(grim.capture_output("DP-1"), Err(Error::Cancelled)));
+    assert!(matches!(grim.get_outputs(), Err(Error::Cancelled)));
+
+    grim.set_cancellation_token(Some(CancellationToken::new().unwrap()));
+    assert_eq!(grim.capture_output("DP-1").unwrap().width(), 4);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn cancel_wakes_an_async_capture() {
+    use grim_rs::async_capture::AsyncGrim;
+
+    let compositor =
+        MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4).stall_captures(true)]).unwrap();
+    let mut grim = AsyncGrim::tokio(connect(&compositor)).unwrap();
+    let token = CancellationToken::new().unwrap();
+    grim.set_cancellation_token(Some(token.clone()));
+
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        token.cancel();
+    });
+    assert!(matches!(
+        grim.capture_output("DP-1").await,
+        Err(Error::Cancelled)
+    ));
+    canceller.join().unwrap();
+    wait_for_no_frames(&compositor);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn dropped_async_capture_destroys_the_frame() {
+    use grim_rs::async_capture::AsyncGrim;
+
+    let compositor =
+        MockCompositor::new(vec![MockOutput::new("DP-1", 4, 4).stall_captures(true)]).unwrap();
+    let mut grim = AsyncGrim::tokio(connect(&compositor)).unwrap();
+
+    let capture = tokio::time::timeout(Duration::from_millis(50), grim.capture_output("DP-1"));
+    assert!(capture.await.is_err());
+    // The connection stays usable after an abandoned capture.
+    grim.get_outputs().await.unwrap();
+    wait_for_no_frames(&compositor);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn stalled_async_capture_times_out() {
+    use grim_rs::async_capture::AsyncGrim;
+
+    let compositor = MockCompositor::new(vec![
+        MockOutput::new("DP-1", 4, 4),
+        MockOutput::new("DP-2", 4, 4)
+            .position(4, 0)
+            .stall_captures(true),
+    ])
+    .unwrap();
+    let mut grim = AsyncGrim::tokio(connect(&compositor)).unwrap();
+    grim.set_timeout(Some(Duration::from_millis(100)));
+
+    let start = Instant::now();
+    assert!(matches!(
+        grim.capture_output("DP-2").await,
+        Err(Error::Timeout(t)) if t == Duration::from_millis(100)
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    wait_for_no_frames(&compositor);
+
+    grim.set_timeout(None);
+    let parameters = vec![CaptureParameters::new("DP-2").timeout(Duration::from_millis(50))];
+    assert!(matches!(
+        grim.capture_outputs(parameters).await,
+        Err(Error::Timeout(t)) if t == Duration::from_millis(50)
+    ));
+    // The connection is still usable after a timeout.
+    assert_eq!(grim.capture_output("DP-1").await.unwrap().width(), 4);
+    wait_for_no_frames(&compositor);
+}
@@ -0,0 +1,224 @@
+//! WebP encoding. Encoding needs only the `webp` feature; the tests that capture, save
+//! or run the CLI also need `testing` for the mock compositor.
+
+mod common;
+
+use common::encode;
+#[cfg(feature = "testing")]
+use common::grim;
+use grim_rs::{EncodeOptions, ImageFormat};
+
+/// Opaque test pattern with sharp edges and a gradient, like UI content.
+#[cfg(feature = "webp")]
+fn pattern(width: u32, height: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let stripe = if (x / 4 + y / 4) % 2 == 0 { 0x20 } else { 0xe0 };
+            data.extend_from_slice(&[
+                stripe,
+                (x * 255 / width) as u8,
+                (y * 255 / height) as u8,
+                255,
+            ]);
+        }
+    }
+    data
+}
+
+/// Decode `webp` into RGBA, whether or not it kept an alpha channel.
+#[cfg(feature = "webp")]
+fn decode(webp: &[u8]) -> (u32, u32, Vec<u8>) {
+    let image = webp::Decoder::new(webp).decode().expect("invalid WebP");
+    let rgba = if image.is_alpha() {
+        image.to_vec()
+    } else {
+        image
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect()
+    };
+    (image.width(), image.height(), rgba)
+}
+
+#[test]
+#[cfg(feature = "webp")]
+fn lossless_webp_round_trips_exactly() {
+    let data = pattern(37, 21);
+    let webp = encode(&data, 37, 21, EncodeOptions::new(ImageFormat::Webp)).unwrap();
+    assert_eq!(&webp[..4], b"RIFF");
+    assert_eq!(&webp[8..16], b"WEBPVP8L");
+    assert_eq!(decode(&webp), (37, 21, data));
+}
+
+#[test]
+#[cfg(feature = "webp")]
+fn lossless_webp_keeps_alpha() {
+    let mut data = pattern(8, 8);
+    for (i, pixel) in data.chunks_exact_mut(4).enumerate() {
+        pixel[3] = (i * 4) as u8;
+    }
+    let webp = encode(&data, 8, 8, EncodeOptions::new(ImageFormat::Webp)).unwrap();
+    let (_, _, decoded) = decode(&webp);
+    let alpha: Vec<u8> = decoded.chunks_exact(4).map(|pixel| pixel[3]).collect();
+    let expected: Vec<u8> = data.chunks_exact(4).map(|pixel| pixel[3]).collect();
+    assert_eq!(alpha, expected);
+}
+
+#[test]
+#[cfg(feature = "webp")]
+fn lossy_webp_round_trips_approximately() {
+    // Smooth content; lossy WebP subsamples chroma, which blurs sharp colour edges.
+    let data: Vec<u8> = (0..48u32)
+        .flat_map(|y| (0..64u32).flat_map(move |x| [(x * 4) as u8, (y * 5) as u8, 0x80, 255]))
+        .collect();
+    let webp = encode(
+        &data,
+        64,
+        48,
+        EncodeOptions::new(ImageFormat::Webp).quality(95),
+    )
+    .unwrap();
+    assert_eq!(&webp[8..16], b"WEBPVP8 ");
+
+    let (width, height, decoded) = decode(&webp);
+    assert_eq!((width, height), (64, 48));
+    let error: u64 = data
+        .iter()
+        .zip(&decoded)
+        .map(|(a, b)| u64::from(a.abs_diff(*b)))
+        .sum();
+    let mean_error = error as f64 / data.len() as f64;
+    assert!(mean_error < 8.0, "mean error {}", mean_error);
+}
+
+#[test]
+#[cfg(feature = "webp")]
+fn lower_webp_quality_gives_smaller_files() {
+    let data = pattern(128, 96);
+    let high = encode(
+        &data,
+        128,
+        96,
+        EncodeOptions::new(ImageFormat::Webp).quality(95),
+    )
+    .unwrap();
+    let low = encode(
+        &data,
+        128,
+        96,
+        EncodeOptions::new(ImageFormat::Webp).quality(10),
+    )
+    .unwrap();
+    assert!(low.len() < high.len(), "{} >= {}", low.len(), high.len());
+}
+
+#[test]
+#[cfg(all(feature = "webp", feature = "testing"))]
+fn saved_webp_matches_the_encoded_bytes() {
+    let (_compositor, grim) = grim();
+    let data = pattern(16, 16);
+    let dir = tempfile::tempdir().unwrap();
+
+    let image = grim_rs::CaptureResult::new(data.clone(), 16, 16);
+
+    let lossless = dir.path().join("lossless.webp");
+    grim.save(&image, &lossless, &EncodeOptions::default())
+        .unwrap();
+    assert_eq!(
+        std::fs::read(&lossless).unwrap(),
+        encode(&data, 16, 16, EncodeOptions::new(ImageFormat::Webp)).unwrap()
+    );
+    let lossy = dir.path().join("lossy.webp");
+    grim.save(&image, &lossy, &EncodeOptions::default().quality(50))
+        .unwrap();
+    assert_eq!(decode(&std::fs::read(&lossy).unwrap()).0, 16);
+}
+
+#[test]
+#[cfg(feature = "webp")]
+fn webp_rejects_mismatched_or_oversized_input() {
+    assert!(encode(&[0; 15], 2, 2, EncodeOptions::new(ImageFormat::Webp)).is_err());
+    assert!(encode(
+        &[0; 20],
+        2,
+        2,
+        EncodeOptions::new(ImageFormat::Webp).quality(80)
+    )
+    .is_err());
+    assert!(encode(&[], 0, 0, EncodeOptions::new(ImageFormat::Webp)).is_err());
+    // WebP cannot store sides longer than 16383 pixels.
+    assert!(encode(
+        &[0; 16384 * 4],
+        16384,
+        1,
+        EncodeOptions::new(ImageFormat::Webp)
+    )
+    .is_err());
+}
+
+#[test]
+#[cfg(not(feature = "webp"))]
+fn webp_is_unavailable_without_the_feature() {
+    assert!(encode(&[0; 16], 2, 2, EncodeOptions::new(ImageFormat::Webp)).is_err());
+    assert!(encode(
+        &[0; 16],
+        2,
+        2,
+        EncodeOptions::new(ImageFormat::Webp).quality(80)
+    )
+    .is_err());
+}
+
+#[test]
+#[cfg(all(feature = "webp", feature = "testing"))]
+fn captured_output_round_trips_through_lossless_webp() {
+    let (_compositor, mut grim) = grim();
+    let result = grim.capture_output("DP-1").unwrap();
+    let mut webp = Vec::new();
+    grim.encode(&result, &EncodeOptions::new(ImageFormat::Webp), &mut webp)
+        .unwrap();
+    assert_eq!(decode(&webp), (16, 8, result.data().to_vec()));
+}
+
+#[test]
+#[cfg(all(feature = "webp", feature = "testing"))]
+fn cli_writes_webp() {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::process::Command;
+
+    let (compositor, _grim) = grim();
+    let dir = tempfile::tempdir().unwrap();
+    for (name, args) in [
+        ("lossless.webp", &[][..]),
+        ("lossy.webp", &["-q", "70"][..]),
+    ] {
+        let file = dir.path().join(name);
+        let socket: UnixStream = compositor.connect_socket().unwrap();
+        let fd = socket.as_raw_fd();
+        // SAFETY: `fd` is owned by `socket`, which outlives the child spawn.
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            assert_eq!(libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC), 0);
+        }
+        let output = Command::new(env!("CARGO_BIN_EXE_grim-rs"))
+            .args(["-t", "webp"])
+            .args(args)
+            .arg(&file)
+            .env("WAYLAND_SOCKET", fd.to_string())
+            .env_remove("WAYLAND_DISPLAY")
+            .output()
+            .unwrap();
+        drop(socket);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let webp = std::fs::read(&file).unwrap();
+        let chunk = if args.is_empty() { b"VP8L" } else { b"VP8 " };
+        assert_eq!(&webp[12..16], chunk);
+        assert_eq!(decode(&webp).0, 16);
+    }
+}